@@ -0,0 +1,282 @@
+use crate::rest::AssistPathHint;
+use anyhow::{Context, Result, anyhow};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket as StdUdpSocket};
+use std::time::Duration;
+use tokio::net::{UdpSocket, lookup_host};
+use tokio::time::timeout;
+
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_SUCCESS: u16 = 0x0101;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_a442;
+const STUN_ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const STUN_ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const STUN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Приоритеты кандидатов: больше — предпочтительнее.
+const PRIORITY_MANUAL: u8 = 200;
+const PRIORITY_REFLEXIVE: u8 = 150;
+const PRIORITY_HOST_V4: u8 = 100;
+const PRIORITY_HOST_V6: u8 = 90;
+
+/// Кандидаты путей для `/api/p2p/assist`.
+pub struct Candidates {
+    pub paths: Vec<AssistPathHint>,
+    /// Почему не удалось получить внешний адрес через STUN.
+    pub stun_error: Option<String>,
+    /// Сокет, чей порт объявлен в host- и srflx-кандидатах. Пока он открыт,
+    /// на этих адресах есть кто слушать, а NAT держит отображение.
+    pub socket: Option<UdpSocket>,
+}
+
+/// Разбирает подсказку `ip:port` из аргументов `/assist`.
+pub fn parse_hint(input: &str) -> Result<SocketAddr> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid path hint '{}', expected ip:port", input))
+}
+
+/// Собирает локальные адреса, внешний адрес от STUN (если задан) и ручные подсказки.
+/// Порт — локальный порт UDP-сокета, с которого уходит STUN-запрос; сокет
+/// возвращается вызывающему. Он слушает только IPv4, поэтому локальные
+/// IPv6-адреса с его портом не объявляются.
+pub async fn gather_candidates(stun_server: Option<&str>, extra: &[SocketAddr]) -> Candidates {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.ok();
+    let port = socket
+        .as_ref()
+        .and_then(|socket| socket.local_addr().ok())
+        .map_or(0, |addr| addr.port());
+    let (reflexive, stun_error) = match (stun_server, socket.as_ref()) {
+        (Some(server), Some(socket)) => match stun_binding(socket, server).await {
+            Ok(addr) => (Some(addr), None),
+            Err(err) => (None, Some(format!("{:#}", err))),
+        },
+        (Some(_), None) => (None, Some("cannot bind udp socket".to_string())),
+        (None, _) => (None, None),
+    };
+    let local: Vec<IpAddr> = local_addresses()
+        .into_iter()
+        .filter(IpAddr::is_ipv4)
+        .collect();
+    Candidates {
+        paths: build_paths(&local, port, reflexive, extra),
+        stun_error,
+        socket,
+    }
+}
+
+/// Адреса интерфейсов, через которые уходит трафик по умолчанию (IPv4 и IPv6).
+/// `connect` у UDP только выбирает маршрут и ничего не отправляет.
+pub fn local_addresses() -> Vec<IpAddr> {
+    let probes: [(IpAddr, IpAddr); 2] = [
+        (
+            Ipv4Addr::UNSPECIFIED.into(),
+            Ipv4Addr::new(192, 0, 2, 1).into(),
+        ),
+        (
+            Ipv6Addr::UNSPECIFIED.into(),
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(),
+        ),
+    ];
+    probes
+        .into_iter()
+        .filter_map(|(bind, target)| {
+            let socket = StdUdpSocket::bind((bind, 0)).ok()?;
+            socket.connect((target, 9)).ok()?;
+            let ip = socket.local_addr().ok()?.ip();
+            (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+        })
+        .collect()
+}
+
+/// Формирует подсказки без повторов: ручные, затем внешний адрес, затем локальные.
+pub fn build_paths(
+    local: &[IpAddr],
+    port: u16,
+    reflexive: Option<SocketAddr>,
+    extra: &[SocketAddr],
+) -> Vec<AssistPathHint> {
+    let mut candidates: Vec<(String, SocketAddr, u8)> = Vec::new();
+    for (index, addr) in extra.iter().enumerate() {
+        candidates.push((format!("hint-{}", index + 1), *addr, PRIORITY_MANUAL));
+    }
+    if let Some(addr) = reflexive {
+        candidates.push(("srflx".to_string(), addr, PRIORITY_REFLEXIVE));
+    }
+    if port != 0 {
+        for ip in local {
+            let (id, priority) = if ip.is_ipv4() {
+                ("host-v4", PRIORITY_HOST_V4)
+            } else {
+                ("host-v6", PRIORITY_HOST_V6)
+            };
+            candidates.push((id.to_string(), SocketAddr::new(*ip, port), priority));
+        }
+    }
+    let mut paths: Vec<AssistPathHint> = Vec::new();
+    for (id, addr, priority) in candidates {
+        let address = addr.ip().to_string();
+        let duplicate = paths.iter().any(|path| {
+            path.address.as_deref() == Some(&address) && path.port == Some(addr.port())
+        });
+        if duplicate {
+            continue;
+        }
+        paths.push(AssistPathHint {
+            address: Some(address),
+            id: Some(id),
+            port: Some(addr.port()),
+            priority: Some(priority),
+            ..Default::default()
+        });
+    }
+    paths
+}
+
+/// STUN Binding (RFC 5389): внешний адрес и порт `socket`, как их видит сервер.
+pub async fn stun_binding(socket: &UdpSocket, server: &str) -> Result<SocketAddr> {
+    let target = lookup_host(server)
+        .await
+        .with_context(|| format!("resolve stun server {}", server))?
+        .find(|addr| addr.is_ipv4())
+        .ok_or_else(|| anyhow!("stun server {} has no ipv4 address", server))?;
+    let mut transaction = [0u8; 12];
+    getrandom::getrandom(&mut transaction).map_err(|err| anyhow!("rng failure: {}", err))?;
+    socket
+        .send_to(&stun_request(&transaction), target)
+        .await
+        .context("send stun request")?;
+    let mut buffer = [0u8; 512];
+    loop {
+        let (len, from) = timeout(STUN_TIMEOUT, socket.recv_from(&mut buffer))
+            .await
+            .map_err(|_| anyhow!("stun server {} did not answer", server))?
+            .context("receive stun response")?;
+        if from == target {
+            return parse_stun_response(&buffer[..len], &transaction);
+        }
+    }
+}
+
+pub fn stun_request(transaction: &[u8; 12]) -> [u8; 20] {
+    let mut message = [0u8; 20];
+    message[0..2].copy_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    message[4..8].copy_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    message[8..20].copy_from_slice(transaction);
+    message
+}
+
+pub fn parse_stun_response(message: &[u8], transaction: &[u8; 12]) -> Result<SocketAddr> {
+    if message.len() < 20 {
+        return Err(anyhow!("stun response too short"));
+    }
+    let kind = u16::from_be_bytes([message[0], message[1]]);
+    let length = u16::from_be_bytes([message[2], message[3]]) as usize;
+    if kind != STUN_BINDING_SUCCESS {
+        return Err(anyhow!("unexpected stun message type {:#06x}", kind));
+    }
+    if message[4..8] != STUN_MAGIC_COOKIE.to_be_bytes() || &message[8..20] != transaction {
+        return Err(anyhow!("stun response does not match the request"));
+    }
+    let body = message
+        .get(20..20 + length)
+        .ok_or_else(|| anyhow!("stun response truncated"))?;
+    let mut mapped = None;
+    let mut offset = 0;
+    while offset + 4 <= body.len() {
+        let attr = u16::from_be_bytes([body[offset], body[offset + 1]]);
+        let attr_len = u16::from_be_bytes([body[offset + 2], body[offset + 3]]) as usize;
+        let value = body
+            .get(offset + 4..offset + 4 + attr_len)
+            .ok_or_else(|| anyhow!("stun attribute truncated"))?;
+        match attr {
+            STUN_ATTR_XOR_MAPPED_ADDRESS => return decode_address(value, Some(transaction)),
+            STUN_ATTR_MAPPED_ADDRESS => mapped = Some(decode_address(value, None)?),
+            _ => {}
+        }
+        offset += 4 + attr_len.div_ceil(4) * 4;
+    }
+    mapped.ok_or_else(|| anyhow!("stun response has no mapped address"))
+}
+
+fn decode_address(value: &[u8], xor_transaction: Option<&[u8; 12]>) -> Result<SocketAddr> {
+    if value.len() < 4 {
+        return Err(anyhow!("stun address too short"));
+    }
+    let cookie = STUN_MAGIC_COOKIE.to_be_bytes();
+    let mut port = u16::from_be_bytes([value[2], value[3]]);
+    let mut mask = [0u8; 16];
+    if let Some(transaction) = xor_transaction {
+        port ^= (STUN_MAGIC_COOKIE >> 16) as u16;
+        mask[..4].copy_from_slice(&cookie);
+        mask[4..].copy_from_slice(transaction);
+    }
+    let ip = match (value[1], value.len()) {
+        (0x01, 8) => {
+            let bytes: [u8; 4] = std::array::from_fn(|i| value[4 + i] ^ mask[i]);
+            IpAddr::from(bytes)
+        }
+        (0x02, 20) => {
+            let bytes: [u8; 16] = std::array::from_fn(|i| value[4 + i] ^ mask[i]);
+            IpAddr::from(bytes)
+        }
+        (family, _) => return Err(anyhow!("unsupported stun address family {}", family)),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_ordered_and_deduplicated() {
+        let lan: IpAddr = "192.168.1.20".parse().unwrap();
+        let v6: IpAddr = "2001:db8::20".parse().unwrap();
+        let manual: SocketAddr = "192.168.1.20:40000".parse().unwrap();
+        let reflexive: SocketAddr = "203.0.113.5:61000".parse().unwrap();
+        let paths = build_paths(&[lan, v6], 40000, Some(reflexive), &[manual]);
+        let ids: Vec<_> = paths.iter().map(|p| p.id.clone().unwrap()).collect();
+        assert_eq!(ids, ["hint-1", "srflx", "host-v6"]);
+        assert_eq!(paths[1].address.as_deref(), Some("203.0.113.5"));
+        assert_eq!(paths[1].port, Some(61000));
+        assert!(paths[0].priority > paths[1].priority);
+        assert!(build_paths(&[lan], 0, None, &[]).is_empty());
+    }
+
+    #[test]
+    fn stun_xor_mapped_address_is_decoded() {
+        let transaction = [7u8; 12];
+        let request = stun_request(&transaction);
+        assert_eq!(&request[0..2], &[0x00, 0x01]);
+
+        let mut response = request.to_vec();
+        response[0..2].copy_from_slice(&STUN_BINDING_SUCCESS.to_be_bytes());
+        response[2..4].copy_from_slice(&12u16.to_be_bytes());
+        let cookie = STUN_MAGIC_COOKIE.to_be_bytes();
+        let port = 61000u16 ^ (STUN_MAGIC_COOKIE >> 16) as u16;
+        response.extend_from_slice(&STUN_ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+        response.extend_from_slice(&8u16.to_be_bytes());
+        response.extend_from_slice(&[0, 0x01]);
+        response.extend_from_slice(&port.to_be_bytes());
+        response.extend(
+            [203u8, 0, 113, 5]
+                .iter()
+                .zip(cookie.iter())
+                .map(|(b, m)| b ^ m),
+        );
+        assert_eq!(
+            parse_stun_response(&response, &transaction).unwrap(),
+            "203.0.113.5:61000".parse::<SocketAddr>().unwrap()
+        );
+        assert!(parse_stun_response(&response, &[8u8; 12]).is_err());
+    }
+
+    #[test]
+    fn hints_require_ip_and_port() {
+        assert_eq!(parse_hint(" 10.0.0.2:5000").unwrap().port(), 5000);
+        assert!(parse_hint("[2001:db8::1]:443").is_ok());
+        assert!(parse_hint("10.0.0.2").is_err());
+        assert!(parse_hint("example.org:80").is_err());
+    }
+}
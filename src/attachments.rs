@@ -0,0 +1,528 @@
+use crate::config;
+use crate::hexutil::encode_hex;
+use anyhow::{Context, Result, anyhow};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Байт файла в одном кадре MSG (до base64).
+pub const CHUNK_SIZE: usize = 16 * 1024;
+/// Самый крупный кусок, который примет получатель.
+pub const MAX_CHUNK_SIZE: u32 = 256 * 1024;
+/// Сколько входящих файлов собирается одновременно; лишний вытесняет самый старый.
+const MAX_INCOMING: usize = 8;
+
+/// Заголовок куска файла в теле MSG: `{"file": {...}, "data": "<base64>"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHeader {
+    /// Случайный id передачи; куски одного файла делят его.
+    pub id: String,
+    /// Устройство отправителя; у старых клиентов поля нет.
+    #[serde(default)]
+    pub sender: String,
+    pub name: String,
+    pub mime: String,
+    /// Размер файла целиком.
+    pub size: u64,
+    /// Размер куска у отправителя; последний кусок может быть короче.
+    pub chunk_size: u32,
+    pub chunk: u32,
+    pub chunks: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkBody {
+    file: FileHeader,
+    data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChunk {
+    pub header: FileHeader,
+    pub data: Vec<u8>,
+}
+
+impl FileChunk {
+    /// Кусок файла из тела MSG; обычные сообщения и мусор — `None`.
+    pub fn parse(body: &[u8]) -> Option<Self> {
+        let body: ChunkBody = serde_json::from_slice(body).ok()?;
+        let header = body.file;
+        if header.id.is_empty() || header.chunks == 0 || header.chunk >= header.chunks {
+            return None;
+        }
+        let data = BASE64.decode(body.data).ok()?;
+        Some(Self { header, data })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(&ChunkBody {
+            file: self.header.clone(),
+            data: BASE64.encode(&self.data),
+        })
+        .context("encode file chunk")
+    }
+}
+
+/// Исходящий файл: куски читаются из `source` и кодируются по одному,
+/// когда подходит их очередь, а не все сразу.
+pub struct Upload {
+    pub id: String,
+    pub size: u64,
+    pub chunks: u32,
+    sender: String,
+    name: String,
+    mime: String,
+    chunk_size: u32,
+    source: Box<dyn Read + Send>,
+    next: u32,
+}
+
+impl Upload {
+    pub fn new(
+        sender: &str,
+        name: &str,
+        mime: &str,
+        source: impl Read + Send + 'static,
+        size: u64,
+        chunk_size: usize,
+    ) -> Result<Self> {
+        let chunk_size = u32::try_from(chunk_size.max(1)).context("chunk size too large")?;
+        let mut id = [0u8; 8];
+        getrandom::getrandom(&mut id).map_err(|err| anyhow!("rng failure: {}", err))?;
+        // Пустой файл — один пустой кусок, иначе получатель его не увидит.
+        let chunks = u32::try_from(size.div_ceil(u64::from(chunk_size)).max(1))
+            .context("file has too many chunks")?;
+        Ok(Self {
+            id: encode_hex(&id),
+            size,
+            chunks,
+            sender: sender.to_string(),
+            name: name.to_string(),
+            mime: mime.to_string(),
+            chunk_size,
+            source: Box::new(source),
+            next: 0,
+        })
+    }
+
+    /// Следующий кусок к отправке; `None`, когда отправлены все.
+    pub fn next_body(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.next >= self.chunks {
+            return Ok(None);
+        }
+        let offset = u64::from(self.next) * u64::from(self.chunk_size);
+        let len = (self.size - offset).min(u64::from(self.chunk_size)) as usize;
+        let mut data = vec![0u8; len];
+        self.source
+            .read_exact(&mut data)
+            .context("file changed while sending")?;
+        let body = FileChunk {
+            header: FileHeader {
+                id: self.id.clone(),
+                sender: self.sender.clone(),
+                name: self.name.clone(),
+                mime: self.mime.clone(),
+                size: self.size,
+                chunk_size: self.chunk_size,
+                chunk: self.next,
+                chunks: self.chunks,
+            },
+            data,
+        }
+        .encode()?;
+        self.next += 1;
+        Ok(Some(body))
+    }
+
+    pub fn remaining(&self) -> usize {
+        (self.chunks - self.next) as usize
+    }
+}
+
+/// Что дал очередной входящий кусок.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Progress {
+    /// Получено `received` байт из заголовочного размера.
+    Partial {
+        received: u64,
+    },
+    Complete(Vec<u8>),
+    /// Передача отброшена целиком.
+    Rejected(String),
+}
+
+struct Partial {
+    header: FileHeader,
+    parts: Vec<Option<Vec<u8>>>,
+    received: u64,
+}
+
+/// Сборка входящих файлов по отправителю и id передачи.
+pub struct Assembler {
+    max_size: u64,
+    order: VecDeque<(String, String)>,
+    partial: HashMap<(String, String), Partial>,
+    /// Недособранный файл, вытесненный последним `push`.
+    evicted: Option<(String, FileHeader)>,
+}
+
+impl Assembler {
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            order: VecDeque::new(),
+            partial: HashMap::new(),
+            evicted: None,
+        }
+    }
+
+    pub fn push(&mut self, sender: &str, chunk: FileChunk) -> Progress {
+        let header = chunk.header;
+        if header.size > self.max_size {
+            return Progress::Rejected(format!(
+                "{} is larger than the {} limit",
+                format_size(header.size),
+                format_size(self.max_size)
+            ));
+        }
+        if header.chunk_size == 0 || header.chunk_size > MAX_CHUNK_SIZE {
+            return Progress::Rejected(format!(
+                "chunk size {} is outside 1..={}",
+                header.chunk_size, MAX_CHUNK_SIZE
+            ));
+        }
+        // `chunks` задаёт размер буфера сборки, поэтому верить ему на слово нельзя.
+        let expected = header.size.div_ceil(u64::from(header.chunk_size)).max(1);
+        if u64::from(header.chunks) != expected {
+            return Progress::Rejected(format!(
+                "{} chunks announced for {}, expected {}",
+                header.chunks,
+                format_size(header.size),
+                expected
+            ));
+        }
+        let key = (sender.to_string(), header.id.clone());
+        let same_file = self.partial.get(&key).is_some_and(|partial| {
+            partial.header.size == header.size
+                && partial.header.chunk_size == header.chunk_size
+                && partial.header.chunks == header.chunks
+        });
+        if !same_file {
+            self.forget(&key);
+            if self.order.len() >= MAX_INCOMING
+                && let Some(oldest) = self.order.pop_front()
+                && let Some(dropped) = self.partial.remove(&oldest)
+            {
+                self.evicted = Some((oldest.0, dropped.header));
+            }
+            self.order.push_back(key.clone());
+            self.partial.insert(
+                key.clone(),
+                Partial {
+                    parts: vec![None; header.chunks as usize],
+                    header: header.clone(),
+                    received: 0,
+                },
+            );
+        }
+        let Some(partial) = self.partial.get_mut(&key) else {
+            return Progress::Rejected("transfer state lost".to_string());
+        };
+        let slot = &mut partial.parts[header.chunk as usize];
+        if slot.is_none() {
+            partial.received += chunk.data.len() as u64;
+            *slot = Some(chunk.data);
+        }
+        if partial.received > partial.header.size {
+            self.forget(&key);
+            return Progress::Rejected("more data than announced".to_string());
+        }
+        if partial.parts.iter().any(Option::is_none) {
+            return Progress::Partial {
+                received: partial.received,
+            };
+        }
+        let partial = self.forget(&key).expect("transfer present");
+        if partial.received != partial.header.size {
+            return Progress::Rejected("less data than announced".to_string());
+        }
+        Progress::Complete(partial.parts.into_iter().flatten().flatten().collect())
+    }
+
+    /// Отправитель и заголовок файла, который пришлось бросить ради нового.
+    pub fn take_evicted(&mut self) -> Option<(String, FileHeader)> {
+        self.evicted.take()
+    }
+
+    fn forget(&mut self, key: &(String, String)) -> Option<Partial> {
+        self.order.retain(|known| known != key);
+        self.partial.remove(key)
+    }
+}
+
+/// MIME-тип по расширению; неизвестное — `application/octet-stream`.
+pub fn mime_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("txt" | "log" | "md") => "text/plain",
+        Some("json") => "application/json",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "opus") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Имя из чужого заголовка без каталогов и управляющих символов:
+/// сохранение не должно выйти за пределы выбранной папки.
+pub fn safe_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let clean: String = base.chars().filter(|c| !c.is_control()).collect();
+    let clean = clean.trim();
+    if clean.is_empty() || clean.chars().all(|c| c == '.') {
+        "file".to_string()
+    } else {
+        clean.to_string()
+    }
+}
+
+/// Каталог собранных входящих файлов этого процесса в кэше клиента:
+/// у параллельно запущенного клиента (или `replay`) он свой.
+fn received_dir() -> Result<PathBuf> {
+    Ok(config::cache_path("files")?.join(std::process::id().to_string()))
+}
+
+/// Кладёт собранный входящий файл в кэш; в ленте остаётся только путь,
+/// а не содержимое.
+pub fn store_received(name: &str, data: &[u8]) -> Result<PathBuf> {
+    let dir = received_dir()?;
+    fs::create_dir_all(&dir).context("file cache directory")?;
+    let path = free_path(&dir, &safe_file_name(name));
+    fs::write(&path, data).with_context(|| format!("write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Удаляет файлы, полученные этим процессом; вызывается при выходе из TUI,
+/// когда лента, ссылавшаяся на них, исчезает.
+pub fn clear_received() {
+    if let Ok(dir) = received_dir() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
+
+/// Папка загрузок пользователя, иначе текущий каталог.
+pub fn download_dir() -> PathBuf {
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `dir/name`, а если такой файл уже есть — `name (1).ext`, `name (2).ext`…
+pub fn free_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let name = Path::new(name);
+    let stem = name
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = name
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("unbounded candidates")
+}
+
+/// `512 B`, `12.3 KiB`, `4.0 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(name: &str, data: &[u8], chunk_size: usize) -> Upload {
+        let source = std::io::Cursor::new(data.to_vec());
+        Upload::new(
+            "alice",
+            name,
+            "text/plain",
+            source,
+            data.len() as u64,
+            chunk_size,
+        )
+        .unwrap()
+    }
+
+    fn chunks_of(upload: &mut Upload) -> Vec<FileChunk> {
+        std::iter::from_fn(|| upload.next_body().unwrap())
+            .map(|body| FileChunk::parse(&body).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn files_survive_chunking_in_any_order() {
+        let data: Vec<u8> = (0..10_000u32).map(|n| n as u8).collect();
+        let mut upload = upload("notes.bin", &data, 4096);
+        assert_eq!(upload.chunks, 3);
+        assert_eq!(upload.remaining(), 3);
+        let mut chunks = chunks_of(&mut upload);
+        assert_eq!(upload.remaining(), 0);
+        assert!(chunks.iter().all(|chunk| chunk.header.sender == "alice"));
+        chunks.swap(0, 2);
+
+        let mut assembler = Assembler::new(1 << 20);
+        assert_eq!(
+            assembler.push("alice", chunks[0].clone()),
+            Progress::Partial {
+                received: 10_000 - 8192
+            }
+        );
+        // Повтор куска не удваивает счётчик.
+        assert_eq!(
+            assembler.push("alice", chunks[0].clone()),
+            Progress::Partial {
+                received: 10_000 - 8192
+            }
+        );
+        assembler.push("alice", chunks[1].clone());
+        assert_eq!(
+            assembler.push("alice", chunks[2].clone()),
+            Progress::Complete(data)
+        );
+
+        let chunk = chunks_of(&mut upload("empty", &[], 4096)).remove(0);
+        assert_eq!(assembler.push("bob", chunk), Progress::Complete(Vec::new()));
+
+        // Получатель верит размеру куска отправителя, а не своему CHUNK_SIZE.
+        let data = vec![9u8; 100_000];
+        let mut chunks = chunks_of(&mut upload("wide", &data, 64 * 1024));
+        assert_eq!(chunks.len(), 2);
+        assembler.push("carol", chunks.remove(1));
+        assert_eq!(
+            assembler.push("carol", chunks.remove(0)),
+            Progress::Complete(data)
+        );
+        assert!(FileChunk::parse(br#"{"text":"hi"}"#).is_none());
+    }
+
+    #[test]
+    fn oversized_or_inconsistent_transfers_are_rejected() {
+        let data = vec![7u8; 3000];
+        let chunks = chunks_of(&mut upload("big", &data, 1000));
+        let mut small = Assembler::new(2000);
+        assert!(matches!(
+            small.push("alice", chunks[0].clone()),
+            Progress::Rejected(_)
+        ));
+
+        let mut assembler = Assembler::new(1 << 20);
+        let mut liar = chunks[0].clone();
+        liar.data = vec![0; 4000];
+        assert!(matches!(
+            assembler.push("alice", liar),
+            Progress::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn chunk_counts_must_match_the_announced_size() {
+        let mut assembler = Assembler::new(1 << 20);
+        let bogus = FileChunk::parse(
+            br#"{"file":{"id":"x","name":"a","mime":"text/plain","size":0,"chunk_size":16384,"chunk":0,"chunks":4294967295},"data":""}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            assembler.push("mallory", bogus),
+            Progress::Rejected(_)
+        ));
+
+        let data = vec![1u8; CHUNK_SIZE + 1];
+        let mut chunks = chunks_of(&mut upload("two", &data, CHUNK_SIZE));
+        assert_eq!(chunks.len(), 2);
+        chunks[0].header.chunks = 3;
+        assert!(matches!(
+            assembler.push("alice", chunks[0].clone()),
+            Progress::Rejected(_)
+        ));
+
+        // Огромный кусок сделал бы проверку `chunks` бессмысленной.
+        let mut huge = chunks[1].clone();
+        huge.header.chunk_size = MAX_CHUNK_SIZE + 1;
+        huge.header.chunks = 1;
+        assert!(matches!(
+            assembler.push("alice", huge),
+            Progress::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn evicted_transfers_are_reported() {
+        let mut assembler = Assembler::new(1 << 20);
+        let data = vec![3u8; 2000];
+        for n in 0..=MAX_INCOMING {
+            let first = chunks_of(&mut upload(&format!("f{}", n), &data, 1000)).remove(0);
+            assert!(matches!(
+                assembler.push("alice", first),
+                Progress::Partial { .. }
+            ));
+            if n < MAX_INCOMING {
+                assert_eq!(assembler.take_evicted(), None);
+            }
+        }
+        let (sender, header) = assembler.take_evicted().unwrap();
+        assert_eq!((sender.as_str(), header.name.as_str()), ("alice", "f0"));
+        assert_eq!(assembler.take_evicted(), None);
+    }
+
+    #[test]
+    fn shrunken_sources_fail_instead_of_padding() {
+        let source = std::io::Cursor::new(vec![1u8; 10]);
+        let mut upload = Upload::new("alice", "gone", "text/plain", source, 20, 8).unwrap();
+        assert!(upload.next_body().unwrap().is_some());
+        assert!(upload.next_body().is_err());
+    }
+
+    #[test]
+    fn received_names_cannot_escape_the_folder() {
+        assert_eq!(safe_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(safe_file_name("C:\\Users\\x\\photo.png"), "photo.png");
+        assert_eq!(safe_file_name(".."), "file");
+        assert_eq!(safe_file_name("a\u{1b}[2Jb.txt"), "a[2Jb.txt");
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(12_595), "12.3 KiB");
+        assert_eq!(format_size(4 << 20), "4.0 MiB");
+    }
+}
@@ -0,0 +1,374 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig};
+use opus::{
+    Application as OpusApplication, Channels as OpusChannels, Decoder as OpusDecoder,
+    Encoder as OpusEncoder,
+};
+
+use crate::voice::VoiceMessage;
+
+/// Частота дискретизации, с которой кодируются голосовые кадры.
+pub const CAPTURE_SAMPLE_RATE: u32 = 48_000;
+/// Длительность одного Opus-кадра.
+pub const FRAME_DURATION_MS: u32 = 20;
+const MAX_PACKET_SIZE: usize = 4_000;
+
+/// Режет PCM на кадры по 20 мс и кодирует их в Opus.
+pub struct OpusFrameEncoder {
+    encoder: OpusEncoder,
+    frame_samples: usize,
+    pending: Vec<f32>,
+}
+
+impl OpusFrameEncoder {
+    pub fn new(sample_rate: u32) -> Result<Self> {
+        let encoder = OpusEncoder::new(sample_rate, OpusChannels::Mono, OpusApplication::Voip)
+            .context("failed to initialise Opus encoder")?;
+        Ok(Self {
+            encoder,
+            frame_samples: (sample_rate * FRAME_DURATION_MS / 1_000) as usize,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Добавляет моно-сэмплы и возвращает все целиком набранные пакеты.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Vec<Vec<u8>>> {
+        self.pending.extend_from_slice(samples);
+        let mut packets = Vec::new();
+        while self.pending.len() >= self.frame_samples {
+            let frame: Vec<f32> = self.pending.drain(..self.frame_samples).collect();
+            let mut packet = vec![0u8; MAX_PACKET_SIZE];
+            let len = self
+                .encoder
+                .encode_float(&frame, &mut packet)
+                .context("failed to encode Opus frame")?;
+            packet.truncate(len);
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+}
+
+/// Захват микрофона через устройство ввода по умолчанию.
+pub struct MicrophoneCapture {
+    _stream: Stream,
+    samples: Receiver<Vec<f32>>,
+    encoder: OpusFrameEncoder,
+    level: f32,
+}
+
+impl MicrophoneCapture {
+    pub fn open() -> Result<Self> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| anyhow!("no audio input device"))?;
+        let range = device
+            .supported_input_configs()
+            .context("failed to query input configs")?
+            .filter(|range| {
+                range.min_sample_rate().0 <= CAPTURE_SAMPLE_RATE
+                    && range.max_sample_rate().0 >= CAPTURE_SAMPLE_RATE
+            })
+            .min_by_key(|range| range.channels())
+            .ok_or_else(|| anyhow!("input device does not support 48 kHz"))?;
+        let format = range.sample_format();
+        let config: StreamConfig = range
+            .with_sample_rate(SampleRate(CAPTURE_SAMPLE_RATE))
+            .into();
+
+        let (tx, rx) = channel();
+        let stream = match format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, tx)?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, tx)?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, tx)?,
+            other => return Err(anyhow!("unsupported sample format {other}")),
+        };
+        stream.play().context("failed to start audio capture")?;
+
+        Ok(Self {
+            _stream: stream,
+            samples: rx,
+            encoder: OpusFrameEncoder::new(CAPTURE_SAMPLE_RATE)?,
+            level: 0.0,
+        })
+    }
+
+    /// Забирает накопленный звук и возвращает готовые Opus-пакеты.
+    pub fn drain(&mut self) -> Result<Vec<Vec<u8>>> {
+        let mut packets = Vec::new();
+        while let Ok(chunk) = self.samples.try_recv() {
+            if !chunk.is_empty() {
+                let peak = chunk.iter().fold(0.0_f32, |acc, s| acc.max(s.abs()));
+                self.level = peak.clamp(0.0, 1.0);
+            }
+            packets.extend(self.encoder.push(&chunk)?);
+        }
+        Ok(packets)
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+}
+
+/// Декодирует голосовое сообщение в интерливинговый 16-битный PCM.
+pub fn decode_voice_message(voice: &VoiceMessage) -> Result<Vec<i16>> {
+    let packets = voice
+        .decode_frames()
+        .context("voice message contains invalid base64 frames")?;
+    let channels = if voice.channels > 1 {
+        OpusChannels::Stereo
+    } else {
+        OpusChannels::Mono
+    };
+    let mut decoder = OpusDecoder::new(voice.sample_rate, channels)
+        .context("failed to initialise Opus decoder")?;
+    let channel_count = usize::from(voice.channels.max(1));
+    let mut samples = Vec::new();
+    let mut buffer = vec![0i16; 5_760 * channel_count];
+    for packet in &packets {
+        let decoded = decoder
+            .decode(packet, &mut buffer, false)
+            .context("failed to decode Opus frame")?;
+        samples.extend_from_slice(&buffer[..decoded * channel_count]);
+    }
+    Ok(samples)
+}
+
+/// Сохраняет голосовое сообщение в WAV (16 бит PCM) или, при `raw`, как
+/// поток Opus-пакетов с префиксом длины (u16 BE). Возвращает длительность.
+pub fn export_voice_message(voice: &VoiceMessage, path: &Path, raw: bool) -> Result<Duration> {
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("create {}", path.display()))?);
+    let duration = if raw {
+        let packets = voice
+            .decode_frames()
+            .context("voice message contains invalid base64 frames")?;
+        for packet in &packets {
+            let len = u16::try_from(packet.len()).context("Opus packet too large")?;
+            writer.write_all(&len.to_be_bytes())?;
+            writer.write_all(packet)?;
+        }
+        Duration::from_millis(u64::from(voice.frame_duration_ms) * packets.len() as u64)
+    } else {
+        let samples = decode_voice_message(voice)?;
+        let channels = voice.channels.max(1);
+        write_wav(&mut writer, voice.sample_rate, channels, &samples)?;
+        pcm_duration(samples.len(), voice.sample_rate, channels)
+    };
+    writer.flush()?;
+    Ok(duration)
+}
+
+fn pcm_duration(samples: usize, sample_rate: u32, channels: u8) -> Duration {
+    let frames = (samples / usize::from(channels.max(1))) as u64;
+    Duration::from_millis(frames * 1_000 / u64::from(sample_rate.max(1)))
+}
+
+/// Воспроизведение PCM через устройство вывода по умолчанию.
+pub struct VoicePlayback {
+    _stream: Stream,
+    position: Arc<AtomicUsize>,
+    total: usize,
+    envelope: Vec<u8>,
+    duration: Duration,
+}
+
+impl VoicePlayback {
+    pub fn start(voice: &VoiceMessage) -> Result<Self> {
+        let samples = decode_voice_message(voice)?;
+        if samples.is_empty() {
+            return Err(anyhow!("voice message has no audio"));
+        }
+        let source_channels = usize::from(voice.channels.max(1));
+        let mono: Vec<f32> = samples
+            .chunks(source_channels)
+            .map(|frame| {
+                frame.iter().map(|s| f32::from(*s) / 32_768.0).sum::<f32>() / source_channels as f32
+            })
+            .collect();
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| anyhow!("no audio output device"))?;
+        let range = device
+            .supported_output_configs()
+            .context("failed to query output configs")?
+            .filter(|range| {
+                range.min_sample_rate().0 <= voice.sample_rate
+                    && range.max_sample_rate().0 >= voice.sample_rate
+            })
+            .min_by_key(|range| range.channels())
+            .ok_or_else(|| anyhow!("output device does not support {} Hz", voice.sample_rate))?;
+        let format = range.sample_format();
+        let config: StreamConfig = range.with_sample_rate(SampleRate(voice.sample_rate)).into();
+
+        let frame_samples =
+            (voice.sample_rate * u32::from(voice.frame_duration_ms.max(1)) / 1_000) as usize;
+        let envelope = mono
+            .chunks(frame_samples.max(1))
+            .map(|chunk| {
+                let peak = chunk.iter().fold(0.0_f32, |acc, s| acc.max(s.abs()));
+                (peak.clamp(0.0, 1.0) * 255.0) as u8
+            })
+            .collect();
+        let duration = pcm_duration(mono.len(), voice.sample_rate, 1);
+        let total = mono.len();
+        let position = Arc::new(AtomicUsize::new(0));
+        let pcm = Arc::new(mono);
+        let stream = match format {
+            SampleFormat::F32 => build_output::<f32>(&device, &config, pcm, position.clone())?,
+            SampleFormat::I16 => build_output::<i16>(&device, &config, pcm, position.clone())?,
+            SampleFormat::U16 => build_output::<u16>(&device, &config, pcm, position.clone())?,
+            other => return Err(anyhow!("unsupported sample format {other}")),
+        };
+        stream.play().context("failed to start playback")?;
+
+        Ok(Self {
+            _stream: stream,
+            position,
+            total,
+            envelope,
+            duration,
+        })
+    }
+
+    pub fn progress(&self) -> f32 {
+        (self.position.load(Ordering::Relaxed) as f32 / self.total as f32).clamp(0.0, 1.0)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position.load(Ordering::Relaxed) >= self.total
+    }
+
+    /// Огибающая уже проигранной части (по одному значению на кадр).
+    pub fn played_envelope(&self) -> &[u8] {
+        let played = (self.progress() * self.envelope.len() as f32).ceil() as usize;
+        &self.envelope[..played.min(self.envelope.len())]
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+fn build_output<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    pcm: Arc<Vec<f32>>,
+    position: Arc<AtomicUsize>,
+) -> Result<Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = usize::from(config.channels.max(1));
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                let mut index = position.load(Ordering::Relaxed);
+                for frame in data.chunks_mut(channels) {
+                    let sample = pcm.get(index).copied().unwrap_or(0.0);
+                    index = (index + 1).min(pcm.len());
+                    for out in frame {
+                        *out = T::from_sample(sample);
+                    }
+                }
+                position.store(index, Ordering::Relaxed);
+            },
+            |err| tracing::warn!("audio playback error: {}", err),
+            None,
+        )
+        .context("failed to open audio output stream")
+}
+
+fn write_wav<W: Write>(
+    writer: &mut W,
+    sample_rate: u32,
+    channels: u8,
+    samples: &[i16],
+) -> Result<()> {
+    let channels = u16::from(channels);
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    tx: Sender<Vec<f32>>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = usize::from(config.channels.max(1));
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                let mono = data
+                    .chunks(channels)
+                    .map(|frame| {
+                        frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / channels as f32
+                    })
+                    .collect();
+                let _ = tx.send(mono);
+            },
+            |err| tracing::warn!("audio capture error: {}", err),
+            None,
+        )
+        .context("failed to open audio input stream")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_header_describes_pcm_payload() {
+        let mut out = Vec::new();
+        write_wav(&mut out, 48_000, 1, &[0, 1, -1]).expect("wav");
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(&out[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(out[24..28].try_into().unwrap()), 48_000);
+        assert_eq!(&out[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(out[40..44].try_into().unwrap()), 6);
+        assert_eq!(out.len(), 44 + 6);
+    }
+
+    #[test]
+    fn encoder_emits_one_packet_per_20ms() {
+        let mut encoder = OpusFrameEncoder::new(CAPTURE_SAMPLE_RATE).expect("encoder");
+        let samples = vec![0.0_f32; 960 * 2 + 100];
+        let packets = encoder.push(&samples).expect("encode");
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|packet| !packet.is_empty()));
+        assert_eq!(encoder.pending.len(), 100);
+    }
+}
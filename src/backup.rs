@@ -0,0 +1,239 @@
+use crate::config::ClientState;
+use crate::hexutil::{decode_hex, encode_hex};
+use crate::rest::normalize_server_url;
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+const BACKUP_FORMAT: &str = "commucat-backup";
+/// Версия формата `.ccbak`; файлы новее этой версии не восстанавливаются.
+pub const BACKUP_VERSION: u32 = 1;
+/// 64 МиБ, 3 прохода, один поток: подбор фразы дорог и на GPU.
+const KDF_PARAMS: KdfParams = KdfParams {
+    m_cost_kib: 64 * 1024,
+    t_cost: 3,
+    p_cost: 1,
+};
+/// Потолки параметров из файла: чужой `.ccbak` не должен занять гигабайты
+/// памяти или минуты процессора до проверки фразы.
+const MAX_M_COST_KIB: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 8;
+
+/// Файл `.ccbak`: профиль целиком (ключи, сервер, друзья, настройки)
+/// открытым JSON или зашифрованный парольной фразой.
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupFile {
+    format: String,
+    version: u32,
+    /// RFC 3339, только для человека.
+    created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<ClientState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed: Option<Sealed>,
+}
+
+/// ChaCha20-Poly1305 с ключом из argon2id по фразе и соли.
+#[derive(Debug, Serialize, Deserialize)]
+struct Sealed {
+    argon2id: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Параметры argon2id: память в КиБ, число проходов и потоков.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct KdfParams {
+    m_cost_kib: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl KdfParams {
+    fn argon2(self) -> Result<Argon2<'static>> {
+        if self.m_cost_kib > MAX_M_COST_KIB || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST
+        {
+            bail!(
+                "параметры argon2id в резервной копии слишком велики (m={} КиБ, t={}, p={})",
+                self.m_cost_kib,
+                self.t_cost,
+                self.p_cost
+            );
+        }
+        let params = Params::new(self.m_cost_kib, self.t_cost, self.p_cost, Some(32))
+            .map_err(|err| anyhow!("неверные параметры argon2id: {}", err))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Сериализует профиль в `.ccbak`; с `passphrase` содержимое шифруется.
+pub fn create(state: &ClientState, passphrase: Option<&str>) -> Result<String> {
+    create_with_params(state, passphrase, KDF_PARAMS)
+}
+
+fn create_with_params(
+    state: &ClientState,
+    passphrase: Option<&str>,
+    params: KdfParams,
+) -> Result<String> {
+    let (state, sealed) = match passphrase {
+        Some(passphrase) => {
+            let plain = serde_json::to_vec(state).context("serialize state")?;
+            (None, Some(seal(&plain, passphrase, params)?))
+        }
+        None => (Some(state.clone()), None),
+    };
+    let file = BackupFile {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        state,
+        sealed,
+    };
+    serde_json::to_string_pretty(&file).context("serialize backup")
+}
+
+/// Разбирает `.ccbak` и проверяет, что профиль пригоден для работы.
+pub fn open(data: &str, passphrase: Option<&str>) -> Result<ClientState> {
+    let file: BackupFile =
+        serde_json::from_str(data).context("файл не похож на резервную копию")?;
+    if file.format != BACKUP_FORMAT {
+        bail!("неизвестный формат резервной копии: {}", file.format);
+    }
+    if file.version > BACKUP_VERSION {
+        bail!(
+            "резервная копия версии {} создана более новым клиентом (поддерживается до {})",
+            file.version,
+            BACKUP_VERSION
+        );
+    }
+    let state = match (file.state, file.sealed) {
+        (Some(state), None) => state,
+        (None, Some(sealed)) => {
+            let passphrase = passphrase
+                .ok_or_else(|| anyhow!("резервная копия зашифрована; укажите --passphrase"))?;
+            let plain = unseal(&sealed, passphrase)?;
+            serde_json::from_slice(&plain).context("повреждённый профиль в резервной копии")?
+        }
+        _ => bail!("в резервной копии нет профиля"),
+    };
+    validate(&state)?;
+    Ok(state)
+}
+
+fn validate(state: &ClientState) -> Result<()> {
+    if state.device_id.trim().is_empty() {
+        bail!("в профиле нет device_id");
+    }
+    state
+        .device_keypair()
+        .context("в профиле повреждены ключи устройства")?;
+    normalize_server_url(&state.server_url).context("в профиле неверный server_url")?;
+    Ok(())
+}
+
+fn seal(plain: &[u8], passphrase: &str, params: KdfParams) -> Result<Sealed> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    getrandom::getrandom(&mut salt).map_err(|err| anyhow!("rng failure: {}", err))?;
+    getrandom::getrandom(&mut nonce).map_err(|err| anyhow!("rng failure: {}", err))?;
+    let key = derive_key(passphrase, &salt, params)?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|_| anyhow!("не удалось зашифровать резервную копию"))?;
+    Ok(Sealed {
+        argon2id: params,
+        salt: encode_hex(&salt),
+        nonce: encode_hex(&nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn unseal(sealed: &Sealed, passphrase: &str) -> Result<Vec<u8>> {
+    let salt = decode_hex(&sealed.salt).context("salt")?;
+    let nonce = decode_hex(&sealed.nonce).context("nonce")?;
+    if nonce.len() != 12 {
+        bail!("неверная длина nonce в резервной копии");
+    }
+    let ciphertext = BASE64.decode(&sealed.ciphertext).context("ciphertext")?;
+    let key = derive_key(passphrase, &salt, sealed.argon2id)?;
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("неверная парольная фраза или файл повреждён"))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    params
+        .argon2()?
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("argon2id: {}", err))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{FriendEntry, test_state};
+
+    /// Минимум argon2id, чтобы тесты не тратили 64 МиБ на каждый вызов.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        m_cost_kib: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn sample_state() -> ClientState {
+        let mut state = test_state();
+        state.user_handle = Some("alice".to_string());
+        state.user_id = Some("u-1".to_string());
+        state.friends = vec![FriendEntry::parse("u-42 @bob Bobby").unwrap()];
+        state
+    }
+
+    #[test]
+    fn profiles_round_trip_with_and_without_a_passphrase() {
+        let state = sample_state();
+        let plain = create_with_params(&state, None, TEST_PARAMS).unwrap();
+        let restored = open(&plain, None).unwrap();
+        assert_eq!(restored.private_key, state.private_key);
+        assert_eq!(restored.friends, state.friends);
+
+        let sealed = create_with_params(&state, Some("correct horse"), TEST_PARAMS).unwrap();
+        assert!(!sealed.contains(&state.private_key));
+        assert!(open(&sealed, None).is_err());
+        assert!(open(&sealed, Some("wrong")).is_err());
+        let restored = open(&sealed, Some("correct horse")).unwrap();
+        assert_eq!(restored.device_id, "device");
+        assert_eq!(restored.user_handle.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn newer_or_foreign_files_are_rejected() {
+        let mut value: serde_json::Value =
+            serde_json::from_str(&create_with_params(&sample_state(), None, TEST_PARAMS).unwrap())
+                .unwrap();
+        value["version"] = serde_json::json!(BACKUP_VERSION + 1);
+        assert!(open(&value.to_string(), None).is_err());
+        value["version"] = serde_json::json!(BACKUP_VERSION);
+        value["state"]["private_key"] = serde_json::json!("zz");
+        assert!(open(&value.to_string(), None).is_err());
+        assert!(open("{\"format\":\"other\",\"version\":1}", None).is_err());
+    }
+
+    #[test]
+    fn oversized_kdf_parameters_are_refused_before_deriving() {
+        let sealed = create_with_params(&sample_state(), Some("pw"), TEST_PARAMS).unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&sealed).unwrap();
+        value["sealed"]["argon2id"]["m_cost_kib"] = serde_json::json!(u32::MAX);
+        let err = open(&value.to_string(), Some("pw")).unwrap_err();
+        assert!(format!("{:#}", err).contains("слишком велики"));
+    }
+}
@@ -1,19 +1,48 @@
 use chrono::Utc;
 pub use commucat_proto::call::{CallAnswer, CallEnd, CallOffer, CallStats};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Сколько последних CallStats хранится на звонок: старые вытесняются.
+pub const MAX_QUALITY_SAMPLES: usize = 128;
 
 #[derive(Default)]
 pub struct CallManager {
     active_calls: HashMap<String, ActiveCall>,
 }
 
+/// Одна точка временного ряда качества звонка.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualitySample {
+    pub timestamp: Option<u64>,
+    /// Потери аудио, %.
+    pub audio_loss: Option<f32>,
+    /// Потери видео, %.
+    pub video_loss: Option<f32>,
+    pub jitter_ms: Option<u32>,
+    /// Суммарный битрейт аудио и видео, бит/с.
+    pub bitrate: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct ActiveCall {
     pub offer: CallOffer,
     pub answer: Option<CallAnswer>,
-    pub stats: Vec<CallStats>,
+    pub stats: VecDeque<CallStats>,
     pub started_at: Option<i64>,
     pub ended_at: Option<i64>,
+    /// Микрофон выключен: исходящие VoiceFrame не отправляются.
+    pub muted: bool,
+}
+
+impl ActiveCall {
+    /// Длительность разговора: до `ended_at` или до текущего момента.
+    /// `None`, пока соединение не установлено.
+    pub fn duration(&self) -> Option<Duration> {
+        let started = self.started_at?;
+        let end = self.ended_at.unwrap_or_else(|| Utc::now().timestamp());
+        Some(Duration::from_secs((end - started).max(0) as u64))
+    }
 }
 
 impl CallManager {
@@ -32,9 +61,10 @@ impl CallManager {
             .or_insert_with(|| ActiveCall {
                 offer,
                 answer: None,
-                stats: Vec::new(),
+                stats: VecDeque::new(),
                 started_at: None,
                 ended_at: None,
+                muted: false,
             });
     }
 
@@ -52,6 +82,17 @@ impl CallManager {
         }
     }
 
+    /// Отмечает поступление медиа. Если явного ответа не было, звонок
+    /// считается начавшимся с первого кадра.
+    pub fn mark_media(&mut self, call_id: &str) {
+        if let Some(call) = self.active_calls.get_mut(call_id)
+            && call.started_at.is_none()
+            && call.ended_at.is_none()
+        {
+            call.started_at = Some(Utc::now().timestamp());
+        }
+    }
+
     pub fn end_call(&mut self, call_id: &str) -> bool {
         if let Some(call) = self.active_calls.get_mut(call_id) {
             call.ended_at = Some(Utc::now().timestamp());
@@ -61,20 +102,57 @@ impl CallManager {
         }
     }
 
+    pub fn toggle_mute(&mut self, call_id: &str) -> Option<bool> {
+        let call = self.active_calls.get_mut(call_id)?;
+        call.muted = !call.muted;
+        Some(call.muted)
+    }
+
     pub fn push_stats(&mut self, stats: CallStats) {
         if let Some(call) = self.active_calls.get_mut(&stats.call_id) {
-            call.stats.push(stats);
-            if call.stats.len() > 256 {
-                call.stats.drain(..call.stats.len() - 256);
+            call.stats.push_back(stats);
+            while call.stats.len() > MAX_QUALITY_SAMPLES {
+                call.stats.pop_front();
             }
         }
     }
 
-    pub fn get_active_calls(&self) -> Vec<String> {
-        self.active_calls
+    /// Все известные звонки: сначала идущие, затем завершённые от новых к старым.
+    pub fn history(&self) -> Vec<&ActiveCall> {
+        let mut calls: Vec<&ActiveCall> = self.active_calls.values().collect();
+        calls.sort_by_key(|call| {
+            (
+                call.ended_at.is_some(),
+                std::cmp::Reverse(call.ended_at.or(call.started_at)),
+            )
+        });
+        calls
+    }
+
+    pub fn quality_series(&self, call_id: &str) -> Vec<QualitySample> {
+        let Some(call) = self.active_calls.get(call_id) else {
+            return Vec::new();
+        };
+        call.stats
             .iter()
-            .filter(|(_, call)| call.started_at.is_some() && call.ended_at.is_none())
-            .map(|(id, _)| id.clone())
+            .map(|stats| QualitySample {
+                timestamp: stats.timestamp,
+                audio_loss: stats
+                    .audio
+                    .as_ref()
+                    .map(|audio| audio.packet_loss.clamp(0.0, 1.0) * 100.0),
+                video_loss: stats
+                    .video
+                    .as_ref()
+                    .map(|video| video.packet_loss.clamp(0.0, 1.0) * 100.0),
+                jitter_ms: stats
+                    .audio
+                    .as_ref()
+                    .or(stats.video.as_ref())
+                    .map(|stream| stream.jitter_ms),
+                bitrate: stats.audio.as_ref().map_or(0, |audio| audio.bitrate)
+                    + stats.video.as_ref().map_or(0, |video| video.bitrate),
+            })
             .collect()
     }
 
@@ -82,3 +160,129 @@ impl CallManager {
         self.active_calls.get(call_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(call_id: &str) -> CallOffer {
+        CallOffer {
+            call_id: call_id.to_string(),
+            from: "alice".to_string(),
+            to: vec!["bob".to_string()],
+            media: Default::default(),
+            metadata: serde_json::Value::Null,
+            transport: None,
+            expires_at: None,
+            ephemeral_key: None,
+        }
+    }
+
+    #[test]
+    fn accept_then_stats_then_end_has_duration() {
+        let mut manager = CallManager::new();
+        manager.upsert_offer(offer("call-1"));
+        assert!(manager.get_call("call-1").unwrap().duration().is_none());
+        manager.accept_answer(CallAnswer {
+            call_id: "call-1".to_string(),
+            accept: true,
+            media: None,
+            transport: None,
+            reason: None,
+            metadata: serde_json::Value::Null,
+        });
+        manager.push_stats(CallStats {
+            call_id: "call-1".to_string(),
+            direction: commucat_proto::call::CallMediaDirection::Receive,
+            audio: None,
+            video: None,
+            timestamp: None,
+        });
+        let call = manager.active_calls.get_mut("call-1").unwrap();
+        call.started_at = call.started_at.map(|started| started - 42);
+        assert!(manager.end_call("call-1"));
+        let call = manager.get_call("call-1").unwrap();
+        assert_eq!(call.stats.len(), 1);
+        assert_eq!(call.duration(), Some(Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn offer_then_media_then_end_starts_on_first_frame() {
+        let mut manager = CallManager::new();
+        manager.upsert_offer(offer("call-2"));
+        manager.mark_media("call-2");
+        let started = manager.get_call("call-2").unwrap().started_at;
+        assert!(started.is_some());
+        manager.mark_media("call-2");
+        assert_eq!(manager.get_call("call-2").unwrap().started_at, started);
+        assert!(manager.end_call("call-2"));
+        assert!(manager.get_call("call-2").unwrap().duration().is_some());
+        manager.mark_media("call-2");
+        assert_eq!(manager.get_call("call-2").unwrap().started_at, started);
+    }
+
+    #[test]
+    fn quality_series_keeps_separate_streams() {
+        use commucat_proto::call::{CallMediaDirection, MediaStreamStats};
+
+        let stream = |bitrate, packet_loss, jitter_ms| MediaStreamStats {
+            bitrate,
+            packet_loss,
+            jitter_ms,
+            rtt_ms: None,
+            frames_per_second: None,
+            key_frames: None,
+        };
+        let mut manager = CallManager::new();
+        manager.upsert_offer(offer("call-3"));
+        manager.push_stats(CallStats {
+            call_id: "call-3".to_string(),
+            direction: CallMediaDirection::Receive,
+            audio: Some(stream(32_000, 0.25, 12)),
+            video: Some(stream(500_000, 0.5, 30)),
+            timestamp: Some(1),
+        });
+        manager.push_stats(CallStats {
+            call_id: "call-3".to_string(),
+            direction: CallMediaDirection::Receive,
+            audio: Some(stream(24_000, 0.0, 8)),
+            video: None,
+            timestamp: Some(2),
+        });
+        let series = manager.quality_series("call-3");
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].audio_loss, Some(25.0));
+        assert_eq!(series[0].video_loss, Some(50.0));
+        assert_eq!(series[0].jitter_ms, Some(12));
+        assert_eq!(series[0].bitrate, 532_000);
+        assert_eq!(series[1].video_loss, None);
+        assert_eq!(series[1].bitrate, 24_000);
+        assert!(manager.quality_series("missing").is_empty());
+
+        for timestamp in 0..MAX_QUALITY_SAMPLES as u64 + 10 {
+            manager.push_stats(CallStats {
+                call_id: "call-3".to_string(),
+                direction: CallMediaDirection::Receive,
+                audio: None,
+                video: None,
+                timestamp: Some(timestamp),
+            });
+        }
+        let series = manager.quality_series("call-3");
+        assert_eq!(series.len(), MAX_QUALITY_SAMPLES);
+        assert_eq!(
+            series.last().unwrap().timestamp,
+            Some(MAX_QUALITY_SAMPLES as u64 + 9)
+        );
+    }
+
+    #[test]
+    fn toggle_mute_flips_state_for_known_calls() {
+        let mut manager = CallManager::new();
+        manager.upsert_offer(offer("call-1"));
+        assert_eq!(manager.toggle_mute("call-1"), Some(true));
+        assert!(manager.get_call("call-1").unwrap().muted);
+        assert_eq!(manager.toggle_mute("call-1"), Some(false));
+        assert_eq!(manager.toggle_mute("missing"), None);
+    }
+}
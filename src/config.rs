@@ -1,11 +1,48 @@
 use crate::hexutil::{decode_hex32, encode_hex};
+use crate::media::{DEFAULT_MAX_VIDEO_HEIGHT, DEFAULT_MAX_VIDEO_WIDTH};
 use anyhow::{Context, Result, anyhow};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, TimeZone, Utc};
 use commucat_crypto::{DeviceCertificate, DeviceKeyPair};
 use directories::BaseDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_CERTIFICATE_WARN_HOURS: u64 = 72;
+
+/// Почему не удалось взять данные из профиля; по варианту CLI выбирает код выхода.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileError {
+    Missing,
+    Invalid,
+    NoSession,
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProfileError::Missing => "state file not found",
+            ProfileError::Invalid => "invalid state",
+            ProfileError::NoSession => {
+                "сессионный токен не найден: подключитесь (:connect) или передайте --session"
+            }
+        })
+    }
+}
+const DEFAULT_KEEPALIVE_TIMEOUT_SECS: u64 = 90;
+const DEFAULT_ASSIST_FEC_MTU: u16 = 1200;
+const DEFAULT_ASSIST_FEC_OVERHEAD: f32 = 0.18;
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+pub const DEFAULT_MESSAGE_HISTORY_LIMIT: usize = 500;
+pub const MIN_MESSAGE_HISTORY_LIMIT: usize = 10;
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientState {
@@ -18,11 +55,16 @@ pub struct ClientState {
     pub prologue: String,
     pub tls_ca_path: Option<String>,
     pub server_static: Option<String>,
+    /// Без значения по умолчанию: отключённая проверка TLS всегда видна в файле.
     pub insecure: bool,
     pub presence_state: String,
     pub presence_interval_secs: u64,
     pub traceparent: Option<String>,
     #[serde(default)]
+    pub auto_trace: bool,
+    #[serde(default)]
+    pub connect_timeout_secs: u64,
+    #[serde(default)]
     pub user_handle: Option<String>,
     #[serde(default)]
     pub user_display_name: Option<String>,
@@ -52,6 +94,220 @@ pub struct ClientState {
     pub device_certificate_expires_at: Option<i64>,
     #[serde(default)]
     pub device_ca_public: Option<String>,
+    #[serde(default)]
+    pub certificate_warn_hours: u64,
+    #[serde(default)]
+    pub ui_theme: Option<String>,
+    #[serde(default = "default_true")]
+    pub animations_enabled: bool,
+    #[serde(default = "default_true")]
+    pub sound_enabled: bool,
+    #[serde(default = "default_true")]
+    pub emoji_mode: bool,
+    #[serde(default = "default_true")]
+    pub markdown_enabled: bool,
+    /// Подключаться сразу при запуске TUI.
+    #[serde(default = "default_true")]
+    pub auto_connect: bool,
+    /// Упоминания пробиваются сквозь заглушенные каналы.
+    #[serde(default = "default_true")]
+    pub mentions_break_mute: bool,
+    /// Показывать имя с сервера вместо локального псевдонима друга.
+    #[serde(default)]
+    pub prefer_server_names: bool,
+    /// Уведомлять о каждой смене статуса, без сводки и прореживания.
+    #[serde(default)]
+    pub presence_verbose: bool,
+    /// Заставка с котиком при запуске TUI.
+    #[serde(default = "default_true")]
+    pub show_splash: bool,
+    /// Вкладка, открытая при последнем выходе из TUI (`chat`, `friends`, …).
+    #[serde(default)]
+    pub last_view: Option<String>,
+    /// Команда для звука уведомлений; без неё звучит терминальный bell.
+    #[serde(default)]
+    pub sound_command: Option<String>,
+    /// Команды для отдельных хуков (`success`, `warning`, `error`, `mention`).
+    #[serde(default)]
+    pub sound_commands: BTreeMap<String, String>,
+    /// Формат времени `strftime` для отображения в локальной зоне.
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+    /// `relative` показывает «5m ago» для свежих событий вместо `time_format`.
+    #[serde(default)]
+    pub time_style: TimeStyle,
+    /// Короткий отпечаток закреплённого `server_static` для отображения.
+    #[serde(default)]
+    pub server_static_fingerprint: Option<String>,
+    /// Через сколько секунд без входящих кадров соединение считается мёртвым (0 — не проверять).
+    #[serde(default = "default_keepalive_timeout")]
+    pub keepalive_timeout_secs: u64,
+    /// STUN-сервер (`host:port`) для внешнего адреса в `/assist`.
+    #[serde(default)]
+    pub assist_stun_server: Option<String>,
+    #[serde(default = "default_true")]
+    pub assist_prefer_reality: bool,
+    #[serde(default = "default_assist_fec_mtu")]
+    pub assist_fec_mtu: u16,
+    #[serde(default = "default_assist_fec_overhead")]
+    pub assist_fec_overhead: f32,
+    /// Потолок разрешения входящего видео; кадры больше отбрасываются.
+    #[serde(default = "default_max_video_width")]
+    pub max_video_width: u32,
+    #[serde(default = "default_max_video_height")]
+    pub max_video_height: u32,
+    /// Сколько сообщений на канал держать в памяти TUI (не меньше 10).
+    #[serde(default = "default_message_history_limit")]
+    pub message_history_limit: usize,
+    /// Предельный размер отправляемого и принимаемого файла, байт.
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_keepalive_timeout() -> u64 {
+    DEFAULT_KEEPALIVE_TIMEOUT_SECS
+}
+
+fn default_assist_fec_mtu() -> u16 {
+    DEFAULT_ASSIST_FEC_MTU
+}
+
+fn default_assist_fec_overhead() -> f32 {
+    DEFAULT_ASSIST_FEC_OVERHEAD
+}
+
+fn default_max_video_width() -> u32 {
+    DEFAULT_MAX_VIDEO_WIDTH
+}
+
+fn default_max_video_height() -> u32 {
+    DEFAULT_MAX_VIDEO_HEIGHT
+}
+
+fn default_message_history_limit() -> usize {
+    DEFAULT_MESSAGE_HISTORY_LIMIT
+}
+
+fn default_max_attachment_bytes() -> u64 {
+    DEFAULT_MAX_ATTACHMENT_BYTES
+}
+
+fn default_time_format() -> String {
+    DEFAULT_TIME_FORMAT.to_string()
+}
+
+/// Проверяет, что строка формата времени разбирается chrono без ошибок.
+pub fn is_valid_time_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+/// Как показывать время событий в TUI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeStyle {
+    #[default]
+    Absolute,
+    Relative,
+}
+
+impl TimeStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeStyle::Absolute => "absolute",
+            TimeStyle::Relative => "relative",
+        }
+    }
+}
+
+/// Относительное время события: «just now», «5m ago», «3h ago» в пределах суток,
+/// «yesterday <absolute>» для вчерашней даты, дальше — `absolute` как есть.
+pub fn relative_time<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    at: &DateTime<Tz>,
+    absolute: &str,
+) -> String {
+    let age = now.clone().signed_duration_since(at.clone());
+    let minutes = age.num_minutes();
+    if age.num_seconds() < -60 {
+        absolute.to_string()
+    } else if minutes < 1 {
+        "just now".to_string()
+    } else if minutes < 60 {
+        format!("{}m ago", minutes)
+    } else if age.num_hours() < 24 {
+        format!("{}h ago", age.num_hours())
+    } else if now.date_naive().pred_opt() == Some(at.date_naive()) {
+        format!("yesterday {}", absolute)
+    } else {
+        absolute.to_string()
+    }
+}
+
+/// Короткий отпечаток ключа сервера: первые 8 байт hex группами по 4 символа.
+pub fn server_key_fingerprint(key_hex: &str) -> String {
+    let normalized: Vec<char> = key_hex
+        .trim()
+        .chars()
+        .filter(|ch| ch.is_ascii_hexdigit())
+        .take(16)
+        .map(|ch| ch.to_ascii_lowercase())
+        .collect();
+    normalized
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Результат сверки ключа, предъявленного сервером, с закреплённым (TOFU).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerKeyCheck {
+    /// Ключ ещё не закреплён — закрепляем предъявленный.
+    FirstUse,
+    Match,
+    Changed,
+}
+
+pub fn check_server_key(pinned: Option<&str>, presented: &str) -> ServerKeyCheck {
+    match pinned.map(str::trim) {
+        None | Some("") => ServerKeyCheck::FirstUse,
+        Some(pinned) if pinned.eq_ignore_ascii_case(presented.trim()) => ServerKeyCheck::Match,
+        Some(_) => ServerKeyCheck::Changed,
+    }
+}
+
+/// Состояние сертификата устройства относительно текущего времени.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateStatus {
+    Valid,
+    ExpiringSoon(Duration),
+    Expired,
+    None,
+}
+
+/// Положение момента `now` относительно окна `issued_at..expires_at` сертификата.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateWindow {
+    /// Сертификат начнёт действовать через указанное время.
+    NotYetValid(Duration),
+    /// Сертификат действует; указано оставшееся время.
+    Valid(Duration),
+    /// Сертификат истёк указанное время назад.
+    Expired(Duration),
+}
+
+pub fn certificate_window(issued_at: i64, expires_at: i64, now: i64) -> CertificateWindow {
+    if now < issued_at {
+        CertificateWindow::NotYetValid(Duration::from_secs((issued_at - now) as u64))
+    } else if now >= expires_at {
+        CertificateWindow::Expired(Duration::from_secs((now - expires_at) as u64))
+    } else {
+        CertificateWindow::Valid(Duration::from_secs((expires_at - now) as u64))
+    }
 }
 
 /// Параметры формирования ClientState без чтения из файла.
@@ -64,6 +320,99 @@ pub struct FriendEntry {
     pub alias: Option<String>,
 }
 
+impl FriendEntry {
+    /// Разбирает ввод TUI `<user_id> [@handle] [псевдоним…]`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.split_whitespace().peekable();
+        let user_id = parts.next()?.to_string();
+        let handle = parts
+            .next_if(|part| part.len() > 1 && part.starts_with('@'))
+            .map(|part| part[1..].to_string());
+        let alias = parts.collect::<Vec<_>>().join(" ");
+        Some(Self {
+            user_id,
+            handle,
+            alias: (!alias.is_empty()).then_some(alias),
+        })
+    }
+
+    /// Имя для списка: псевдоним, затем handle, затем user_id.
+    pub fn label(&self) -> &str {
+        self.alias
+            .as_deref()
+            .or(self.handle.as_deref())
+            .unwrap_or(&self.user_id)
+    }
+}
+
+/// Чем список друзей `after` отличается от `before`, по user_id.
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct FriendsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Тот же user_id, но другие handle или псевдоним.
+    pub updated: Vec<String>,
+}
+
+impl FriendsDiff {
+    pub fn between(before: &[FriendEntry], after: &[FriendEntry]) -> Self {
+        let find = |list: &[FriendEntry], user_id: &str| {
+            list.iter()
+                .find(|friend| friend.user_id == user_id)
+                .cloned()
+        };
+        let mut diff = Self::default();
+        for friend in after {
+            match find(before, &friend.user_id) {
+                None => diff.added.push(friend.user_id.clone()),
+                Some(old) if old != *friend => diff.updated.push(friend.user_id.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .iter()
+            .filter(|friend| find(after, &friend.user_id).is_none())
+            .map(|friend| friend.user_id.clone())
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+
+    /// Строки `+ id`, `- id`, `~ id` для текстового вывода.
+    pub fn lines(&self) -> Vec<String> {
+        let tagged = |mark: char, ids: &[String]| {
+            ids.iter()
+                .map(move |id| format!("{} {}", mark, id))
+                .collect::<Vec<_>>()
+        };
+        [
+            tagged('+', &self.added),
+            tagged('-', &self.removed),
+            tagged('~', &self.updated),
+        ]
+        .concat()
+    }
+}
+
+/// Имя собеседника: локальный псевдоним, затем имя с сервера, затем handle.
+/// `prefer_server_names` ставит имя с сервера впереди псевдонима.
+pub fn pick_display_name<'a>(
+    alias: Option<&'a str>,
+    server_name: Option<&'a str>,
+    handle: Option<&'a str>,
+    prefer_server_names: bool,
+) -> Option<&'a str> {
+    let (first, second) = if prefer_server_names {
+        (server_name, alias)
+    } else {
+        (alias, server_name)
+    };
+    first.or(second).or(handle)
+}
+
 pub struct ClientStateParams {
     pub device_id: String,
     pub server_url: String,
@@ -77,6 +426,8 @@ pub struct ClientStateParams {
     pub presence_state: String,
     pub presence_interval_secs: u64,
     pub traceparent: Option<String>,
+    pub auto_trace: bool,
+    pub connect_timeout_secs: u64,
     pub user_handle: Option<String>,
     pub user_display_name: Option<String>,
     pub user_avatar_url: Option<String>,
@@ -91,8 +442,8 @@ pub struct ClientStateParams {
 impl ClientState {
     pub fn load() -> Result<Self> {
         let path = state_path()?;
-        let data = fs::read_to_string(path).context("state file not found")?;
-        let mut state: ClientState = serde_json::from_str(&data).context("invalid state")?;
+        let data = fs::read_to_string(path).context(ProfileError::Missing)?;
+        let mut state: ClientState = serde_json::from_str(&data).context(ProfileError::Invalid)?;
         if state.noise_pattern.is_empty() {
             state.noise_pattern = "XK".to_string();
         }
@@ -105,24 +456,37 @@ impl ClientState {
         if state.presence_state.is_empty() {
             state.presence_state = "online".to_string();
         }
+        if state.connect_timeout_secs == 0 {
+            state.connect_timeout_secs = 15;
+        }
+        if state.certificate_warn_hours == 0 {
+            state.certificate_warn_hours = DEFAULT_CERTIFICATE_WARN_HOURS;
+        }
         Ok(state)
     }
 
     pub fn save(&self) -> Result<()> {
         let path = state_path()?;
-        if let Some(parent) = path.parent() {
-            if parent.exists() && !parent.is_dir() {
-                return Err(anyhow!(format!(
-                    "state directory {} exists and is not a directory",
-                    parent.display()
-                )));
-            }
-            fs::create_dir_all(parent).context("state directory")?;
-        }
+        ensure_state_dir(&path)?;
         let payload = serde_json::to_string_pretty(self).context("serialize state")?;
         fs::write(path, payload).context("write state")
     }
 
+    /// Атомарно перезаписывает состояние, сохраняя прежнюю версию в `client.json.bak`.
+    pub fn save_with_backup(&self) -> Result<PathBuf> {
+        let path = state_path()?;
+        ensure_state_dir(&path)?;
+        let backup = path.with_extension("json.bak");
+        if path.exists() {
+            fs::copy(&path, &backup).context("backup state")?;
+        }
+        let payload = serde_json::to_string_pretty(self).context("serialize state")?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, payload).context("write state")?;
+        fs::rename(&tmp, &path).context("replace state")?;
+        Ok(backup)
+    }
+
     pub fn device_keypair(&self) -> Result<DeviceKeyPair> {
         let private = decode_hex32(&self.private_key)?;
         let public = decode_hex32(&self.public_key)?;
@@ -143,6 +507,8 @@ impl ClientState {
             presence_state,
             presence_interval_secs,
             traceparent,
+            auto_trace,
+            connect_timeout_secs,
             user_handle,
             user_display_name,
             user_avatar_url,
@@ -153,6 +519,7 @@ impl ClientState {
             device_certificate,
             device_ca_public,
         } = params;
+        let server_static_fingerprint = server_static.as_deref().map(server_key_fingerprint);
         let device_certificate_json = device_certificate
             .as_ref()
             .map(|cert| serde_json::to_string(cert).expect("certificate serialization"));
@@ -180,6 +547,8 @@ impl ClientState {
             presence_state,
             presence_interval_secs,
             traceparent,
+            auto_trace,
+            connect_timeout_secs,
             user_handle,
             user_display_name,
             user_avatar_url,
@@ -195,9 +564,42 @@ impl ClientState {
             device_certificate_issued_at,
             device_certificate_expires_at,
             device_ca_public,
+            certificate_warn_hours: DEFAULT_CERTIFICATE_WARN_HOURS,
+            ui_theme: None,
+            animations_enabled: true,
+            sound_enabled: true,
+            emoji_mode: true,
+            markdown_enabled: true,
+            auto_connect: true,
+            mentions_break_mute: true,
+            prefer_server_names: false,
+            presence_verbose: false,
+            show_splash: true,
+            last_view: None,
+            sound_command: None,
+            sound_commands: BTreeMap::new(),
+            time_format: default_time_format(),
+            time_style: TimeStyle::Absolute,
+            server_static_fingerprint,
+            keepalive_timeout_secs: DEFAULT_KEEPALIVE_TIMEOUT_SECS,
+            assist_stun_server: None,
+            assist_prefer_reality: true,
+            assist_fec_mtu: DEFAULT_ASSIST_FEC_MTU,
+            assist_fec_overhead: DEFAULT_ASSIST_FEC_OVERHEAD,
+            max_video_width: DEFAULT_MAX_VIDEO_WIDTH,
+            max_video_height: DEFAULT_MAX_VIDEO_HEIGHT,
+            message_history_limit: DEFAULT_MESSAGE_HISTORY_LIMIT,
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
         }
     }
 
+    /// Закрепляет ключ сервера вместе с отпечатком.
+    pub fn pin_server_key(&mut self, key_hex: &str) {
+        let key = key_hex.trim().to_ascii_lowercase();
+        self.server_static_fingerprint = Some(server_key_fingerprint(&key));
+        self.server_static = Some(key);
+    }
+
     pub fn friends(&self) -> &[FriendEntry] {
         &self.friends
     }
@@ -206,15 +608,18 @@ impl ClientState {
         self.friends = friends;
     }
 
-    pub fn upsert_friend(&mut self, entry: FriendEntry) {
+    /// Добавляет или обновляет друга; возвращает `true`, если запись новая.
+    pub fn upsert_friend(&mut self, entry: FriendEntry) -> bool {
         if let Some(existing) = self
             .friends
             .iter_mut()
             .find(|friend| friend.user_id == entry.user_id)
         {
             *existing = entry;
+            false
         } else {
             self.friends.push(entry);
+            true
         }
     }
 
@@ -253,6 +658,36 @@ impl ClientState {
         Ok(())
     }
 
+    /// Есть ли у устройства сертификат, срок которого ещё не истёк.
+    pub fn has_valid_certificate(&self) -> bool {
+        matches!(
+            self.certificate_status(),
+            CertificateStatus::Valid | CertificateStatus::ExpiringSoon(_)
+        )
+    }
+
+    pub fn certificate_status(&self) -> CertificateStatus {
+        self.certificate_status_at(Utc::now().timestamp())
+    }
+
+    fn certificate_status_at(&self, now: i64) -> CertificateStatus {
+        if self.device_certificate.is_none() {
+            return CertificateStatus::None;
+        }
+        let Some(expires_at) = self.device_certificate_expires_at else {
+            return CertificateStatus::Valid;
+        };
+        if expires_at <= now {
+            return CertificateStatus::Expired;
+        }
+        let remaining = (expires_at - now) as u64;
+        if remaining <= self.certificate_warn_hours.saturating_mul(3600) {
+            CertificateStatus::ExpiringSoon(Duration::from_secs(remaining))
+        } else {
+            CertificateStatus::Valid
+        }
+    }
+
     pub fn clear_certificate(&mut self) {
         self.device_certificate = None;
         self.device_certificate_serial = None;
@@ -262,43 +697,259 @@ impl ClientState {
     }
 }
 
-pub fn state_path() -> Result<PathBuf> {
-    if let Ok(path) = env::var("COMMUCAT_CLIENT_HOME") {
-        return Ok(PathBuf::from(path).join("client.json"));
+fn ensure_state_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if parent.exists() && !parent.is_dir() {
+            return Err(anyhow!(format!(
+                "state directory {} exists and is not a directory",
+                parent.display()
+            )));
+        }
+        fs::create_dir_all(parent).context("state directory")?;
+    }
+    Ok(())
+}
+
+/// Переопределяет каталог профиля для всего процесса (глобальный `--config-dir`).
+pub fn set_config_dir(path: PathBuf) -> Result<()> {
+    CONFIG_DIR
+        .set(path)
+        .map_err(|_| anyhow!("config directory already set"))
+}
+
+/// `--config-dir` или `COMMUCAT_CLIENT_HOME`: тогда все файлы клиента,
+/// и настройки, и данные, и кэш, лежат в этом одном каталоге.
+fn home_override() -> Option<PathBuf> {
+    CONFIG_DIR
+        .get()
+        .cloned()
+        .or_else(|| env::var_os("COMMUCAT_CLIENT_HOME").map(PathBuf::from))
+}
+
+/// Каталог клиента одного вида: переопределение, системный каталог
+/// из `BaseDirs` или `~/<fallback>`.
+fn base_dir(pick: fn(&BaseDirs) -> &Path, fallback: &str) -> Result<PathBuf> {
+    if let Some(path) = home_override() {
+        return Ok(path);
     }
 
     if let Some(base_dirs) = BaseDirs::new() {
-        return Ok(base_dirs.config_dir().join("commucat").join("client.json"));
+        return Ok(pick(&base_dirs).join("commucat"));
     }
 
     if let Ok(home) = env::var("HOME") {
-        return Ok(Path::new(&home)
-            .join(".config")
-            .join("commucat")
-            .join("client.json"));
+        return Ok(Path::new(&home).join(fallback).join("commucat"));
     }
 
     Err(anyhow!("unable to determine state directory"))
 }
 
+/// Настройки: `client.json` и его копия. `--config-dir`, затем
+/// `COMMUCAT_CLIENT_HOME`, системный каталог конфигурации или `~/.config`.
+pub fn config_dir() -> Result<PathBuf> {
+    base_dir(BaseDirs::config_dir, ".config")
+}
+
+/// Накопленные данные (отметки непрочитанного и т. п.), `~/.local/share`.
+pub fn data_dir() -> Result<PathBuf> {
+    base_dir(BaseDirs::data_dir, ".local/share")
+}
+
+/// То, что можно удалить без потерь (журналы кадров), `~/.cache`.
+pub fn cache_dir() -> Result<PathBuf> {
+    base_dir(BaseDirs::cache_dir, ".cache")
+}
+
+pub fn data_path(name: &str) -> Result<PathBuf> {
+    Ok(data_dir()?.join(name))
+}
+
+pub fn cache_path(name: &str) -> Result<PathBuf> {
+    Ok(cache_dir()?.join(name))
+}
+
+pub fn state_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("client.json"))
+}
+
+fn docs_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("docs")
+}
+
 pub fn docs_path(lang: &str) -> Result<PathBuf> {
-    let file = match lang {
-        "ru" => "docs/README.ru.md",
-        "en" => "docs/README.en.md",
-        other => return Err(anyhow!(format!("unsupported language: {}", other))),
-    };
-    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+    if lang.is_empty()
+        || !lang
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+        return Err(anyhow!(format!("unsupported language: {}", lang)));
+    }
+    let path = docs_dir().join(format!("README.{}.md", lang));
     if path.exists() {
         Ok(path)
     } else {
-        Err(anyhow!("documentation not found"))
+        Err(anyhow!(format!(
+            "documentation not found for language: {}",
+            lang
+        )))
     }
 }
 
+/// Коды языков, для которых в `docs/` есть `README.<lang>.md`.
+pub fn available_docs() -> Result<Vec<String>> {
+    list_docs_in(&docs_dir())
+}
+
+fn list_docs_in(dir: &Path) -> Result<Vec<String>> {
+    let entries = fs::read_dir(dir).context("documentation directory not found")?;
+    let mut langs: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let lang = name.strip_prefix("README.")?.strip_suffix(".md")?;
+            (!lang.is_empty()).then(|| lang.to_string())
+        })
+        .collect();
+    langs.sort();
+    Ok(langs)
+}
+
+/// Язык документации по умолчанию: `COMMUCAT_LANG`, затем локаль из `LANG`, иначе `ru`.
+pub fn default_docs_lang() -> String {
+    env::var("COMMUCAT_LANG")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| value.trim().to_string())
+        .or_else(|| {
+            env::var("LANG")
+                .ok()
+                .and_then(|value| lang_from_locale(&value))
+        })
+        .unwrap_or_else(|| "ru".to_string())
+}
+
+/// `ru_RU.UTF-8` → `ru`; `C` и `POSIX` языка не задают.
+fn lang_from_locale(locale: &str) -> Option<String> {
+    let lang = locale
+        .split(['_', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if lang.is_empty() || lang == "c" || lang == "posix" {
+        None
+    } else {
+        Some(lang)
+    }
+}
+
+/// Профиль для тестов: example.org, ключи `[1; 32]`/`[2; 32]`, без
+/// сессии и друзей. Тесты меняют нужные поля сами.
+#[cfg(test)]
+pub(crate) fn test_state() -> ClientState {
+    ClientState::from_params(ClientStateParams {
+        device_id: "device".to_string(),
+        server_url: "https://example.org:8443".to_string(),
+        domain: "example.org".to_string(),
+        keys: DeviceKeyPair {
+            public: [1u8; 32],
+            private: [2u8; 32],
+        },
+        pattern: "XK".to_string(),
+        prologue: "commucat".to_string(),
+        tls_ca_path: None,
+        server_static: None,
+        insecure: false,
+        presence_state: "online".to_string(),
+        presence_interval_secs: 30,
+        traceparent: None,
+        auto_trace: false,
+        connect_timeout_secs: 15,
+        user_handle: None,
+        user_display_name: None,
+        user_avatar_url: None,
+        user_id: None,
+        session_token: None,
+        device_name: None,
+        friends: Vec::new(),
+        device_certificate: None,
+        device_ca_public: None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn time_format_validation() {
+        assert!(is_valid_time_format(DEFAULT_TIME_FORMAT));
+        assert!(is_valid_time_format("%Y-%m-%d %H:%M:%S"));
+        assert!(!is_valid_time_format("%H:%Q"));
+    }
+
+    #[test]
+    fn recent_times_are_humanized() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 23, 0, 0).unwrap();
+        let ago = |secs: i64| now - chrono::Duration::seconds(secs);
+        assert_eq!(relative_time(&now, &ago(20), "22:59"), "just now");
+        assert_eq!(relative_time(&now, &ago(-5), "23:00"), "just now");
+        assert_eq!(relative_time(&now, &ago(5 * 60), "22:55"), "5m ago");
+        assert_eq!(relative_time(&now, &ago(3 * 3600), "20:00"), "3h ago");
+        assert_eq!(
+            relative_time(&now, &ago(25 * 3600), "22:00"),
+            "yesterday 22:00"
+        );
+        assert_eq!(relative_time(&now, &ago(3 * 86400), "05-07"), "05-07");
+        assert_eq!(relative_time(&now, &ago(-3600), "00:00"), "00:00");
+    }
+
+    #[test]
+    fn friend_input_is_parsed() {
+        let friend = FriendEntry::parse(" u-42 @bob Bobby Tables ").unwrap();
+        assert_eq!(friend.user_id, "u-42");
+        assert_eq!(friend.handle.as_deref(), Some("bob"));
+        assert_eq!(friend.label(), "Bobby Tables");
+        let bare = FriendEntry::parse("u-7 Alice").unwrap();
+        assert_eq!(bare.handle, None);
+        assert_eq!(bare.alias.as_deref(), Some("Alice"));
+        assert_eq!(FriendEntry::parse("u-8").unwrap().label(), "u-8");
+        assert!(FriendEntry::parse("   ").is_none());
+    }
+
+    #[test]
+    fn friend_lists_are_diffed_by_user_id() {
+        let before = [
+            FriendEntry::parse("u-1 @ann").unwrap(),
+            FriendEntry::parse("u-2 Bob").unwrap(),
+            FriendEntry::parse("u-3").unwrap(),
+        ];
+        let after = [
+            FriendEntry::parse("u-1 @ann").unwrap(),
+            FriendEntry::parse("u-2 Robert").unwrap(),
+            FriendEntry::parse("u-4").unwrap(),
+        ];
+        let diff = FriendsDiff::between(&before, &after);
+        assert_eq!(diff.lines(), ["+ u-4", "- u-3", "~ u-2"]);
+        assert!(FriendsDiff::between(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn aliases_win_over_server_names_unless_inverted() {
+        let (alias, server, handle) = (Some("Mom"), Some("Jane Doe"), Some("jane"));
+        assert_eq!(pick_display_name(alias, server, handle, false), Some("Mom"));
+        assert_eq!(
+            pick_display_name(alias, server, handle, true),
+            Some("Jane Doe")
+        );
+        assert_eq!(
+            pick_display_name(None, server, handle, false),
+            Some("Jane Doe")
+        );
+        assert_eq!(pick_display_name(alias, None, handle, true), Some("Mom"));
+        assert_eq!(pick_display_name(None, None, handle, false), Some("jane"));
+        assert_eq!(pick_display_name(None, None, None, true), None);
+    }
+
     #[test]
     fn build_state_from_keys() {
         let keys = DeviceKeyPair {
@@ -318,6 +969,8 @@ mod tests {
             presence_state: "online".to_string(),
             presence_interval_secs: 30,
             traceparent: None,
+            auto_trace: false,
+            connect_timeout_secs: 15,
             user_handle: Some("alice".to_string()),
             user_display_name: None,
             user_avatar_url: None,
@@ -335,4 +988,95 @@ mod tests {
         assert_eq!(pair.private, [2u8; 32]);
         assert_eq!(state.user_handle.as_deref(), Some("alice"));
     }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let mut value = serde_json::to_value(test_state()).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        for name in [
+            "message_history_limit",
+            "auto_connect",
+            "last_view",
+            "mentions_break_mute",
+        ] {
+            fields.remove(name);
+        }
+        let restored: ClientState = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            restored.message_history_limit,
+            DEFAULT_MESSAGE_HISTORY_LIMIT
+        );
+        assert!(restored.auto_connect);
+        assert_eq!(restored.last_view, None);
+        assert!(restored.mentions_break_mute);
+    }
+
+    #[test]
+    fn certificate_status_windows() {
+        let mut state = test_state();
+        assert_eq!(state.certificate_status_at(1_000), CertificateStatus::None);
+        state.device_certificate = Some("{}".to_string());
+        state.device_certificate_expires_at = Some(1_000 + 100 * 3600);
+        assert_eq!(state.certificate_status_at(1_000), CertificateStatus::Valid);
+        assert_eq!(
+            state.certificate_status_at(1_000 + 30 * 3600),
+            CertificateStatus::ExpiringSoon(Duration::from_secs(70 * 3600))
+        );
+        assert_eq!(
+            state.certificate_status_at(1_000 + 100 * 3600),
+            CertificateStatus::Expired
+        );
+    }
+
+    #[test]
+    fn certificate_window_bounds() {
+        assert_eq!(
+            certificate_window(100, 200, 40),
+            CertificateWindow::NotYetValid(Duration::from_secs(60))
+        );
+        assert_eq!(
+            certificate_window(100, 200, 100),
+            CertificateWindow::Valid(Duration::from_secs(100))
+        );
+        assert_eq!(
+            certificate_window(100, 200, 200),
+            CertificateWindow::Expired(Duration::from_secs(0))
+        );
+    }
+
+    #[test]
+    fn locale_maps_to_docs_language() {
+        assert_eq!(lang_from_locale("ru_RU.UTF-8").as_deref(), Some("ru"));
+        assert_eq!(lang_from_locale("de").as_deref(), Some("de"));
+        assert_eq!(lang_from_locale("C.UTF-8"), None);
+        assert_eq!(lang_from_locale("POSIX"), None);
+        assert!(docs_path("../secret").is_err());
+    }
+
+    #[test]
+    fn docs_listing_collects_language_codes() {
+        let dir = env::temp_dir().join(format!("commucat-docs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["README.en.md", "README.ru.md", "README.md", "notes.md"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let langs = list_docs_in(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(langs, ["en", "ru"]);
+    }
+
+    #[test]
+    fn server_key_is_pinned_on_first_use() {
+        let key = "AB".repeat(32);
+        assert_eq!(check_server_key(None, &key), ServerKeyCheck::FirstUse);
+        assert_eq!(
+            check_server_key(Some(&key.to_lowercase()), &key),
+            ServerKeyCheck::Match
+        );
+        assert_eq!(
+            check_server_key(Some(&"cd".repeat(32)), &key),
+            ServerKeyCheck::Changed
+        );
+        assert_eq!(server_key_fingerprint(&key), "abab:abab:abab:abab");
+    }
 }
@@ -1,12 +1,56 @@
-use crate::hexutil::encode_hex;
+use crate::hexutil::{decode_hex32, encode_hex};
+use crate::keyfmt::{KeyFormat, PUBLIC_HRP, SECRET_HRP, encode_key};
+use anyhow::bail;
 use anyhow::{Context, Result};
 use chrono::Utc;
-use commucat_crypto::DeviceKeyPair;
+use clap::ValueEnum;
+use commucat_crypto::{DeviceKeyPair, EventSigner};
 use getrandom::getrandom;
+use uuid::Uuid;
 
-pub fn generate_device_id(prefix: &str) -> String {
-    let ts = Utc::now().timestamp_millis();
-    format!("{}-{}", prefix, ts)
+/// Как придумывается device_id, если он не задан явно.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DeviceIdScheme {
+    /// `device-<uuid v4>`
+    #[default]
+    Uuid,
+    /// `device-<32 hex>`
+    Random,
+    /// `device-<миллисекунды>`: прежняя схема, выдаёт время создания
+    /// и совпадает у двух `init` в одну миллисекунду.
+    Timestamp,
+}
+
+pub fn generate_device_id(prefix: &str, scheme: DeviceIdScheme) -> Result<String> {
+    let suffix = match scheme {
+        DeviceIdScheme::Uuid => Uuid::new_v4().hyphenated().to_string(),
+        DeviceIdScheme::Random => {
+            let mut bytes = [0u8; 16];
+            getrandom(&mut bytes).context("sample entropy")?;
+            encode_hex(&bytes)
+        }
+        DeviceIdScheme::Timestamp => Utc::now().timestamp_millis().to_string(),
+    };
+    Ok(format!("{}-{}", prefix, suffix))
+}
+
+/// device_id попадает в URL REST и в имена файлов, поэтому только
+/// `A-Z a-z 0-9 . _ -`, не длиннее 128 символов и не с точки.
+pub fn parse_device_id(raw: &str) -> Result<String, String> {
+    let id = raw.trim();
+    if id.is_empty() || id.len() > 128 {
+        return Err("device_id должен быть от 1 до 128 символов".to_string());
+    }
+    if id.starts_with('.') {
+        return Err("device_id не может начинаться с точки".to_string());
+    }
+    if let Some(bad) = id
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        return Err(format!("недопустимый символ {:?} в device_id", bad));
+    }
+    Ok(id.to_string())
 }
 
 pub fn generate_keypair() -> Result<DeviceKeyPair> {
@@ -15,11 +59,63 @@ pub fn generate_keypair() -> Result<DeviceKeyPair> {
     DeviceKeyPair::from_seed(&seed).context("derive keypair")
 }
 
-pub fn describe_keys(id: &str, keys: &DeviceKeyPair) -> String {
+/// Ключи устройства, выданные сервером при pairing. Публичный ключ
+/// сверяется с выведенным из приватного до того, как что-то сохранится.
+pub fn keypair_from_claim(private_hex: &str, public_hex: &str) -> Result<DeviceKeyPair> {
+    let private = decode_hex32(private_hex).context("pairing returned invalid private_key")?;
+    let public = decode_hex32(public_hex).context("pairing returned invalid public_key")?;
+    let keys = DeviceKeyPair { public, private };
+    if EventSigner::new(&keys).public_key() != public {
+        bail!("pairing returned a public_key that does not match private_key");
+    }
+    Ok(keys)
+}
+
+pub fn describe_keys(id: &str, keys: &DeviceKeyPair, format: KeyFormat) -> String {
     format!(
         "device_id={}\npublic_key={}\nprivate_key={}",
         id,
-        encode_hex(&keys.public),
-        encode_hex(&keys.private)
+        encode_key(&keys.public, format, PUBLIC_HRP),
+        encode_key(&keys.private, format, SECRET_HRP)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claimed_keys_must_match() {
+        let keys = DeviceKeyPair::from_seed(&[7u8; 64]).unwrap();
+        let (private, public) = (encode_hex(&keys.private), encode_hex(&keys.public));
+        assert_eq!(
+            keypair_from_claim(&private, &public).unwrap().public,
+            keys.public
+        );
+
+        let short = keypair_from_claim(&private[..62], &public).unwrap_err();
+        assert!(format!("{:#}", short).contains("invalid private_key"));
+        let other = encode_hex(&DeviceKeyPair::from_seed(&[8u8; 64]).unwrap().public);
+        let mismatch = keypair_from_claim(&private, &other).unwrap_err();
+        assert!(mismatch.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn generated_ids_are_unique_and_safe() {
+        for scheme in [
+            DeviceIdScheme::Uuid,
+            DeviceIdScheme::Random,
+            DeviceIdScheme::Timestamp,
+        ] {
+            let id = generate_device_id("device", scheme).unwrap();
+            assert_eq!(parse_device_id(&id), Ok(id.clone()));
+        }
+        let first = generate_device_id("device", DeviceIdScheme::Random).unwrap();
+        let second = generate_device_id("device", DeviceIdScheme::Random).unwrap();
+        assert_ne!(first, second);
+        assert!(parse_device_id("../etc").is_err());
+        assert!(parse_device_id("a/b").is_err());
+        assert!(parse_device_id("laptop?x=1").is_err());
+        assert_eq!(parse_device_id(" laptop-01 ").unwrap(), "laptop-01");
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::data_path;
+use crate::rest::DeviceEntry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Последний загруженный список своих устройств (`devices.json`): вкладка
+/// показывает его без подключения, пока сервер не ответит снова.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCache {
+    pub fetched_at: DateTime<Utc>,
+    #[serde(default)]
+    pub devices: Vec<DeviceEntry>,
+}
+
+impl DeviceCache {
+    pub fn path() -> Result<PathBuf> {
+        data_path("devices.json")
+    }
+
+    /// `None`, если список ещё ни разу не загружался.
+    pub fn load() -> Result<Option<Self>> {
+        Self::load_from(&Self::path()?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map(Some)
+                .context("invalid device cache"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context("read device cache"),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("state directory")?;
+        }
+        let payload = serde_json::to_string_pretty(self).context("serialize device cache")?;
+        fs::write(path, payload).context("write device cache")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("commucat-devices-{}", std::process::id()));
+        let path = dir.join("devices.json");
+        assert!(DeviceCache::load_from(&path).unwrap().is_none());
+
+        let cache = DeviceCache {
+            fetched_at: Utc::now(),
+            devices: vec![DeviceEntry {
+                device_id: "device-a".to_string(),
+                status: "active".to_string(),
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                public_key: "00".to_string(),
+                current: true,
+            }],
+        };
+        cache.save_to(&path).unwrap();
+
+        let restored = DeviceCache::load_from(&path).unwrap().unwrap();
+        assert_eq!(restored.fetched_at, cache.fetched_at);
+        assert_eq!(restored.devices.len(), 1);
+        assert_eq!(restored.devices[0].device_id, "device-a");
+        assert!(restored.devices[0].current);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,42 +1,78 @@
-use crate::config::ClientState;
+use crate::calls::{CallAnswer, CallEnd, CallOffer};
+use crate::config::{ClientState, ServerKeyCheck, check_server_key, server_key_fingerprint};
+use crate::flow::{CoalesceKey, Coalescer, FlowStats, is_media};
+use crate::framedump::FrameDump;
 use crate::hexutil::{decode_hex, decode_hex32, encode_hex};
+use crate::rest::RestClient;
 use anyhow::{Context, Result, anyhow};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use chrono::Utc;
 use commucat_crypto::{DeviceCertificate, HandshakePattern, NoiseConfig, build_handshake, zkp};
+use commucat_proto::call::{CallEndReason, CallMediaProfile};
 use commucat_proto::{ControlEnvelope, Frame, FramePayload, FrameType, PROTOCOL_VERSION};
 use futures::future::poll_fn;
-use h2::{RecvStream, SendStream, client};
+use getrandom::getrandom;
+use h2::{Ping, PingPong, RecvStream, SendStream, client};
 use http::{Request, Uri, header};
 use rustls::client::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
 use rustls::{
     Certificate, ClientConfig, DigitallySignedStruct, OwnedTrustAnchor, RootCertStore, ServerName,
 };
 use serde_json::{self, Map, Value, json};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::net::{TcpStream, lookup_host};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use tokio::time::timeout;
 use tokio_rustls::TlsConnector;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
+use uuid::Uuid;
 use webpki_roots::TLS_SERVER_ROOTS;
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
 const USER_AGENT: &str = "CommuCat-CLI/0.1";
 const CERT_MAX_FUTURE_SKEW: i64 = 300;
+const DNS_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const SERVER_INFO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Сколько ждать, пока h2-драйвер допишет END_STREAM перед закрытием.
+const STREAM_CLOSE_GRACE: Duration = Duration::from_millis(500);
+/// Сколько ждать h2 PONG, проверяя, живо ли существующее соединение.
+const LIVENESS_PING_TIMEOUT: Duration = Duration::from_secs(3);
 
 pub struct EngineHandle {
     sender: mpsc::Sender<EngineCommand>,
+    task: Option<JoinHandle<()>>,
+    flow: Arc<FlowStats>,
+}
+
+/// Если в очереди событий осталось меньше мест, частые кадры начинают
+/// схлопываться (см. `flow::CoalesceKey`).
+const EVENT_BACKLOG_WATERMARK: usize = 64;
+
+/// Куда задача чтения отдаёт медиакадры и где считает потери.
+#[derive(Clone)]
+struct FrameSink {
+    media: mpsc::Sender<Frame>,
+    stats: Arc<FlowStats>,
 }
 
 #[derive(Debug)]
 pub enum EngineCommand {
-    Connect(Box<ClientState>),
-    Disconnect,
+    Connect {
+        state: Box<ClientState>,
+        /// Разрешить замену закреплённого ключа сервера, если он изменился.
+        accept_new_server_key: bool,
+    },
+    /// Закрыть соединение, предварительно объявив `presence`, если он задан.
+    Disconnect {
+        presence: Option<String>,
+    },
     Join {
         channel_id: u64,
         members: Vec<String>,
@@ -44,14 +80,47 @@ pub enum EngineCommand {
     },
     SendMessage {
         channel_id: u64,
+        /// Номер сообщения в TUI; возвращается в `MessageSent`/`SendFailed`.
+        local_id: u64,
         body: Vec<u8>,
     },
     Leave {
         channel_id: u64,
     },
+    /// Индикатор набора; без соединения молча игнорируется.
+    Typing {
+        channel_id: u64,
+        typing: bool,
+    },
     Presence {
         state: String,
     },
+    React {
+        channel_id: u64,
+        target_sequence: u64,
+        target_sender: String,
+        emoji: String,
+        remove: bool,
+    },
+    StartCall {
+        channel_id: u64,
+        to: Vec<String>,
+        media: CallMediaProfile,
+    },
+    AnswerCall {
+        channel_id: u64,
+        answer: CallAnswer,
+    },
+    EndCall {
+        channel_id: u64,
+        call_id: String,
+        reason: CallEndReason,
+    },
+    SendVoiceFrame {
+        channel_id: u64,
+        payload: Vec<u8>,
+    },
+    Shutdown,
 }
 
 #[derive(Debug, Clone)]
@@ -59,24 +128,76 @@ pub enum ClientEvent {
     Connected {
         session_id: String,
         pairing_required: bool,
+        /// Что сервер объявил при рукопожатии.
+        server: ServerCapabilities,
     },
     Disconnected {
         reason: String,
     },
+    /// Номер кадра, под которым уйдёт сообщение; ACK ссылается на него.
+    MessageSent {
+        channel_id: u64,
+        local_id: u64,
+        sequence: u64,
+    },
+    /// Номер кадра PRESENCE, отправленного по `EngineCommand::Presence`.
+    PresenceSent {
+        sequence: u64,
+    },
+    /// Сообщение `local_id` не ушло в сеть.
+    SendFailed {
+        local_id: u64,
+        detail: String,
+    },
     Frame(Frame),
+    CallStarted {
+        channel_id: u64,
+        offer: CallOffer,
+    },
+    Gap {
+        channel_id: u64,
+        expected: u64,
+        got: u64,
+    },
     Error {
         detail: String,
     },
     Log {
         line: String,
     },
+    /// Замер RTT по h2 PING после heartbeat; `None` — ответ не пришёл вовремя.
+    Latency {
+        rtt: Option<Duration>,
+    },
+    /// Ключ сервера закреплён заново; `previous` — отпечаток заменённого ключа.
+    ServerKeyPinned {
+        server_static: String,
+        fingerprint: String,
+        previous: Option<String>,
+    },
 }
 
-pub fn create_engine(buffer: usize, queue: usize) -> (EngineHandle, mpsc::Receiver<ClientEvent>) {
+/// Возвращает управление движком, очередь событий и отдельную очередь
+/// голосовых/видеокадров ёмкостью `media_queue`.
+pub fn create_engine(
+    buffer: usize,
+    queue: usize,
+    media_queue: usize,
+) -> (
+    EngineHandle,
+    mpsc::Receiver<ClientEvent>,
+    mpsc::Receiver<Frame>,
+) {
     let (tx, rx) = mpsc::channel(buffer);
     let (event_tx, event_rx) = mpsc::channel(queue);
-    tokio::spawn(async move {
-        if let Err(err) = engine_loop(rx, event_tx.clone()).await {
+    let (media_tx, media_rx) = mpsc::channel(media_queue);
+    let flow = Arc::new(FlowStats::default());
+    let sink = FrameSink {
+        media: media_tx,
+        stats: flow.clone(),
+    };
+    let task = tokio::spawn(async move {
+        if let Err(err) = engine_loop(rx, event_tx.clone(), sink).await {
             let _ = event_tx
                 .send(ClientEvent::Error {
                     detail: err.to_string(),
@@ -84,29 +205,166 @@ pub fn create_engine(buffer: usize, queue: usize) -> (EngineHandle, mpsc::Receiv
                 .await;
         }
     });
-    (EngineHandle { sender: tx }, event_rx)
+    (
+        EngineHandle {
+            sender: tx,
+            task: Some(task),
+            flow,
+        },
+        event_rx,
+        media_rx,
+    )
 }
 
 impl EngineHandle {
+    pub fn flow_stats(&self) -> &FlowStats {
+        &self.flow
+    }
+
     pub async fn send(&self, command: EngineCommand) -> Result<()> {
         self.sender
             .send(command)
             .await
             .map_err(|_| anyhow!("engine offline"))
     }
+
+    /// Закрывает соединение после уже поставленных в очередь команд и ждёт
+    /// остановки движка не дольше `wait`, включая постановку самих команд
+    /// в очередь. Не успел — задача движка прерывается.
+    pub async fn shutdown(&mut self, presence: Option<String>, wait: Duration) -> Result<()> {
+        let Some(mut task) = self.task.take() else {
+            return Ok(());
+        };
+        let sender = self.sender.clone();
+        let stop = async {
+            // Закрытая очередь значит, что движок уже остановился.
+            let _ = sender.send(EngineCommand::Disconnect { presence }).await;
+            let _ = sender.send(EngineCommand::Shutdown).await;
+            (&mut task).await
+        };
+        let joined = timeout(wait, stop).await;
+        match joined {
+            Ok(result) => result.context("engine task failed"),
+            Err(_) => {
+                task.abort();
+                Err(anyhow!("engine shutdown timed out"))
+            }
+        }
+    }
+}
+
+/// Версия протокола и возможности, объявленные сервером в рукопожатии
+/// (полезная нагрузка Noise и ACK). Пусто — сервер ничего не сообщил.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    pub protocol_version: Option<u64>,
+    pub capabilities: Vec<String>,
+}
+
+impl ServerCapabilities {
+    /// Поля из ACK дополняют и уточняют то, что пришло в Auth.
+    fn absorb(&mut self, value: &Value) {
+        if let Some(version) = value.get("protocol_version").and_then(Value::as_u64) {
+            self.protocol_version = Some(version);
+        }
+        if let Some(list) = value.get("capabilities").and_then(Value::as_array) {
+            self.capabilities = list
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_ascii_lowercase)
+                .collect();
+            self.capabilities.sort();
+            self.capabilities.dedup();
+        }
+    }
+
+    /// `protocol v1 · caps: pq, zstd` для журнала и TUI.
+    pub fn summary(&self) -> String {
+        let version = self
+            .protocol_version
+            .map(|version| format!("protocol v{}", version))
+            .unwrap_or_else(|| "protocol ?".to_string());
+        if self.capabilities.is_empty() {
+            format!("{} · caps: none advertised", version)
+        } else {
+            format!("{} · caps: {}", version, self.capabilities.join(", "))
+        }
+    }
+}
+
+/// Реакция на сообщение: MSG-кадр с полем `reaction`. `target` и
+/// `target_sender` указывают сообщение, `sender` — кто реагирует.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    pub sender: String,
+    pub emoji: String,
+    pub target: Option<u64>,
+    pub target_sender: Option<String>,
+    pub remove: bool,
+}
+
+impl Reaction {
+    fn to_json(&self) -> Value {
+        json!({
+            "reaction": self.emoji,
+            "sender": self.sender,
+            "target": self.target,
+            "target_sender": self.target_sender,
+            "remove": self.remove,
+        })
+    }
+
+    /// Реакция из тела MSG-кадра; автор берётся из `sender` или `from`.
+    pub fn parse(value: &Value) -> Option<Self> {
+        let emoji = value.get("reaction").and_then(Value::as_str)?;
+        let sender = value
+            .get("sender")
+            .or_else(|| value.get("from"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        Some(Self {
+            sender: sender.to_string(),
+            emoji: emoji.to_string(),
+            target: value.get("target").and_then(Value::as_u64),
+            target_sender: value
+                .get("target_sender")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            remove: value
+                .get("remove")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        })
+    }
 }
 
 struct ActiveConnection {
     session_id: String,
+    device_id: String,
     send_stream: SendStream<Bytes>,
     sequence: u64,
     reader_task: JoinHandle<()>,
     driver_task: JoinHandle<()>,
+    ping_pong: Option<PingPong>,
+    /// Замер RTT в фоне; на время замера `ping_pong` живёт в задаче.
+    rtt_probe: Option<JoinHandle<PingPong>>,
     pairing_required: bool,
+    server_caps: ServerCapabilities,
+    /// Момент последнего входящего кадра; обновляется задачей чтения.
+    last_inbound: Arc<Mutex<Instant>>,
+    presence_state: String,
+    keepalive_interval: Duration,
+    keepalive_timeout: Option<Duration>,
+    next_keepalive: tokio::time::Instant,
 }
 
 impl ActiveConnection {
-    async fn connect(mut state: ClientState, events: mpsc::Sender<ClientEvent>) -> Result<Self> {
+    async fn connect(
+        mut state: ClientState,
+        accept_new_server_key: bool,
+        events: mpsc::Sender<ClientEvent>,
+        sink: FrameSink,
+    ) -> Result<Self> {
         let mut state_dirty = false;
         let uri: Uri = state.server_url.parse().context("invalid server url")?;
         let scheme = uri.scheme_str().unwrap_or("https");
@@ -127,8 +385,9 @@ impl ActiveConnection {
             _ => "/connect".to_string(),
         };
         let addr = format!("{}:{}", host, port);
-        let addrs = lookup_host(addr.clone())
+        let addrs = timeout(DNS_LOOKUP_TIMEOUT, lookup_host(addr.clone()))
             .await
+            .map_err(|_| anyhow!("dns lookup timed out"))?
             .context("dns lookup failed")?
             .collect::<Vec<_>>();
         if addrs.is_empty() {
@@ -137,7 +396,15 @@ impl ActiveConnection {
         let mut last_err = None;
         let mut tcp_opt = None;
         for candidate in addrs.iter() {
-            match TcpStream::connect(candidate).await {
+            let attempt = timeout(TCP_CONNECT_TIMEOUT, TcpStream::connect(candidate))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "connect timed out",
+                    ))
+                });
+            match attempt {
                 Ok(stream) => {
                     tcp_opt = Some(stream);
                     let _ = events
@@ -172,9 +439,15 @@ impl ActiveConnection {
             .connect(server_name, tcp)
             .await
             .context("tls connect failed")?;
-        let (mut sender, connection) = client::handshake(tls)
-            .await
-            .context("h2 handshake failed")?;
+        let alpn = tls.get_ref().1.alpn_protocol().map(<[u8]>::to_vec);
+        check_alpn(alpn.as_deref())?;
+        let (mut sender, mut connection) =
+            client::handshake(tls).await.context(if alpn.is_none() {
+                "h2 handshake failed (server negotiated no ALPN protocol; does it speak HTTP/2?)"
+            } else {
+                "h2 handshake failed"
+            })?;
+        let mut ping_pong = connection.ping_pong();
         let driver_task = tokio::spawn(async move {
             if let Err(err) = connection.await {
                 warn!("h2 connection ended: {}", err);
@@ -193,7 +466,20 @@ impl ActiveConnection {
             .header(header::CONTENT_TYPE, "application/octet-stream")
             .header(header::USER_AGENT, USER_AGENT)
             .header(header::TE, "trailers");
-        if let Some(tp) = state.traceparent.as_ref() {
+        let traceparent = match state.traceparent.clone() {
+            Some(tp) => Some(tp),
+            None if state.auto_trace => {
+                let tp = generate_traceparent()?;
+                let _ = events
+                    .send(ClientEvent::Log {
+                        line: format!("traceparent {}", tp),
+                    })
+                    .await;
+                Some(tp)
+            }
+            None => None,
+        };
+        if let Some(tp) = traceparent.as_ref() {
             request_builder = request_builder.header("traceparent", tp.as_str());
         }
         let request = request_builder.body(())?;
@@ -270,11 +556,14 @@ impl ActiveConnection {
             certificate_for_hello = Some(cert.clone());
         }
         let pattern = parse_pattern(&state.noise_pattern)?;
-        let remote_static = if matches!(pattern, HandshakePattern::Ik | HandshakePattern::Xk) {
-            let raw = state
-                .server_static
-                .as_ref()
-                .ok_or_else(|| anyhow!("server_static required for this pattern"))?;
+        let static_required = pattern_requires_remote_static(pattern);
+        if verify_server_key(&mut state, accept_new_server_key, static_required, &events).await? {
+            state_dirty = true;
+        }
+        let remote_static = if static_required {
+            let raw = state.server_static.as_ref().ok_or_else(|| {
+                anyhow!("server_static required for pattern {}", state.noise_pattern)
+            })?;
             Some(decode_hex32(raw)?)
         } else {
             None
@@ -360,6 +649,7 @@ impl ActiveConnection {
         let mut recv_stream = response.into_body();
         let mut buffer = BytesMut::new();
         let mut session_id = String::new();
+        let mut server_caps = ServerCapabilities::default();
         let mut next_sequence = 2u64;
         let connection = 'handshake: loop {
             match recv_stream.data().await {
@@ -385,6 +675,7 @@ impl ActiveConnection {
                                 if !payload.is_empty() {
                                     let value: serde_json::Value = serde_json::from_slice(&payload)
                                         .context("handshake payload decode")?;
+                                    server_caps.absorb(&value);
                                     session_id = value
                                         .get("session")
                                         .and_then(|v| v.as_str())
@@ -461,6 +752,9 @@ impl ActiveConnection {
                             }
                             FrameType::Ack => {
                                 if let Some(ack) = parse_handshake_ack(&frame) {
+                                    if let FramePayload::Control(envelope) = &frame.payload {
+                                        server_caps.absorb(&envelope.properties);
+                                    }
                                     if let Some(ca_hex) = ack.device_ca_public.as_ref() {
                                         match state.device_ca_public.as_ref() {
                                             Some(existing) if existing == ca_hex => {}
@@ -531,15 +825,35 @@ impl ActiveConnection {
                                     if session_id.is_empty() {
                                         session_id = "unknown".to_string();
                                     }
-                                    let reader_task =
-                                        spawn_reader(recv_stream, buffer, events.clone());
+                                    let last_inbound = Arc::new(Mutex::new(Instant::now()));
+                                    let reader_task = spawn_reader(
+                                        recv_stream,
+                                        buffer,
+                                        last_inbound.clone(),
+                                        events.clone(),
+                                        sink,
+                                    );
+                                    let keepalive_interval =
+                                        Duration::from_secs(state.presence_interval_secs.max(1));
                                     let connection = ActiveConnection {
                                         session_id: session_id.clone(),
+                                        device_id: device_id.clone(),
                                         send_stream,
                                         sequence: next_sequence,
                                         reader_task,
                                         driver_task,
+                                        ping_pong: ping_pong.take(),
+                                        rtt_probe: None,
                                         pairing_required: ack.pairing_required,
+                                        server_caps: server_caps.clone(),
+                                        last_inbound,
+                                        presence_state: state.presence_state.clone(),
+                                        keepalive_interval,
+                                        keepalive_timeout: (state.keepalive_timeout_secs > 0).then(
+                                            || Duration::from_secs(state.keepalive_timeout_secs),
+                                        ),
+                                        next_keepalive: tokio::time::Instant::now()
+                                            + keepalive_interval,
                                     };
                                     if ack.pairing_required {
                                         let _ = events
@@ -554,6 +868,11 @@ impl ActiveConnection {
                                             line: format!("handshake ok: session {}", session_id),
                                         })
                                         .await;
+                                    let _ = events
+                                        .send(ClientEvent::Log {
+                                            line: format!("server {}", server_caps.summary()),
+                                        })
+                                        .await;
                                     break 'handshake connection;
                                 }
                                 let _ = events.send(ClientEvent::Frame(frame)).await;
@@ -611,20 +930,136 @@ impl ActiveConnection {
         self.send(frame).await
     }
 
-    async fn send_message(&mut self, channel_id: u64, body: Vec<u8>) -> Result<()> {
+    async fn send_typing(&mut self, channel_id: u64, typing: bool) -> Result<()> {
         let frame = Frame {
             channel_id,
             sequence: self.next_sequence(),
+            frame_type: FrameType::Typing,
+            payload: FramePayload::Control(ControlEnvelope {
+                properties: json!({
+                    "device": self.device_id,
+                    "typing": typing,
+                }),
+            }),
+        };
+        self.send(frame).await
+    }
+
+    async fn send_message(&mut self, channel_id: u64, sequence: u64, body: Vec<u8>) -> Result<()> {
+        let frame = Frame {
+            channel_id,
+            sequence,
             frame_type: FrameType::Msg,
             payload: FramePayload::Opaque(body),
         };
         self.send(frame).await
     }
 
-    async fn send_presence(&mut self, state: String) -> Result<()> {
+    async fn send_reaction(
+        &mut self,
+        channel_id: u64,
+        target_sequence: u64,
+        target_sender: String,
+        emoji: String,
+        remove: bool,
+    ) -> Result<()> {
+        let body = Reaction {
+            sender: self.device_id.clone(),
+            emoji,
+            target: Some(target_sequence),
+            target_sender: Some(target_sender),
+            remove,
+        }
+        .to_json();
         let frame = Frame {
-            channel_id: 0,
+            channel_id,
+            sequence: self.next_sequence(),
+            frame_type: FrameType::Msg,
+            payload: FramePayload::Opaque(serde_json::to_vec(&body).context("encode reaction")?),
+        };
+        self.send(frame).await
+    }
+
+    async fn send_call_offer(
+        &mut self,
+        channel_id: u64,
+        to: Vec<String>,
+        media: CallMediaProfile,
+    ) -> Result<CallOffer> {
+        let offer = CallOffer {
+            call_id: Uuid::new_v4().to_string(),
+            from: self.device_id.clone(),
+            to,
+            media,
+            metadata: Value::Null,
+            transport: None,
+            expires_at: None,
+            ephemeral_key: None,
+        };
+        let frame = Frame {
+            channel_id,
             sequence: self.next_sequence(),
+            frame_type: FrameType::CallOffer,
+            payload: FramePayload::Control(
+                ControlEnvelope::try_from(&offer)
+                    .map_err(|err| anyhow!("encode call offer: {:?}", err))?,
+            ),
+        };
+        self.send(frame).await?;
+        Ok(offer)
+    }
+
+    async fn send_call_answer(&mut self, channel_id: u64, answer: CallAnswer) -> Result<()> {
+        let frame = Frame {
+            channel_id,
+            sequence: self.next_sequence(),
+            frame_type: FrameType::CallAnswer,
+            payload: FramePayload::Control(
+                ControlEnvelope::try_from(&answer)
+                    .map_err(|err| anyhow!("encode call answer: {:?}", err))?,
+            ),
+        };
+        self.send(frame).await
+    }
+
+    async fn send_call_end(
+        &mut self,
+        channel_id: u64,
+        call_id: String,
+        reason: CallEndReason,
+    ) -> Result<()> {
+        let end = CallEnd {
+            call_id,
+            reason,
+            metadata: Value::Null,
+        };
+        let frame = Frame {
+            channel_id,
+            sequence: self.next_sequence(),
+            frame_type: FrameType::CallEnd,
+            payload: FramePayload::Control(
+                ControlEnvelope::try_from(&end)
+                    .map_err(|err| anyhow!("encode call end: {:?}", err))?,
+            ),
+        };
+        self.send(frame).await
+    }
+
+    async fn send_voice_frame(&mut self, channel_id: u64, payload: Vec<u8>) -> Result<()> {
+        let frame = Frame {
+            channel_id,
+            sequence: self.next_sequence(),
+            frame_type: FrameType::VoiceFrame,
+            payload: FramePayload::Opaque(payload),
+        };
+        self.send(frame).await
+    }
+
+    async fn send_presence(&mut self, sequence: u64, state: String) -> Result<()> {
+        self.presence_state = state.clone();
+        let frame = Frame {
+            channel_id: 0,
+            sequence,
             frame_type: FrameType::Presence,
             payload: FramePayload::Control(ControlEnvelope {
                 properties: json!({
@@ -646,8 +1081,77 @@ impl ActiveConnection {
         current
     }
 
+    /// Прикладной heartbeat: повторяет текущий presence с пометкой `keepalive`.
+    /// Ошибка означает, что сервер молчит дольше `keepalive_timeout`.
+    async fn keepalive(&mut self) -> Result<()> {
+        self.next_keepalive = tokio::time::Instant::now() + self.keepalive_interval;
+        let last_inbound = *self
+            .last_inbound
+            .lock()
+            .map_err(|_| anyhow!("keepalive state poisoned"))?;
+        if keepalive_expired(last_inbound, Instant::now(), self.keepalive_timeout) {
+            return Err(anyhow!(
+                "no frames from server for {}s",
+                last_inbound.elapsed().as_secs()
+            ));
+        }
+        let frame = Frame {
+            channel_id: 0,
+            sequence: self.next_sequence(),
+            frame_type: FrameType::Presence,
+            payload: FramePayload::Control(ControlEnvelope {
+                properties: json!({
+                    "state": self.presence_state,
+                    "keepalive": true,
+                }),
+            }),
+        };
+        self.send(frame).await
+    }
+
+    /// Проверяет, что соединение живо: задачи чтения и h2 не завершились,
+    /// а сервер отвечает на PING (полуоткрытый TCP так не пройдёт).
+    async fn is_alive(&mut self) -> bool {
+        if self.reader_task.is_finished() || self.driver_task.is_finished() {
+            return false;
+        }
+        if let Some(probe) = self.rtt_probe.take() {
+            self.ping_pong = probe.await.ok();
+        }
+        let Some(ping_pong) = self.ping_pong.as_mut() else {
+            return true;
+        };
+        matches!(
+            timeout(LIVENESS_PING_TIMEOUT, ping_pong.ping(Ping::opaque())).await,
+            Ok(Ok(_))
+        )
+    }
+
+    /// Запускает замер RTT по h2 PING в отдельной задаче: ожидание ответа
+    /// (до `LIVENESS_PING_TIMEOUT`) не держит цикл команд. Результат уходит
+    /// событием `Latency`; пока предыдущий замер идёт, новый не начинается.
+    fn start_rtt_probe(&mut self, events: &mpsc::Sender<ClientEvent>) {
+        if let Some(probe) = self.rtt_probe.take_if(|probe| probe.is_finished()) {
+            self.ping_pong = futures::FutureExt::now_or_never(probe).and_then(Result::ok);
+        }
+        let Some(mut ping_pong) = self.ping_pong.take() else {
+            return;
+        };
+        let events = events.clone();
+        self.rtt_probe = Some(tokio::spawn(async move {
+            let started = Instant::now();
+            let rtt = match timeout(LIVENESS_PING_TIMEOUT, ping_pong.ping(Ping::opaque())).await {
+                Ok(Ok(_)) => Some(started.elapsed()),
+                _ => None,
+            };
+            let _ = events.send(ClientEvent::Latency { rtt }).await;
+            ping_pong
+        }));
+    }
+
     async fn shutdown(&mut self) {
         let _ = self.send_stream.send_data(Bytes::new(), true);
+        let _ = timeout(STREAM_CLOSE_GRACE, &mut self.driver_task).await;
     }
 }
 
@@ -655,35 +1159,180 @@ impl Drop for ActiveConnection {
     fn drop(&mut self) {
         self.reader_task.abort();
         self.driver_task.abort();
+        if let Some(probe) = self.rtt_probe.take() {
+            probe.abort();
+        }
+    }
+}
+
+/// Сверяет ключ, который сервер публикует в server-info, с закреплённым
+/// `server_static` (TOFU). Возвращает `true`, если состояние изменилось.
+/// `static_required` — паттерн (IK/XK) не может без `server_static`: если его нет
+/// в профиле, ключ подтягивается отсюда, а недоступный server-info становится ошибкой.
+async fn verify_server_key(
+    state: &mut ClientState,
+    accept_new_server_key: bool,
+    static_required: bool,
+    events: &mpsc::Sender<ClientEvent>,
+) -> Result<bool> {
+    let must_fetch = static_required && state.server_static.is_none();
+    let rest = RestClient::with_state(state)?;
+    let fetched = timeout(SERVER_INFO_TIMEOUT, rest.server_info())
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("server info timed out")));
+    let info = match fetched {
+        Ok(info) => info,
+        Err(err) if must_fetch => {
+            return Err(anyhow!(
+                "server_static is missing for pattern {} and could not be fetched: {:#}; \
+                 pass --server-static to init or use --pattern XX",
+                state.noise_pattern,
+                err
+            ));
+        }
+        Err(err) => {
+            warn!("server key check skipped: {:#}", err);
+            return Ok(false);
+        }
+    };
+    let presented = info.noise_public.trim().to_ascii_lowercase();
+    decode_hex32(&presented).context("server published invalid noise_public")?;
+    let presented_fingerprint = server_key_fingerprint(&presented);
+    let previous = state.server_static.as_deref().map(server_key_fingerprint);
+    match check_server_key(state.server_static.as_deref(), &presented) {
+        ServerKeyCheck::Match => {
+            if state.server_static_fingerprint.as_deref() == Some(presented_fingerprint.as_str()) {
+                return Ok(false);
+            }
+            state.pin_server_key(&presented);
+            return Ok(true);
+        }
+        ServerKeyCheck::Changed if !accept_new_server_key => {
+            return Err(anyhow!(
+                "SERVER KEY CHANGED: pinned {} but server presents {}; \
+                 refusing to connect. If the change is expected, restart with \
+                 --accept-new-server-key",
+                previous.unwrap_or_default(),
+                presented_fingerprint
+            ));
+        }
+        ServerKeyCheck::Changed => {
+            warn!(
+                "server key changed: {} -> {} (accepted)",
+                previous.as_deref().unwrap_or_default(),
+                presented_fingerprint
+            );
+        }
+        ServerKeyCheck::FirstUse if must_fetch => {
+            info!(
+                "server_static missing for pattern {}, fetched {} from server info",
+                state.noise_pattern, presented_fingerprint
+            );
+            let _ = events
+                .send(ClientEvent::Log {
+                    line: format!(
+                        "server_static missing for pattern {}: fetched noise_public {} from server info",
+                        state.noise_pattern, presented_fingerprint
+                    ),
+                })
+                .await;
+        }
+        ServerKeyCheck::FirstUse => {}
     }
+    state.pin_server_key(&presented);
+    let _ = events
+        .send(ClientEvent::ServerKeyPinned {
+            server_static: presented,
+            fingerprint: presented_fingerprint,
+            previous,
+        })
+        .await;
+    Ok(true)
 }
 
 async fn engine_loop(
     mut commands: mpsc::Receiver<EngineCommand>,
     events: mpsc::Sender<ClientEvent>,
+    sink: FrameSink,
 ) -> Result<()> {
     let mut connection: Option<ActiveConnection> = None;
-    while let Some(command) = commands.recv().await {
+    loop {
+        let command = match connection.as_ref().map(|conn| conn.next_keepalive) {
+            Some(deadline) => tokio::select! {
+                command = commands.recv() => command,
+                _ = tokio::time::sleep_until(deadline) => {
+                    if let Some(conn) = connection.as_mut() {
+                        match conn.keepalive().await {
+                            Ok(()) => conn.start_rtt_probe(&events),
+                            Err(err) => {
+                                warn!("connection considered dead: {}", err);
+                                connection = None;
+                                let _ = events
+                                    .send(ClientEvent::Disconnected {
+                                        reason: format!("keepalive failed: {}", err),
+                                    })
+                                    .await;
+                            }
+                        }
+                    }
+                    continue;
+                }
+            },
+            None => commands.recv().await,
+        };
+        let Some(command) = command else {
+            break;
+        };
         match command {
-            EngineCommand::Connect(state) => {
-                if connection.is_some() {
+            EngineCommand::Connect {
+                state,
+                accept_new_server_key,
+            } => {
+                if let Some(mut stale) = connection.take() {
+                    if stale.is_alive().await {
+                        connection = Some(stale);
+                        let _ = events
+                            .send(ClientEvent::Error {
+                                detail: "already connected".to_string(),
+                            })
+                            .await;
+                        continue;
+                    }
+                    warn!("reaping stale connection {}", stale.session_id);
                     let _ = events
-                        .send(ClientEvent::Error {
-                            detail: "already connected".to_string(),
+                        .send(ClientEvent::Log {
+                            line: format!(
+                                "previous connection (session {}) is dead, reconnecting",
+                                stale.session_id
+                            ),
                         })
                         .await;
-                    continue;
+                    stale.shutdown().await;
                 }
-                match ActiveConnection::connect(*state, events.clone()).await {
-                    Ok(conn) => {
+                let connect_timeout = Duration::from_secs(state.connect_timeout_secs.max(1));
+                match timeout(
+                    connect_timeout,
+                    ActiveConnection::connect(
+                        *state,
+                        accept_new_server_key,
+                        events.clone(),
+                        sink.clone(),
+                    ),
+                )
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("connect timed out")))
+                {
+                    Ok(mut conn) => {
                         let session = conn.session_id.clone();
                         let pairing_required = conn.pairing_required;
                         let _ = events
                             .send(ClientEvent::Connected {
                                 session_id: session,
                                 pairing_required,
+                                server: conn.server_caps.clone(),
                             })
                             .await;
+                        conn.start_rtt_probe(&events);
                         connection = Some(conn);
                     }
                     Err(err) => {
@@ -696,8 +1345,14 @@ async fn engine_loop(
                     }
                 }
             }
-            EngineCommand::Disconnect => {
+            EngineCommand::Disconnect { presence } => {
                 if let Some(mut conn) = connection.take() {
+                    if let Some(state) = presence {
+                        let sequence = conn.next_sequence();
+                        if let Err(err) = conn.send_presence(sequence, state).await {
+                            warn!("failed to announce presence before disconnect: {}", err);
+                        }
+                    }
                     conn.shutdown().await;
                     let _ = events
                         .send(ClientEvent::Disconnected {
@@ -706,6 +1361,7 @@ async fn engine_loop(
                         .await;
                 }
             }
+            EngineCommand::Shutdown => break,
             EngineCommand::Join {
                 channel_id,
                 members,
@@ -727,18 +1383,33 @@ async fn engine_loop(
                         .await;
                 }
             }
-            EngineCommand::SendMessage { channel_id, body } => {
+            EngineCommand::SendMessage {
+                channel_id,
+                local_id,
+                body,
+            } => {
                 if let Some(conn) = connection.as_mut() {
-                    if let Err(err) = conn.send_message(channel_id, body).await {
+                    let sequence = conn.next_sequence();
+                    // Номер сообщаем до записи: ACK может обогнать возврат из send.
+                    let _ = events
+                        .send(ClientEvent::MessageSent {
+                            channel_id,
+                            local_id,
+                            sequence,
+                        })
+                        .await;
+                    if let Err(err) = conn.send_message(channel_id, sequence, body).await {
                         let _ = events
-                            .send(ClientEvent::Error {
+                            .send(ClientEvent::SendFailed {
+                                local_id,
                                 detail: err.to_string(),
                             })
                             .await;
                     }
                 } else {
                     let _ = events
-                        .send(ClientEvent::Error {
+                        .send(ClientEvent::SendFailed {
+                            local_id,
                             detail: "no active connection".to_string(),
                         })
                         .await;
@@ -761,9 +1432,110 @@ async fn engine_loop(
                         .await;
                 }
             }
+            EngineCommand::Typing { channel_id, typing } => {
+                if let Some(conn) = connection.as_mut()
+                    && let Err(err) = conn.send_typing(channel_id, typing).await
+                {
+                    warn!("failed to send typing indicator: {}", err);
+                }
+            }
             EngineCommand::Presence { state } => {
                 if let Some(conn) = connection.as_mut() {
-                    if let Err(err) = conn.send_presence(state).await {
+                    let sequence = conn.next_sequence();
+                    let _ = events.send(ClientEvent::PresenceSent { sequence }).await;
+                    if let Err(err) = conn.send_presence(sequence, state).await {
+                        let _ = events
+                            .send(ClientEvent::Error {
+                                detail: err.to_string(),
+                            })
+                            .await;
+                    }
+                } else {
+                    let _ = events
+                        .send(ClientEvent::Error {
+                            detail: "no active connection".to_string(),
+                        })
+                        .await;
+                }
+            }
+            EngineCommand::React {
+                channel_id,
+                target_sequence,
+                target_sender,
+                emoji,
+                remove,
+            } => {
+                if let Some(conn) = connection.as_mut() {
+                    if let Err(err) = conn
+                        .send_reaction(channel_id, target_sequence, target_sender, emoji, remove)
+                        .await
+                    {
+                        let _ = events
+                            .send(ClientEvent::Error {
+                                detail: err.to_string(),
+                            })
+                            .await;
+                    }
+                } else {
+                    let _ = events
+                        .send(ClientEvent::Error {
+                            detail: "no active connection".to_string(),
+                        })
+                        .await;
+                }
+            }
+            EngineCommand::StartCall {
+                channel_id,
+                to,
+                media,
+            } => {
+                if let Some(conn) = connection.as_mut() {
+                    match conn.send_call_offer(channel_id, to, media).await {
+                        Ok(offer) => {
+                            let _ = events
+                                .send(ClientEvent::CallStarted { channel_id, offer })
+                                .await;
+                        }
+                        Err(err) => {
+                            let _ = events
+                                .send(ClientEvent::Error {
+                                    detail: err.to_string(),
+                                })
+                                .await;
+                        }
+                    }
+                } else {
+                    let _ = events
+                        .send(ClientEvent::Error {
+                            detail: "no active connection".to_string(),
+                        })
+                        .await;
+                }
+            }
+            EngineCommand::AnswerCall { channel_id, answer } => {
+                if let Some(conn) = connection.as_mut() {
+                    if let Err(err) = conn.send_call_answer(channel_id, answer).await {
+                        let _ = events
+                            .send(ClientEvent::Error {
+                                detail: err.to_string(),
+                            })
+                            .await;
+                    }
+                } else {
+                    let _ = events
+                        .send(ClientEvent::Error {
+                            detail: "no active connection".to_string(),
+                        })
+                        .await;
+                }
+            }
+            EngineCommand::EndCall {
+                channel_id,
+                call_id,
+                reason,
+            } => {
+                if let Some(conn) = connection.as_mut() {
+                    if let Err(err) = conn.send_call_end(channel_id, call_id, reason).await {
                         let _ = events
                             .send(ClientEvent::Error {
                                 detail: err.to_string(),
@@ -778,16 +1550,46 @@ async fn engine_loop(
                         .await;
                 }
             }
+            EngineCommand::SendVoiceFrame {
+                channel_id,
+                payload,
+            } => {
+                if let Some(conn) = connection.as_mut()
+                    && let Err(err) = conn.send_voice_frame(channel_id, payload).await
+                {
+                    let _ = events
+                        .send(ClientEvent::Error {
+                            detail: err.to_string(),
+                        })
+                        .await;
+                }
+            }
         }
     }
     Ok(())
 }
 
+/// Паттерны, которые умеет `commucat_crypto`. NK и XX в текущей версии
+/// крейта отсутствуют, поэтому для них выдаём понятную ошибку.
 fn parse_pattern(pattern: &str) -> Result<HandshakePattern> {
     match pattern.to_uppercase().as_str() {
         "XK" => Ok(HandshakePattern::Xk),
         "IK" => Ok(HandshakePattern::Ik),
-        other => Err(anyhow!(format!("unsupported pattern: {}", other))),
+        other @ ("NK" | "XX") => Err(anyhow!(
+            "pattern {} is not implemented by commucat-crypto yet (supported: XK, IK)",
+            other
+        )),
+        other => Err(anyhow!(
+            "unsupported pattern: {} (supported: XK, IK)",
+            other
+        )),
+    }
+}
+
+/// Нужен ли заранее известный статический ключ сервера.
+fn pattern_requires_remote_static(pattern: HandshakePattern) -> bool {
+    match pattern {
+        HandshakePattern::Xk | HandshakePattern::Ik => true,
     }
 }
 
@@ -848,6 +1650,17 @@ fn parse_handshake_ack(frame: &Frame) -> Option<HandshakeAck> {
     })
 }
 
+/// Номер кадра, который подтверждает ACK (`{"ack": N}`).
+pub fn acked_sequence(frame: &Frame) -> Option<u64> {
+    if frame.frame_type != FrameType::Ack {
+        return None;
+    }
+    let FramePayload::Control(ControlEnvelope { properties }) = &frame.payload else {
+        return None;
+    };
+    properties.get("ack").and_then(Value::as_u64)
+}
+
 fn parse_ack_certificate(value: &Value) -> Option<AckCertificateInfo> {
     let obj = value.as_object()?;
     let serial = obj.get("serial")?.as_u64()?;
@@ -860,17 +1673,98 @@ fn parse_ack_certificate(value: &Value) -> Option<AckCertificateInfo> {
     })
 }
 
+/// Последний принятый sequence по каждому каналу. Канал 0 — служебный
+/// поток, где кадры законно перемежаются, поэтому он не проверяется.
+#[derive(Default)]
+struct SequenceTracker {
+    last: HashMap<u64, u64>,
+}
+
+impl SequenceTracker {
+    /// Возвращает `(ожидаемый, полученный)`, если sequence перескочил вперёд.
+    fn observe(&mut self, channel_id: u64, sequence: u64) -> Option<(u64, u64)> {
+        if channel_id == 0 {
+            return None;
+        }
+        match self.last.get(&channel_id).copied() {
+            Some(last) if sequence <= last => None,
+            Some(last) => {
+                self.last.insert(channel_id, sequence);
+                let expected = last + 1;
+                (sequence > expected).then_some((expected, sequence))
+            }
+            None => {
+                self.last.insert(channel_id, sequence);
+                None
+            }
+        }
+    }
+}
+
+fn keepalive_expired(last_inbound: Instant, now: Instant, timeout: Option<Duration>) -> bool {
+    timeout.is_some_and(|timeout| now.saturating_duration_since(last_inbound) > timeout)
+}
+
 fn spawn_reader(
     mut stream: RecvStream,
     mut buffer: BytesMut,
+    last_inbound: Arc<Mutex<Instant>>,
     events: mpsc::Sender<ClientEvent>,
+    sink: FrameSink,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
+        let mut sequences = SequenceTracker::default();
+        let mut dump = FrameDump::from_env();
+        let mut coalescer = Coalescer::default();
         loop {
             loop {
                 match Frame::decode(&buffer) {
                     Ok((frame, consumed)) => {
+                        if let Some(writer) = dump.as_mut()
+                            && let Err(err) = writer.record(&frame, &buffer[..consumed])
+                        {
+                            warn!("frame dump disabled: {:#}", err);
+                            dump = None;
+                        }
                         buffer.advance(consumed);
+                        if let Ok(mut last) = last_inbound.lock() {
+                            *last = Instant::now();
+                        }
+                        if let Some((expected, got)) =
+                            sequences.observe(frame.channel_id, frame.sequence)
+                        {
+                            warn!(
+                                "sequence gap on channel {}: expected {}, got {}",
+                                frame.channel_id, expected, got
+                            );
+                            let gap = ClientEvent::Gap {
+                                channel_id: frame.channel_id,
+                                expected,
+                                got,
+                            };
+                            if events.send(gap).await.is_err() {
+                                return;
+                            }
+                        }
+                        if is_media(frame.frame_type) {
+                            match sink.media.try_send(frame) {
+                                Ok(()) => {}
+                                Err(mpsc::error::TrySendError::Full(_)) => {
+                                    sink.stats.record_media_dropped();
+                                }
+                                Err(mpsc::error::TrySendError::Closed(_)) => return,
+                            }
+                            continue;
+                        }
+                        if let Some(key) = CoalesceKey::of(&frame)
+                            && (!coalescer.is_empty()
+                                || events.capacity() < EVENT_BACKLOG_WATERMARK)
+                        {
+                            if coalescer.push(key, frame) {
+                                sink.stats.record_coalesced();
+                            }
+                            continue;
+                        }
                         if events.send(ClientEvent::Frame(frame)).await.is_err() {
                             return;
                         }
@@ -888,6 +1782,12 @@ fn spawn_reader(
                     }
                 }
             }
+            // Пачка разобрана: отложенные состояния отдаём до ожидания новых данных.
+            while let Some(frame) = coalescer.pop() {
+                if events.send(ClientEvent::Frame(frame)).await.is_err() {
+                    return;
+                }
+            }
             match stream.data().await {
                 Some(Ok(bytes)) => buffer.put_slice(&bytes),
                 Some(Err(err)) => {
@@ -930,6 +1830,19 @@ async fn send_frame_raw(stream: &mut SendStream<Bytes>, payload: Vec<u8>) -> Res
         .map_err(|err| anyhow!(format!("send failed: {}", err)))
 }
 
+/// `http/1.1` в ALPN объявляется, чтобы распознать сервер без HTTP/2 и
+/// сказать об этом прямо, а не падать на рукопожатии h2.
+fn check_alpn(negotiated: Option<&[u8]>) -> Result<()> {
+    match negotiated {
+        None | Some(b"h2") => Ok(()),
+        Some(other) => Err(anyhow!(
+            "server negotiated {} instead of h2: /connect requires HTTP/2; \
+             enable h2 on the server or the reverse proxy in front of it",
+            String::from_utf8_lossy(other)
+        )),
+    }
+}
+
 fn build_tls_connector(state: &ClientState) -> Result<TlsConnector> {
     let mut roots = RootCertStore::empty();
     if let Some(path) = state.tls_ca_path.as_ref() {
@@ -964,6 +1877,23 @@ fn build_tls_connector(state: &ClientState) -> Result<TlsConnector> {
     Ok(TlsConnector::from(Arc::new(config)))
 }
 
+/// W3C traceparent версии 00 со случайными trace-id/span-id и флагом sampled.
+fn generate_traceparent() -> Result<String> {
+    let mut trace_id = [0u8; 16];
+    let mut span_id = [0u8; 8];
+    while trace_id.iter().all(|b| *b == 0) {
+        getrandom(&mut trace_id).context("sample trace id")?;
+    }
+    while span_id.iter().all(|b| *b == 0) {
+        getrandom(&mut span_id).context("sample span id")?;
+    }
+    Ok(format!(
+        "00-{}-{}-01",
+        encode_hex(&trace_id),
+        encode_hex(&span_id)
+    ))
+}
+
 fn derive_noise_keys(private: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
     let secret = StaticSecret::from(*private);
     let public = X25519PublicKey::from(&secret);
@@ -1003,3 +1933,394 @@ impl ServerCertVerifier for NoVerifier {
         Ok(HandshakeSignatureValid::assertion())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_traceparent_is_w3c_compliant() {
+        let first = generate_traceparent().unwrap();
+        let parts: Vec<&str> = first.split('-').collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[1].len(), 32);
+        assert_eq!(parts[2].len(), 16);
+        assert_eq!(parts[3], "01");
+        assert_ne!(first, generate_traceparent().unwrap());
+    }
+
+    #[test]
+    fn ack_frames_expose_the_acked_sequence() {
+        let ack = |frame_type, properties| Frame {
+            channel_id: 0,
+            sequence: 9,
+            frame_type,
+            payload: FramePayload::Control(ControlEnvelope { properties }),
+        };
+        assert_eq!(
+            acked_sequence(&ack(FrameType::Ack, json!({ "ack": 4 }))),
+            Some(4)
+        );
+        assert_eq!(
+            acked_sequence(&ack(FrameType::Ack, json!({ "handshake": "ok" }))),
+            None
+        );
+        assert_eq!(
+            acked_sequence(&ack(FrameType::Presence, json!({ "ack": 4 }))),
+            None
+        );
+    }
+
+    #[test]
+    fn keepalive_expires_only_after_timeout() {
+        let start = Instant::now();
+        let timeout = Some(Duration::from_secs(90));
+        assert!(!keepalive_expired(
+            start,
+            start + Duration::from_secs(90),
+            timeout
+        ));
+        assert!(keepalive_expired(
+            start,
+            start + Duration::from_secs(91),
+            timeout
+        ));
+        assert!(!keepalive_expired(
+            start,
+            start + Duration::from_secs(900),
+            None
+        ));
+    }
+
+    #[test]
+    fn reactions_round_trip_with_their_sender() {
+        let reaction = Reaction {
+            sender: "device-b".to_string(),
+            emoji: "🎉".to_string(),
+            target: Some(12),
+            target_sender: Some("device-a".to_string()),
+            remove: true,
+        };
+        let bytes = serde_json::to_vec(&reaction.to_json()).unwrap();
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(Reaction::parse(&value), Some(reaction));
+        let legacy = Reaction::parse(&json!({"reaction": "👍", "from": "device-c"})).unwrap();
+        assert_eq!(legacy.sender, "device-c");
+        assert_eq!(legacy.target, None);
+        assert!(!legacy.remove);
+        assert_eq!(Reaction::parse(&json!({"text": "hi"})), None);
+    }
+
+    #[test]
+    fn sequence_tracker_reports_forward_gaps_only() {
+        let mut tracker = SequenceTracker::default();
+        assert_eq!(tracker.observe(7, 10), None);
+        assert_eq!(tracker.observe(7, 11), None);
+        assert_eq!(tracker.observe(7, 14), Some((12, 14)));
+        assert_eq!(tracker.observe(7, 13), None);
+        assert_eq!(tracker.observe(7, 15), None);
+        assert_eq!(tracker.observe(8, 1), None);
+    }
+
+    #[test]
+    fn sequence_tracker_ignores_control_channel() {
+        let mut tracker = SequenceTracker::default();
+        assert_eq!(tracker.observe(0, 1), None);
+        assert_eq!(tracker.observe(0, 50), None);
+    }
+
+    #[test]
+    fn server_capabilities_merge_auth_and_ack() {
+        let mut caps = ServerCapabilities::default();
+        assert_eq!(caps.summary(), "protocol ? · caps: none advertised");
+        caps.absorb(
+            &json!({"session": "s", "protocol_version": 1, "capabilities": ["ZSTD", "noise"]}),
+        );
+        caps.absorb(&json!({"handshake": "ok", "capabilities": ["zstd", "pq", "zstd", 7]}));
+        assert_eq!(caps.protocol_version, Some(1));
+        assert_eq!(caps.capabilities, ["pq", "zstd"]);
+        assert_eq!(caps.summary(), "protocol v1 · caps: pq, zstd");
+    }
+
+    #[test]
+    fn http1_only_servers_get_a_clear_error() {
+        assert!(check_alpn(Some(b"h2")).is_ok());
+        assert!(check_alpn(None).is_ok());
+        let err = check_alpn(Some(b"http/1.1")).unwrap_err().to_string();
+        assert!(
+            err.starts_with("server negotiated http/1.1 instead of h2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn parse_pattern_xk_is_case_insensitive() {
+        assert!(matches!(parse_pattern("xk").unwrap(), HandshakePattern::Xk));
+        assert!(matches!(parse_pattern("XK").unwrap(), HandshakePattern::Xk));
+        assert!(pattern_requires_remote_static(HandshakePattern::Xk));
+    }
+
+    #[test]
+    fn parse_pattern_ik_is_case_insensitive() {
+        assert!(matches!(parse_pattern("ik").unwrap(), HandshakePattern::Ik));
+        assert!(matches!(parse_pattern("Ik").unwrap(), HandshakePattern::Ik));
+        assert!(pattern_requires_remote_static(HandshakePattern::Ik));
+    }
+
+    #[test]
+    fn parse_pattern_reports_unimplemented_patterns() {
+        for name in ["nk", "Xx"] {
+            let err = parse_pattern(name).unwrap_err().to_string();
+            assert!(err.contains("not implemented"), "{err}");
+        }
+        let err = parse_pattern("kk").unwrap_err().to_string();
+        assert!(err.contains("unsupported pattern: KK"), "{err}");
+    }
+
+    /// Что отвечает тестовый сервер на `/connect`.
+    #[derive(Clone)]
+    enum MockScript {
+        Accept {
+            session: &'static str,
+            pairing_required: bool,
+        },
+        Reject(&'static str),
+        /// Обрывает поток посреди кадра AUTH.
+        Truncate,
+    }
+
+    const MOCK_SERVER_PRIVATE: [u8; 32] = [7u8; 32];
+    const MOCK_PROLOGUE: &str = "commucat-test";
+
+    /// Поднимает h2+TLS сервер на 127.0.0.1 и возвращает его порт.
+    /// `/api/server-info` отвечает 404, поэтому проверка ключа пропускается
+    /// и работает закреплённый `server_static`.
+    async fn spawn_mock_server(script: MockScript) -> u16 {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![Certificate(cert.serialize_der().unwrap())],
+                rustls::PrivateKey(cert.serialize_private_key_der()),
+            )
+            .unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let script = script.clone();
+                tokio::spawn(async move {
+                    let Ok(tls) = acceptor.accept(tcp).await else {
+                        return;
+                    };
+                    let Ok(mut conn) = h2::server::handshake(tls).await else {
+                        return;
+                    };
+                    while let Some(Ok((request, respond))) = conn.accept().await {
+                        tokio::spawn(serve_mock_request(request, respond, script.clone()));
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    async fn serve_mock_request(
+        request: http::Request<RecvStream>,
+        mut respond: h2::server::SendResponse<Bytes>,
+        script: MockScript,
+    ) {
+        if request.uri().path() != "/connect" {
+            let response = http::Response::builder().status(404).body(()).unwrap();
+            let _ = respond.send_response(response, true);
+            return;
+        }
+        let mut body = request.into_body();
+        let response = http::Response::builder().status(200).body(()).unwrap();
+        let mut stream = respond.send_response(response, false).unwrap();
+        let mut buffer = BytesMut::new();
+
+        let hello = read_mock_frame(&mut body, &mut buffer).await;
+        assert_eq!(hello.frame_type, FrameType::Hello);
+        let (server_private, server_public) = derive_noise_keys(&MOCK_SERVER_PRIVATE);
+        let mut responder = build_handshake(
+            &NoiseConfig {
+                pattern: HandshakePattern::Xk,
+                prologue: MOCK_PROLOGUE.as_bytes().to_vec(),
+                local_private: server_private,
+                local_static_public: Some(server_public),
+                remote_static_public: None,
+            },
+            false,
+        )
+        .unwrap();
+        responder
+            .read_message(&mock_handshake_bytes(hello))
+            .unwrap();
+
+        match script {
+            MockScript::Accept {
+                session,
+                pairing_required,
+            } => {
+                let payload = json!({ "session": session }).to_string();
+                let reply = responder.write_message(payload.as_bytes()).unwrap();
+                let auth = mock_control(
+                    FrameType::Auth,
+                    1,
+                    json!({ "handshake": encode_hex(&reply) }),
+                );
+                send_frame_raw(&mut stream, auth).await.unwrap();
+                let finish = read_mock_frame(&mut body, &mut buffer).await;
+                assert_eq!(finish.frame_type, FrameType::Auth);
+                assert_eq!(finish.sequence, 2);
+                responder
+                    .read_message(&mock_handshake_bytes(finish))
+                    .unwrap();
+                let ack = mock_control(
+                    FrameType::Ack,
+                    2,
+                    json!({ "handshake": "ok", "pairing_required": pairing_required }),
+                );
+                send_frame_raw(&mut stream, ack).await.unwrap();
+            }
+            MockScript::Reject(reason) => {
+                let error = mock_control(FrameType::Error, 1, json!({ "error": reason }));
+                send_frame_raw(&mut stream, error).await.unwrap();
+                let _ = stream.send_data(Bytes::new(), true);
+            }
+            MockScript::Truncate => {
+                let reply = responder.write_message(&[]).unwrap();
+                let auth = mock_control(
+                    FrameType::Auth,
+                    1,
+                    json!({ "handshake": encode_hex(&reply) }),
+                );
+                let cut = auth.len() / 2;
+                let _ = stream.send_data(Bytes::copy_from_slice(&auth[..cut]), true);
+            }
+        }
+        // Без этого h2 сбросит поток (RST_STREAM) вместо чистого конца.
+        while let Some(Ok(_)) = body.data().await {}
+    }
+
+    async fn read_mock_frame(body: &mut RecvStream, buffer: &mut BytesMut) -> Frame {
+        loop {
+            match Frame::decode(buffer) {
+                Ok((frame, consumed)) => {
+                    buffer.advance(consumed);
+                    return frame;
+                }
+                Err(commucat_proto::CodecError::UnexpectedEof) => {}
+                Err(err) => panic!("mock server decode failed: {:?}", err),
+            }
+            let chunk = body
+                .data()
+                .await
+                .expect("client closed the stream")
+                .unwrap();
+            let _ = body.flow_control().release_capacity(chunk.len());
+            buffer.put_slice(&chunk);
+        }
+    }
+
+    fn mock_handshake_bytes(frame: Frame) -> Vec<u8> {
+        let envelope = control_payload(frame.payload).unwrap();
+        decode_hex(envelope["handshake"].as_str().unwrap()).unwrap()
+    }
+
+    fn mock_control(frame_type: FrameType, sequence: u64, properties: Value) -> Vec<u8> {
+        Frame {
+            channel_id: 0,
+            sequence,
+            frame_type,
+            payload: FramePayload::Control(ControlEnvelope { properties }),
+        }
+        .encode()
+        .unwrap()
+    }
+
+    fn mock_client_state(port: u16) -> ClientState {
+        let (_, server_public) = derive_noise_keys(&MOCK_SERVER_PRIVATE);
+        let mut state = crate::config::test_state();
+        state.device_id = "device-test".to_string();
+        state.server_url = format!("https://localhost:{}", port);
+        state.domain = "localhost".to_string();
+        state.update_keys(&crate::device::generate_keypair().unwrap());
+        state.prologue = MOCK_PROLOGUE.to_string();
+        state.pin_server_key(&encode_hex(&server_public));
+        state.insecure = true;
+        state.connect_timeout_secs = 10;
+        state
+    }
+
+    /// Подключается к тестовому серверу и ждёт `Connected` или `Error`.
+    async fn connect_to_mock(script: MockScript) -> ClientEvent {
+        let port = spawn_mock_server(script).await;
+        let (handle, mut events, _media) = create_engine(8, 64, 8);
+        handle
+            .send(EngineCommand::Connect {
+                state: Box::new(mock_client_state(port)),
+                accept_new_server_key: false,
+            })
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(15), async {
+            loop {
+                let event = events.recv().await.expect("engine stopped");
+                if matches!(
+                    event,
+                    ClientEvent::Connected { .. } | ClientEvent::Error { .. }
+                ) {
+                    return event;
+                }
+            }
+        })
+        .await
+        .expect("no connect outcome")
+    }
+
+    #[tokio::test]
+    async fn handshake_with_mock_server_reports_session() {
+        for pairing in [false, true] {
+            let event = connect_to_mock(MockScript::Accept {
+                session: "sess-42",
+                pairing_required: pairing,
+            })
+            .await;
+            let ClientEvent::Connected {
+                session_id,
+                pairing_required,
+                ..
+            } = event
+            else {
+                panic!("expected Connected, got {:?}", event);
+            };
+            assert_eq!(session_id, "sess-42");
+            assert_eq!(pairing_required, pairing);
+        }
+    }
+
+    #[tokio::test]
+    async fn handshake_error_frame_is_a_rejection() {
+        let event = connect_to_mock(MockScript::Reject("device revoked")).await;
+        let ClientEvent::Error { detail } = event else {
+            panic!("expected Error, got {:?}", event);
+        };
+        assert_eq!(detail, "handshake rejected");
+    }
+
+    #[tokio::test]
+    async fn truncated_handshake_stream_is_reported() {
+        let event = connect_to_mock(MockScript::Truncate).await;
+        let ClientEvent::Error { detail } = event else {
+            panic!("expected Error, got {:?}", event);
+        };
+        assert_eq!(detail, "server closed during handshake");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::ProfileError;
+use crate::rest::{ServerRejected, SessionRejected};
+use std::io::ErrorKind;
+use std::process::ExitCode;
+
+/// Коды выхода CLI: по ним скрипты различают причину отказа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitKind {
+    /// Всё остальное.
+    Failure = 1,
+    /// Профиль (`client.json`) не найден или повреждён.
+    Config = 2,
+    /// Сервер недоступен: DNS, TCP, TLS, таймаут.
+    Network = 3,
+    /// Нет сессионного токена или сервер его отверг (401/403).
+    Auth = 4,
+    /// Сервер отклонил запрос: неверный pair-код, конфликт, 5xx.
+    Rejected = 5,
+    /// Неверные аргументы командной строки (как EX_USAGE).
+    Usage = 64,
+}
+
+impl ExitKind {
+    pub fn classify(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<SessionRejected>().is_some() {
+            return ExitKind::Auth;
+        }
+        if err.downcast_ref::<ServerRejected>().is_some() {
+            return ExitKind::Rejected;
+        }
+        match err.downcast_ref::<ProfileError>() {
+            Some(ProfileError::NoSession) => return ExitKind::Auth,
+            Some(_) => return ExitKind::Config,
+            None => {}
+        }
+        if err.downcast_ref::<reqwest::Error>().is_some() {
+            return ExitKind::Network;
+        }
+        let network_io = err.chain().any(|cause| {
+            cause.downcast_ref::<std::io::Error>().is_some_and(|io| {
+                matches!(
+                    io.kind(),
+                    ErrorKind::ConnectionRefused
+                        | ErrorKind::ConnectionReset
+                        | ErrorKind::ConnectionAborted
+                        | ErrorKind::NotConnected
+                        | ErrorKind::AddrNotAvailable
+                        | ErrorKind::TimedOut
+                )
+            })
+        });
+        if network_io {
+            ExitKind::Network
+        } else {
+            ExitKind::Failure
+        }
+    }
+}
+
+impl From<ExitKind> for ExitCode {
+    fn from(kind: ExitKind) -> Self {
+        ExitCode::from(kind as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+    use reqwest::StatusCode;
+
+    #[test]
+    fn errors_map_to_distinct_codes() {
+        let missing: anyhow::Result<()> =
+            Err(std::io::Error::from(ErrorKind::NotFound)).context(ProfileError::Missing);
+        let missing = missing.context("load profile").unwrap_err();
+        assert_eq!(ExitKind::classify(&missing), ExitKind::Config);
+
+        let refused = anyhow::Error::new(std::io::Error::from(ErrorKind::ConnectionRefused))
+            .context("request /api/devices");
+        assert_eq!(ExitKind::classify(&refused), ExitKind::Network);
+
+        let expired = anyhow::Error::new(SessionRejected {
+            status: StatusCode::UNAUTHORIZED,
+            detail: "expired".to_string(),
+        });
+        assert_eq!(ExitKind::classify(&expired), ExitKind::Auth);
+        let no_session = anyhow!(ProfileError::NoSession);
+        assert_eq!(ExitKind::classify(&no_session), ExitKind::Auth);
+
+        let bad_code =
+            anyhow::Error::new(ServerRejected("pair code not found".to_string())).context("claim");
+        assert_eq!(ExitKind::classify(&bad_code), ExitKind::Rejected);
+
+        assert_eq!(ExitKind::classify(&anyhow!("boom")), ExitKind::Failure);
+    }
+}
@@ -0,0 +1,255 @@
+use commucat_proto::{Frame, FramePayload, FrameType};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Кадры, у которых важно только последнее значение: при заторе очереди
+/// событий новый кадр с тем же ключом заменяет ещё не отданный старый.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoalesceKey {
+    Presence(String),
+    Typing(u64, String),
+    CallStats(String),
+}
+
+impl CoalesceKey {
+    pub fn of(frame: &Frame) -> Option<Self> {
+        let FramePayload::Control(envelope) = &frame.payload else {
+            return None;
+        };
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| envelope.properties.get(*name)?.as_str())
+                .map(str::to_string)
+        };
+        match frame.frame_type {
+            FrameType::Presence => field(&["entity"]).map(CoalesceKey::Presence),
+            FrameType::Typing => field(&["device", "device_id", "sender"])
+                .map(|device| CoalesceKey::Typing(frame.channel_id, device)),
+            FrameType::CallStats => field(&["call_id"]).map(CoalesceKey::CallStats),
+            _ => None,
+        }
+    }
+}
+
+/// Голос и видео идут отдельным каналом: опоздавший медиакадр бесполезен,
+/// поэтому при переполнении его выбрасывают, а не ждут.
+pub fn is_media(frame_type: FrameType) -> bool {
+    matches!(frame_type, FrameType::VoiceFrame | FrameType::VideoFrame)
+}
+
+/// Отложенные при заторе кадры в порядке первого появления ключа.
+#[derive(Default)]
+pub struct Coalescer {
+    pending: VecDeque<(CoalesceKey, Frame)>,
+}
+
+impl Coalescer {
+    /// `true`, если кадр заменил ещё не отданный кадр с тем же ключом.
+    pub fn push(&mut self, key: CoalesceKey, frame: Frame) -> bool {
+        match self.pending.iter_mut().find(|(known, _)| *known == key) {
+            Some((_, slot)) => {
+                *slot = frame;
+                true
+            }
+            None => {
+                self.pending.push_back((key, frame));
+                false
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Frame> {
+        self.pending.pop_front().map(|(_, frame)| frame)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Счётчики потерь задачи чтения; общие для движка и TUI.
+#[derive(Debug, Default)]
+pub struct FlowStats {
+    coalesced: AtomicU64,
+    media_dropped: AtomicU64,
+}
+
+impl FlowStats {
+    pub fn record_coalesced(&self) {
+        self.coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_media_dropped(&self) {
+        self.media_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Заменённые при заторе кадры presence/typing/stats.
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// Выброшенные из переполненной очереди голосовые и видеокадры.
+    pub fn media_dropped(&self) -> u64 {
+        self.media_dropped.load(Ordering::Relaxed)
+    }
+
+    fn counts(&self) -> FlowCounts {
+        FlowCounts {
+            media_dropped: self.media_dropped(),
+            coalesced: self.coalesced(),
+        }
+    }
+}
+
+/// Потери за отрезок времени.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FlowCounts {
+    pub media_dropped: u64,
+    pub coalesced: u64,
+}
+
+impl FlowCounts {
+    fn since(self, base: Self) -> Self {
+        Self {
+            media_dropped: self.media_dropped.saturating_sub(base.media_dropped),
+            coalesced: self.coalesced.saturating_sub(base.coalesced),
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.media_dropped == 0 && self.coalesced == 0
+    }
+}
+
+/// Потери за прошлое и текущее окно по накопительным `FlowStats`:
+/// предупреждение в строке статуса гаснет, когда затор прошёл.
+pub struct FlowWindow {
+    window: Duration,
+    started: Instant,
+    /// Накопленные счётчики на начало текущего окна.
+    base: FlowCounts,
+    previous: FlowCounts,
+    current: FlowCounts,
+}
+
+impl FlowWindow {
+    pub fn new(window: Duration, stats: &FlowStats, now: Instant) -> Self {
+        Self {
+            window,
+            started: now,
+            base: stats.counts(),
+            previous: FlowCounts::default(),
+            current: FlowCounts::default(),
+        }
+    }
+
+    pub fn sample(&mut self, stats: &FlowStats, now: Instant) {
+        let total = stats.counts();
+        if now.saturating_duration_since(self.started) >= self.window {
+            self.previous = total.since(self.base);
+            self.base = total;
+            self.started = now;
+        }
+        self.current = total.since(self.base);
+    }
+
+    /// Потери не старше двух окон.
+    pub fn recent(&self) -> FlowCounts {
+        FlowCounts {
+            media_dropped: self.previous.media_dropped + self.current.media_dropped,
+            coalesced: self.previous.coalesced + self.current.coalesced,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commucat_proto::ControlEnvelope;
+    use serde_json::json;
+
+    fn control(frame_type: FrameType, channel_id: u64, properties: serde_json::Value) -> Frame {
+        Frame {
+            channel_id,
+            sequence: 1,
+            frame_type,
+            payload: FramePayload::Control(ControlEnvelope { properties }),
+        }
+    }
+
+    #[test]
+    fn only_the_latest_state_per_key_survives_a_backlog() {
+        let online = control(
+            FrameType::Presence,
+            0,
+            json!({"entity": "alice", "state": "online"}),
+        );
+        let away = control(
+            FrameType::Presence,
+            0,
+            json!({"entity": "alice", "state": "away"}),
+        );
+        let bob = control(
+            FrameType::Presence,
+            0,
+            json!({"entity": "bob", "state": "online"}),
+        );
+        let typing = control(
+            FrameType::Typing,
+            7,
+            json!({"device": "d1", "typing": true}),
+        );
+        let msg = Frame {
+            channel_id: 7,
+            sequence: 2,
+            frame_type: FrameType::Msg,
+            payload: FramePayload::Opaque(b"hi".to_vec()),
+        };
+        assert_eq!(CoalesceKey::of(&msg), None);
+        assert_eq!(
+            CoalesceKey::of(&typing),
+            Some(CoalesceKey::Typing(7, "d1".to_string()))
+        );
+
+        let mut coalescer = Coalescer::default();
+        for frame in [online, bob.clone(), away.clone(), typing.clone()] {
+            let key = CoalesceKey::of(&frame).unwrap();
+            coalescer.push(key, frame);
+        }
+        assert_eq!(coalescer.pop(), Some(away));
+        assert_eq!(coalescer.pop(), Some(bob));
+        assert_eq!(coalescer.pop(), Some(typing));
+        assert!(coalescer.is_empty());
+
+        assert!(is_media(FrameType::VideoFrame));
+        assert!(!is_media(FrameType::CallStats));
+    }
+
+    #[test]
+    fn losses_fade_once_the_backlog_clears() {
+        let stats = FlowStats::default();
+        stats.record_media_dropped();
+        let start = Instant::now();
+        let window = Duration::from_secs(10);
+        let mut flow = FlowWindow::new(window, &stats, start);
+        assert!(flow.recent().is_empty());
+
+        stats.record_media_dropped();
+        stats.record_coalesced();
+        flow.sample(&stats, start + Duration::from_secs(1));
+        assert_eq!(
+            flow.recent(),
+            FlowCounts {
+                media_dropped: 1,
+                coalesced: 1
+            }
+        );
+
+        flow.sample(&stats, start + window);
+        assert_eq!(flow.recent().media_dropped, 1);
+        flow.sample(&stats, start + window * 2);
+        assert!(flow.recent().is_empty());
+    }
+}
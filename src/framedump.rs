@@ -0,0 +1,205 @@
+use crate::config::cache_path;
+use crate::hexutil::{decode_hex, encode_hex};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use commucat_proto::{Frame, FramePayload, FrameType};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Путь к журналу входящих кадров; без переменной журнал не пишется.
+pub const FRAME_DUMP_ENV: &str = "COMMUCAT_FRAME_DUMP";
+/// Значение переменной, при котором журнал ложится в кэш клиента.
+const FRAME_DUMP_AUTO: &str = "auto";
+const SUMMARY_LIMIT: usize = 200;
+
+/// Одна строка журнала кадров (JSON Lines).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameRecord {
+    /// Момент приёма, RFC 3339.
+    pub at: String,
+    pub frame_type: FrameType,
+    pub channel_id: u64,
+    pub sequence: u64,
+    /// Краткое содержимое для чтения глазами.
+    pub payload: String,
+    /// Кадр в том виде, в каком пришёл по сети (hex); по нему работает replay.
+    pub raw: String,
+}
+
+impl FrameRecord {
+    pub fn new(frame: &Frame, raw: &[u8], at: DateTime<Utc>) -> Self {
+        Self {
+            at: at.to_rfc3339(),
+            frame_type: frame.frame_type,
+            channel_id: frame.channel_id,
+            sequence: frame.sequence,
+            payload: summarize(&frame.payload),
+            raw: encode_hex(raw),
+        }
+    }
+
+    pub fn frame(&self) -> Result<Frame> {
+        let raw = decode_hex(&self.raw)?;
+        let (frame, _) = Frame::decode(&raw).map_err(|err| anyhow!("decode frame: {:?}", err))?;
+        Ok(frame)
+    }
+}
+
+/// Control — JSON без лишних пробелов, Opaque — только размер.
+pub fn summarize(payload: &FramePayload) -> String {
+    match payload {
+        FramePayload::Control(envelope) => {
+            let mut text = envelope.properties.to_string();
+            if text.len() > SUMMARY_LIMIT {
+                let mut end = SUMMARY_LIMIT;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text.truncate(end);
+                text.push('…');
+            }
+            text
+        }
+        FramePayload::Opaque(data) => format!("opaque {} bytes", data.len()),
+    }
+}
+
+/// Тело сообщения для просмотра: JSON с отступами, иначе текст,
+/// а не UTF-8 — hex.
+pub fn pretty_body(data: &[u8]) -> String {
+    if let Ok(value) = serde_json::from_slice::<serde_json::Value>(data)
+        && let Ok(pretty) = serde_json::to_string_pretty(&value)
+    {
+        return pretty;
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => encode_hex(data),
+    }
+}
+
+/// Дописывает принятые кадры в файл из `COMMUCAT_FRAME_DUMP`.
+pub struct FrameDump {
+    writer: LineWriter<File>,
+}
+
+impl FrameDump {
+    /// `None`, если переменная не задана или файл не открылся (с предупреждением в журнал).
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var_os(FRAME_DUMP_ENV)?;
+        let path = if path == FRAME_DUMP_AUTO {
+            match cache_path("frames.jsonl") {
+                Ok(path) => path,
+                Err(err) => {
+                    tracing::warn!("frame dump disabled: {:#}", err);
+                    return None;
+                }
+            }
+        } else {
+            PathBuf::from(path)
+        };
+        match Self::open(&path) {
+            Ok(dump) => Some(dump),
+            Err(err) => {
+                tracing::warn!("frame dump disabled: {:#}", err);
+                None
+            }
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        // В журнале содержимое кадров: читать его может только владелец.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .with_context(|| format!("open frame dump {}", path.display()))?;
+        Ok(Self {
+            writer: LineWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, frame: &Frame, raw: &[u8]) -> Result<()> {
+        let line = serde_json::to_string(&FrameRecord::new(frame, raw, Utc::now()))
+            .context("serialize frame record")?;
+        writeln!(self.writer, "{}", line).context("write frame dump")
+    }
+}
+
+/// Читает журнал кадров для `replay`; пустые строки пропускаются.
+pub fn read_dump(path: &Path) -> Result<Vec<Frame>> {
+    let data =
+        fs::read_to_string(path).with_context(|| format!("read frame dump {}", path.display()))?;
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<FrameRecord>(line)
+                .context("invalid frame record")
+                .and_then(|record| record.frame())
+                .with_context(|| format!("line {}", index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commucat_proto::ControlEnvelope;
+    use serde_json::json;
+
+    #[test]
+    fn dumped_frames_replay_byte_for_byte() {
+        let path =
+            std::env::temp_dir().join(format!("commucat-frames-{}.jsonl", std::process::id()));
+        let frames = [
+            Frame {
+                channel_id: 7,
+                sequence: 3,
+                frame_type: FrameType::Msg,
+                payload: FramePayload::Opaque(b"hello".to_vec()),
+            },
+            Frame {
+                channel_id: 0,
+                sequence: 4,
+                frame_type: FrameType::Presence,
+                payload: FramePayload::Control(ControlEnvelope {
+                    properties: json!({"user": "alice", "state": "online"}),
+                }),
+            },
+        ];
+        let mut dump = FrameDump::open(&path).unwrap();
+        for frame in &frames {
+            dump.record(frame, &frame.encode().unwrap()).unwrap();
+        }
+        drop(dump);
+
+        let text = fs::read_to_string(&path).unwrap();
+        let first: FrameRecord = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(first.payload, "opaque 5 bytes");
+        assert_eq!(first.channel_id, 7);
+        assert_eq!(read_dump(&path).unwrap(), frames);
+
+        fs::write(&path, "{\"broken\": true}\n").unwrap();
+        let err = read_dump(&path).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 1"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn bodies_are_pretty_printed() {
+        assert_eq!(pretty_body(br#"{"text":"hi"}"#), "{\n  \"text\": \"hi\"\n}");
+        assert_eq!(pretty_body(b"plain"), "plain");
+        assert_eq!(pretty_body(&[0xff, 0x00]), "ff00");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::rest::FriendEntryPayload;
+use anyhow::{Context, Result, bail};
+
+const HEADER: [&str; 3] = ["user_id", "handle", "alias"];
+
+/// CSV `user_id,handle,alias` для `friends export`; поля с запятыми,
+/// кавычками и переводами строк берутся в кавычки по RFC 4180.
+pub fn to_csv(entries: &[FriendEntryPayload]) -> String {
+    let mut out = format!("{}\n", HEADER.join(","));
+    for entry in entries {
+        let fields = [
+            entry.user_id.as_str(),
+            entry.handle.as_deref().unwrap_or(""),
+            entry.alias.as_deref().unwrap_or(""),
+        ];
+        let row: Vec<String> = fields.into_iter().map(quote).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn from_csv(raw: &str) -> Result<Vec<FriendEntryPayload>> {
+    let mut records = parse_records(raw)?
+        .into_iter()
+        .filter(|(_, record)| record.iter().any(|field| !field.trim().is_empty()));
+    let (_, header) = records.next().context("friends csv is empty")?;
+    let columns: Vec<&str> = header.iter().map(|column| column.trim()).collect();
+    if columns != HEADER {
+        bail!("ожидается заголовок CSV user_id,handle,alias");
+    }
+    let optional = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let mut entries = Vec::new();
+    for (line, fields) in records {
+        if fields.len() > HEADER.len() {
+            bail!(
+                "строка {}: {} полей вместо {} (запятые внутри поля берутся в кавычки)",
+                line,
+                fields.len(),
+                HEADER.len()
+            );
+        }
+        let mut fields = fields.into_iter();
+        let user_id = fields.next().unwrap_or_default();
+        let user_id = user_id.trim();
+        if user_id.is_empty() {
+            bail!("строка {}: пустой user_id", line);
+        }
+        entries.push(FriendEntryPayload {
+            user_id: user_id.to_string(),
+            handle: optional(fields.next()),
+            alias: optional(fields.next()),
+        });
+    }
+    Ok(entries)
+}
+
+/// Поле в кавычках, если без них его не прочитать; кавычки внутри удваиваются.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Записи CSV по RFC 4180 с номером строки, где запись начинается: поле
+/// в кавычках может содержать запятые, переводы строк и удвоенные кавычки.
+fn parse_records(raw: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\n' {
+            line += 1;
+        }
+        if quoted {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(ch),
+            }
+            continue;
+        }
+        match ch {
+            '"' => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                start = line;
+            }
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        bail!("строка {}: в CSV не закрыта кавычка", start);
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(user_id: &str, handle: Option<&str>, alias: Option<&str>) -> FriendEntryPayload {
+        FriendEntryPayload {
+            user_id: user_id.to_string(),
+            handle: handle.map(str::to_string),
+            alias: alias.map(str::to_string),
+        }
+    }
+
+    fn fields(entries: &[FriendEntryPayload]) -> Vec<(String, Option<String>, Option<String>)> {
+        entries
+            .iter()
+            .map(|e| (e.user_id.clone(), e.handle.clone(), e.alias.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn awkward_fields_round_trip() {
+        let entries = vec![
+            entry("u1", Some("smith"), Some("Smith, John")),
+            entry("u2", None, Some("say \"hi\"\nsecond line")),
+            entry("u3", Some("plain"), None),
+        ];
+        let csv = to_csv(&entries);
+        assert!(csv.contains("\"Smith, John\""));
+        assert_eq!(fields(&from_csv(&csv).unwrap()), fields(&entries));
+    }
+
+    #[test]
+    fn broken_files_name_the_line() {
+        let crlf = from_csv("user_id,handle,alias\r\nu1,smith,\"Smith, John\"\r\n\r\n").unwrap();
+        assert_eq!(crlf[0].alias.as_deref(), Some("Smith, John"));
+
+        let err = from_csv("user_id,handle,alias\n\nu1,\"two\nlines\",x\nu2,smith,Smith, John\n")
+            .unwrap_err();
+        assert!(err.to_string().starts_with("строка 5:"), "{}", err);
+        let err = from_csv("user_id,handle,alias\n\n\n,x,y").unwrap_err();
+        assert!(err.to_string().starts_with("строка 4:"), "{}", err);
+
+        assert!(from_csv("user_id,handle,alias\nu1,\"open").is_err());
+        assert!(from_csv("id,name\nu1,x").is_err());
+        assert!(from_csv("").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use ratatui::buffer::Buffer;
+use unicode_width::UnicodeWidthStr;
+
+/// ASCII-замены значков интерфейса. Замена не шире исходного символа,
+/// поэтому раскладка экрана после подстановки не съезжает.
+const FALLBACKS: &[(&str, &str)] = &[
+    // Вкладки и действия
+    ("💬", "#"),
+    ("👥", "G"),
+    ("👫", "F"),
+    ("📞", "C"),
+    ("📴", "x"),
+    ("📵", "x"),
+    ("📹", "V"),
+    ("🎥", "V"),
+    ("🎤", "M"),
+    ("🎙", "M"),
+    ("📱", "D"),
+    ("💻", "D"),
+    ("⚙", "*"),
+    ("🐱", "=^"),
+    ("⌘", "K"),
+    ("📎", "@"),
+    ("➕", "+"),
+    ("✕", "x"),
+    ("×", "x"),
+    // Статусы
+    ("🟢", "o"),
+    ("⚫", "."),
+    ("🔴", "x"),
+    ("●", "o"),
+    ("✓", "v"),
+    ("✅", "v"),
+    ("✗", "x"),
+    ("❌", "x"),
+    ("⛔", "x"),
+    ("🚫", "x"),
+    ("⚠", "!"),
+    ("‼", "!"),
+    ("❓", "?"),
+    ("❔", "?"),
+    ("ℹ", "i"),
+    ("⏳", "~"),
+    ("🔕", "z"),
+    ("🔔", "b"),
+    ("🔇", "m"),
+    ("🔊", "s"),
+    ("🔐", "k"),
+    ("🔏", "k"),
+    ("🔑", "k"),
+    ("🔓", "u"),
+    ("🔗", "&"),
+    ("🔁", "r"),
+    ("🔌", "p"),
+    ("📶", "q"),
+    ("📡", "q"),
+    ("🛰", "q"),
+    ("📊", "%"),
+    ("🧩", "P"),
+    ("👑", "^"),
+    ("⭐", "*"),
+    ("★", "*"),
+    ("✨", "*"),
+    ("✧", "*"),
+    // Уведомления и сообщения
+    ("💌", ">"),
+    ("📣", "@"),
+    ("📨", ">"),
+    ("👋", "~"),
+    ("🎉", "!"),
+    ("🎵", "~"),
+    ("♪", "~"),
+    ("🎟", "T"),
+    ("📸", "P"),
+    ("👤", "U"),
+    ("🆔", "#"),
+    ("📅", "d"),
+    ("🕒", "t"),
+    ("🏷", "N"),
+    ("🎨", "T"),
+    ("📝", "m"),
+    ("😊", ":)"),
+    ("📋", "="),
+    ("📜", "="),
+    ("📌", "*"),
+    ("📍", "*"),
+    ("💾", "S"),
+    ("⏹", "#"),
+    ("⏪", "<<"),
+    ("▶", ">"),
+    ("💭", "."),
+    ("💤", "z"),
+    // Пунктуация и стрелки
+    ("·", "|"),
+    ("•", "*"),
+    ("…", "."),
+    ("—", "-"),
+    ("→", ">"),
+    ("←", "<"),
+    ("↑", "^"),
+    ("↓", "v"),
+    ("›", ">"),
+    ("≈", "~"),
+    ("≋", "~"),
+    ("°", "o"),
+    // Котики и kaomoji
+    ("∧", "^"),
+    ("ω", "w"),
+    ("≧", ">"),
+    ("≦", "<"),
+    ("˘", "-"),
+    ("∀", "A"),
+    ("⊂", "c"),
+    ("⊃", "D"),
+    ("つ", "c"),
+    ("と", "C"),
+    ("◕", "o"),
+    ("‿", "_"),
+    ("◡", "u"),
+    ("◠", "n"),
+    ("▽", "v"),
+    ("♡", "<"),
+    ("✿", "*"),
+    ("✌", "V"),
+    ("ʕ", "("),
+    ("ʔ", ")"),
+    ("ᴥ", "w"),
+    ("﹏", "_"),
+    ("´", "'"),
+    ("･", "."),
+    ("・", "."),
+    ("ﾟ", "'"),
+    ("｡", "."),
+    // Спиннеры
+    ("⠋", "|"),
+    ("⠙", "/"),
+    ("⠹", "-"),
+    ("⠸", "\\"),
+    ("⠼", "|"),
+    ("⠴", "/"),
+    ("⠦", "-"),
+    ("⠧", "\\"),
+    ("⠇", "|"),
+    ("⠏", "/"),
+    ("◐", "|"),
+    ("◓", "/"),
+    ("◑", "-"),
+    ("◒", "\\"),
+];
+
+/// Замена из таблицы `FALLBACKS`, без учёта селектора варианта.
+fn table_fallback(symbol: &str) -> Option<&'static str> {
+    let symbol = symbol.trim_end_matches('\u{fe0f}');
+    FALLBACKS
+        .iter()
+        .find(|(glyph, _)| *glyph == symbol)
+        .map(|(_, ascii)| *ascii)
+}
+
+/// ASCII-замена для символа из таблицы или по его блоку Unicode.
+pub fn ascii_for(symbol: &str) -> &'static str {
+    if let Some(ascii) = table_fallback(symbol) {
+        return ascii;
+    }
+    match symbol.chars().next() {
+        Some('─' | '━' | '═' | '┄' | '┈' | '╌') => "-",
+        Some('│' | '┃' | '║' | '┆' | '┊' | '╎' | '▏' | '▕') => "|",
+        Some('\u{2500}'..='\u{257f}') => "+",
+        Some('\u{2580}'..='\u{259f}') => "#",
+        Some('\u{2800}'..='\u{28ff}') => "*",
+        _ => "?",
+    }
+}
+
+/// Заменяет в готовом кадре значки и псевдографику. Буквы (кириллица в
+/// подписях, имена, текст сообщений) остаются как есть, кроме тех, что
+/// есть в таблице: ω, つ, ʕ и другие части kaomoji. Широкий символ
+/// занимает несколько ячеек; замена пишется в них же и добивается пробелами.
+pub fn asciify(buffer: &mut Buffer) {
+    let width = buffer.area.width as usize;
+    for row in buffer.content.chunks_mut(width.max(1)) {
+        let mut x = 0;
+        while x < row.len() {
+            let symbol = row[x].symbol();
+            if symbol.is_ascii()
+                || (table_fallback(symbol).is_none()
+                    && symbol.chars().next().is_some_and(char::is_alphabetic))
+            {
+                x += 1;
+                continue;
+            }
+            let cells = symbol.width().clamp(1, row.len() - x);
+            let mut ascii = ascii_for(symbol).chars();
+            for cell in &mut row[x..x + cells] {
+                cell.set_char(ascii.next().unwrap_or(' '));
+            }
+            x += cells;
+        }
+    }
+}
+
+/// Терминал без Unicode: консоль Linux, `dumb`, VT-эмуляторы или локаль
+/// без UTF-8. Отсутствие локали считается UTF-8.
+pub fn ascii_terminal(term: Option<&str>, locale: Option<&str>) -> bool {
+    let term = term.unwrap_or_default();
+    if matches!(term, "linux" | "dumb" | "ansi" | "cons25") || term.starts_with("vt") {
+        return true;
+    }
+    locale
+        .filter(|locale| !locale.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            !(locale.contains("utf-8") || locale.contains("utf8"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    #[test]
+    fn fallbacks_fit_the_glyph_they_replace() {
+        for (glyph, ascii) in FALLBACKS {
+            assert!(ascii.is_ascii(), "{}", glyph);
+            assert!(
+                ascii.len() <= glyph.width().max(1),
+                "{} -> {}",
+                glyph,
+                ascii
+            );
+        }
+        assert_eq!(ascii_for("⚙️"), "*");
+        assert_eq!(ascii_for("╭"), "+");
+        assert_eq!(ascii_for("ツ"), "?");
+    }
+
+    #[test]
+    fn wide_glyphs_keep_the_layout() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 8, 2));
+        buffer.set_string(0, 0, "🟢 bob│", Style::default());
+        buffer.set_string(0, 1, "╭─✓", Style::default());
+        asciify(&mut buffer);
+        let rows: Vec<String> = buffer
+            .content
+            .chunks(8)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect();
+        assert_eq!(rows, ["o  bob| ", "+-v     "]);
+    }
+
+    #[test]
+    fn letters_survive_the_pass() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 9, 1));
+        buffer.set_string(0, 0, "💬 Чат ✓", Style::default());
+        asciify(&mut buffer);
+        let row: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        assert_eq!(row, "#  Чат v ");
+    }
+
+    #[test]
+    fn kaomoji_letters_use_their_fallbacks() {
+        let (_, kaomoji, _) = crate::tui::KAWAII_REACTIONS
+            .iter()
+            .find(|(name, _, _)| *name == "thinking")
+            .unwrap();
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 12, 1));
+        buffer.set_string(0, 0, kaomoji, Style::default());
+        asciify(&mut buffer);
+        let row: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(row.is_ascii(), "{}", row);
+        assert_eq!(row.trim_end(), "(..w..)");
+    }
+
+    #[test]
+    fn bare_consoles_and_non_utf8_locales_need_ascii() {
+        assert!(ascii_terminal(Some("linux"), Some("en_US.UTF-8")));
+        assert!(ascii_terminal(Some("vt100"), None));
+        assert!(ascii_terminal(Some("xterm-256color"), Some("C")));
+        assert!(!ascii_terminal(Some("xterm-256color"), Some("ru_RU.utf8")));
+        assert!(!ascii_terminal(Some("screen"), None));
+    }
+}
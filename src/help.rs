@@ -0,0 +1,152 @@
+/// Горячая клавиша для справки F1. Подписи в панелях TUI берутся отсюда же,
+/// поэтому новую клавишу достаточно добавить в `KEYBINDINGS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub keys: &'static str,
+    /// Где действует клавиша: `Global` или название вкладки/оверлея.
+    pub context: &'static str,
+    pub description: &'static str,
+    /// Значок для панели быстрых действий; пусто — в панель не попадает.
+    pub icon: &'static str,
+}
+
+/// Порядок разделов в справке.
+pub const CONTEXTS: &[&str] = &[
+    "Global", "Chat", "Calls", "Voice", "Friends", "Devices", "Settings", "Overlays",
+];
+
+const fn bind(
+    keys: &'static str,
+    context: &'static str,
+    description: &'static str,
+    icon: &'static str,
+) -> KeyBinding {
+    KeyBinding {
+        keys,
+        context,
+        description,
+        icon,
+    }
+}
+
+pub const KEYBINDINGS: &[KeyBinding] = &[
+    bind("F1", "Global", "Show/hide this help", ""),
+    bind("F2", "Global", "My info and friend link", ""),
+    bind("Ctrl+K", "Global", "Commands", "⌘"),
+    bind("F12", "Global", "Engine log", "📜"),
+    bind("Ctrl+F3", "Global", "Voice call", "📞"),
+    bind("Ctrl+F4", "Global", "Video call", "🎥"),
+    bind("Ctrl+F5", "Global", "Send file", "📎"),
+    bind("Ctrl+F6", "Global", "Voice message", "🎤"),
+    bind("Ctrl+F7", "Global", "Add member", "➕"),
+    bind("Ctrl+F8", "Global", "Settings", "⚙️"),
+    bind("1-6, 9", "Global", "Switch view", ""),
+    bind("Tab", "Global", "Next view", ""),
+    bind("↑/↓", "Global", "Previous/next channel", ""),
+    bind("Alt+1..9", "Global", "Insert a reaction emoji", ""),
+    bind("Enter", "Global", "Send message or run /command", ""),
+    bind(
+        "Esc",
+        "Global",
+        "Clear the input line, or cancel the newest task if it is empty",
+        "",
+    ),
+    bind(
+        "F10",
+        "Global",
+        "Quit (asks first during a call, recording or draft)",
+        "",
+    ),
+    bind("Ctrl+C", "Global", "Quit immediately", ""),
+    bind("Ctrl+S", "Chat", "Select messages", ""),
+    bind("Ctrl+R", "Chat", "Retry failed messages", ""),
+    bind("Ctrl+N", "Chat", "Mute or unmute the channel", ""),
+    bind("↑/↓, j/k", "Chat", "Move the selection", ""),
+    bind("y", "Chat", "Copy the selected message", ""),
+    bind("r", "Chat", "React to the selected message", ""),
+    bind("o", "Chat", "Open a link from the selected message", ""),
+    bind("s", "Chat", "Save the selected file", ""),
+    bind("v", "Chat", "Show the raw payload (--debug-frames)", ""),
+    bind("Esc", "Chat", "Leave selection mode", ""),
+    bind("c", "Calls", "Start a voice call", ""),
+    bind("v", "Calls", "Start a video call", ""),
+    bind("a", "Calls", "Accept the incoming call", ""),
+    bind("x", "Calls", "Reject the incoming call", ""),
+    bind("m", "Calls", "Mute/unmute the microphone", ""),
+    bind("e", "Calls", "End the active call", ""),
+    bind("Space", "Voice", "Start/stop recording", ""),
+    bind("p", "Voice", "Play the last message", ""),
+    bind("s", "Voice", "Save the selected message as WAV", ""),
+    bind("↑/↓", "Voice", "Select a message", ""),
+    bind("↑/↓", "Friends", "Select a friend", ""),
+    bind("a", "Friends", "Add or update a friend", ""),
+    bind("d", "Friends", "Remove the selected friend", ""),
+    bind("Enter", "Friends", "Message the selected friend", ""),
+    bind("r", "Friends", "Pull the list from the server", ""),
+    bind("p", "Friends", "Push the list to the server", ""),
+    bind("r", "Devices", "Refresh devices", ""),
+    bind("f", "Devices", "Cycle the status filter", ""),
+    bind("↑/↓", "Devices", "Select a device", ""),
+    bind("x", "Devices", "Revoke the selected device", ""),
+    bind("PageDown", "Devices", "Load more", ""),
+    bind("t", "Settings", "Change theme", ""),
+    bind("a", "Settings", "Toggle animations", ""),
+    bind("s", "Settings", "Toggle sound", ""),
+    bind("e", "Settings", "Toggle emoji mode", ""),
+    bind("m", "Settings", "Toggle Markdown rendering", ""),
+    bind("c", "Settings", "Toggle connecting on launch", ""),
+    bind("i", "Settings", "Toggle the splash screen", ""),
+    bind("b", "Settings", "Toggle mentions in muted channels", ""),
+    bind("r", "Settings", "Switch absolute/relative timestamps", ""),
+    bind("n", "Settings", "Prefer server names over aliases", ""),
+    bind("v", "Settings", "Toggle verbose presence notifications", ""),
+    bind("Esc", "Overlays", "Close the open overlay", ""),
+    bind(
+        "↑/↓, PgUp/PgDn",
+        "Overlays",
+        "Scroll help and engine log",
+        "",
+    ),
+    bind(
+        "Home, End",
+        "Overlays",
+        "Engine log: top / follow the tail",
+        "",
+    ),
+    bind("r", "Overlays", "P2P assist report: refresh", ""),
+];
+
+/// Клавиши одного раздела в порядке таблицы.
+pub fn bindings_for(context: &'static str) -> impl Iterator<Item = &'static KeyBinding> {
+    KEYBINDINGS
+        .iter()
+        .filter(move |binding| binding.context == context)
+}
+
+/// Клавиши со значком для панели быстрых действий.
+pub fn quick_actions() -> impl Iterator<Item = &'static KeyBinding> {
+    KEYBINDINGS
+        .iter()
+        .filter(|binding| !binding.icon.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn every_binding_belongs_to_a_known_section_once() {
+        let mut seen = HashSet::new();
+        for binding in KEYBINDINGS {
+            assert!(CONTEXTS.contains(&binding.context), "{:?}", binding);
+            assert!(
+                seen.insert((binding.context, binding.keys)),
+                "duplicate {:?}",
+                binding
+            );
+        }
+        assert_eq!(bindings_for("Settings").count(), 11);
+        assert!(quick_actions().any(|binding| binding.keys == "Ctrl+K"));
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// Соответствие device_id ↔ user_id. Кадры несут то одно, то другое;
+/// по этой таблице имена, статусы и участники сводятся к одному человеку.
+#[derive(Debug, Default)]
+pub struct Identities {
+    user_by_device: HashMap<String, String>,
+    devices_by_user: HashMap<String, BTreeSet<String>>,
+}
+
+impl Identities {
+    /// Запоминает, что устройство принадлежит пользователю; устройство,
+    /// переехавшее к другому пользователю, отвязывается от прежнего.
+    pub fn link(&mut self, device_id: &str, user_id: &str) {
+        if device_id.is_empty() || user_id.is_empty() || device_id == user_id {
+            return;
+        }
+        if let Some(previous) = self
+            .user_by_device
+            .insert(device_id.to_string(), user_id.to_string())
+            && previous != user_id
+            && let Some(devices) = self.devices_by_user.get_mut(&previous)
+        {
+            devices.remove(device_id);
+        }
+        self.devices_by_user
+            .entry(user_id.to_string())
+            .or_default()
+            .insert(device_id.to_string());
+    }
+
+    /// user_id для устройства; неизвестный id считается user_id сам по себе.
+    pub fn user_of<'a>(&'a self, id: &'a str) -> &'a str {
+        self.user_by_device
+            .get(id)
+            .map(String::as_str)
+            .unwrap_or(id)
+    }
+
+    /// Известные устройства пользователя (или самого устройства).
+    pub fn devices_of<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.devices_by_user
+            .get(self.user_of(id))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Один ли человек стоит за двумя id (устройства или пользователя).
+    pub fn same(&self, a: &str, b: &str) -> bool {
+        a == b || self.user_of(a) == self.user_of(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_and_users_resolve_to_one_person() {
+        let mut ids = Identities::default();
+        ids.link("dev-a1", "alice");
+        ids.link("dev-a2", "alice");
+        ids.link("dev-b1", "bob");
+
+        assert_eq!(ids.user_of("dev-a2"), "alice");
+        assert_eq!(ids.user_of("alice"), "alice");
+        assert_eq!(ids.user_of("stranger"), "stranger");
+        assert!(ids.same("dev-a1", "alice"));
+        assert!(ids.same("dev-a1", "dev-a2"));
+        assert!(!ids.same("dev-a1", "dev-b1"));
+        assert_eq!(
+            ids.devices_of("dev-a1").collect::<Vec<_>>(),
+            ["dev-a1", "dev-a2"]
+        );
+
+        ids.link("dev-a2", "bob");
+        assert_eq!(ids.devices_of("alice").collect::<Vec<_>>(), ["dev-a1"]);
+        assert_eq!(
+            ids.devices_of("bob").collect::<Vec<_>>(),
+            ["dev-a2", "dev-b1"]
+        );
+        assert_eq!(ids.devices_of("stranger").count(), 0);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::hexutil::{decode_hex, decode_hex32, encode_hex};
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+
+/// Префикс bech32 для публичных ключей (устройства и CA).
+pub const PUBLIC_HRP: &str = "ccpub";
+/// Префикс bech32 для приватного ключа устройства.
+pub const SECRET_HRP: &str = "ccsec";
+
+const CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// Формат, в котором ключи печатаются пользователю. На диске всегда hex.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum KeyFormat {
+    #[default]
+    Hex,
+    Base32,
+    Bech32,
+}
+
+pub fn encode_key(bytes: &[u8], format: KeyFormat, hrp: &str) -> String {
+    match format {
+        KeyFormat::Hex => encode_hex(bytes),
+        KeyFormat::Base32 => encode_base32(bytes),
+        KeyFormat::Bech32 => encode_bech32(hrp, bytes),
+    }
+}
+
+/// Перекодирует hex-значение из состояния; нераспознанное значение печатается как есть.
+pub fn format_hex_key(hex: &str, format: KeyFormat, hrp: &str) -> String {
+    match decode_hex(hex) {
+        Ok(bytes) => encode_key(&bytes, format, hrp),
+        Err(_) => hex.to_string(),
+    }
+}
+
+/// Разбирает 32-байтовый ключ в hex, base32 (Crockford) или bech32 (`ccpub1…`/`ccsec1…`).
+pub fn decode_key32(input: &str) -> Result<[u8; 32]> {
+    let trimmed = input.trim();
+    if trimmed.len() == 64 && trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return decode_hex32(trimmed);
+    }
+    let bech32 = [PUBLIC_HRP, SECRET_HRP].iter().any(|hrp| {
+        trimmed
+            .get(..=hrp.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}1", hrp)))
+    });
+    let bytes = if bech32 {
+        decode_bech32(trimmed)?.1
+    } else {
+        decode_base32(trimmed)?
+    };
+    bytes.try_into().map_err(|_| anyhow!("expected 32 bytes"))
+}
+
+pub fn encode_base32(bytes: &[u8]) -> String {
+    convert_bits(bytes, 8, 5, true)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|value| CROCKFORD_ALPHABET[value as usize] as char)
+        .collect()
+}
+
+pub fn decode_base32(input: &str) -> Result<Vec<u8>> {
+    let mut values = Vec::with_capacity(input.len());
+    for ch in input.chars().filter(|ch| *ch != '-') {
+        let ch = match ch.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            other => other,
+        };
+        let value = CROCKFORD_ALPHABET
+            .iter()
+            .position(|c| *c as char == ch)
+            .ok_or_else(|| anyhow!("invalid base32 character: {}", ch))?;
+        values.push(value as u8);
+    }
+    convert_bits(&values, 5, 8, false)
+}
+
+pub fn encode_bech32(hrp: &str, bytes: &[u8]) -> String {
+    let data = convert_bits(bytes, 8, 5, true).unwrap_or_default();
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = bech32_polymod(&values) ^ 1;
+    let mut output = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    output.push_str(hrp);
+    output.push('1');
+    for value in data {
+        output.push(BECH32_CHARSET[value as usize] as char);
+    }
+    for i in 0..6 {
+        let value = (checksum >> (5 * (5 - i))) & 31;
+        output.push(BECH32_CHARSET[value as usize] as char);
+    }
+    output
+}
+
+/// Декодирует bech32 (BIP-173) и возвращает hrp и данные.
+pub fn decode_bech32(input: &str) -> Result<(String, Vec<u8>)> {
+    if input.chars().any(|c| c.is_ascii_lowercase())
+        && input.chars().any(|c| c.is_ascii_uppercase())
+    {
+        return Err(anyhow!("bech32 string mixes upper and lower case"));
+    }
+    let lowered = input.to_ascii_lowercase();
+    let (hrp, data) = lowered
+        .rsplit_once('1')
+        .ok_or_else(|| anyhow!("bech32 separator not found"))?;
+    if hrp.is_empty() || data.len() < 6 {
+        return Err(anyhow!("bech32 string too short"));
+    }
+    let mut values = Vec::with_capacity(data.len());
+    for ch in data.bytes() {
+        let value = BECH32_CHARSET
+            .iter()
+            .position(|c| *c == ch)
+            .ok_or_else(|| anyhow!("invalid bech32 character: {}", ch as char))?;
+        values.push(value as u8);
+    }
+    let mut check = hrp_expand(hrp);
+    check.extend_from_slice(&values);
+    if bech32_polymod(&check) != 1 {
+        return Err(anyhow!("bech32 checksum mismatch"));
+    }
+    let payload = convert_bits(&values[..values.len() - 6], 5, 8, false)?;
+    Ok((hrp.to_string(), payload))
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in BECH32_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>> {
+    let max_value = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut output = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        if u32::from(value) >> from != 0 {
+            return Err(anyhow!("invalid {}-bit value", from));
+        }
+        acc = ((acc << from) | u32::from(value)) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            output.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            output.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        return Err(anyhow!("invalid padding"));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_roundtrip_in_every_format() {
+        let key: [u8; 32] = std::array::from_fn(|i| (i * 7) as u8);
+        for format in [KeyFormat::Hex, KeyFormat::Base32, KeyFormat::Bech32] {
+            let encoded = encode_key(&key, format, PUBLIC_HRP);
+            assert_eq!(decode_key32(&encoded).unwrap(), key, "{:?}", format);
+        }
+        assert!(encode_key(&key, KeyFormat::Bech32, SECRET_HRP).starts_with("ccsec1"));
+        assert_eq!(encode_key(&key, KeyFormat::Base32, PUBLIC_HRP).len(), 52);
+    }
+
+    #[test]
+    fn bech32_matches_reference_vector() {
+        assert_eq!(encode_bech32("a", &[]), "a12uel5l");
+        let (hrp, data) = decode_bech32("A12UEL5L").unwrap();
+        assert_eq!(hrp, "a");
+        assert!(data.is_empty());
+        assert!(decode_bech32("a12uel5m").is_err());
+    }
+
+    #[test]
+    fn base32_accepts_crockford_aliases() {
+        let encoded = encode_base32(&[0x00, 0x44, 0x32]);
+        let sloppy = encoded.replace('0', "o").to_lowercase();
+        assert_eq!(decode_base32(&sloppy).unwrap(), [0x00, 0x44, 0x32]);
+        assert!(decode_base32("U").is_err());
+    }
+
+    #[test]
+    fn bech32_typo_is_rejected() {
+        let key = [9u8; 32];
+        let mut encoded = encode_key(&key, KeyFormat::Bech32, PUBLIC_HRP);
+        let last = encoded.pop().unwrap();
+        encoded.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(decode_key32(&encoded).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use ratatui::style::Color;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+const GOOD_RTT: Duration = Duration::from_millis(150);
+const FAIR_RTT: Duration = Duration::from_millis(400);
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Качество связи по последнему замеру RTT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkQuality {
+    Good,
+    Fair,
+    Poor,
+    /// Сервер не ответил на PING вовремя.
+    Lost,
+}
+
+impl LinkQuality {
+    pub fn from_rtt(rtt: Option<Duration>) -> Self {
+        match rtt {
+            Some(rtt) if rtt < GOOD_RTT => LinkQuality::Good,
+            Some(rtt) if rtt < FAIR_RTT => LinkQuality::Fair,
+            Some(_) => LinkQuality::Poor,
+            None => LinkQuality::Lost,
+        }
+    }
+
+    pub fn bars(self) -> &'static str {
+        match self {
+            LinkQuality::Good => "▂▄▆",
+            LinkQuality::Fair => "▂▄ ",
+            LinkQuality::Poor => "▂  ",
+            LinkQuality::Lost => "✕  ",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            LinkQuality::Good => Color::Green,
+            LinkQuality::Fair => Color::Yellow,
+            LinkQuality::Poor | LinkQuality::Lost => Color::Red,
+        }
+    }
+}
+
+/// Последние замеры RTT; `None` — PING остался без ответа.
+pub struct LatencyHistory {
+    samples: VecDeque<Option<Duration>>,
+    capacity: usize,
+}
+
+impl LatencyHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, rtt: Option<Duration>) {
+        self.samples.push_back(rtt);
+        if self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Последний замер; `None`, пока замеров не было.
+    pub fn latest(&self) -> Option<Option<Duration>> {
+        self.samples.back().copied()
+    }
+
+    /// Мини-график из `width` последних замеров, нормированный по максимуму.
+    /// Потерянный PING рисуется как `·`.
+    pub fn sparkline(&self, width: usize) -> String {
+        let recent: Vec<_> = self
+            .samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(width))
+            .collect();
+        let max = recent
+            .iter()
+            .filter_map(|rtt| **rtt)
+            .max()
+            .unwrap_or_default()
+            .as_secs_f32();
+        recent
+            .into_iter()
+            .map(|rtt| match rtt {
+                Some(rtt) if max > 0.0 => {
+                    let level = rtt.as_secs_f32() / max * (SPARK_LEVELS.len() - 1) as f32;
+                    SPARK_LEVELS[level.round() as usize]
+                }
+                Some(_) => SPARK_LEVELS[0],
+                None => '·',
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_follows_rtt_thresholds() {
+        let ms = |value| Some(Duration::from_millis(value));
+        assert_eq!(LinkQuality::from_rtt(ms(40)), LinkQuality::Good);
+        assert_eq!(LinkQuality::from_rtt(ms(150)), LinkQuality::Fair);
+        assert_eq!(LinkQuality::from_rtt(ms(900)), LinkQuality::Poor);
+        assert_eq!(LinkQuality::from_rtt(None), LinkQuality::Lost);
+    }
+
+    #[test]
+    fn sparkline_scales_to_recent_maximum() {
+        let mut history = LatencyHistory::new(4);
+        assert_eq!(history.latest(), None);
+        for ms in [500, 10, 80, 40] {
+            history.push(Some(Duration::from_millis(ms)));
+        }
+        history.push(None);
+        assert_eq!(history.latest(), Some(None));
+        assert_eq!(history.sparkline(8), "▂█▅·");
+        assert_eq!(history.sparkline(2), "█·");
+    }
+}
@@ -0,0 +1,95 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+/// Стиль ссылки в сообщениях и в списке ссылок (`/urls`).
+pub fn link_style() -> Style {
+    Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::UNDERLINED)
+}
+
+/// Диапазоны `http(s)://` ссылок в тексте (без завершающей пунктуации).
+fn find_urls(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| text[offset..].find(scheme))
+        .min()
+    {
+        let start = offset + found;
+        let tail = &text[start..];
+        let mut end = start + tail.find(char::is_whitespace).unwrap_or(tail.len());
+        end = start
+            + text[start..end]
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}', '>', '"', '\''])
+                .len();
+        if text[start..end].ends_with("://") {
+            offset = end;
+            continue;
+        }
+        ranges.push((start, end));
+        offset = end;
+    }
+    ranges
+}
+
+pub fn extract_urls(text: &str) -> Vec<String> {
+    find_urls(text)
+        .into_iter()
+        .map(|(start, end)| text[start..end].to_string())
+        .collect()
+}
+
+/// Текст с подсвеченными ссылками; подходит как `plain` для
+/// `render_markdown`.
+pub fn text_with_links(text: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, end) in find_urls(text) {
+        if start > last {
+            spans.push(Span::raw(text[last..start].to_string()));
+        }
+        spans.push(Span::styled(text[start..end].to_string(), link_style()));
+        last = end;
+    }
+    if last < text.len() {
+        spans.push(Span::raw(text[last..].to_string()));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_punctuation_is_not_part_of_the_link() {
+        assert_eq!(
+            extract_urls("see https://example.org/a, (http://b.test/x?q=1). ok"),
+            ["https://example.org/a", "http://b.test/x?q=1"]
+        );
+        assert_eq!(
+            extract_urls("\"https://example.org/path!\"?"),
+            ["https://example.org/path"]
+        );
+    }
+
+    #[test]
+    fn bare_schemes_are_skipped() {
+        assert!(extract_urls("type https:// then the host").is_empty());
+        assert_eq!(
+            extract_urls("http://, then https://example.org"),
+            ["https://example.org"]
+        );
+    }
+
+    #[test]
+    fn links_are_styled_between_plain_spans() {
+        let spans = text_with_links("go to https://example.org now");
+        let contents: Vec<_> = spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(contents, ["go to ", "https://example.org", " now"]);
+        assert_eq!(spans[0].style, Style::default());
+        assert_eq!(spans[1].style, link_style());
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use ratatui::style::Color;
+use std::collections::VecDeque;
+
+/// Важность строки журнала движка.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Движок присылает строки без уровня, поэтому угадываем его по тексту.
+    pub fn classify(line: &str) -> Self {
+        let lower = line.to_lowercase();
+        if lower.contains("error") || lower.contains("ошибка") {
+            LogLevel::Error
+        } else if lower.contains("failed") || lower.contains("не удалось") || lower.contains("warn")
+        {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Info => "INFO ",
+            LogLevel::Warn => "WARN ",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            LogLevel::Info => Color::Gray,
+            LogLevel::Warn => Color::Yellow,
+            LogLevel::Error => Color::LightRed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub line: String,
+}
+
+/// Кольцевой буфер последних строк журнала с собственной прокруткой.
+/// `scroll` — сколько строк отступить от конца; 0 означает «следить за хвостом».
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    scroll: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            scroll: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn push(&mut self, level: LogLevel, line: String, timestamp: DateTime<Utc>) {
+        self.entries.push_back(LogEntry {
+            timestamp,
+            level,
+            line,
+        });
+        if self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+        // Прокрученный вверх вид не должен уезжать от новых строк.
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.entries.len().saturating_sub(1));
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = self.entries.len().saturating_sub(1);
+    }
+
+    pub fn follow(&mut self) {
+        self.scroll = 0;
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.scroll == 0
+    }
+
+    /// Строки, попадающие в окно высотой `height` с учётом прокрутки.
+    pub fn visible(&self, height: usize) -> impl Iterator<Item = &LogEntry> {
+        let end = self.entries.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+        self.entries.range(start..end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(buffer: &LogBuffer, height: usize) -> Vec<String> {
+        buffer
+            .visible(height)
+            .map(|entry| entry.line.clone())
+            .collect()
+    }
+
+    #[test]
+    fn buffer_keeps_only_last_entries() {
+        let mut buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(LogLevel::Info, i.to_string(), Utc::now());
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(lines(&buffer, 10), ["2", "3", "4"]);
+        assert_eq!(lines(&buffer, 2), ["3", "4"]);
+    }
+
+    #[test]
+    fn scrolled_view_stays_put_while_lines_arrive() {
+        let mut buffer = LogBuffer::new(10);
+        for i in 0..5 {
+            buffer.push(LogLevel::Info, i.to_string(), Utc::now());
+        }
+        buffer.scroll_up(2);
+        assert_eq!(lines(&buffer, 2), ["1", "2"]);
+        buffer.push(LogLevel::Info, "5".to_string(), Utc::now());
+        assert_eq!(lines(&buffer, 2), ["1", "2"]);
+        buffer.follow();
+        assert_eq!(lines(&buffer, 2), ["4", "5"]);
+        buffer.scroll_down(3);
+        assert!(buffer.is_following());
+    }
+
+    #[test]
+    fn severity_is_guessed_from_text() {
+        assert_eq!(
+            LogLevel::classify("connect attempt 1.2.3.4 failed: refused"),
+            LogLevel::Warn
+        );
+        assert_eq!(
+            LogLevel::classify("handshake decode error"),
+            LogLevel::Error
+        );
+        assert_eq!(
+            LogLevel::classify("handshake ok: session 1"),
+            LogLevel::Info
+        );
+    }
+}
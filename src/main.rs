@@ -1,28 +1,75 @@
 mod animations;
 mod ascii_art;
+mod assist;
+mod attachments;
+mod audio;
+mod backup;
 mod calls;
 mod config;
 mod device;
+mod devicecache;
 mod engine;
+mod exitcode;
+mod flow;
+mod framedump;
+mod friendcsv;
+mod glyphs;
 mod groups;
+mod help;
 mod hexutil;
+mod identity;
+mod keyfmt;
+mod latency;
+mod links;
+mod logview;
+mod markdown;
 mod media;
+mod palette;
+mod presence;
+mod profiles;
+mod qr;
+mod ratelimit;
 mod rest;
+mod tasks;
 mod tui;
+mod unread;
+mod videoart;
 mod voice;
 
-use crate::config::{ClientState, ClientStateParams, FriendEntry, docs_path, state_path};
-use crate::device::describe_keys;
-use crate::hexutil::decode_hex32;
+use crate::config::{
+    CertificateWindow, ClientState, ClientStateParams, FriendEntry, FriendsDiff, ProfileError,
+    ServerKeyCheck, available_docs, certificate_window, check_server_key, default_docs_lang,
+    docs_path, server_key_fingerprint, set_config_dir, state_path,
+};
+use crate::device::{DeviceIdScheme, describe_keys, keypair_from_claim};
+use crate::engine::{ClientEvent, EngineCommand, EngineHandle, acked_sequence, create_engine};
+use crate::exitcode::ExitKind;
+use crate::keyfmt::{KeyFormat, PUBLIC_HRP, decode_key32, format_hex_key};
 use crate::rest::{
-    DeviceEntry, FriendEntryPayload, PairingClaimResponse, PairingTicket, RestClient,
+    DeviceEntry, DevicesQuery, FriendEntryPayload, PairingClaimResponse, PairingTicket, RestClient,
+    avatar_content_type, device_status_summary, friend_from_payload, friends_to_payload,
+    is_session_rejected, normalize_server_url, warn_insecure_tls,
 };
-use anyhow::{Context, Result, bail};
-use clap::{Args, Parser, Subcommand};
-use commucat_crypto::{DeviceCertificate, DeviceKeyPair};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use clap::builder::{BoolishValueParser, FalseyValueParser};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use commucat_crypto::DeviceCertificate;
+use crossterm::style::Stylize;
+use serde::Serialize;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+const CERTIFICATE_WARN_SECS: i64 = 7 * 24 * 60 * 60;
 
 #[derive(Parser)]
 #[command(
@@ -32,10 +79,24 @@ use tracing_subscriber::EnvFilter;
     propagate_version = true
 )]
 struct Cli {
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Каталог профиля вместо COMMUCAT_CLIENT_HOME и системного по умолчанию
+    #[arg(long, global = true, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+    /// Дублировать журнал в файл с ежедневной ротацией (`<FILE>.YYYY-MM-DD`)
+    #[arg(long, global = true, env = "COMMUCAT_LOG_FILE", value_name = "FILE")]
+    log_file: Option<PathBuf>,
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Command {
@@ -46,9 +107,31 @@ enum Command {
     #[command(subcommand)]
     Friends(FriendsCommand),
     Claim(ClaimArgs),
-    Export,
+    RotateKeys(RotateKeysArgs),
+    Status,
+    /// Обновление отображаемого имени и аватара.
+    Profile(ProfileArgs),
+    /// Установка статуса присутствия без TUI (для скриптов и хуков).
+    Presence(PresenceArgs),
+    Export(ExportArgs),
+    /// Резервная копия профиля в переносимый файл `.ccbak`.
+    Backup(BackupArgs),
+    /// Восстановление профиля из файла `.ccbak`.
+    Restore(RestoreArgs),
+    /// Подключение устройства по ссылке commucat://pair?code=…&server=…&domain=…
+    Open(OpenArgs),
     Docs(DocsArgs),
-    Tui,
+    Tui(TuiArgs),
+    /// Прогон кадров из журнала COMMUCAT_FRAME_DUMP через TUI без подключения.
+    #[command(hide = true)]
+    Replay {
+        file: PathBuf,
+    },
+    /// Печать скрипта автодополнения для оболочки.
+    #[command(
+        after_help = "Установка:\n  bash: commucat-cli-client completions bash > ~/.local/share/bash-completion/completions/commucat-cli-client\n  zsh:  commucat-cli-client completions zsh > ~/.zfunc/_commucat-cli-client\n  fish: commucat-cli-client completions fish > ~/.config/fish/completions/commucat-cli-client.fish\n  powershell: commucat-cli-client completions powershell >> $PROFILE"
+    )]
+    Completions(CompletionsArgs),
 }
 
 #[derive(Subcommand)]
@@ -65,18 +148,89 @@ enum FriendsCommand {
     Remove(FriendsRemoveArgs),
     Pull(FriendsSessionArgs),
     Push(FriendsSessionArgs),
+    Export(FriendsExportArgs),
+    Import(FriendsImportArgs),
 }
 
 #[derive(Args)]
 struct DevicesListArgs {
-    #[arg(long)]
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
+    /// Размер страницы (если сервер поддерживает пагинацию)
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Курсор страницы из предыдущего ответа
+    #[arg(long)]
+    cursor: Option<String>,
+    /// Загрузить все страницы, следуя курсорам
+    #[arg(long)]
+    all: bool,
+    /// Показать только устройства с этим статусом
+    #[arg(long, value_enum)]
+    status: Option<DeviceStatusFilter>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DeviceStatusFilter {
+    Active,
+    Revoked,
+    Pending,
+}
+
+impl DeviceStatusFilter {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeviceStatusFilter::Active => "active",
+            DeviceStatusFilter::Revoked => "revoked",
+            DeviceStatusFilter::Pending => "pending",
+        }
+    }
 }
 
 #[derive(Args)]
 struct DevicesRevokeArgs {
     device_id: String,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
+    session: Option<String>,
+    /// Не спрашивать подтверждение
+    #[arg(long, short = 'y')]
+    yes: bool,
+    /// Разрешить отзыв текущего устройства
+    #[arg(long)]
+    force: bool,
+    /// Только показать устройство, которое будет отозвано
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+struct ProfileArgs {
+    #[arg(long)]
+    display_name: Option<String>,
+    #[arg(long, conflicts_with = "avatar_file")]
+    avatar_url: Option<String>,
+    /// Локальное изображение (png/jpeg/gif/webp) для загрузки на сервер
     #[arg(long)]
+    avatar_file: Option<PathBuf>,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
+    session: Option<String>,
+}
+
+#[derive(Args)]
+struct PresenceArgs {
+    /// Статус: online, away, busy, offline или свой
+    state: String,
+    /// Только сохранить статус по умолчанию для следующих подключений
+    #[arg(long, default_value_t = false)]
+    persist_only: bool,
+    /// Сколько ждать подтверждения сервера после отправки, секунд
+    #[arg(long, default_value_t = 5)]
+    wait: u64,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
 }
 
@@ -86,11 +240,15 @@ struct DevicesAttachCertArgs {
     certificate: String,
     #[arg(long)]
     issuer: Option<String>,
+    /// Сохранить сертификат, даже если он истёк или ещё не действует
+    #[arg(long)]
+    allow_expired: bool,
 }
 
 #[derive(Args)]
 struct InitArgs {
-    #[arg(long)]
+    /// Адрес сервера; без схемы подставляется https, путь отбрасывается
+    #[arg(long, value_parser = normalize_server_url)]
     server: String,
     #[arg(long)]
     domain: String,
@@ -102,8 +260,12 @@ struct InitArgs {
     display_name: Option<String>,
     #[arg(long)]
     avatar_url: Option<String>,
-    #[arg(long)]
+    /// Явный device_id: `A-Z a-z 0-9 . _ -`
+    #[arg(long, value_parser = device::parse_device_id)]
     device_id: Option<String>,
+    /// Схема device_id, если `--device-id` не задан
+    #[arg(long, value_enum, default_value_t = DeviceIdScheme::Uuid)]
+    device_id_scheme: DeviceIdScheme,
     #[arg(long)]
     device_name: Option<String>,
     #[arg(long, default_value = "XK")]
@@ -122,31 +284,103 @@ struct InitArgs {
     presence_interval: u64,
     #[arg(long)]
     traceparent: Option<String>,
-    #[arg(long)]
+    #[arg(long, default_value_t = false)]
+    auto_trace: bool,
+    #[arg(long, default_value_t = 15)]
+    connect_timeout: u64,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
-    #[arg(long)]
+    /// Pair-код; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     pair_code: Option<String>,
     #[arg(long, default_value_t = false)]
     force: bool,
+    /// Разрешить --force перезаписать закреплённый ключ сервера другим
+    #[arg(long, default_value_t = false)]
+    accept_new_server_key: bool,
+    /// Формат печати ключей (в файле состояния всегда hex)
+    #[arg(long, value_enum, default_value_t = KeyFormat::Hex)]
+    key_format: KeyFormat,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Формат печати ключей
+    #[arg(long, value_enum, default_value_t = KeyFormat::Hex)]
+    key_format: KeyFormat,
+}
+
+#[derive(Args)]
+struct BackupArgs {
+    /// Куда записать резервную копию (обычно `*.ccbak`)
+    file: PathBuf,
+    /// Зашифровать парольной фразой; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_name = "PASSPHRASE", value_parser = parse_secret)]
+    encrypt: Option<String>,
+}
+
+#[derive(Args)]
+struct RestoreArgs {
+    file: PathBuf,
+    /// Парольная фраза зашифрованной копии; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
+    passphrase: Option<String>,
+    /// Заменить существующий профиль (прежний сохраняется в `client.json.bak`)
+    #[arg(long, default_value_t = false)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct RotateKeysArgs {
+    #[arg(long, default_value_t = false)]
+    force: bool,
+}
+
+#[derive(Args)]
+struct CompletionsArgs {
+    #[arg(value_enum)]
+    shell: Shell,
 }
 
 #[derive(Args)]
 struct PairArgs {
     #[arg(long)]
     ttl: Option<i64>,
-    #[arg(long)]
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
+    /// Показать pair-код QR-кодом в терминале
+    #[arg(long, default_value_t = false)]
+    qr: bool,
+    /// Закодировать в QR ссылку commucat://pair?code=…&server=…&domain=… вместо голого кода
+    #[arg(long, default_value_t = false)]
+    qr_uri: bool,
+    /// Сохранить QR-код в PNG
+    #[arg(long, value_name = "PNG")]
+    qr_file: Option<PathBuf>,
 }
 
 #[derive(Args)]
-struct ClaimArgs {
+struct OpenArgs {
     #[arg()]
-    pair_code: String,
+    uri: String,
     #[arg(long)]
     device_name: Option<String>,
+}
+
+#[derive(Args)]
+struct ClaimArgs {
+    /// Pair-код; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(value_parser = parse_secret)]
+    pair_code: String,
     #[arg(long)]
+    device_name: Option<String>,
+    /// Адрес сервера; без схемы подставляется https, путь отбрасывается
+    #[arg(long, value_parser = normalize_server_url)]
     server: Option<String>,
-    #[arg(long)]
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
 }
 
@@ -160,7 +394,8 @@ struct FriendsAddArgs {
     alias: Option<String>,
     #[arg(long)]
     push: bool,
-    #[arg(long)]
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
 }
 
@@ -168,7 +403,25 @@ struct FriendsAddArgs {
 struct FriendsRemoveArgs {
     #[arg()]
     user_id: String,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
+    session: Option<String>,
     #[arg(long)]
+    push: bool,
+}
+
+#[derive(Args)]
+struct FriendsExportArgs {
+    #[arg(long)]
+    file: String,
+}
+
+#[derive(Args)]
+struct FriendsImportArgs {
+    #[arg(long)]
+    file: String,
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
     #[arg(long)]
     push: bool,
@@ -176,40 +429,178 @@ struct FriendsRemoveArgs {
 
 #[derive(Args)]
 struct FriendsSessionArgs {
-    #[arg(long)]
+    /// Сессионный токен; `@файл` или `env:ПЕРЕМЕННАЯ` — прочитать оттуда
+    #[arg(long, value_parser = parse_secret)]
     session: Option<String>,
+    /// Показать разницу с сервером, ничего не меняя
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args, Default)]
+struct TuiArgs {
+    #[arg(long, default_value_t = false)]
+    no_url_open: bool,
+    /// Записывать звук с микрофона (голосовые сообщения и звонки)
+    #[arg(long, default_value_t = false)]
+    audio_in: bool,
+    /// Частота перерисовки анимаций (кадров в секунду)
+    #[arg(long, env = "COMMUCAT_FPS")]
+    fps: Option<u64>,
+    /// Запуск без анимаций: перерисовка только по событиям
+    #[arg(long, env = "COMMUCAT_NO_ANIM", value_parser = FalseyValueParser::new())]
+    no_anim: bool,
+    /// Монохромная тема без цветов (также при непустом NO_COLOR)
+    #[arg(long, default_value_t = false)]
+    no_color: bool,
+    /// Не показывать заставку при запуске
+    #[arg(long, env = "COMMUCAT_NO_SPLASH", value_parser = FalseyValueParser::new())]
+    no_splash: bool,
+    /// Только ASCII вместо эмодзи и псевдографики; без флага — по TERM и локали
+    #[arg(
+        long,
+        env = "COMMUCAT_ASCII",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_parser = BoolishValueParser::new()
+    )]
+    ascii: Option<bool>,
+    /// Статус присутствия при отключении (пустая строка — не отправлять)
+    #[arg(long, default_value = "offline")]
+    offline_state: String,
+    /// Принять изменившийся ключ сервера и закрепить его заново
+    #[arg(long, default_value_t = false)]
+    accept_new_server_key: bool,
+    /// Показывать входящее видео полублоками в панели звонка (нагружает CPU)
+    #[arg(long, default_value_t = false)]
+    video_ascii: bool,
+    /// Не подключаться при запуске; подключиться позже командой `/connect`
+    #[arg(long, default_value_t = false)]
+    offline: bool,
+    /// Открыть вкладку вместо запомненной с прошлого запуска
+    #[arg(long, value_parser = tui::VIEW_NAMES.to_vec())]
+    view: Option<String>,
+    /// Хранить тела входящих сообщений: `v` в режиме выбора показывает JSON
+    #[arg(long, default_value_t = false)]
+    debug_frames: bool,
 }
 
 #[derive(Args)]
 struct DocsArgs {
-    #[arg(long, default_value = "ru")]
-    lang: String,
+    #[command(subcommand)]
+    command: Option<DocsCommand>,
+    /// Язык руководства (по умолчанию из COMMUCAT_LANG или LANG)
+    #[arg(long)]
+    lang: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum DocsCommand {
+    /// Список доступных языков руководства.
+    List,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    init_tracing();
-    let cli = Cli::parse();
-    match cli.command {
-        Some(Command::Init(args)) => init_profile(args).await?,
-        Some(Command::Pair(args)) => issue_pair(args).await?,
-        Some(Command::Devices(cmd)) => handle_devices(cmd).await?,
-        Some(Command::Friends(cmd)) => handle_friends(cmd).await?,
-        Some(Command::Claim(args)) => claim_device(args).await?,
-        Some(Command::Export) => export_profile()?,
-        Some(Command::Docs(args)) => print_docs(&args.lang)?,
-        Some(Command::Tui) => launch_tui().await?,
-        None => launch_tui().await?,
+async fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return if err.use_stderr() {
+                ExitKind::Usage.into()
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitKind::classify(&err).into()
+        }
     }
-    Ok(())
 }
 
-fn init_tracing() {
+async fn run(cli: Cli) -> Result<()> {
+    let _log_guard = init_tracing(cli.log_file.as_deref())?;
+    let output = cli.output;
+    if let Some(dir) = cli.config_dir {
+        set_config_dir(dir)?;
+    }
+    let result = match cli.command {
+        Some(Command::Init(args)) => init_profile(args).await,
+        Some(Command::Pair(args)) => issue_pair(args, output).await,
+        Some(Command::Devices(cmd)) => handle_devices(cmd, output).await,
+        Some(Command::Friends(cmd)) => handle_friends(cmd, output).await,
+        Some(Command::Claim(args)) => claim_device(args, output).await,
+        Some(Command::RotateKeys(args)) => rotate_keys(args),
+        Some(Command::Status) => show_status().await,
+        Some(Command::Profile(args)) => update_profile(args).await,
+        Some(Command::Presence(args)) => set_presence(args, output).await,
+        Some(Command::Export(args)) => export_profile(args),
+        Some(Command::Backup(args)) => backup_profile(args),
+        Some(Command::Restore(args)) => restore_profile(args),
+        Some(Command::Open(args)) => open_link(args, output).await,
+        Some(Command::Docs(args)) => match args.command {
+            Some(DocsCommand::List) => list_docs(),
+            None => print_docs(&args.lang.unwrap_or_else(default_docs_lang)),
+        },
+        Some(Command::Tui(args)) => launch_tui(args).await,
+        Some(Command::Replay { file }) => replay_frames(&file).await,
+        Some(Command::Completions(args)) => {
+            print_completions(args.shell);
+            Ok(())
+        }
+        None => launch_tui(TuiArgs::default()).await,
+    };
+    if let Err(err) = &result
+        && is_session_rejected(err)
+    {
+        eprintln!(
+            "{}",
+            "сессия истекла или отозвана — подключитесь заново (tui, :connect), \
+             чтобы обновить токен, или передайте свежий --session"
+                .yellow()
+        );
+    }
+    result
+}
+
+/// Журнал в stderr и, если задан `log_file`, в файл через неблокирующий писатель.
+/// Возвращённый guard нужно держать до выхода, иначе хвост журнала потеряется.
+fn init_tracing(log_file: Option<&Path>) -> Result<Option<WorkerGuard>> {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(filter)
+    let stderr_layer = tracing_subscriber::fmt::layer()
         .with_target(false)
+        .with_filter(filter_fn(|meta| meta.target() != tui::LOG_TARGET));
+    let Some(log_file) = log_file else {
+        let _ = tracing_subscriber::registry()
+            .with(filter)
+            .with(stderr_layer)
+            .try_init();
+        return Ok(None);
+    };
+    let file_name = log_file
+        .file_name()
+        .with_context(|| format!("некорректный путь журнала: {}", log_file.display()))?;
+    let dir = match log_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("не удалось создать каталог журнала {}", dir.display()))?;
+    let (writer, guard) =
+        tracing_appender::non_blocking(tracing_appender::rolling::daily(dir, file_name));
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(writer);
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr_layer)
+        .with(file_layer)
         .try_init();
+    Ok(Some(guard))
 }
 
 async fn init_profile(args: InitArgs) -> Result<()> {
@@ -221,6 +612,7 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         display_name,
         avatar_url,
         device_id,
+        device_id_scheme,
         device_name,
         pattern,
         prologue,
@@ -230,9 +622,13 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         presence,
         presence_interval,
         traceparent,
+        auto_trace,
+        connect_timeout,
         session,
         pair_code,
         force,
+        accept_new_server_key,
+        key_format,
     } = args;
     let mut server_ca_from_info: Option<String> = None;
     let path = state_path()?;
@@ -243,8 +639,9 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         bail!("укажите --username (для нового пользователя) или --user-id (для существующего)");
     }
     if let Some(code) = pair_code {
-        let rest = RestClient::new(&server)?;
+        let rest = RestClient::with_tls(&server, tls_ca.as_deref(), insecure)?;
         let claim = rest.claim_pairing(&code, device_name.as_deref()).await?;
+        let keys = keypair_from_claim(&claim.private_key, &claim.public_key)?;
         let server_static_resolved = match server_static.clone() {
             Some(value) => Some(value),
             None => {
@@ -277,9 +674,11 @@ async fn init_profile(args: InitArgs) -> Result<()> {
                 Some(info.noise_public)
             }
         };
-        let private = decode_hex32(&claim.private_key)?;
-        let public = decode_hex32(&claim.public_key)?;
-        let keys = DeviceKeyPair { public, private };
+        guard_pinned_server_key(
+            &server,
+            server_static_resolved.as_deref(),
+            accept_new_server_key,
+        )?;
         let device_ca_public = claim
             .device_ca_public
             .clone()
@@ -297,6 +696,8 @@ async fn init_profile(args: InitArgs) -> Result<()> {
             presence_state: presence,
             presence_interval_secs: presence_interval,
             traceparent,
+            auto_trace,
+            connect_timeout_secs: connect_timeout,
             user_handle: Some(claim.user.handle.clone()),
             user_display_name: claim.user.display_name.clone(),
             user_avatar_url: claim.user.avatar_url.clone(),
@@ -311,8 +712,9 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         println!("state saved to {}", path.display());
         println!(
             "{}",
-            describe_keys(&claim.device_id, &state.device_keypair()?)
+            describe_keys(&claim.device_id, &state.device_keypair()?, key_format)
         );
+        print_server_key_fingerprint(&state);
         print_claim_summary(&claim);
         if let Some(cert) = claim.device_certificate.as_ref() {
             println!(
@@ -321,18 +723,24 @@ async fn init_profile(args: InitArgs) -> Result<()> {
             );
         }
         if let Some(ca_hex) = state.device_ca_public.as_ref() {
-            println!("device_ca_public={}", ca_hex);
+            println!(
+                "device_ca_public={}",
+                format_hex_key(ca_hex, key_format, PUBLIC_HRP)
+            );
         }
         return Ok(());
     }
 
     let handle_for_state = username.clone();
-    let generated_device = device_id.unwrap_or_else(|| device::generate_device_id("device"));
+    let generated_device = match device_id {
+        Some(device_id) => device_id,
+        None => device::generate_device_id("device", device_id_scheme)?,
+    };
     let keys = device::generate_keypair()?;
     let server_static_resolved = match server_static.clone() {
         Some(value) => Some(value),
         None => {
-            let rest = RestClient::new(&server)?;
+            let rest = RestClient::with_tls(&server, tls_ca.as_deref(), insecure)?;
             let info = rest.server_info().await.context("fetch server info")?;
             if info.domain != domain {
                 println!("warning: server reports domain {}", info.domain);
@@ -362,6 +770,11 @@ async fn init_profile(args: InitArgs) -> Result<()> {
             Some(info.noise_public)
         }
     };
+    guard_pinned_server_key(
+        &server,
+        server_static_resolved.as_deref(),
+        accept_new_server_key,
+    )?;
     let state = ClientState::from_params(ClientStateParams {
         device_id: generated_device.clone(),
         server_url: server,
@@ -375,6 +788,8 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         presence_state: presence,
         presence_interval_secs: presence_interval,
         traceparent,
+        auto_trace,
+        connect_timeout_secs: connect_timeout,
         user_handle: handle_for_state,
         user_display_name: display_name.clone(),
         user_avatar_url: avatar_url.clone(),
@@ -387,7 +802,8 @@ async fn init_profile(args: InitArgs) -> Result<()> {
     });
     state.save()?;
     println!("state saved to {}", path.display());
-    println!("{}", describe_keys(&generated_device, &keys));
+    println!("{}", describe_keys(&generated_device, &keys, key_format));
+    print_server_key_fingerprint(&state);
     if let Some(name) = username.as_ref() {
         println!(
             "Устройство зарегистрируется автоматически при первом подключении как пользователь '{}'.",
@@ -404,62 +820,382 @@ async fn init_profile(args: InitArgs) -> Result<()> {
         println!("session={} (будет использована REST API)", token);
     }
     if let Some(ca_hex) = state.device_ca_public.as_ref() {
-        println!("device_ca_public={}", ca_hex);
+        println!(
+            "device_ca_public={}",
+            format_hex_key(ca_hex, key_format, PUBLIC_HRP)
+        );
     }
-    if let Ok(doc_path) = docs_path("ru") {
+    if let Ok(doc_path) = docs_path(&default_docs_lang()) {
         println!("Руководство: {}", doc_path.display());
     }
     Ok(())
 }
 
-fn export_profile() -> Result<()> {
+/// Профили, созданные до нормализации адреса, могут хранить его с путём или `/`.
+fn same_server(stored: &str, other: &str) -> bool {
+    match (normalize_server_url(stored), normalize_server_url(other)) {
+        (Ok(stored), Ok(other)) => stored == other,
+        _ => stored == other,
+    }
+}
+
+/// Не даёт `init --force` молча заменить закреплённый ключ того же сервера.
+fn guard_pinned_server_key(server: &str, resolved: Option<&str>, accept: bool) -> Result<()> {
+    let (Ok(existing), Some(resolved)) = (ClientState::load(), resolved) else {
+        return Ok(());
+    };
+    if !same_server(&existing.server_url, server)
+        || check_server_key(existing.server_static.as_deref(), resolved) != ServerKeyCheck::Changed
+    {
+        return Ok(());
+    }
+    let pinned = existing
+        .server_static
+        .as_deref()
+        .map(server_key_fingerprint)
+        .unwrap_or_default();
+    if !accept {
+        bail!(
+            "ключ сервера {} изменился: закреплён {}, получен {}. \
+             Если смена ожидаема, повторите с --accept-new-server-key",
+            server,
+            pinned,
+            server_key_fingerprint(resolved)
+        );
+    }
+    eprintln!(
+        "{}",
+        format!(
+            "внимание: ключ сервера заменён ({} → {})",
+            pinned,
+            server_key_fingerprint(resolved)
+        )
+        .red()
+    );
+    Ok(())
+}
+
+fn print_server_key_fingerprint(state: &ClientState) {
+    if let Some(fingerprint) = state.server_static_fingerprint.as_deref() {
+        println!("server_key_fingerprint={}", fingerprint);
+    }
+}
+
+fn export_profile(args: ExportArgs) -> Result<()> {
     let state = ClientState::load()?;
     let keys = state.device_keypair()?;
-    println!("{}", describe_keys(&state.device_id, &keys));
+    println!(
+        "{}",
+        describe_keys(&state.device_id, &keys, args.key_format)
+    );
+    if let Some(ca_hex) = state.device_ca_public.as_ref() {
+        println!(
+            "device_ca_public={}",
+            format_hex_key(ca_hex, args.key_format, PUBLIC_HRP)
+        );
+    }
     println!("server_url={} domain={}", state.server_url, state.domain);
     Ok(())
 }
 
+fn backup_profile(args: BackupArgs) -> Result<()> {
+    let state = ClientState::load()?;
+    let payload = backup::create(&state, args.encrypt.as_deref())?;
+    let mut options = fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    // В копии закрытый ключ устройства: читать её может только владелец.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&args.file)
+        .and_then(|mut file| file.write_all(payload.as_bytes()))
+        .with_context(|| format!("не удалось записать {}", args.file.display()))?;
+    println!(
+        "Профиль {} сохранён в {}{}",
+        state.device_id,
+        args.file.display(),
+        if args.encrypt.is_some() {
+            " (зашифрован)"
+        } else {
+            ""
+        }
+    );
+    if args.encrypt.is_none() {
+        eprintln!(
+            "warning: закрытый ключ устройства записан без шифрования; храните файл как пароль или используйте --encrypt"
+        );
+    }
+    Ok(())
+}
+
+fn restore_profile(args: RestoreArgs) -> Result<()> {
+    let data = fs::read_to_string(&args.file)
+        .with_context(|| format!("не удалось прочитать {}", args.file.display()))?;
+    let state = backup::open(&data, args.passphrase.as_deref())?;
+    let path = state_path()?;
+    if path.exists() {
+        if !args.force {
+            bail!(
+                "профиль уже существует: {}; добавьте --force, чтобы заменить его",
+                path.display()
+            );
+        }
+        let previous = state.save_with_backup()?;
+        println!("Предыдущее состояние сохранено в {}", previous.display());
+    } else {
+        state.save()?;
+    }
+    println!(
+        "Профиль {} восстановлен: server_url={} domain={} friends={}",
+        state.device_id,
+        state.server_url,
+        state.domain,
+        state.friends().len()
+    );
+    Ok(())
+}
+
+async fn show_status() -> Result<()> {
+    let state = ClientState::load()?;
+    println!("device_id={}", state.device_id);
+    println!("user_id={}", state.user_id.as_deref().unwrap_or("-"));
+    println!(
+        "user_handle={}",
+        state.user_handle.as_deref().unwrap_or("-")
+    );
+    println!("server_url={} domain={}", state.server_url, state.domain);
+    if state.insecure {
+        println!("{}", "tls_verify=DISABLED (insecure=true)".red());
+    } else {
+        println!("tls_verify=enabled");
+    }
+    print_server_key_fingerprint(&state);
+    print_certificate_status(&state);
+    let rest = RestClient::with_state(&state)?;
+    let info = rest
+        .server_info()
+        .await
+        .with_context(|| format!("сервер {} недоступен", state.server_url))?;
+    println!("server=reachable domain={}", info.domain);
+    if check_server_key(state.server_static.as_deref(), &info.noise_public)
+        == ServerKeyCheck::Changed
+    {
+        println!(
+            "{}",
+            format!(
+                "server_key=CHANGED presented={}",
+                server_key_fingerprint(&info.noise_public)
+            )
+            .red()
+        );
+    }
+    if !info.supported_patterns.is_empty() {
+        println!("supported_patterns={}", info.supported_patterns.join(","));
+    }
+    if !info.supported_versions.is_empty() {
+        let versions: Vec<String> = info
+            .supported_versions
+            .iter()
+            .map(|version| version.to_string())
+            .collect();
+        println!("supported_versions={}", versions.join(","));
+    }
+    Ok(())
+}
+
+fn format_timestamp(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .map(|ts| ts.to_rfc3339())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+fn format_days_hours(duration: std::time::Duration) -> String {
+    let hours = duration.as_secs() / 3600;
+    format!("{}d {}h", hours / 24, hours % 24)
+}
+
+fn print_certificate_status(state: &ClientState) {
+    if state.device_certificate.is_none() {
+        println!("certificate=none");
+        return;
+    }
+    let Some(expires_at) = state.device_certificate_expires_at else {
+        println!("certificate=valid");
+        return;
+    };
+    let remaining = expires_at - Utc::now().timestamp();
+    let when = format_timestamp(expires_at);
+    let line = if remaining <= 0 {
+        format!("certificate=expired expires_at={}", when)
+    } else {
+        format!(
+            "certificate=valid expires_at={} ({}d left)",
+            when,
+            remaining / 86_400
+        )
+    };
+    if remaining <= CERTIFICATE_WARN_SECS {
+        println!("{}", line.red());
+    } else {
+        println!("{}", line);
+    }
+}
+
+fn rotate_keys(args: RotateKeysArgs) -> Result<()> {
+    let mut state = ClientState::load()?;
+    if state.has_valid_certificate() && !args.force {
+        bail!("у устройства есть действующий сертификат; используйте --force для ротации ключей");
+    }
+    let keys = device::generate_keypair()?;
+    state.update_keys(&keys);
+    let backup = state.save_with_backup()?;
+    println!("warning: прежний ключ устройства больше не будет принят сервером");
+    println!("{}", describe_keys(&state.device_id, &keys, KeyFormat::Hex));
+    println!("Предыдущее состояние сохранено в {}", backup.display());
+    println!(
+        "Прикрепите новый сертификат (devices attach-cert <file>) или повторите pair/claim для этого устройства."
+    );
+    Ok(())
+}
+
+fn list_docs() -> Result<()> {
+    for lang in available_docs()? {
+        println!("{}", lang);
+    }
+    Ok(())
+}
+
 fn print_docs(lang: &str) -> Result<()> {
-    let path = docs_path(lang)?;
+    let path = match docs_path(lang) {
+        Ok(path) => path,
+        Err(err) if lang != "en" => {
+            let fallback = docs_path("en").map_err(|_| err)?;
+            eprintln!(
+                "{}",
+                format!(
+                    "warning: руководство на языке '{}' не найдено, показываю en",
+                    lang
+                )
+                .yellow()
+            );
+            fallback
+        }
+        Err(err) => return Err(err),
+    };
     let text = fs::read_to_string(&path).context("read docs")?;
     println!("{}", text);
     Ok(())
 }
 
-async fn launch_tui() -> Result<()> {
+async fn launch_tui(args: TuiArgs) -> Result<()> {
+    let state = ClientState::load()?;
+    let options = tui::TuiOptions {
+        no_url_open: args.no_url_open,
+        audio_in: args.audio_in,
+        fps: args.fps,
+        no_animations: args.no_anim,
+        offline_state: args.offline_state,
+        accept_new_server_key: args.accept_new_server_key,
+        video_ascii: args.video_ascii,
+        no_color: args.no_color || no_color_env(),
+        ascii: args.ascii.unwrap_or_else(ascii_env),
+        no_splash: args.no_splash,
+        offline: args.offline,
+        view: args.view,
+        debug_frames: args.debug_frames,
+        ..Default::default()
+    };
+    tui::run_tui(state, options).await
+}
+
+/// Юникод не поддерживается: консоль без UTF-8 по `TERM` или локали.
+fn ascii_env() -> bool {
+    let term = std::env::var("TERM").ok();
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+    glyphs::ascii_terminal(term.as_deref(), locale.as_deref())
+}
+
+/// `NO_COLOR` по https://no-color.org: любое непустое значение отключает цвет.
+fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+async fn replay_frames(file: &Path) -> Result<()> {
     let state = ClientState::load()?;
-    tui::run_tui(state).await
+    let options = tui::TuiOptions {
+        no_animations: true,
+        no_color: no_color_env(),
+        ascii: ascii_env(),
+        replay: framedump::read_dump(file)?,
+        ..Default::default()
+    };
+    tui::run_tui(state, options).await
 }
 
-async fn issue_pair(args: PairArgs) -> Result<()> {
-    let PairArgs { ttl, session } = args;
+async fn issue_pair(args: PairArgs, output: OutputFormat) -> Result<()> {
+    let PairArgs {
+        ttl,
+        session,
+        qr,
+        qr_uri,
+        qr_file,
+    } = args;
     let mut state = ClientState::load()?;
     let session = resolve_session(session.as_deref(), &state)?;
-    let rest = RestClient::new(&state.server_url)?;
+    let rest = RestClient::with_state(&state)?;
     let ticket = rest.create_pairing(&session, ttl).await?;
     state.last_pairing_code = Some(ticket.pair_code.clone());
     state.last_pairing_expires_at = Some(ticket.expires_at.clone());
     state.last_pairing_issuer_device_id = ticket.issuer_device_id.clone();
     state.session_token = Some(session);
     state.save()?;
-    print_pairing_summary(&ticket);
+    match output {
+        OutputFormat::Json => print_json(&ticket)?,
+        OutputFormat::Text => print_pairing_summary(&ticket),
+    }
+    if qr || qr_file.is_some() {
+        let payload = if qr_uri {
+            qr::pair_uri(&ticket.pair_code, &state.server_url, &state.domain)?
+        } else {
+            ticket.pair_code.clone()
+        };
+        if qr {
+            let rendered = qr::render_terminal(&payload)?;
+            // В JSON-режиме stdout занят машиночитаемым выводом.
+            if output == OutputFormat::Json {
+                eprintln!("{}", rendered);
+            } else {
+                println!("{}", rendered);
+            }
+        }
+        if let Some(path) = qr_file {
+            qr::write_png(&payload, &path)?;
+            eprintln!("QR-код сохранён в {}", path.display());
+        }
+    }
     Ok(())
 }
 
-async fn handle_devices(command: DevicesCommand) -> Result<()> {
+async fn handle_devices(command: DevicesCommand, output: OutputFormat) -> Result<()> {
     match command {
-        DevicesCommand::List(args) => list_devices(args).await,
+        DevicesCommand::List(args) => list_devices(args, output).await,
         DevicesCommand::Revoke(args) => revoke_device(args).await,
         DevicesCommand::AttachCert(args) => attach_device_certificate(args).await,
     }
 }
 
-async fn handle_friends(command: FriendsCommand) -> Result<()> {
+async fn handle_friends(command: FriendsCommand, output: OutputFormat) -> Result<()> {
     match command {
         FriendsCommand::List => {
             let state = ClientState::load()?;
-            if state.friends().is_empty() {
+            if output == OutputFormat::Json {
+                print_json(&friends_to_payload(state.friends()))?;
+            } else if state.friends().is_empty() {
                 println!("Список друзей пуст.");
             } else {
                 for entry in state.friends() {
@@ -486,7 +1222,7 @@ async fn handle_friends(command: FriendsCommand) -> Result<()> {
             println!("Добавлен друг {}", args.user_id);
             if args.push {
                 let session = resolve_session(args.session.as_deref(), &state)?;
-                let rest = RestClient::new(&state.server_url)?;
+                let rest = RestClient::with_state(&state)?;
                 rest.update_friends(&session, &friends_to_payload(state.friends()))
                     .await?;
                 println!("Список друзей синхронизирован.");
@@ -500,7 +1236,7 @@ async fn handle_friends(command: FriendsCommand) -> Result<()> {
                 println!("Удалён друг {}", args.user_id);
                 if args.push {
                     let session = resolve_session(args.session.as_deref(), &state)?;
-                    let rest = RestClient::new(&state.server_url)?;
+                    let rest = RestClient::with_state(&state)?;
                     rest.update_friends(&session, &friends_to_payload(state.friends()))
                         .await?;
                     println!("Список друзей синхронизирован.");
@@ -513,50 +1249,351 @@ async fn handle_friends(command: FriendsCommand) -> Result<()> {
         FriendsCommand::Pull(args) => {
             let mut state = ClientState::load()?;
             let session = resolve_session(args.session.as_deref(), &state)?;
-            let rest = RestClient::new(&state.server_url)?;
+            let rest = RestClient::with_state(&state)?;
             let remote = rest.list_friends(&session).await?;
             let entries = remote
                 .into_iter()
                 .map(friend_from_payload)
                 .collect::<Vec<_>>();
+            if args.dry_run {
+                let diff = FriendsDiff::between(state.friends(), &entries);
+                return print_friends_dry_run(&diff, None, output);
+            }
             state.set_friends(entries);
             state.save()?;
-            println!("Загружено друзей: {}", state.friends().len());
+            match output {
+                OutputFormat::Json => print_json(&friends_to_payload(state.friends()))?,
+                OutputFormat::Text => println!("Загружено друзей: {}", state.friends().len()),
+            }
             Ok(())
         }
         FriendsCommand::Push(args) => {
             let state = ClientState::load()?;
             let session = resolve_session(args.session.as_deref(), &state)?;
-            let rest = RestClient::new(&state.server_url)?;
-            rest.update_friends(&session, &friends_to_payload(state.friends()))
-                .await?;
+            let rest = RestClient::with_state(&state)?;
+            let payload = friends_to_payload(state.friends());
+            if args.dry_run {
+                let remote = rest
+                    .list_friends(&session)
+                    .await?
+                    .into_iter()
+                    .map(friend_from_payload)
+                    .collect::<Vec<_>>();
+                let diff = FriendsDiff::between(&remote, state.friends());
+                return print_friends_dry_run(&diff, Some(&payload), output);
+            }
+            rest.update_friends(&session, &payload).await?;
             println!("Список друзей синхронизирован.");
             Ok(())
         }
+        FriendsCommand::Export(args) => {
+            let state = ClientState::load()?;
+            let payload = friends_to_payload(state.friends());
+            let rendered = if is_csv_path(&args.file) {
+                friendcsv::to_csv(&payload)
+            } else {
+                serde_json::to_string_pretty(&payload).context("serialize friends")?
+            };
+            fs::write(&args.file, rendered).context("write friends file")?;
+            println!("Экспортировано друзей: {} -> {}", payload.len(), args.file);
+            Ok(())
+        }
+        FriendsCommand::Import(args) => {
+            let mut state = ClientState::load()?;
+            let raw = fs::read_to_string(&args.file).context("read friends file")?;
+            let entries = if is_csv_path(&args.file) {
+                friendcsv::from_csv(&raw)?
+            } else {
+                serde_json::from_str::<Vec<FriendEntryPayload>>(&raw)
+                    .context("parse friends json")?
+            };
+            let mut added = 0usize;
+            let mut updated = 0usize;
+            for entry in entries {
+                if state.upsert_friend(friend_from_payload(entry)) {
+                    added += 1;
+                } else {
+                    updated += 1;
+                }
+            }
+            state.save()?;
+            println!("Импорт друзей: добавлено {}, обновлено {}", added, updated);
+            if args.push {
+                let session = resolve_session(args.session.as_deref(), &state)?;
+                let rest = RestClient::with_state(&state)?;
+                rest.update_friends(&session, &friends_to_payload(state.friends()))
+                    .await?;
+                println!("Список друзей синхронизирован.");
+            }
+            Ok(())
+        }
     }
 }
 
-async fn list_devices(args: DevicesListArgs) -> Result<()> {
-    let DevicesListArgs { session } = args;
+#[derive(Serialize)]
+struct FriendsDryRun<'a> {
+    #[serde(flatten)]
+    diff: &'a FriendsDiff,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<&'a [FriendEntryPayload]>,
+}
+
+/// Вывод `--dry-run` для friends pull/push: что изменилось бы и, для push,
+/// тело, которое ушло бы на сервер.
+fn print_friends_dry_run(
+    diff: &FriendsDiff,
+    payload: Option<&[FriendEntryPayload]>,
+    output: OutputFormat,
+) -> Result<()> {
+    if output == OutputFormat::Json {
+        return print_json(&FriendsDryRun { diff, payload });
+    }
+    if diff.is_empty() {
+        println!("Без изменений.");
+    }
+    for line in diff.lines() {
+        println!("{}", line);
+    }
+    if let Some(payload) = payload {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(payload).context("serialize friends")?
+        );
+    }
+    println!("Dry run: ничего не изменено.");
+    Ok(())
+}
+
+fn is_csv_path(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+async fn list_devices(args: DevicesListArgs, output: OutputFormat) -> Result<()> {
+    let DevicesListArgs {
+        session,
+        limit,
+        cursor,
+        all,
+        status,
+    } = args;
     let state = ClientState::load()?;
     let session = resolve_session(session.as_deref(), &state)?;
-    let rest = RestClient::new(&state.server_url)?;
-    let devices = rest.list_devices(&session).await?;
-    if devices.is_empty() {
+    let rest = RestClient::with_state(&state)?;
+    let status = status.map(DeviceStatusFilter::as_str);
+    let mut query = DevicesQuery {
+        limit,
+        cursor,
+        status: status.map(str::to_string),
+    };
+    let mut devices = Vec::new();
+    let next_cursor = loop {
+        let page = rest.list_devices(&session, &query).await?;
+        devices.extend(page.devices);
+        match page.next_cursor {
+            // Повтор курсора означает, что сервер не двигается дальше.
+            Some(next) if all && query.cursor.as_deref() != Some(next.as_str()) => {
+                query.cursor = Some(next);
+            }
+            next => break next.filter(|_| !all),
+        }
+    };
+    let summary = device_status_summary(&devices);
+    if let Some(status) = status {
+        devices.retain(|device| device.status.eq_ignore_ascii_case(status));
+    }
+    if output == OutputFormat::Json {
+        print_json(&devices)?;
+    } else if devices.is_empty() {
         println!("Нет зарегистрированных устройств.");
     } else {
         for device in devices {
             print_device_entry(&device);
         }
+        println!("Итого: {}", summary);
+    }
+    if let Some(next) = next_cursor {
+        // В JSON-режиме подсказка уходит в stderr, чтобы не ломать вывод для jq.
+        let hint = format!("Есть ещё устройства: --cursor {} или --all", next);
+        if output == OutputFormat::Json {
+            eprintln!("{}", hint);
+        } else {
+            println!("{}", hint);
+        }
+    }
+    Ok(())
+}
+
+async fn update_profile(args: ProfileArgs) -> Result<()> {
+    let ProfileArgs {
+        display_name,
+        mut avatar_url,
+        avatar_file,
+        session,
+    } = args;
+    let mut state = ClientState::load()?;
+    if display_name.is_none() && avatar_url.is_none() && avatar_file.is_none() {
+        println!(
+            "display_name={}",
+            state.user_display_name.as_deref().unwrap_or("-")
+        );
+        println!(
+            "avatar_url={}",
+            state.user_avatar_url.as_deref().unwrap_or("-")
+        );
+        return Ok(());
+    }
+    let session = resolve_session(session.as_deref(), &state)?;
+    let rest = RestClient::with_state(&state)?;
+    if let Some(path) = avatar_file {
+        let content_type = avatar_content_type(&path)?;
+        let data = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let url = rest.upload_avatar(&session, data, content_type).await?;
+        println!("Аватар загружен: {}", url);
+        avatar_url = Some(url);
+    }
+    rest.update_profile(&session, display_name.as_deref(), avatar_url.as_deref())
+        .await?;
+    if display_name.is_some() {
+        state.user_display_name = display_name;
+    }
+    if avatar_url.is_some() {
+        state.user_avatar_url = avatar_url;
+    }
+    // Engine отправляет профиль из state при каждом рукопожатии.
+    state.save()?;
+    println!("Профиль обновлён; изменения уйдут и при следующем подключении");
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct PresenceReport<'a> {
+    state: &'a str,
+    confirmed: bool,
+}
+
+/// Сохраняет статус в профиль и, если не `--persist-only`, объявляет его:
+/// подключение, кадр PRESENCE, ожидание ACK, отключение без смены статуса.
+async fn set_presence(args: PresenceArgs, output: OutputFormat) -> Result<()> {
+    let presence = args.state.trim();
+    if presence.is_empty() {
+        bail!("статус не может быть пустым");
+    }
+    let mut state = ClientState::load()?;
+    state.presence_state = presence.to_string();
+    state.save()?;
+    if !args.persist_only {
+        if state.insecure {
+            warn_insecure_tls();
+        }
+        let limit = Duration::from_secs(state.connect_timeout_secs.max(1) + args.wait.max(1));
+        let (mut engine, mut events, _media) = create_engine(8, 64, 1);
+        let mut connect_state = state.clone();
+        if let Some(session) = args.session {
+            connect_state.session_token = Some(session);
+        }
+        engine
+            .send(EngineCommand::Connect {
+                state: Box::new(connect_state),
+                accept_new_server_key: false,
+            })
+            .await?;
+        let result = tokio::time::timeout(limit, announce_presence(&engine, &mut events, presence))
+            .await
+            .unwrap_or_else(|_| {
+                Err(anyhow!(
+                    "сервер не подтвердил статус за {} с",
+                    limit.as_secs()
+                ))
+            });
+        // Отключаемся молча: иначе сервер сразу увидит offline_state.
+        if let Err(err) = engine.shutdown(None, Duration::from_secs(2)).await {
+            tracing::debug!("engine shutdown: {:#}", err);
+        }
+        result?;
+    }
+    if output == OutputFormat::Json {
+        print_json(&PresenceReport {
+            state: presence,
+            confirmed: !args.persist_only,
+        })?;
+    } else if args.persist_only {
+        println!(
+            "Статус «{}» сохранён и будет объявлен при следующем подключении",
+            presence
+        );
+    } else {
+        println!("Статус «{}» подтверждён сервером", presence);
     }
     Ok(())
 }
 
+async fn announce_presence(
+    engine: &EngineHandle,
+    events: &mut tokio::sync::mpsc::Receiver<ClientEvent>,
+    presence: &str,
+) -> Result<()> {
+    let mut sent = None;
+    while let Some(event) = events.recv().await {
+        match event {
+            ClientEvent::Connected { .. } => {
+                engine
+                    .send(EngineCommand::Presence {
+                        state: presence.to_string(),
+                    })
+                    .await?;
+            }
+            ClientEvent::PresenceSent { sequence } => sent = Some(sequence),
+            ClientEvent::Frame(frame) if sent.is_some() && acked_sequence(&frame) == sent => {
+                return Ok(());
+            }
+            ClientEvent::Error { detail } => bail!("{}", detail),
+            ClientEvent::Disconnected { reason } => bail!("соединение закрыто: {}", reason),
+            _ => {}
+        }
+    }
+    bail!("engine offline")
+}
+
 async fn revoke_device(args: DevicesRevokeArgs) -> Result<()> {
-    let DevicesRevokeArgs { device_id, session } = args;
+    let DevicesRevokeArgs {
+        device_id,
+        session,
+        yes,
+        force,
+        dry_run,
+    } = args;
     let state = ClientState::load()?;
+    if device_id == state.device_id && !force {
+        bail!(
+            "{} — текущее устройство; после отзыва доступ будет потерян. Добавьте --force, если уверены",
+            device_id
+        );
+    }
     let session = resolve_session(session.as_deref(), &state)?;
-    let rest = RestClient::new(&state.server_url)?;
+    let rest = RestClient::with_state(&state)?;
+    if !yes || dry_run {
+        let devices = rest
+            .list_devices(&session, &DevicesQuery::default())
+            .await?
+            .devices;
+        match devices.iter().find(|entry| entry.device_id == device_id) {
+            Some(entry) => println!(
+                "Устройство {}: статус {}, создано {}",
+                entry.device_id, entry.status, entry.created_at
+            ),
+            None => println!("Устройство {} не найдено в списке", device_id),
+        }
+        if dry_run {
+            println!("Dry run: устройство {} было бы отозвано.", device_id);
+            return Ok(());
+        }
+        if !confirm("Отозвать устройство? [y/N] ")? {
+            println!("Отменено.");
+            return Ok(());
+        }
+    }
     rest.revoke_device(&session, &device_id).await?;
     println!("Устройство {} помечено как revoked", device_id);
     Ok(())
@@ -566,6 +1603,7 @@ async fn attach_device_certificate(args: DevicesAttachCertArgs) -> Result<()> {
     let DevicesAttachCertArgs {
         certificate,
         issuer,
+        allow_expired,
     } = args;
     let mut state = ClientState::load()?;
     let raw = if Path::new(&certificate).exists() {
@@ -597,8 +1635,8 @@ async fn attach_device_certificate(args: DevicesAttachCertArgs) -> Result<()> {
         _ => {}
     }
     let issuer_bytes = match issuer {
-        Some(hex) => {
-            let bytes = decode_hex32(&hex)?;
+        Some(encoded) => {
+            let bytes = decode_key32(&encoded)?;
             if bytes != certificate.data.issuer {
                 bail!("указанный issuer не совпадает с полем issuer сертификата");
             }
@@ -609,16 +1647,86 @@ async fn attach_device_certificate(args: DevicesAttachCertArgs) -> Result<()> {
     certificate
         .verify(&issuer_bytes)
         .context("подпись сертификата невалидна")?;
+    let window = certificate_window(
+        certificate.data.issued_at,
+        certificate.data.expires_at,
+        Utc::now().timestamp(),
+    );
+    let problem = match window {
+        CertificateWindow::Valid(_) => None,
+        CertificateWindow::NotYetValid(left) => Some(format!(
+            "сертификат начнёт действовать только через {}",
+            format_days_hours(left)
+        )),
+        CertificateWindow::Expired(ago) => {
+            Some(format!("сертификат истёк {} назад", format_days_hours(ago)))
+        }
+    };
+    if let Some(problem) = problem {
+        if !allow_expired {
+            bail!(
+                "{}; рукопожатие с ним не пройдёт (--allow-expired, чтобы сохранить всё равно)",
+                problem
+            );
+        }
+        println!("{}", format!("warning: {}", problem).red());
+    }
     state.set_certificate(&certificate)?;
     state.save()?;
     println!(
         "Сертификат устройства serial={} сохранён. Срок действия до {}.",
-        certificate.data.serial, certificate.data.expires_at
+        certificate.data.serial,
+        format_timestamp(certificate.data.expires_at)
     );
+    if let CertificateWindow::Valid(left) = window {
+        println!("Осталось: {}", format_days_hours(left));
+    }
     Ok(())
 }
 
-async fn claim_device(args: ClaimArgs) -> Result<()> {
+/// Разбирает pair-ссылку и передаёт её в `claim` (профиль есть) или `init --pair-code`.
+async fn open_link(args: OpenArgs, output: OutputFormat) -> Result<()> {
+    let link = qr::parse_pair_uri(&args.uri).context("не удалось разобрать ссылку")?;
+    if let Ok(state) = ClientState::load() {
+        if !same_server(&state.server_url, &link.server) {
+            bail!(
+                "профиль привязан к {}, а ссылка ведёт на {}; создайте отдельный профиль через --config-dir",
+                state.server_url,
+                link.server
+            );
+        }
+        return claim_device(
+            ClaimArgs {
+                pair_code: link.code,
+                device_name: args.device_name,
+                server: Some(link.server),
+                session: None,
+            },
+            output,
+        )
+        .await;
+    }
+    let Some(domain) = link.domain else {
+        bail!("в ссылке нет domain, а профиль ещё не создан; используйте init --pair-code");
+    };
+    let mut argv = vec![
+        "init".to_string(),
+        "--server".to_string(),
+        link.server,
+        "--domain".to_string(),
+        domain,
+        "--pair-code".to_string(),
+        link.code,
+    ];
+    if let Some(name) = args.device_name {
+        argv.extend(["--device-name".to_string(), name]);
+    }
+    // Остальные параметры init берут значения по умолчанию из его описания.
+    let matches = InitArgs::augment_args(clap::Command::new("init")).try_get_matches_from(argv)?;
+    init_profile(InitArgs::from_arg_matches(&matches)?).await
+}
+
+async fn claim_device(args: ClaimArgs, output: OutputFormat) -> Result<()> {
     let ClaimArgs {
         pair_code,
         device_name,
@@ -633,18 +1741,24 @@ async fn claim_device(args: ClaimArgs) -> Result<()> {
     } else {
         bail!("укажите --server или инициализируйте профиль через init");
     };
-    let rest = RestClient::new(&server)?;
+    let rest = match &state_opt {
+        Some(state) => RestClient::with_tls(&server, state.tls_ca_path.as_deref(), state.insecure)?,
+        None => RestClient::new(&server)?,
+    };
     let claim = rest
         .claim_pairing(&pair_code, device_name.as_deref())
         .await?;
-    print_claim_summary(&claim);
-    if let Some(session) = session.as_ref() {
-        println!("session={} (используйте для REST)", session);
+    let keys = keypair_from_claim(&claim.private_key, &claim.public_key)?;
+    let text = output == OutputFormat::Text;
+    if text {
+        print_claim_summary(&claim);
+        if let Some(session) = session.as_ref() {
+            println!("session={} (используйте для REST)", session);
+        }
+    } else {
+        print_json(&claim)?;
     }
     if let Some(ref mut state) = state_opt {
-        let private = decode_hex32(&claim.private_key)?;
-        let public = decode_hex32(&claim.public_key)?;
-        let keys = DeviceKeyPair { public, private };
         state.device_id = claim.device_id.clone();
         state.update_keys(&keys);
         if let Some(cert) = claim.device_certificate.as_ref() {
@@ -666,37 +1780,38 @@ async fn claim_device(args: ClaimArgs) -> Result<()> {
             state.session_token = Some(session);
         }
         state.save()?;
-        println!("state обновлён в {}", state_path()?.display());
-        if let Some(cert) = claim.device_certificate.as_ref() {
-            println!(
-                "certificate_serial={} expires_at={}",
-                cert.data.serial, cert.data.expires_at
-            );
-        }
-        if let Some(ca_hex) = state.device_ca_public.as_ref() {
-            println!("device_ca_public={}", ca_hex);
+        if text {
+            println!("state обновлён в {}", state_path()?.display());
+            if let Some(cert) = claim.device_certificate.as_ref() {
+                println!(
+                    "certificate_serial={} expires_at={}",
+                    cert.data.serial, cert.data.expires_at
+                );
+            }
+            if let Some(ca_hex) = state.device_ca_public.as_ref() {
+                println!("device_ca_public={}", ca_hex);
+            }
         }
     }
     Ok(())
 }
 
-fn friend_from_payload(payload: FriendEntryPayload) -> FriendEntry {
-    FriendEntry {
-        user_id: payload.user_id,
-        handle: payload.handle,
-        alias: payload.alias,
+/// Значение секретного аргумента: `@path` читается из файла, `env:NAME` — из
+/// переменной окружения, иначе берётся как есть. Так токен не попадает в
+/// историю оболочки и вывод `ps`.
+fn parse_secret(raw: &str) -> Result<String> {
+    let value = if let Some(path) = raw.strip_prefix('@') {
+        fs::read_to_string(path).map_err(|err| anyhow!("не удалось прочитать {}: {}", path, err))?
+    } else if let Some(name) = raw.strip_prefix("env:") {
+        std::env::var(name).map_err(|_| anyhow!("переменная окружения {} не задана", name))?
+    } else {
+        return Ok(raw.to_string());
+    };
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("{} не содержит значения", raw);
     }
-}
-
-fn friends_to_payload(entries: &[FriendEntry]) -> Vec<FriendEntryPayload> {
-    entries
-        .iter()
-        .map(|entry| FriendEntryPayload {
-            user_id: entry.user_id.clone(),
-            handle: entry.handle.clone(),
-            alias: entry.alias.clone(),
-        })
-        .collect()
+    Ok(value.to_string())
 }
 
 fn resolve_session(explicit: Option<&str>, state: &ClientState) -> Result<String> {
@@ -706,7 +1821,31 @@ fn resolve_session(explicit: Option<&str>, state: &ClientState) -> Result<String
     if let Some(value) = state.session_token.as_ref() {
         return Ok(value.clone());
     }
-    bail!("сессионный токен не найден: подключитесь (:connect) или передайте --session");
+    Err(anyhow!(ProfileError::NoSession))
+}
+
+/// Спрашивает y/N в терминале; пустой ответ или закрытый stdin — отказ.
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush().context("flush stdout")?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).context("read answer")?;
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes" | "д" | "да"
+    ))
+}
+
+fn print_completions(shell: Shell) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let rendered = serde_json::to_string_pretty(value).context("serialize output")?;
+    println!("{}", rendered);
+    Ok(())
 }
 
 fn print_device_entry(entry: &DeviceEntry) {
@@ -0,0 +1,114 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+
+/// Небольшое подмножество Markdown для сообщений: `**жирный**`, `*курсив*`,
+/// `` `код` `` и блоки ```…```. Обычный текст отдаётся в `plain`, чтобы
+/// вызывающий код мог, например, подсветить ссылки.
+pub fn render_markdown(
+    text: &str,
+    plain: fn(&str) -> Vec<Span<'static>>,
+) -> Vec<Vec<Span<'static>>> {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            lines.push(vec![
+                Span::styled("│ ", code_block_style()),
+                Span::styled(line.to_string(), code_block_style()),
+            ]);
+        } else {
+            lines.push(render_inline(line, plain));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+    lines
+}
+
+fn render_inline(line: &str, plain: fn(&str) -> Vec<Span<'static>>) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut pending = String::new();
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        let marker = if rest.starts_with("**") {
+            Some(("**", Style::default().add_modifier(Modifier::BOLD)))
+        } else if rest.starts_with('`') {
+            Some(("`", inline_code_style()))
+        } else if rest.starts_with('*') {
+            Some(("*", Style::default().add_modifier(Modifier::ITALIC)))
+        } else {
+            None
+        };
+        if let Some((marker, style)) = marker
+            && let Some(end) = rest[marker.len()..].find(marker)
+            && end > 0
+            && !rest[marker.len()..].starts_with(char::is_whitespace)
+        {
+            if !pending.is_empty() {
+                spans.extend(plain(&pending));
+                pending.clear();
+            }
+            let inner = &rest[marker.len()..marker.len() + end];
+            spans.push(Span::styled(inner.to_string(), style));
+            rest = &rest[marker.len() * 2 + end..];
+            continue;
+        }
+        pending.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    if !pending.is_empty() {
+        spans.extend(plain(&pending));
+    }
+    spans
+}
+
+fn inline_code_style() -> Style {
+    Style::default().fg(Color::LightYellow).bg(Color::DarkGray)
+}
+
+fn code_block_style() -> Style {
+    Style::default().fg(Color::Gray).add_modifier(Modifier::DIM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(text: &str) -> Vec<Span<'static>> {
+        vec![Span::raw(text.to_string())]
+    }
+
+    fn contents(line: &[Span<'static>]) -> Vec<String> {
+        line.iter().map(|span| span.content.to_string()).collect()
+    }
+
+    #[test]
+    fn inline_markers_become_styled_spans() {
+        let lines = render_markdown("a **b** *c* `d`", raw);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(contents(&lines[0]), ["a ", "b", " ", "c", " ", "d"]);
+        assert!(lines[0][1].style.add_modifier.contains(Modifier::BOLD));
+        assert!(lines[0][3].style.add_modifier.contains(Modifier::ITALIC));
+        assert_eq!(lines[0][5].style, inline_code_style());
+    }
+
+    #[test]
+    fn unclosed_markers_stay_literal() {
+        let lines = render_markdown("2 * 3 = **6", raw);
+        assert_eq!(contents(&lines[0]), ["2 * 3 = **6"]);
+    }
+
+    #[test]
+    fn fenced_blocks_are_dimmed_and_fences_dropped() {
+        let lines = render_markdown("before\n```rust\nlet x = *y*;\n```\nafter", raw);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(contents(&lines[1]), ["│ ", "let x = *y*;"]);
+        assert_eq!(lines[1][1].style, code_block_style());
+        assert_eq!(contents(&lines[2]), ["after"]);
+    }
+}
@@ -1,4 +1,8 @@
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::ratelimit::RateLimiter;
 
 use anyhow::{Context, Result, anyhow, bail};
 use chrono::{DateTime, Utc};
@@ -8,7 +12,17 @@ use commucat_proto::call::{
 };
 use opus::{Channels as OpusChannels, Decoder as OpusDecoder};
 use vpx_rs::dec::CodecId as DecoderCodecId;
-use vpx_rs::{Decoder, DecoderConfig};
+use vpx_rs::image::UVImagePlanes;
+use vpx_rs::{DecodedImageData, Decoder, DecoderConfig, ImageFormat, YUVImageData};
+
+/// Наибольший размер уменьшенного кадра для отрисовки в терминале.
+const FRAME_MAX_WIDTH: usize = 160;
+const FRAME_MAX_HEIGHT: usize = 120;
+/// Потолок разрешения входящего видео по умолчанию.
+pub const DEFAULT_MAX_VIDEO_WIDTH: u32 = 1920;
+pub const DEFAULT_MAX_VIDEO_HEIGHT: u32 = 1080;
+/// Самый длинный пакет Opus — 120 мс.
+const OPUS_MAX_PACKET_MS: u32 = 120;
 
 #[derive(Debug, Clone)]
 pub struct AudioMetrics {
@@ -24,12 +38,42 @@ pub struct VideoMetrics {
     pub width: u32,
     pub height: u32,
     pub frames_decoded: u64,
+    /// Кадры, отброшенные из-за превышения потолка разрешения.
+    pub frames_dropped: u64,
+    /// Сглаженная частота декодированных кадров.
+    pub fps: f32,
     pub timestamp: DateTime<Utc>,
 }
 
+/// Уменьшенная RGB-копия последнего декодированного кадра.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl VideoFrame {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        let raw = self.pixels.iter().flatten().copied().collect();
+        image::RgbImage::from_raw(self.width as u32, self.height as u32, raw)
+            .ok_or_else(|| anyhow!("frame buffer does not match its dimensions"))?
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
 pub struct MediaManager {
     audio_streams: HashMap<String, AudioStream>,
     video_streams: HashMap<String, VideoStream>,
+    /// Как часто сохранять кадр для отрисовки; `None` — кадры не нужны.
+    frame_interval: Option<Duration>,
+    max_width: u32,
+    max_height: u32,
 }
 
 impl MediaManager {
@@ -37,9 +81,49 @@ impl MediaManager {
         Self {
             audio_streams: HashMap::new(),
             video_streams: HashMap::new(),
+            frame_interval: None,
+            max_width: DEFAULT_MAX_VIDEO_WIDTH,
+            max_height: DEFAULT_MAX_VIDEO_HEIGHT,
+        }
+    }
+
+    /// Кадры больше `width`×`height` отбрасываются, не доходя до отрисовки.
+    pub fn with_max_resolution(mut self, width: u32, height: u32) -> Self {
+        self.max_width = width.max(1);
+        self.max_height = height.max(1);
+        self
+    }
+
+    /// Сохранять уменьшенные кадры не чаще одного раза за `interval`.
+    pub fn with_frame_capture(mut self, interval: Duration) -> Self {
+        self.frame_interval = Some(interval);
+        self
+    }
+
+    /// Просит сохранить следующий декодированный кадр в полном разрешении.
+    /// Возвращает `false`, если у звонка нет видеопотока.
+    pub fn request_snapshot(&mut self, call_id: &str) -> bool {
+        match self.video_streams.get_mut(call_id) {
+            Some(stream) => {
+                stream.snapshot_requested = true;
+                true
+            }
+            None => false,
         }
     }
 
+    pub fn take_snapshot(&mut self, call_id: &str) -> Option<VideoFrame> {
+        self.video_streams
+            .get_mut(call_id)
+            .and_then(|stream| stream.snapshot.take())
+    }
+
+    pub fn latest_frame(&self, call_id: &str) -> Option<&VideoFrame> {
+        self.video_streams
+            .get(call_id)
+            .and_then(|stream| stream.last_frame.as_ref())
+    }
+
     pub fn initialise_from_media(&mut self, call_id: &str, media: &MediaConfig) -> Result<()> {
         if !self.audio_streams.contains_key(call_id) {
             let stream = AudioStream::from_config(&media.audio)
@@ -50,8 +134,9 @@ impl MediaManager {
         if let Some(video) = media.video.as_ref()
             && !self.video_streams.contains_key(call_id)
         {
-            let stream = VideoStream::from_config(video)
+            let mut stream = VideoStream::from_config(video, self.max_width, self.max_height)
                 .with_context(|| "failed to initialise VPX decoder")?;
+            stream.frame_limiter = self.frame_interval.map(RateLimiter::new);
             self.video_streams.insert(call_id.to_string(), stream);
         }
 
@@ -121,6 +206,14 @@ impl AudioStream {
         {
             expected_samples = samples;
         }
+        let max_samples = (self.sample_rate * OPUS_MAX_PACKET_MS / 1000) as usize;
+        if expected_samples > max_samples {
+            bail!(
+                "Opus packet declares {} samples, limit is {}",
+                expected_samples,
+                max_samples
+            );
+        }
 
         let required_len = expected_samples * self.channels as usize;
         if self.pcm_buffer.len() < required_len {
@@ -164,57 +257,223 @@ impl AudioStream {
 
 struct VideoStream {
     decoder: Decoder,
+    codec: VideoCodec,
     width: u32,
     height: u32,
+    max_width: u32,
+    max_height: u32,
     frames_decoded: u64,
+    frames_dropped: u64,
+    fps: f32,
+    last_decoded: Option<Instant>,
+    frame_limiter: Option<RateLimiter>,
+    last_frame: Option<VideoFrame>,
+    snapshot_requested: bool,
+    snapshot: Option<VideoFrame>,
 }
 
 impl VideoStream {
-    fn from_config(config: &VideoConfig) -> Result<Self> {
+    fn from_config(config: &VideoConfig, max_width: u32, max_height: u32) -> Result<Self> {
         let codec = match config.codec {
             VideoCodec::Vp8 => DecoderCodecId::VP8,
             VideoCodec::Vp9 => DecoderCodecId::VP9,
         };
 
-        let width = config.max_resolution.width.max(1) as u32;
-        let height = config.max_resolution.height.max(1) as u32;
+        // Собеседник сам объявляет max_resolution, поэтому доверяем ему только в пределах потолка.
+        let width = (config.max_resolution.width as u32).clamp(1, max_width);
+        let height = (config.max_resolution.height as u32).clamp(1, max_height);
         let decoder_config = DecoderConfig::new(codec, width, height);
         let decoder = Decoder::new(decoder_config).context("failed to create VPX decoder")?;
 
         Ok(Self {
             decoder,
+            codec: config.codec,
             width,
             height,
+            max_width,
+            max_height,
             frames_decoded: 0,
+            frames_dropped: 0,
+            fps: 0.0,
+            last_decoded: None,
+            frame_limiter: None,
+            last_frame: None,
+            snapshot_requested: false,
+            snapshot: None,
         })
     }
 
+    fn exceeds_limit(&self, width: u32, height: u32) -> bool {
+        width > self.max_width || height > self.max_height
+    }
+
+    fn drop_oversized(&mut self, width: u32, height: u32) {
+        self.frames_dropped += 1;
+        tracing::warn!(
+            width,
+            height,
+            max_width = self.max_width,
+            max_height = self.max_height,
+            frames_dropped = self.frames_dropped,
+            "dropping oversized video frame"
+        );
+    }
+
+    fn metrics(&self) -> VideoMetrics {
+        VideoMetrics {
+            width: self.width,
+            height: self.height,
+            frames_decoded: self.frames_decoded,
+            frames_dropped: self.frames_dropped,
+            fps: self.fps,
+            timestamp: Utc::now(),
+        }
+    }
+
     fn ingest(&mut self, payload: &[u8]) -> Result<VideoMetrics> {
+        // Ключевой кадр VP8 объявляет размер в заголовке: огромный кадр не отдаём
+        // libvpx вовсе, чтобы он не выделил под него буферы.
+        if self.codec == VideoCodec::Vp8
+            && let Some((width, height)) = vp8_keyframe_dimensions(payload)
+            && self.exceeds_limit(width, height)
+        {
+            self.drop_oversized(width, height);
+            return Ok(self.metrics());
+        }
+
         let frames = self
             .decoder
             .decode(payload)
             .context("failed to decode VPX frame")?;
 
+        let now = Instant::now();
+        let (max_width, max_height) = (self.max_width, self.max_height);
         let mut produced = 0u64;
+        let mut oversized = Vec::new();
         for frame in frames {
-            produced += 1;
             let width = frame.width();
             let height = frame.height();
+            if width > max_width || height > max_height {
+                oversized.push((width, height));
+                continue;
+            }
+            produced += 1;
             if width > 0 && height > 0 {
                 self.width = width;
                 self.height = height;
             }
+            if let DecodedImageData::Data8b(image) = frame.data() {
+                if self.snapshot_requested {
+                    self.snapshot_requested = false;
+                    self.snapshot = Some(convert_frame(&image, usize::MAX, usize::MAX));
+                }
+                if self
+                    .frame_limiter
+                    .as_mut()
+                    .is_some_and(|limiter| limiter.check(now))
+                {
+                    self.last_frame =
+                        Some(convert_frame(&image, FRAME_MAX_WIDTH, FRAME_MAX_HEIGHT));
+                }
+            }
         }
 
+        if produced > 0 {
+            if let Some(last) = self.last_decoded {
+                let elapsed = now.duration_since(last).as_secs_f32();
+                if elapsed > 0.0 {
+                    let instant = produced as f32 / elapsed;
+                    self.fps = if self.fps == 0.0 {
+                        instant
+                    } else {
+                        self.fps * 0.8 + instant * 0.2
+                    };
+                }
+            }
+            self.last_decoded = Some(now);
+        }
         self.frames_decoded += produced;
+        for (width, height) in oversized {
+            self.drop_oversized(width, height);
+        }
 
-        Ok(VideoMetrics {
-            width: self.width,
-            height: self.height,
-            frames_decoded: self.frames_decoded,
-            timestamp: Utc::now(),
-        })
+        Ok(self.metrics())
+    }
+}
+
+/// Размер из заголовка ключевого кадра VP8 (RFC 6386, 9.1); `None` для
+/// межкадров и повреждённых заголовков.
+fn vp8_keyframe_dimensions(payload: &[u8]) -> Option<(u32, u32)> {
+    let header = payload.get(..10)?;
+    let is_keyframe = header[0] & 0x01 == 0;
+    if !is_keyframe || header[3..6] != [0x9d, 0x01, 0x2a] {
+        return None;
+    }
+    let width = u16::from_le_bytes([header[6], header[7]]) & 0x3fff;
+    let height = u16::from_le_bytes([header[8], header[9]]) & 0x3fff;
+    Some((width as u32, height as u32))
+}
+
+/// Переводит YUV-кадр в RGB (BT.601, ограниченный диапазон), уменьшая его
+/// до `max_width`×`max_height` с сохранением пропорций.
+fn convert_frame(image: &YUVImageData<u8>, max_width: usize, max_height: usize) -> VideoFrame {
+    let (src_width, src_height) = (image.width().max(1), image.height().max(1));
+    let scale = (max_width as f32 / src_width as f32)
+        .min(max_height as f32 / src_height as f32)
+        .min(1.0);
+    let width = ((src_width as f32 * scale) as usize).max(1);
+    let height = ((src_height as f32 * scale) as usize).max(1);
+    let (shift_x, shift_y) = match image.format() {
+        ImageFormat::I444 => (0, 0),
+        ImageFormat::I422 => (1, 0),
+        ImageFormat::I440 => (0, 1),
+        _ => (1, 1),
+    };
+    let planes = image.planes();
+    let y_stride = planes.y_stride();
+    let chroma = |x: usize, y: usize| -> (u8, u8) {
+        let (cx, cy) = (x >> shift_x, y >> shift_y);
+        match &planes.uv {
+            UVImagePlanes::Separate(uv) => (
+                uv.u.get(cy * uv.u_stride() + cx).copied().unwrap_or(128),
+                uv.v.get(cy * uv.v_stride() + cx).copied().unwrap_or(128),
+            ),
+            UVImagePlanes::Interleaved(uv) => {
+                let offset = cy * uv.uv_stride() + cx * 2;
+                (
+                    uv.uv.get(offset).copied().unwrap_or(128),
+                    uv.uv.get(offset + 1).copied().unwrap_or(128),
+                )
+            }
+        }
+    };
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in 0..height {
+        let y = row * src_height / height;
+        for col in 0..width {
+            let x = col * src_width / width;
+            let luma = planes.y.get(y * y_stride + x).copied().unwrap_or(0);
+            let (u, v) = chroma(x, y);
+            pixels.push(yuv_to_rgb(luma, u, v));
+        }
     }
+    VideoFrame {
+        width,
+        height,
+        pixels,
+    }
+}
+
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = (y as f32 - 16.0) * 1.164;
+    let d = u as f32 - 128.0;
+    let e = v as f32 - 128.0;
+    let clamp = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(c + 1.596 * e),
+        clamp(c - 0.392 * d - 0.813 * e),
+        clamp(c + 2.017 * d),
+    ]
 }
 
 #[cfg(test)]
@@ -400,7 +659,9 @@ mod tests {
             adaptive: true,
         };
 
-        let mut stream = VideoStream::from_config(&config).unwrap();
+        let mut stream =
+            VideoStream::from_config(&config, DEFAULT_MAX_VIDEO_WIDTH, DEFAULT_MAX_VIDEO_HEIGHT)
+                .unwrap();
         let mut encoder = TestVp8Encoder::new(320, 180);
 
         let frame1 = encoder.encode_frame(0x10, 0);
@@ -414,5 +675,109 @@ mod tests {
         assert_eq!(metrics2.frames_decoded, 2);
         assert_eq!(metrics2.width, 320);
         assert_eq!(metrics2.height, 180);
+        assert!(stream.last_frame.is_none());
+    }
+
+    #[test]
+    fn vp8_keyframe_header_reports_declared_size() {
+        // Тег кадра, стартовый код и размер 16383×16383 без масштабирования.
+        let mut payload = vec![0x10, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0xff, 0x3f, 0xff, 0x3f];
+        assert_eq!(vp8_keyframe_dimensions(&payload), Some((16383, 16383)));
+        payload[0] |= 0x01;
+        assert_eq!(vp8_keyframe_dimensions(&payload), None);
+        assert_eq!(vp8_keyframe_dimensions(&payload[..6]), None);
+    }
+
+    #[test]
+    fn video_frames_beyond_the_ceiling_are_dropped() {
+        let config = VideoConfig {
+            codec: VideoCodec::Vp8,
+            max_bitrate: 500_000,
+            max_resolution: commucat_proto::call::VideoResolution {
+                width: 320,
+                height: 180,
+            },
+            frame_rate: 24,
+            adaptive: true,
+        };
+        let mut stream = VideoStream::from_config(&config, 160, 120).unwrap();
+        assert_eq!((stream.width, stream.height), (160, 120));
+        let mut encoder = TestVp8Encoder::new(320, 180);
+
+        let metrics = stream.ingest(&encoder.encode_frame(0x10, 0)).unwrap();
+        assert_eq!(metrics.frames_decoded, 0);
+        assert_eq!(metrics.frames_dropped, 1);
+
+        // Поддельный ключевой кадр с огромным размером не доходит до декодера.
+        let forged = [
+            0x10, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0xff, 0x3f, 0xff, 0x3f, 0, 0,
+        ];
+        let metrics = stream.ingest(&forged).unwrap();
+        assert_eq!(metrics.frames_dropped, 2);
+        assert_eq!((metrics.width, metrics.height), (160, 120));
+    }
+
+    #[test]
+    fn video_frames_are_captured_downscaled_and_rate_limited() {
+        let config = VideoConfig {
+            codec: VideoCodec::Vp8,
+            max_bitrate: 500_000,
+            max_resolution: commucat_proto::call::VideoResolution {
+                width: 320,
+                height: 180,
+            },
+            frame_rate: 24,
+            adaptive: true,
+        };
+        let mut manager = MediaManager::new().with_frame_capture(Duration::from_secs(60));
+        let media = MediaConfig {
+            video: Some(config),
+            ..Default::default()
+        };
+        manager.initialise_from_media("call", &media).unwrap();
+        let mut encoder = TestVp8Encoder::new(320, 180);
+
+        manager
+            .decode_video("call", &encoder.encode_frame(0xeb, 0))
+            .unwrap();
+        let frame = manager.latest_frame("call").expect("captured frame");
+        assert_eq!((frame.width, frame.height), (160, 90));
+        assert!(frame.pixel(80, 45)[0] > 200);
+
+        manager
+            .decode_video("call", &encoder.encode_frame(0x10, 1))
+            .unwrap();
+        // Второй кадр пришёл раньше интервала и не заменил первый.
+        assert!(manager.latest_frame("call").unwrap().pixel(80, 45)[0] > 200);
+    }
+
+    #[test]
+    fn video_snapshot_keeps_full_resolution() {
+        let config = VideoConfig {
+            codec: VideoCodec::Vp8,
+            max_bitrate: 500_000,
+            max_resolution: commucat_proto::call::VideoResolution {
+                width: 320,
+                height: 180,
+            },
+            frame_rate: 24,
+            adaptive: true,
+        };
+        let mut manager = MediaManager::new();
+        assert!(!manager.request_snapshot("call"));
+        let media = MediaConfig {
+            video: Some(config),
+            ..Default::default()
+        };
+        manager.initialise_from_media("call", &media).unwrap();
+        let mut encoder = TestVp8Encoder::new(320, 180);
+
+        assert!(manager.request_snapshot("call"));
+        manager
+            .decode_video("call", &encoder.encode_frame(0x80, 0))
+            .unwrap();
+        let snapshot = manager.take_snapshot("call").expect("snapshot");
+        assert_eq!((snapshot.width, snapshot.height), (320, 180));
+        assert!(manager.take_snapshot("call").is_none());
     }
 }
@@ -0,0 +1,203 @@
+/// Описание slash-команды для палитры (Ctrl+K). Новую команду достаточно
+/// добавить сюда, чтобы она появилась в палитре.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Аргументы в нотации usage; пусто — команду можно выполнить сразу.
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "connect",
+        args: "",
+        description: "Connect to the server",
+    },
+    CommandSpec {
+        name: "disconnect",
+        args: "",
+        description: "Close the current connection",
+    },
+    CommandSpec {
+        name: "join",
+        args: "<channel_id> [relay]",
+        description: "Join a channel",
+    },
+    CommandSpec {
+        name: "leave",
+        args: "<channel_id>",
+        description: "Leave a channel",
+    },
+    CommandSpec {
+        name: "msg",
+        args: "<user_id|handle> <text>",
+        description: "Send a direct message to a friend",
+    },
+    CommandSpec {
+        name: "search",
+        args: "<query>",
+        description: "Search messages in all channels",
+    },
+    CommandSpec {
+        name: "open",
+        args: "[link_number]",
+        description: "Open a link from the selected message",
+    },
+    CommandSpec {
+        name: "presence",
+        args: "<state>",
+        description: "Change your presence state",
+    },
+    CommandSpec {
+        name: "theme",
+        args: "",
+        description: "Cycle the colour theme",
+    },
+    CommandSpec {
+        name: "mute",
+        args: "[channel_id]",
+        description: "Mute or unmute a channel",
+    },
+    CommandSpec {
+        name: "friends push",
+        args: "",
+        description: "Upload the friends list to the server",
+    },
+    CommandSpec {
+        name: "friends pull",
+        args: "",
+        description: "Replace the friends list with the server copy",
+    },
+    CommandSpec {
+        name: "group invite",
+        args: "<group_id> <device_id> [role]",
+        description: "Invite a device into a group",
+    },
+    CommandSpec {
+        name: "group remove",
+        args: "<group_id> <device_id>",
+        description: "Remove a device from a group",
+    },
+    CommandSpec {
+        name: "group grant",
+        args: "<group_id> <device_id> <role>",
+        description: "Change a member's role",
+    },
+    CommandSpec {
+        name: "whoami",
+        args: "",
+        description: "Show and copy your user id and friend link",
+    },
+    CommandSpec {
+        name: "pair",
+        args: "[ttl_seconds]",
+        description: "Issue a pair code for a new device",
+    },
+    CommandSpec {
+        name: "assist",
+        args: "<peer_hint> [ip:port...]",
+        description: "Request P2P transport diagnostics",
+    },
+    CommandSpec {
+        name: "voice save",
+        args: "<index> <path> [--raw]",
+        description: "Export a voice message to WAV",
+    },
+    CommandSpec {
+        name: "video snapshot",
+        args: "<path>",
+        description: "Save the next video frame as PNG",
+    },
+    CommandSpec {
+        name: "send-file",
+        args: "<path>",
+        description: "Send a file to the channel",
+    },
+    CommandSpec {
+        name: "save-file",
+        args: "[path]",
+        description: "Save the selected or latest received file",
+    },
+    CommandSpec {
+        name: "quit",
+        args: "",
+        description: "Exit the client",
+    },
+];
+
+impl CommandSpec {
+    /// Обязательные аргументы начинаются с `<`.
+    pub fn needs_args(&self) -> bool {
+        self.args.starts_with('<')
+    }
+}
+
+/// Нечёткое совпадение: символы запроса должны встречаться в тексте по
+/// порядку. Больше очков за совпадение в начале слова и подряд идущие символы;
+/// `None` — совпадения нет.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for needle in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = text[position..].iter().position(|c| *c == needle)? + position;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 5;
+        }
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 3;
+        }
+        score -= (found - position).min(5) as i32;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Индексы `candidates`, подходящие под запрос, от лучшего совпадения к худшему.
+/// Пустой запрос сохраняет исходный порядок.
+pub fn rank<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<usize> {
+    let mut scored: Vec<(usize, i32)> = candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, text)| fuzzy_score(query, text).map(|score| (idx, score)))
+        .collect();
+    scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    scored.into_iter().map(|(idx, _)| idx).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn registry_names_are_unique() {
+        let names: HashSet<_> = COMMANDS.iter().map(|spec| spec.name).collect();
+        assert_eq!(names.len(), COMMANDS.len());
+        assert!(
+            COMMANDS
+                .iter()
+                .any(|spec| spec.name == "join" && spec.needs_args())
+        );
+        assert!(
+            COMMANDS
+                .iter()
+                .any(|spec| spec.name == "pair" && !spec.needs_args())
+        );
+    }
+
+    #[test]
+    fn fuzzy_matching_prefers_word_starts() {
+        assert!(fuzzy_score("xyz", "connect").is_none());
+        assert!(fuzzy_score("cnt", "connect").is_some());
+        let ranked = rank("gr", ["search", "group grant", "program"]);
+        assert_eq!(ranked, [1, 2]);
+        let ranked = rank("dis", ["video snapshot", "disconnect"]);
+        assert_eq!(ranked[0], 1);
+        assert_eq!(rank("", ["a", "b"]), [0, 1]);
+    }
+}
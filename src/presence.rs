@@ -0,0 +1,160 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+/// Сколько после подключения presence считается начальной синхронизацией.
+pub const PRESENCE_BURST: Duration = Duration::from_secs(3);
+/// Повторные уведомления об одном человеке не чаще этого интервала.
+pub const PRESENCE_QUIET: Duration = Duration::from_secs(60);
+
+/// Что делать с уведомлением о смене статуса.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceNotice {
+    Show,
+    Suppress,
+}
+
+/// Прореживает уведомления о статусах: начальная волна после подключения
+/// сводится в одно «N online», «мигающий» собеседник — в одно уведомление
+/// за `PRESENCE_QUIET`. В подробном режиме пропускается всё.
+#[derive(Debug)]
+pub struct PresenceNotifier {
+    burst_until: Option<Instant>,
+    burst_online: BTreeSet<String>,
+    last_shown: HashMap<String, Instant>,
+    quiet: Duration,
+}
+
+impl Default for PresenceNotifier {
+    fn default() -> Self {
+        Self::new(PRESENCE_QUIET)
+    }
+}
+
+impl PresenceNotifier {
+    pub fn new(quiet: Duration) -> Self {
+        Self {
+            burst_until: None,
+            burst_online: BTreeSet::new(),
+            last_shown: HashMap::new(),
+            quiet,
+        }
+    }
+
+    /// Начало сеанса: следующие `PRESENCE_BURST` копятся в сводку.
+    pub fn start_burst(&mut self, now: Instant) {
+        self.burst_until = Some(now + PRESENCE_BURST);
+        self.burst_online.clear();
+        self.last_shown.clear();
+    }
+
+    /// Решение по кадру presence для `user` (уже сведённого к user_id).
+    /// `changed` — статус отличается от известного.
+    pub fn observe(
+        &mut self,
+        user: &str,
+        online: bool,
+        changed: bool,
+        verbose: bool,
+        now: Instant,
+    ) -> PresenceNotice {
+        if self.burst_until.is_some_and(|until| now < until) {
+            if online {
+                self.burst_online.insert(user.to_string());
+            } else {
+                self.burst_online.remove(user);
+            }
+            if !verbose {
+                return PresenceNotice::Suppress;
+            }
+        }
+        if !changed {
+            return PresenceNotice::Suppress;
+        }
+        if verbose {
+            return PresenceNotice::Show;
+        }
+        let recent = self
+            .last_shown
+            .get(user)
+            .is_some_and(|shown| now.saturating_duration_since(*shown) < self.quiet);
+        if recent {
+            return PresenceNotice::Suppress;
+        }
+        self.last_shown.insert(user.to_string(), now);
+        PresenceNotice::Show
+    }
+
+    /// По окончании начальной волны возвращает число людей онлайн (один раз).
+    pub fn finish_burst(&mut self, now: Instant) -> Option<usize> {
+        let until = self.burst_until?;
+        if now < until {
+            return None;
+        }
+        self.burst_until = None;
+        let online = std::mem::take(&mut self.burst_online);
+        for user in &online {
+            self.last_shown.insert(user.clone(), now);
+        }
+        Some(online.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_burst_is_summarized() {
+        let mut notifier = PresenceNotifier::default();
+        let start = Instant::now();
+        notifier.start_burst(start);
+        for user in ["alice", "bob", "carol", "alice"] {
+            assert_eq!(
+                notifier.observe(user, true, true, false, start),
+                PresenceNotice::Suppress
+            );
+        }
+        notifier.observe("carol", false, true, false, start);
+        assert_eq!(notifier.finish_burst(start + Duration::from_secs(1)), None);
+        assert_eq!(notifier.finish_burst(start + PRESENCE_BURST), Some(2));
+        assert_eq!(notifier.finish_burst(start + PRESENCE_BURST), None);
+
+        // Только что сведённые в сводку не всплывают повторно сразу же.
+        let later = start + PRESENCE_BURST + Duration::from_secs(1);
+        assert_eq!(
+            notifier.observe("alice", false, true, false, later),
+            PresenceNotice::Suppress
+        );
+        assert_eq!(
+            notifier.observe("carol", true, true, false, later),
+            PresenceNotice::Show
+        );
+    }
+
+    #[test]
+    fn flapping_peer_notifies_once_per_window() {
+        let mut notifier = PresenceNotifier::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(
+            notifier.observe("bob", true, true, false, at(0)),
+            PresenceNotice::Show
+        );
+        assert_eq!(
+            notifier.observe("bob", false, true, false, at(5)),
+            PresenceNotice::Suppress
+        );
+        assert_eq!(
+            notifier.observe("bob", true, false, false, at(70)),
+            PresenceNotice::Suppress
+        );
+        assert_eq!(
+            notifier.observe("bob", false, true, false, at(70)),
+            PresenceNotice::Show
+        );
+        assert_eq!(
+            notifier.observe("bob", true, true, true, at(71)),
+            PresenceNotice::Show
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::rest::UserSummary;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+struct CachedProfile {
+    /// `None` — сервер не знает пользователя; повторяем запрос только после TTL.
+    user: Option<UserSummary>,
+    fetched_at: Option<Instant>,
+}
+
+/// Ограниченный LRU-кэш профилей пользователей, загруженных через REST.
+pub struct ProfileCache {
+    entries: HashMap<String, CachedProfile>,
+    order: VecDeque<String>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl ProfileCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            ttl,
+        }
+    }
+
+    /// Профиль из кэша, даже если он устарел: для отображения это лучше сырого id.
+    pub fn get(&self, id: &str) -> Option<&UserSummary> {
+        self.entries.get(id).and_then(|entry| entry.user.as_ref())
+    }
+
+    /// Отмечает использование `id` и возвращает `true`, если профиль нужно
+    /// (пере)загрузить. Запись резервируется сразу, чтобы не запрашивать дважды.
+    pub fn request(&mut self, id: &str, now: Instant) -> bool {
+        self.touch(id);
+        match self.entries.get(id) {
+            Some(CachedProfile {
+                fetched_at: None, ..
+            }) => false,
+            Some(CachedProfile {
+                fetched_at: Some(at),
+                ..
+            }) if now.duration_since(*at) < self.ttl => false,
+            Some(_) => {
+                if let Some(entry) = self.entries.get_mut(id) {
+                    entry.fetched_at = None;
+                }
+                true
+            }
+            None => {
+                self.insert_entry(
+                    id.to_string(),
+                    CachedProfile {
+                        user: None,
+                        fetched_at: None,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    /// Сохраняет результат загрузки; `None` кэширует отказ сервера.
+    pub fn insert(&mut self, id: &str, user: Option<UserSummary>, now: Instant) {
+        let previous = self.entries.get(id).and_then(|entry| entry.user.clone());
+        self.touch(id);
+        self.insert_entry(
+            id.to_string(),
+            CachedProfile {
+                user: user.or(previous),
+                fetched_at: Some(now),
+            },
+        );
+    }
+
+    fn touch(&mut self, id: &str) {
+        if let Some(pos) = self.order.iter().position(|key| key == id)
+            && let Some(key) = self.order.remove(pos)
+        {
+            self.order.push_back(key);
+        }
+    }
+
+    fn insert_entry(&mut self, id: String, entry: CachedProfile) {
+        if self.entries.insert(id.clone(), entry).is_none() {
+            self.order.push_back(id);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> UserSummary {
+        UserSummary {
+            id: id.to_string(),
+            handle: format!("{}-handle", id),
+            display_name: Some(id.to_uppercase()),
+            avatar_url: None,
+        }
+    }
+
+    #[test]
+    fn request_fetches_once_until_ttl_expires() {
+        let mut cache = ProfileCache::new(4, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(cache.request("a", now));
+        assert!(!cache.request("a", now));
+        cache.insert("a", Some(user("a")), now);
+        assert_eq!(cache.get("a").unwrap().handle, "a-handle");
+        assert!(!cache.request("a", now + Duration::from_secs(30)));
+        assert!(cache.request("a", now + Duration::from_secs(61)));
+        // Устаревший профиль остаётся доступным, пока идёт обновление.
+        assert!(cache.get("a").is_some());
+    }
+
+    #[test]
+    fn failed_lookup_keeps_previous_profile() {
+        let mut cache = ProfileCache::new(4, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert("a", Some(user("a")), now);
+        cache.insert("a", None, now);
+        assert!(cache.get("a").is_some());
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut cache = ProfileCache::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert("a", Some(user("a")), now);
+        cache.insert("b", Some(user("b")), now);
+        assert!(!cache.request("a", now));
+        cache.insert("c", Some(user("c")), now);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result, anyhow};
+use image::{ImageFormat, Luma};
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+use reqwest::Url;
+use std::path::Path;
+
+/// Схема ссылок, которые понимает `commucat-cli-client open`.
+pub const PAIR_URI_SCHEME: &str = "commucat";
+
+/// `commucat://pair?code=…&server=…&domain=…` для сканирования на другом устройстве.
+pub fn pair_uri(code: &str, server: &str, domain: &str) -> Result<String> {
+    let url = Url::parse_with_params(
+        &format!("{}://pair", PAIR_URI_SCHEME),
+        [("code", code), ("server", server), ("domain", domain)],
+    )
+    .context("build pair uri")?;
+    Ok(url.to_string())
+}
+
+/// `commucat://friend?user=…[&handle=…]&server=…` — чем поделиться,
+/// чтобы тебя добавили в друзья.
+pub fn friend_uri(user_id: &str, handle: Option<&str>, server: &str) -> Result<String> {
+    let mut params = vec![("user", user_id)];
+    params.extend(handle.map(|handle| ("handle", handle)));
+    params.push(("server", server));
+    let url = Url::parse_with_params(&format!("{}://friend", PAIR_URI_SCHEME), params)
+        .context("build friend uri")?;
+    Ok(url.to_string())
+}
+
+/// Параметры, извлечённые из ссылки `commucat://pair?…`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairLink {
+    pub code: String,
+    pub server: String,
+    pub domain: Option<String>,
+}
+
+pub fn parse_pair_uri(uri: &str) -> Result<PairLink> {
+    let url = Url::parse(uri.trim()).context("invalid uri")?;
+    if url.scheme() != PAIR_URI_SCHEME {
+        return Err(anyhow!(
+            "unsupported scheme '{}', expected {}://",
+            url.scheme(),
+            PAIR_URI_SCHEME
+        ));
+    }
+    if url.host_str() != Some("pair") {
+        return Err(anyhow!(
+            "unsupported link '{}', expected {}://pair",
+            url.host_str().unwrap_or_default(),
+            PAIR_URI_SCHEME
+        ));
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let code = param("code").ok_or_else(|| anyhow!("link is missing the 'code' parameter"))?;
+    let server =
+        param("server").ok_or_else(|| anyhow!("link is missing the 'server' parameter"))?;
+    let server_url = Url::parse(&server).context("invalid 'server' parameter")?;
+    if server_url.scheme() != "https" {
+        return Err(anyhow!("'server' must be an https:// url"));
+    }
+    Ok(PairLink {
+        code,
+        server,
+        domain: param("domain"),
+    })
+}
+
+/// QR-код из полублоков Unicode: две строки модулей на строку терминала.
+/// Цвета инвертированы, чтобы код читался на тёмном фоне.
+pub fn render_terminal(data: &str) -> Result<String> {
+    let code = QrCode::new(data.as_bytes()).context("encode qr code")?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+pub fn write_png(data: &str, path: &Path) -> Result<()> {
+    let code = QrCode::new(data.as_bytes()).context("encode qr code")?;
+    code.render::<Luma<u8>>()
+        .min_dimensions(256, 256)
+        .build()
+        .save_with_format(path, ImageFormat::Png)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_uri_escapes_parameters() {
+        let uri = pair_uri("AB CD", "https://chat.example:8443", "chat.example").unwrap();
+        assert!(uri.starts_with("commucat://pair?code=AB+CD&server=https%3A%2F%2F"));
+        assert!(uri.ends_with("&domain=chat.example"));
+    }
+
+    #[test]
+    fn friend_uri_carries_identity() {
+        let uri = friend_uri("u 1", Some("alice"), "https://chat.example").unwrap();
+        assert_eq!(
+            uri,
+            "commucat://friend?user=u+1&handle=alice&server=https%3A%2F%2Fchat.example"
+        );
+        let bare = friend_uri("u-1", None, "https://chat.example").unwrap();
+        assert!(!bare.contains("handle="));
+    }
+
+    #[test]
+    fn pair_uri_roundtrips_through_parser() {
+        let uri = pair_uri("AB CD", "https://chat.example:8443", "chat.example").unwrap();
+        assert_eq!(
+            parse_pair_uri(&uri).unwrap(),
+            PairLink {
+                code: "AB CD".to_string(),
+                server: "https://chat.example:8443".to_string(),
+                domain: Some("chat.example".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn pair_uri_parser_rejects_bad_links() {
+        assert!(parse_pair_uri("https://pair?code=A&server=https://x").is_err());
+        assert!(parse_pair_uri("commucat://join?code=A&server=https://x").is_err());
+        assert!(parse_pair_uri("commucat://pair?server=https://x").is_err());
+        assert!(parse_pair_uri("commucat://pair?code=A").is_err());
+        assert!(parse_pair_uri("commucat://pair?code=A&server=http://x").is_err());
+        assert_eq!(
+            parse_pair_uri("commucat://pair?code=A&server=https://x")
+                .unwrap()
+                .domain,
+            None
+        );
+    }
+
+    #[test]
+    fn terminal_rendering_uses_half_blocks() {
+        let rendered = render_terminal("ABCD-EFGH").unwrap();
+        let rows: Vec<&str> = rendered.lines().collect();
+        assert!(rows.len() > 10);
+        assert!(
+            rows.iter()
+                .all(|row| row.chars().count() == rows[0].chars().count())
+        );
+        assert!(rendered.contains('▀') || rendered.contains('▄'));
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+/// Пропускает событие не чаще одного раза за `min_interval`.
+/// Подходит для индикатора набора, presence и прочих «шумных» кадров.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    last_sent: Option<Instant>,
+    min_interval: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            last_sent: None,
+            min_interval,
+        }
+    }
+
+    /// Возвращает `true` и запоминает момент, если отправлять уже можно.
+    pub fn check(&mut self, now: Instant) -> bool {
+        let allowed = self
+            .last_sent
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.min_interval);
+        if allowed {
+            self.last_sent = Some(now);
+        }
+        allowed
+    }
+
+    /// Забывает последнюю отправку: следующее событие пройдёт сразу.
+    pub fn reset(&mut self) {
+        self.last_sent = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_one_event_per_interval() {
+        let mut limiter = RateLimiter::new(Duration::from_secs(2));
+        let start = Instant::now();
+        assert!(limiter.check(start));
+        assert!(!limiter.check(start + Duration::from_millis(500)));
+        assert!(!limiter.check(start + Duration::from_millis(1999)));
+        assert!(limiter.check(start + Duration::from_secs(2)));
+        assert!(!limiter.check(start + Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn reset_lets_next_event_through() {
+        let mut limiter = RateLimiter::new(Duration::from_secs(2));
+        let start = Instant::now();
+        assert!(limiter.check(start));
+        limiter.reset();
+        assert!(limiter.check(start + Duration::from_millis(10)));
+    }
+}
@@ -1,9 +1,26 @@
+use crate::config::{ClientState, FriendEntry};
 use anyhow::{Context, Result, anyhow};
 use commucat_crypto::DeviceCertificate;
-use reqwest::{Client, StatusCode, Url};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Certificate, Client, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Once;
+
+/// Один раз за запуск предупреждает в stderr, что проверка сертификатов
+/// сервера отключена (`insecure` в профиле или `init --insecure`).
+pub fn warn_insecure_tls() {
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        eprintln!(
+            "warning: TLS verification DISABLED (insecure=true), the server is not authenticated"
+        );
+    });
+}
 
 #[derive(Clone)]
 pub struct RestClient {
@@ -13,14 +30,36 @@ pub struct RestClient {
 
 impl RestClient {
     pub fn new(server_url: &str) -> Result<Self> {
-        let mut url = Url::parse(server_url).context("invalid server url")?;
-        url.set_path("/");
-        url.set_query(None);
-        url.set_fragment(None);
-        let client = Client::builder()
-            .user_agent("commucat-cli-client/0.1")
-            .build()
-            .context("build http client")?;
+        Self::with_tls(server_url, None, false)
+    }
+
+    /// Создаёт клиент с теми же настройками TLS, что использует engine.
+    pub fn with_state(state: &ClientState) -> Result<Self> {
+        Self::with_tls(
+            &state.server_url,
+            state.tls_ca_path.as_deref(),
+            state.insecure,
+        )
+    }
+
+    pub fn with_tls(server_url: &str, tls_ca_path: Option<&str>, insecure: bool) -> Result<Self> {
+        let url = Url::parse(&normalize_server_url(server_url)?).context("invalid server url")?;
+        let mut builder = Client::builder().user_agent("commucat-cli-client/0.1");
+        if let Some(path) = tls_ca_path {
+            let pem = fs::read(path).context("open tls ca")?;
+            let certs = Certificate::from_pem_bundle(&pem).context("parse tls ca")?;
+            if certs.is_empty() {
+                return Err(anyhow!("no certificates loaded"));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if insecure {
+            warn_insecure_tls();
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder.build().context("build http client")?;
         Ok(Self { base: url, client })
     }
 
@@ -71,18 +110,38 @@ impl RestClient {
         Self::parse_response(response, StatusCode::OK).await
     }
 
-    pub async fn list_devices(&self, session: &str) -> Result<Vec<DeviceEntry>> {
-        let mut endpoint = self.base.clone();
-        endpoint.set_path("api/devices");
+    /// Одна страница `api/devices`; без параметров запрос остаётся прежним,
+    /// так что серверы без пагинации просто вернут весь список.
+    pub async fn list_devices(
+        &self,
+        session: &str,
+        query: &DevicesQuery,
+    ) -> Result<DevicesEnvelope> {
         let response = self
             .client
-            .get(endpoint)
+            .get(self.devices_endpoint(query))
             .bearer_auth(session)
             .send()
             .await
             .context("request /api/devices")?;
-        let envelope: DevicesEnvelope = Self::parse_response(response, StatusCode::OK).await?;
-        Ok(envelope.devices)
+        Self::parse_response(response, StatusCode::OK).await
+    }
+
+    fn devices_endpoint(&self, query: &DevicesQuery) -> Url {
+        let mut endpoint = self.base.clone();
+        endpoint.set_path("api/devices");
+        if let Some(limit) = query.limit {
+            endpoint
+                .query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+        if let Some(cursor) = query.cursor.as_deref() {
+            endpoint.query_pairs_mut().append_pair("cursor", cursor);
+        }
+        if let Some(status) = query.status.as_deref() {
+            endpoint.query_pairs_mut().append_pair("status", status);
+        }
+        endpoint
     }
 
     pub async fn p2p_assist(
@@ -138,6 +197,73 @@ impl RestClient {
         Ok(())
     }
 
+    pub async fn get_user(&self, session: &str, user_id: &str) -> Result<UserSummary> {
+        let response = self
+            .client
+            .get(self.user_endpoint(user_id)?)
+            .bearer_auth(session)
+            .send()
+            .await
+            .context("request /api/users")?;
+        Self::parse_response(response, StatusCode::OK).await
+    }
+
+    fn user_endpoint(&self, user_id: &str) -> Result<Url> {
+        let mut endpoint = self.base.clone();
+        endpoint
+            .path_segments_mut()
+            .map_err(|_| anyhow!("server url cannot be a base"))?
+            .clear()
+            .extend(["api", "users", user_id]);
+        Ok(endpoint)
+    }
+
+    /// Обновляет профиль пользователя; `None` оставляет поле без изменений.
+    pub async fn update_profile(
+        &self,
+        session: &str,
+        display_name: Option<&str>,
+        avatar_url: Option<&str>,
+    ) -> Result<()> {
+        let mut endpoint = self.base.clone();
+        endpoint.set_path("api/profile");
+        let response = self
+            .client
+            .put(endpoint)
+            .bearer_auth(session)
+            .json(&ProfileUpdateRequest {
+                display_name: display_name.map(str::to_string),
+                avatar_url: avatar_url.map(str::to_string),
+            })
+            .send()
+            .await
+            .context("request /api/profile")?;
+        let _: Value = Self::parse_response(response, StatusCode::OK).await?;
+        Ok(())
+    }
+
+    /// Загружает изображение аватара и возвращает его URL на сервере.
+    pub async fn upload_avatar(
+        &self,
+        session: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<String> {
+        let mut endpoint = self.base.clone();
+        endpoint.set_path("api/avatar");
+        let response = self
+            .client
+            .post(endpoint)
+            .bearer_auth(session)
+            .header(CONTENT_TYPE, content_type)
+            .body(data)
+            .send()
+            .await
+            .context("request /api/avatar")?;
+        let uploaded: AvatarUploadResponse = Self::parse_response(response, StatusCode::OK).await?;
+        Ok(uploaded.url)
+    }
+
     pub async fn revoke_device(&self, session: &str, device_id: &str) -> Result<()> {
         let mut endpoint = self.base.clone();
         endpoint.set_path("api/devices/revoke");
@@ -163,18 +289,77 @@ impl RestClient {
         if status == expected {
             return response.json::<T>().await.context("decode success payload");
         }
+        let url = response.url().clone();
         let problem = response.json::<ProblemDetails>().await.ok();
-        match problem {
-            Some(details) => Err(anyhow!(details.detail.unwrap_or_else(|| {
-                details
-                    .title
-                    .unwrap_or_else(|| format!("request failed with status {}", status))
-            }))),
-            _ => Err(anyhow!(format!("request failed with status {}", status))),
+        let detail = problem
+            .and_then(|details| details.detail.or(details.title))
+            .unwrap_or_else(|| format!("request failed with status {}", status));
+        tracing::debug!("REST {} {} failed: {}", url.path(), status, detail);
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return Err(SessionRejected { status, detail }.into());
         }
+        Err(ServerRejected(detail).into())
     }
 }
 
+/// Сервер ответил ошибкой на сам запрос (неверный код, конфликт, 5xx).
+#[derive(Debug)]
+pub struct ServerRejected(pub String);
+
+impl fmt::Display for ServerRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ServerRejected {}
+
+/// Сервер отверг сессионный токен (401/403): он истёк или отозван.
+#[derive(Debug)]
+pub struct SessionRejected {
+    pub status: StatusCode,
+    pub detail: String,
+}
+
+impl fmt::Display for SessionRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.detail, self.status.as_u16())
+    }
+}
+
+impl std::error::Error for SessionRejected {}
+
+/// Приводит адрес сервера к виду `https://host[:port]`: без схемы подставляется
+/// https, путь, query и fragment отбрасываются, порт 443 не пишется.
+pub fn normalize_server_url(raw: &str) -> Result<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err(anyhow!("server url is empty"));
+    }
+    let candidate = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+    let url = Url::parse(&candidate).with_context(|| format!("invalid server url '{}'", raw))?;
+    if url.scheme() != "https" {
+        return Err(anyhow!(
+            "server url '{}' must use https (got {}://)",
+            raw,
+            url.scheme()
+        ));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(anyhow!("server url '{}' has no host", raw));
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+/// Ошибка REST вызвана отвергнутой сессией, в том числе под `context`.
+pub fn is_session_rejected(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<SessionRejected>())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct PairingRequest {
     ttl: Option<i64>,
@@ -188,7 +373,7 @@ struct PairingClaimRequest {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PairingTicket {
     pub pair_code: String,
     pub issued_at: String,
@@ -200,7 +385,7 @@ pub struct PairingTicket {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PairingClaimResponse {
     pub device_id: String,
     pub private_key: String,
@@ -216,7 +401,7 @@ pub struct PairingClaimResponse {
     pub device_ca_public: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserSummary {
     pub id: String,
     pub handle: String,
@@ -227,8 +412,33 @@ pub struct UserSummary {
 }
 
 #[derive(Debug, Deserialize)]
-struct DevicesEnvelope {
-    devices: Vec<DeviceEntry>,
+pub struct DevicesEnvelope {
+    pub devices: Vec<DeviceEntry>,
+    /// Курсор следующей страницы; `None` — список закончился.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Параметры постраничного запроса устройств.
+#[derive(Debug, Default, Clone)]
+pub struct DevicesQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    /// Фильтр по статусу; сервер может его проигнорировать.
+    pub status: Option<String>,
+}
+
+/// Сводка по статусам вида `3 active / 2 revoked`.
+pub fn device_status_summary(devices: &[DeviceEntry]) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for device in devices {
+        *counts.entry(device.status.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect::<Vec<_>>()
+        .join(" / ")
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -374,7 +584,7 @@ pub struct SecuritySnapshot {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DeviceEntry {
     pub device_id: String,
     pub status: String,
@@ -393,11 +603,58 @@ pub struct FriendEntryPayload {
     pub alias: Option<String>,
 }
 
+pub fn friend_from_payload(payload: FriendEntryPayload) -> FriendEntry {
+    FriendEntry {
+        user_id: payload.user_id,
+        handle: payload.handle,
+        alias: payload.alias,
+    }
+}
+
+pub fn friends_to_payload(entries: &[FriendEntry]) -> Vec<FriendEntryPayload> {
+    entries
+        .iter()
+        .map(|entry| FriendEntryPayload {
+            user_id: entry.user_id.clone(),
+            handle: entry.handle.clone(),
+            alias: entry.alias.clone(),
+        })
+        .collect()
+}
+
 #[derive(Debug, Serialize)]
 struct DeviceRevokeRequest {
     device_id: String,
 }
 
+#[derive(Debug, Serialize)]
+struct ProfileUpdateRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AvatarUploadResponse {
+    url: String,
+}
+
+/// MIME-тип аватара по расширению файла.
+pub fn avatar_content_type(path: &Path) -> Result<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => Ok("image/png"),
+        Some("jpg" | "jpeg") => Ok("image/jpeg"),
+        Some("gif") => Ok("image/gif"),
+        Some("webp") => Ok("image/webp"),
+        _ => Err(anyhow!("unsupported avatar format: {}", path.display())),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ProblemDetails {
     #[serde(default)]
@@ -411,9 +668,27 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn session_rejection_is_detected_through_context() {
+        let rejected: anyhow::Error = SessionRejected {
+            status: StatusCode::UNAUTHORIZED,
+            detail: "session expired".to_string(),
+        }
+        .into();
+        let wrapped = rejected.context("list devices");
+        assert!(is_session_rejected(&wrapped));
+        assert_eq!(
+            format!("{:#}", wrapped),
+            "list devices: session expired (401)"
+        );
+        assert!(!is_session_rejected(&anyhow!(
+            "request failed with status 500"
+        )));
+    }
+
     #[tokio::test(flavor = "current_thread")]
     async fn p2p_assist_errors_for_unreachable_host() {
-        let client = RestClient::new("http://127.0.0.1:9").unwrap();
+        let client = RestClient::new("https://127.0.0.1:9").unwrap();
         let request = P2pAssistRequest {
             peer_hint: Some("peer-1".to_string()),
             paths: vec![AssistPathHint {
@@ -435,6 +710,96 @@ mod tests {
         assert!(serialized.contains("peer-1"));
     }
 
+    #[test]
+    fn server_urls_are_normalized_to_an_https_origin() {
+        for (raw, expected) in [
+            ("example.org:8443", "https://example.org:8443"),
+            (
+                " https://Example.org:8443/connect?x=1#top ",
+                "https://example.org:8443",
+            ),
+            ("https://example.org:443/", "https://example.org"),
+            ("[::1]:8443/path", "https://[::1]:8443"),
+        ] {
+            assert_eq!(normalize_server_url(raw).unwrap(), expected, "{}", raw);
+        }
+        for raw in ["", "http://example.org", "example.org:99999", "https://"] {
+            assert!(normalize_server_url(raw).is_err(), "{}", raw);
+        }
+        let client = RestClient::new("example.org:8443/ignored").unwrap();
+        assert_eq!(client.base.as_str(), "https://example.org:8443/");
+    }
+
+    #[test]
+    fn devices_endpoint_adds_only_given_params() {
+        let client = RestClient::new("https://example.org:8443").unwrap();
+        let plain = client.devices_endpoint(&DevicesQuery::default());
+        assert_eq!(plain.as_str(), "https://example.org:8443/api/devices");
+        let paged = client.devices_endpoint(&DevicesQuery {
+            limit: Some(20),
+            cursor: Some("abc=".to_string()),
+            status: None,
+        });
+        assert_eq!(paged.query(), Some("limit=20&cursor=abc%3D"));
+
+        let envelope: DevicesEnvelope = serde_json::from_value(json!({ "devices": [] })).unwrap();
+        assert!(envelope.next_cursor.is_none());
+    }
+
+    #[test]
+    fn device_status_summary_counts_each_status() {
+        let device = |status: &str| DeviceEntry {
+            device_id: "dev".to_string(),
+            status: status.to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            public_key: "00".to_string(),
+            current: false,
+        };
+        let devices = [device("active"), device("revoked"), device("active")];
+        assert_eq!(device_status_summary(&devices), "2 active / 1 revoked");
+        assert_eq!(device_status_summary(&[]), "");
+    }
+
+    #[test]
+    fn user_endpoint_escapes_id() {
+        let client = RestClient::new("https://example.org:8443").unwrap();
+        let endpoint = client.user_endpoint("a/b c").unwrap();
+        assert_eq!(endpoint.path(), "/api/users/a%2Fb%20c");
+    }
+
+    #[test]
+    fn avatar_content_type_by_extension() {
+        assert_eq!(
+            avatar_content_type(Path::new("me.PNG")).unwrap(),
+            "image/png"
+        );
+        assert_eq!(
+            avatar_content_type(Path::new("me.jpeg")).unwrap(),
+            "image/jpeg"
+        );
+        assert!(avatar_content_type(Path::new("me.txt")).is_err());
+        let update = ProfileUpdateRequest {
+            display_name: Some("Alice".to_string()),
+            avatar_url: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&update).unwrap(),
+            json!({ "display_name": "Alice" })
+        );
+    }
+
+    #[test]
+    fn with_tls_rejects_missing_ca_file() {
+        let missing = std::env::temp_dir().join("commucat-missing-ca.pem");
+        let result = RestClient::with_tls(
+            "https://example.org:8443",
+            Some(missing.to_str().unwrap()),
+            false,
+        );
+        assert!(result.is_err());
+        assert!(RestClient::with_tls("https://example.org:8443", None, true).is_ok());
+    }
+
     #[test]
     fn assist_response_deserializes() {
         let payload = json!({
@@ -0,0 +1,196 @@
+use crate::attachments::format_size;
+use std::time::{Duration, Instant};
+use tokio::task::AbortHandle;
+
+/// В чём считается прогресс задачи.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measure {
+    /// Байты: показываются скорость и оставшееся время.
+    Bytes,
+    /// Миллисекунды воспроизведения: `0:12 / 0:30`.
+    Millis,
+}
+
+/// Длительная операция в оверлее прогресса. `kind` говорит TUI, что
+/// именно отменять; фоновая задача tokio, если есть, прерывается сама.
+pub struct Task<K> {
+    pub kind: K,
+    pub label: String,
+    pub measure: Measure,
+    pub done: u64,
+    /// `None` — объём заранее неизвестен.
+    pub total: Option<u64>,
+    started: Instant,
+    abort: Option<AbortHandle>,
+}
+
+impl<K> Task<K> {
+    /// Доля выполненного, если известен объём.
+    pub fn ratio(&self) -> Option<f64> {
+        let total = self.total?;
+        Some(if total == 0 {
+            1.0
+        } else {
+            (self.done as f64 / total as f64).min(1.0)
+        })
+    }
+
+    /// Байт в секунду; первые полсекунды скорость не показывается.
+    pub fn rate(&self, now: Instant) -> Option<f64> {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        (self.measure == Measure::Bytes && elapsed >= 0.5 && self.done > 0)
+            .then(|| self.done as f64 / elapsed)
+    }
+
+    pub fn eta(&self, now: Instant) -> Option<Duration> {
+        let left = self.total?.saturating_sub(self.done);
+        let rate = self.rate(now)?;
+        Some(Duration::from_secs_f64(left as f64 / rate))
+    }
+
+    /// Строка под индикатором: объём, скорость и ETA или время проигрывания.
+    pub fn summary(&self, now: Instant) -> String {
+        match (self.measure, self.total) {
+            (Measure::Millis, Some(total)) => {
+                format!("{} / {}", clock(self.done), clock(total))
+            }
+            (Measure::Bytes, Some(total)) => {
+                let mut text = format!("{} of {}", format_size(self.done), format_size(total));
+                if let Some(rate) = self.rate(now) {
+                    text.push_str(&format!(" · {}/s", format_size(rate as u64)));
+                }
+                if let Some(eta) = self.eta(now) {
+                    text.push_str(&format!(" · ETA {}s", eta.as_secs()));
+                }
+                text
+            }
+            (_, None) => format!("{}s", now.saturating_duration_since(self.started).as_secs()),
+        }
+    }
+}
+
+fn clock(millis: u64) -> String {
+    let secs = millis / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Текущие длительные операции; одна на каждый `kind`.
+pub struct Tasks<K> {
+    tasks: Vec<Task<K>>,
+}
+
+impl<K> Default for Tasks<K> {
+    fn default() -> Self {
+        Self { tasks: Vec::new() }
+    }
+}
+
+impl<K: PartialEq> Tasks<K> {
+    /// `false`, если задача этого вида уже идёт.
+    pub fn start(
+        &mut self,
+        kind: K,
+        label: impl Into<String>,
+        measure: Measure,
+        total: Option<u64>,
+        now: Instant,
+    ) -> bool {
+        if self.is_running(&kind) {
+            return false;
+        }
+        self.tasks.push(Task {
+            kind,
+            label: label.into(),
+            measure,
+            done: 0,
+            total,
+            started: now,
+            abort: None,
+        });
+        true
+    }
+
+    /// Фоновая задача tokio, которую прервёт отмена.
+    pub fn set_abort(&mut self, kind: &K, handle: AbortHandle) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.kind == *kind) {
+            task.abort = Some(handle);
+        }
+    }
+
+    pub fn update(&mut self, kind: &K, done: u64) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.kind == *kind) {
+            task.done = done;
+        }
+    }
+
+    /// Убирает завершённую задачу; `false` — её уже отменили.
+    pub fn finish(&mut self, kind: &K) -> bool {
+        let before = self.tasks.len();
+        self.tasks.retain(|task| task.kind != *kind);
+        self.tasks.len() != before
+    }
+
+    /// Отменяет самую новую задачу и возвращает её вид для уборки в TUI.
+    pub fn cancel_latest(&mut self) -> Option<K> {
+        let task = self.tasks.pop()?;
+        if let Some(handle) = task.abort {
+            handle.abort();
+        }
+        Some(task.kind)
+    }
+
+    pub fn is_running(&self, kind: &K) -> bool {
+        self.tasks.iter().any(|task| task.kind == *kind)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Task<K>> {
+        self.tasks.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_tasks_report_rate_and_eta() {
+        let start = Instant::now();
+        let mut tasks = Tasks::default();
+        assert!(tasks.start("upload", "📤 a.bin", Measure::Bytes, Some(4096), start));
+        assert!(!tasks.start("upload", "again", Measure::Bytes, Some(1), start));
+        tasks.update(&"upload", 1024);
+
+        let task = tasks.iter().next().unwrap();
+        assert_eq!(task.ratio(), Some(0.25));
+        assert_eq!(task.rate(start + Duration::from_millis(100)), None);
+        let later = start + Duration::from_secs(2);
+        assert_eq!(task.rate(later), Some(512.0));
+        assert_eq!(task.eta(later), Some(Duration::from_secs(6)));
+        assert_eq!(task.summary(later), "1.0 KiB of 4.0 KiB · 512 B/s · ETA 6s");
+
+        assert!(tasks.finish(&"upload"));
+        assert!(!tasks.finish(&"upload"));
+        assert!(tasks.is_empty());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn cancel_aborts_the_newest_task() {
+        let start = Instant::now();
+        let mut tasks = Tasks::default();
+        tasks.start("play", "▶", Measure::Millis, Some(30_000), start);
+        tasks.update(&"play", 12_500);
+        assert_eq!(tasks.iter().next().unwrap().summary(start), "0:12 / 0:30");
+
+        tasks.start("sync", "🔁", Measure::Bytes, None, start);
+        let handle = tokio::spawn(std::future::pending::<()>());
+        tasks.set_abort(&"sync", handle.abort_handle());
+        assert_eq!(tasks.cancel_latest(), Some("sync"));
+        assert!(handle.await.unwrap_err().is_cancelled());
+        assert_eq!(tasks.cancel_latest(), Some("play"));
+        assert_eq!(tasks.cancel_latest(), None);
+    }
+}
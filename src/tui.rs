@@ -3,20 +3,52 @@ use crate::animations::{
     create_wave_animation,
 };
 use crate::ascii_art;
+use crate::assist;
+use crate::attachments::{self, Assembler, FileChunk, FileHeader, Progress, Upload};
+use crate::audio::{FRAME_DURATION_MS, MicrophoneCapture, VoicePlayback, export_voice_message};
 use crate::calls::{CallAnswer, CallEnd, CallManager, CallOffer, CallStats};
-use crate::config::ClientState;
-use crate::engine::{ClientEvent, EngineCommand, EngineHandle, create_engine};
+use crate::config::{
+    CertificateStatus, ClientState, DEFAULT_TIME_FORMAT, FriendEntry, MIN_MESSAGE_HISTORY_LIMIT,
+    TimeStyle, is_valid_time_format, pick_display_name, relative_time,
+};
+use crate::devicecache::DeviceCache;
+use crate::engine::{
+    ClientEvent, EngineCommand, EngineHandle, Reaction, ServerCapabilities, create_engine,
+};
+use crate::flow::FlowWindow;
+use crate::framedump;
+use crate::glyphs;
 use crate::groups::{Group, GroupAction, GroupRole};
+use crate::help;
 use crate::hexutil::short_hex;
+use crate::identity::Identities;
+use crate::latency::{LatencyHistory, LinkQuality};
+use crate::links::{extract_urls, link_style, text_with_links};
+use crate::logview::{LogBuffer, LogLevel};
+use crate::markdown::render_markdown;
 use crate::media::{AudioMetrics, MediaManager, VideoMetrics};
+use crate::palette::{self, CommandSpec};
+use crate::presence::{PresenceNotice, PresenceNotifier};
+use crate::profiles::ProfileCache;
+use crate::qr;
+use crate::ratelimit::RateLimiter;
 use crate::rest::{
-    AssistFecHint, AssistPathHint, DeviceEntry, P2pAssistRequest, P2pAssistResponse, RestClient,
+    AssistFecHint, DeviceEntry, DevicesEnvelope, DevicesQuery, P2pAssistRequest, P2pAssistResponse,
+    PairingTicket, RestClient, ServerPairingInfo, UserSummary, device_status_summary,
+    friend_from_payload, friends_to_payload, is_session_rejected,
 };
+use crate::tasks::{Measure, Tasks};
+use crate::unread::UnreadStore;
+use crate::videoart;
 use crate::voice::{VoiceMessage, visualize_audio_wave};
+use commucat_proto::call::{CallEndReason, CallMediaProfile, CallRejectReason, VideoParameters};
 
 use anyhow::{Context, Result, bail};
-use chrono::{DateTime, Duration as ChronoDuration, Utc};
-use commucat_proto::{ControlEnvelope, Frame as ProtoFrame, FramePayload, FrameType};
+use arboard::Clipboard;
+use chrono::{DateTime, Duration as ChronoDuration, Local, Utc};
+use commucat_proto::{
+    ControlEnvelope, Frame as ProtoFrame, FramePayload, FrameType, PROTOCOL_VERSION,
+};
 use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{
@@ -28,24 +60,63 @@ use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{
-    Block, BorderType, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Sparkline,
-    Tabs, Wrap,
+    Block, BorderType, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Sparkline,
+    Table, TableState, Tabs, Wrap,
 };
 use ratatui::{Frame as UiFrame, Terminal};
 use serde_json::Value;
-use std::collections::{HashMap, VecDeque};
-use std::io::{Stdout, stdout};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Stdout, Write, stdout};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::{self, Receiver, Sender};
 use uuid::Uuid;
 
 const ENGINE_COMMAND_BUFFER: usize = 256;
 const ENGINE_EVENT_BUFFER: usize = 512;
-const MESSAGE_HISTORY_LIMIT: usize = 500;
+/// Голосовые и видеокадры сверх этого числа выбрасываются, а не копятся.
+const MEDIA_EVENT_BUFFER: usize = 64;
 const ANIMATION_FPS: u64 = 60;
+const MAX_ANIMATION_FPS: u64 = 240;
+/// Период служебного тика, когда анимации выключены.
+const IDLE_TICK: Duration = Duration::from_secs(1);
+const VISIBLE_MESSAGES: usize = 50;
+/// Сколько ждать ACK на отправленное сообщение, прежде чем пометить его «failed».
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// Кусков файла, отдаваемых движку за один тик.
+const UPLOAD_CHUNKS_PER_TICK: usize = 8;
+/// Очередь результатов фоновых задач (синхронизация устройств, экспорт голоса).
+const TASK_EVENT_CAPACITY: usize = 16;
+const SEARCH_RESULT_LIMIT: usize = 100;
+const CERTIFICATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const ENGINE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+const VOICE_LIST_LIMIT: usize = 8;
+const DEVICES_PAGE_SIZE: u32 = 50;
+const DEVICE_STATUS_FILTERS: [&str; 3] = ["active", "revoked", "pending"];
+const PROFILE_CACHE_CAPACITY: usize = 256;
+const PROFILE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const PROFILE_LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+const LOG_BUFFER_CAPACITY: usize = 1000;
+const ENGINE_LOG_PAGE: usize = 20;
+const HELP_PAGE: u16 = 10;
+const TYPING_SEND_INTERVAL: Duration = Duration::from_secs(2);
+const SERVER_INFO_TIMEOUT: Duration = Duration::from_secs(5);
+const LATENCY_HISTORY: usize = 30;
+/// Окно счётчиков потерь в строке статуса.
+const FLOW_WINDOW: Duration = Duration::from_secs(10);
+const LATENCY_SPARKLINE_WIDTH: usize = 6;
+/// Не чаще ~10 кадров в секунду для `--video-ascii`.
+const VIDEO_ASCII_INTERVAL: Duration = Duration::from_millis(100);
+/// При изменении размера терминала на столько ячеек экран очищается полностью.
+const RESIZE_CLEAR_THRESHOLD: u16 = 8;
+/// Минимальный интервал между звуками уведомлений.
+const SOUND_DEBOUNCE: Duration = Duration::from_millis(750);
 
 // Enhanced kawaii emoticons and stickers
-const KAWAII_REACTIONS: &[(&str, &str, &str)] = &[
+pub(crate) const KAWAII_REACTIONS: &[(&str, &str, &str)] = &[
     ("happy", "(◕‿◕)", "✨"),
     ("love", "(♡ω♡)", "💕"),
     ("sad", "(╥﹏╥)", "💧"),
@@ -73,11 +144,81 @@ enum AppView {
     Settings,
 }
 
+impl AppView {
+    fn as_str(self) -> &'static str {
+        match self {
+            AppView::Splash => "splash",
+            AppView::Chat => "chat",
+            AppView::Groups => "groups",
+            AppView::Calls => "calls",
+            AppView::Voice => "voice",
+            AppView::Devices => "devices",
+            AppView::Friends => "friends",
+            AppView::Settings => "settings",
+        }
+    }
+
+    /// Заставка не сохраняется и не открывается по имени.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chat" => Some(AppView::Chat),
+            "groups" => Some(AppView::Groups),
+            "calls" => Some(AppView::Calls),
+            "voice" => Some(AppView::Voice),
+            "devices" => Some(AppView::Devices),
+            "friends" => Some(AppView::Friends),
+            "settings" => Some(AppView::Settings),
+            _ => None,
+        }
+    }
+}
+
+/// Имена вкладок для `tui --view` и `last_view`.
+pub const VIEW_NAMES: &[&str] = &[
+    "chat", "groups", "calls", "voice", "devices", "friends", "settings",
+];
+
+/// Цель журнала для событий TUI: в stderr не пишется, чтобы не портить экран.
+pub const LOG_TARGET: &str = "commucat::tui";
+
+/// Параметры запуска TUI из командной строки.
+#[derive(Debug, Clone, Default)]
+pub struct TuiOptions {
+    pub no_url_open: bool,
+    pub audio_in: bool,
+    pub fps: Option<u64>,
+    pub no_animations: bool,
+    pub offline_state: String,
+    pub accept_new_server_key: bool,
+    pub video_ascii: bool,
+    /// Монохромная тема на весь сеанс (`--no-color`, `NO_COLOR`); выбранная
+    /// в Settings тема при этом не перезаписывается.
+    pub no_color: bool,
+    /// Только ASCII: значки, рамки и котики заменяются по `glyphs`,
+    /// `emoji_mode` выключен на сеанс.
+    pub ascii: bool,
+    /// Сразу открыть интерфейс без заставки (`--no-splash`).
+    pub no_splash: bool,
+    /// Не подключаться при запуске: история, друзья и настройки доступны
+    /// без сети, подключение — по `/connect`.
+    pub offline: bool,
+    /// Вкладка при запуске вместо запомненной `last_view`.
+    pub view: Option<String>,
+    /// Хранить тела входящих сообщений для просмотра (`--debug-frames`).
+    pub debug_frames: bool,
+    /// Кадры из журнала для `replay`: вместо подключения TUI прогоняет их
+    /// через обработчик протокола. Пусто — обычный режим.
+    pub replay: Vec<ProtoFrame>,
+}
+
 pub struct EnhancedApp {
     // Core state
     state: ClientState,
+    options: TuiOptions,
     engine: EngineHandle,
     events: Receiver<ClientEvent>,
+    /// Голосовые и видеокадры отдельно от управляющих событий.
+    media_events: Receiver<ProtoFrame>,
     should_quit: bool,
 
     // Connection state
@@ -103,23 +244,43 @@ pub struct EnhancedApp {
     // Chat state
     channels: Vec<ChannelView>,
     active_channel: usize,
+    /// Сколько самых свежих сообщений пропустить при отрисовке канала.
     message_scroll: usize,
+    selected_message: Option<usize>,
+    clipboard: Option<Clipboard>,
+    url_picker: Option<Vec<String>>,
+    search: Option<SearchOverlay>,
+    palette: Option<CommandPalette>,
+    reaction_picker: Option<ReactionPicker>,
+    call_prompt: Option<CallPrompt>,
 
     // Groups state
     groups: HashMap<String, Group>,
     groups_state: ListState,
 
+    // Friends state
+    friends_state: ListState,
+    /// Ввод `<user_id> [@handle] [псевдоним]` для добавления друга.
+    friend_prompt: Option<String>,
+    /// Друг, ожидающий подтверждения удаления.
+    friend_remove_confirm: Option<FriendEntry>,
+
     // Calls state
     call_manager: CallManager,
     active_call: Option<String>,
-    call_quality_history: VecDeque<f32>,
     call_audio_metrics: Option<AudioMetrics>,
     call_video_metrics: Option<VideoMetrics>,
+    /// Куда записать следующий кадр звонка (`/video snapshot`).
+    pending_snapshot: Option<(String, PathBuf)>,
 
     // Voice state
     voice_recording: bool,
     voice_amplitude: f32,
     voice_buffer: Vec<u8>,
+    voice_frames: Vec<Vec<u8>>,
+    voice_selected: usize,
+    voice_playback: Option<VoicePlayback>,
+    microphone: Option<MicrophoneCapture>,
 
     // Menu state
     menu_items: Vec<MenuItem>,
@@ -129,10 +290,70 @@ pub struct EnhancedApp {
     animations_enabled: bool,
     sound_enabled: bool,
     emoji_mode: bool,
+    markdown_enabled: bool,
 
     // Presence and directory
     presence: HashMap<String, PresenceInfo>,
+    /// Чьё какое устройство: из presence `user` и списка своих устройств.
+    identities: Identities,
+    /// Сводка начальной волны presence и прореживание «мигающих» статусов.
+    presence_notifier: PresenceNotifier,
     devices: Vec<DeviceEntry>,
+    /// Когда загружен список из `devices.json`; `None`, пока он свежий с сервера.
+    devices_cached_at: Option<DateTime<Utc>>,
+    /// Курсор следующей страницы устройств, если сервер её обещал.
+    devices_cursor: Option<String>,
+    devices_filter: Option<&'static str>,
+    devices_selected: usize,
+    /// Устройство, ожидающее подтверждения отзыва.
+    revoke_confirm: Option<DeviceEntry>,
+    /// Причины подтвердить выход по F10 (звонок, запись, черновик).
+    quit_confirm: Option<Vec<&'static str>>,
+    /// Последний ответ `/assist`; показывается оверлеем, пока `show_assist`.
+    assist_report: Option<AssistReport>,
+    show_assist: bool,
+    assist_selected: usize,
+    /// UDP-сокет, чьи адреса ушли в последний `/assist`; живёт до следующего
+    /// запроса или отключения.
+    _assist_socket: Option<tokio::net::UdpSocket>,
+    /// Только что выданный pair-код, показываемый в модальном окне.
+    pair_ticket: Option<PairingTicket>,
+    /// Сырое тело выбранного сообщения в оверлее (`v` в режиме выбора).
+    raw_view: Option<String>,
+    /// Окно «My info» (F2, `/whoami`) и показ QR-кода в нём.
+    show_my_info: bool,
+    my_info_qr: bool,
+    /// Политика pairing из `/api/server-info`, загружается при подключении.
+    server_pairing: Option<ServerPairingInfo>,
+    /// Версия протокола и возможности сервера из рукопожатия текущего подключения.
+    server_caps: Option<ServerCapabilities>,
+    /// Отправленные сообщения без ACK по локальному номеру.
+    pending_sends: HashMap<u64, PendingSend>,
+    next_local_id: u64,
+    /// Файлы, которые уходят по кускам на тиках; первый в очереди — текущий.
+    uploads: VecDeque<PendingUpload>,
+    /// Номера кадров промежуточных кусков: их ACK не относится к сообщению.
+    chunk_sequences: HashSet<(u64, u64)>,
+    incoming_files: Assembler,
+    tasks: Tasks<TaskKind>,
+    task_tx: Sender<TaskEvent>,
+    task_events: Receiver<TaskEvent>,
+    /// Журнал движка для оверлея F12, отдельно от системного канала.
+    engine_log: LogBuffer,
+    show_engine_log: bool,
+    /// Справка F1 и её прокрутка.
+    show_help: bool,
+    help_scroll: u16,
+    /// RTT последних heartbeat-пингов для индикатора в заголовке.
+    latency: LatencyHistory,
+    /// Непрочитанное по каналам, сохраняемое между запусками.
+    unread: UnreadStore,
+    unread_dirty: bool,
+    direct_channels: HashMap<String, u64>,
+    last_certificate_check: Instant,
+    last_sound: Option<Instant>,
+    /// Потери очереди событий за последние окна `FLOW_WINDOW`.
+    flow_window: FlowWindow,
 
     // Media pipeline
     media: MediaManager,
@@ -140,6 +361,9 @@ pub struct EnhancedApp {
 
     // REST integration
     rest_client: Option<RestClient>,
+    profiles: ProfileCache,
+    /// Отправители без имени, которых нужно дозагрузить через REST.
+    pending_profiles: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -156,6 +380,8 @@ struct Notification {
     level: NotificationLevel,
     timestamp: DateTime<Utc>,
     expires_at: DateTime<Utc>,
+    /// Висит до конца сеанса и не вытесняется новыми.
+    sticky: bool,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -166,12 +392,84 @@ enum NotificationLevel {
     Error,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Theme {
     Dark,
     Light,
     Cyberpunk,
     Kawaii,
+    /// Без цветов: цвета терминала по умолчанию, выделение — bold и reverse.
+    Mono,
+}
+
+/// Роль цвета в интерфейсе; конкретный цвет выбирает тема
+/// (`get_theme_tone_style`), поэтому виджеты не пишут `Color::` сами.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tone {
+    /// Подписи, время, подсказки.
+    Muted,
+    /// Второстепенный текст.
+    Dim,
+    /// Поле ввода и текст на цветном фоне.
+    Text,
+    /// Значения, коды, ссылки.
+    Accent,
+    /// Свои реакции, вторая линия графиков.
+    Highlight,
+    /// Котик заставки.
+    Splash,
+    /// Непрочитанные упоминания.
+    Mention,
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl From<NotificationLevel> for Tone {
+    fn from(level: NotificationLevel) -> Self {
+        match level {
+            NotificationLevel::Info => Tone::Info,
+            NotificationLevel::Success => Tone::Success,
+            NotificationLevel::Warning => Tone::Warning,
+            NotificationLevel::Error => Tone::Error,
+        }
+    }
+}
+
+impl NotificationLevel {
+    /// Имя звукового хука уровня; обычные `Info` звука не дают.
+    fn sound_hook(self) -> Option<&'static str> {
+        match self {
+            NotificationLevel::Info => None,
+            NotificationLevel::Success => Some("success"),
+            NotificationLevel::Warning => Some("warning"),
+            NotificationLevel::Error => Some("error"),
+        }
+    }
+}
+
+impl Theme {
+    fn as_str(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Cyberpunk => "cyberpunk",
+            Theme::Kawaii => "kawaii",
+            Theme::Mono => "mono",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Some(Theme::Dark),
+            "light" => Some(Theme::Light),
+            "cyberpunk" => Some(Theme::Cyberpunk),
+            "kawaii" => Some(Theme::Kawaii),
+            "mono" | "monochrome" => Some(Theme::Mono),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -182,8 +480,23 @@ struct ChannelView {
     messages: VecDeque<MessageEntry>,
     typing: HashMap<String, TypingIndicator>,
     unread_count: usize,
+    /// Непрочитанные упоминания; считаются отдельно от `unread_count`.
+    unread_mentions: usize,
+    /// Без уведомлений и звука о новых сообщениях.
+    muted: bool,
     is_group: bool,
     group_id: Option<String>,
+    /// Не чаще одного `typing:true` за `TYPING_SEND_INTERVAL`.
+    typing_limiter: RateLimiter,
+    /// Отправлен `typing:true`, ещё не снятый `typing:false`.
+    typing_sent: bool,
+}
+
+struct AssistReport {
+    peer_hint: String,
+    hints: Vec<SocketAddr>,
+    response: P2pAssistResponse,
+    received_at: DateTime<Utc>,
 }
 
 #[derive(Clone)]
@@ -192,6 +505,57 @@ struct MessageEntry {
     sender: String,
     content: MessageContent,
     reactions: HashMap<String, Vec<String>>,
+    /// Номер кадра отправителя; нужен, чтобы ссылаться на сообщение в реакциях.
+    sequence: Option<u64>,
+    /// Судьба собственного сообщения; у чужих и служебных — `None`.
+    outgoing: Option<Outgoing>,
+    /// Тело кадра как пришло, только с `--debug-frames`.
+    raw: Option<Arc<Vec<u8>>>,
+}
+
+/// Отправленное из этого TUI сообщение: локальный номер связывает запись с
+/// ответами движка (`MessageSent`, `SendFailed`), пока нет номера кадра.
+#[derive(Clone, Copy, Debug)]
+struct Outgoing {
+    local_id: u64,
+    delivery: Delivery,
+}
+
+/// Статус доставки отправленного сообщения: номер кадра приходит от движка
+/// (`MessageSent`), подтверждение — кадром ACK с тем же номером.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Delivery {
+    /// Движок не смог отправить или ACK не пришёл за `DELIVERY_TIMEOUT`.
+    Failed,
+    Pending,
+    Delivered,
+    Read,
+}
+
+impl Delivery {
+    fn marker(self) -> &'static str {
+        match self {
+            Delivery::Failed => "✗",
+            Delivery::Pending => "…",
+            Delivery::Delivered => "✓",
+            Delivery::Read => "✓✓",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Delivery::Failed => "failed",
+            Delivery::Pending => "pending",
+            Delivery::Delivered => "delivered",
+            Delivery::Read => "read",
+        }
+    }
+}
+
+/// Сообщение, ждущее ACK.
+struct PendingSend {
+    channel_id: u64,
+    sent_at: Instant,
 }
 
 #[derive(Clone)]
@@ -201,6 +565,114 @@ enum MessageContent {
     System(String),
     Call(CallInfo),
     GroupEvent(String),
+    File(FileMessage),
+}
+
+struct ReactionPicker {
+    message: usize,
+    selected: usize,
+    input: String,
+}
+
+struct CallPrompt {
+    video: bool,
+    input: String,
+}
+
+/// Палитра команд (Ctrl+K): slash-команды из реестра и переключение вкладок.
+struct CommandPalette {
+    query: String,
+    entries: Vec<PaletteEntry>,
+    /// Индексы `entries`, подходящие под запрос, в порядке релевантности.
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+struct PaletteEntry {
+    target: PaletteTarget,
+    label: String,
+    description: String,
+    hint: String,
+}
+
+enum PaletteTarget {
+    Command(&'static CommandSpec),
+    View(AppView),
+}
+
+impl CommandPalette {
+    fn refilter(&mut self) {
+        self.matches = palette::rank(
+            &self.query,
+            self.entries.iter().map(|entry| entry.label.as_str()),
+        );
+        self.selected = 0;
+    }
+}
+
+struct SearchOverlay {
+    query: String,
+    hits: Vec<SearchHit>,
+    selected: usize,
+}
+
+struct SearchHit {
+    channel: usize,
+    message: usize,
+    label: String,
+}
+
+/// Файл в ленте: входящий собирается по кускам, исходящий уходит по тикам.
+#[derive(Clone)]
+struct FileMessage {
+    id: String,
+    name: String,
+    mime: String,
+    size: u64,
+    /// Байт получено или отдано движку.
+    transferred: u64,
+    /// Файл на диске: исходный у исходящего, копия в кэше у собранного
+    /// входящего. Содержимое в ленте не держится.
+    path: Option<PathBuf>,
+}
+
+/// Длительная операция в оверлее прогресса; Esc отменяет последнюю.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TaskKind {
+    /// Отправка файла по локальному номеру сообщения.
+    Upload(u64),
+    VoicePlayback,
+    VoiceExport,
+    DeviceSync,
+}
+
+/// Результат фоновой задачи, возвращаемый в цикл событий.
+enum TaskEvent {
+    DevicesLoaded {
+        /// Список загружен заново, а не дополнен следующей страницей.
+        reset: bool,
+        result: Result<DevicesEnvelope, String>,
+    },
+    VoiceExported {
+        path: PathBuf,
+        /// Куда файл пишется, пока экспорт не подтверждён.
+        temp: PathBuf,
+        result: Result<Duration, String>,
+    },
+    /// Профиль собеседника; `None` — сервер его не знает или не ответил.
+    ProfileLoaded {
+        id: String,
+        user: Option<UserSummary>,
+    },
+}
+
+/// Исходящий файл и сообщение в ленте, к которому он привязан.
+struct PendingUpload {
+    channel_id: u64,
+    local_id: u64,
+    upload: Upload,
+    /// Кусков, которым движок уже присвоил номер кадра.
+    announced: u32,
 }
 
 #[derive(Clone)]
@@ -235,7 +707,24 @@ impl PresenceInfo {
 }
 
 impl EnhancedApp {
-    pub fn new(state: ClientState, engine: EngineHandle, events: Receiver<ClientEvent>) -> Self {
+    pub fn new(
+        mut state: ClientState,
+        engine: EngineHandle,
+        events: Receiver<ClientEvent>,
+        media_events: Receiver<ProtoFrame>,
+        options: TuiOptions,
+    ) -> Self {
+        let incoming_files = Assembler::new(state.max_attachment_bytes);
+        let (devices, devices_cached_at) = match DeviceCache::load() {
+            Ok(Some(cache)) => (cache.devices, Some(cache.fetched_at)),
+            Ok(None) => (Vec::new(), None),
+            Err(err) => {
+                tracing::warn!(target: LOG_TARGET, "device cache ignored: {:#}", err);
+                (Vec::new(), None)
+            }
+        };
+        let (task_tx, task_events) = mpsc::channel(TASK_EVENT_CAPACITY);
+        let flow_window = FlowWindow::new(FLOW_WINDOW, engine.flow_stats(), Instant::now());
         let menu_items = vec![
             MenuItem {
                 view: AppView::Chat,
@@ -282,7 +771,7 @@ impl EnhancedApp {
         ];
 
         let channels = vec![ChannelView::system()];
-        let rest_client = match RestClient::new(&state.server_url) {
+        let rest_client = match RestClient::with_state(&state) {
             Ok(client) => Some(client),
             Err(err) => {
                 eprintln!("REST client init failed: {err}");
@@ -290,10 +779,39 @@ impl EnhancedApp {
             }
         };
 
-        EnhancedApp {
+        let theme = if options.no_color {
+            Theme::Mono
+        } else {
+            state
+                .ui_theme
+                .as_deref()
+                .and_then(Theme::from_name)
+                .unwrap_or(Theme::Cyberpunk)
+        };
+        let animations_enabled = state.animations_enabled && !options.no_animations;
+        let media =
+            MediaManager::new().with_max_resolution(state.max_video_width, state.max_video_height);
+        let media = if options.video_ascii {
+            media.with_frame_capture(VIDEO_ASCII_INTERVAL)
+        } else {
+            media
+        };
+        let sound_enabled = state.sound_enabled;
+        let emoji_mode = state.emoji_mode && !options.ascii;
+        let markdown_enabled = state.markdown_enabled;
+        let invalid_time_format = (!is_valid_time_format(&state.time_format))
+            .then(|| std::mem::replace(&mut state.time_format, DEFAULT_TIME_FORMAT.to_string()));
+        let invalid_history_limit =
+            (state.message_history_limit < MIN_MESSAGE_HISTORY_LIMIT).then(|| {
+                std::mem::replace(&mut state.message_history_limit, MIN_MESSAGE_HISTORY_LIMIT)
+            });
+
+        let mut app = EnhancedApp {
             state,
+            options,
             engine,
             events,
+            media_events,
             should_quit: false,
             connected: false,
             session_id: None,
@@ -312,41 +830,154 @@ impl EnhancedApp {
             channels,
             active_channel: 0,
             message_scroll: 0,
+            selected_message: None,
+            clipboard: None,
+            url_picker: None,
+            search: None,
+            palette: None,
+            reaction_picker: None,
+            call_prompt: None,
             groups: HashMap::new(),
             groups_state: ListState::default(),
+            friends_state: ListState::default(),
+            friend_prompt: None,
+            friend_remove_confirm: None,
             call_manager: CallManager::new(),
             active_call: None,
-            call_quality_history: VecDeque::new(),
             call_audio_metrics: None,
             call_video_metrics: None,
+            pending_snapshot: None,
             voice_recording: false,
             voice_amplitude: 0.0,
             voice_buffer: Vec::new(),
+            voice_frames: Vec::new(),
+            voice_selected: 0,
+            voice_playback: None,
+            microphone: None,
             menu_items,
-            theme: Theme::Cyberpunk,
-            animations_enabled: true,
-            sound_enabled: true,
-            emoji_mode: true,
+            theme,
+            animations_enabled,
+            sound_enabled,
+            emoji_mode,
+            markdown_enabled,
             presence: HashMap::new(),
-            devices: Vec::new(),
-            media: MediaManager::new(),
+            identities: Identities::default(),
+            presence_notifier: PresenceNotifier::default(),
+            devices,
+            devices_cached_at,
+            devices_cursor: None,
+            devices_filter: None,
+            devices_selected: 0,
+            revoke_confirm: None,
+            quit_confirm: None,
+            assist_report: None,
+            show_assist: false,
+            assist_selected: 0,
+            _assist_socket: None,
+            pair_ticket: None,
+            raw_view: None,
+            show_my_info: false,
+            my_info_qr: false,
+            server_pairing: None,
+            server_caps: None,
+            pending_sends: HashMap::new(),
+            next_local_id: 0,
+            uploads: VecDeque::new(),
+            chunk_sequences: HashSet::new(),
+            incoming_files,
+            tasks: Tasks::default(),
+            task_tx,
+            task_events,
+            engine_log: LogBuffer::new(LOG_BUFFER_CAPACITY),
+            show_engine_log: false,
+            show_help: false,
+            help_scroll: 0,
+            latency: LatencyHistory::new(LATENCY_HISTORY),
+            unread: UnreadStore::load().unwrap_or_else(|err| {
+                tracing::warn!(target: LOG_TARGET, "unread state ignored: {:#}", err);
+                UnreadStore::default()
+            }),
+            unread_dirty: false,
+            direct_channels: HashMap::new(),
+            last_certificate_check: Instant::now(),
+            flow_window,
+            last_sound: None,
+            media,
             call_channels: HashMap::new(),
             rest_client,
+            profiles: ProfileCache::new(PROFILE_CACHE_CAPACITY, PROFILE_CACHE_TTL),
+            pending_profiles: Vec::new(),
+        };
+        if let Some(user_id) = app.state.user_id.as_deref() {
+            app.identities.link(&app.state.device_id, user_id);
+        }
+        for channel_id in app.unread.unread_channels() {
+            app.ensure_channel(channel_id);
+        }
+        if let Some(format) = invalid_time_format {
+            app.push_notification(
+                format!(
+                    "Invalid time_format '{}', using {}",
+                    format, DEFAULT_TIME_FORMAT
+                ),
+                NotificationLevel::Warning,
+            );
         }
+        if let Some(limit) = invalid_history_limit {
+            app.push_notification(
+                format!(
+                    "message_history_limit {} is too small, using {}",
+                    limit, MIN_MESSAGE_HISTORY_LIMIT
+                ),
+                NotificationLevel::Warning,
+            );
+        }
+        app
     }
 
     pub async fn run(&mut self) -> Result<()> {
         let mut terminal = prepare_terminal()?;
         let mut input_stream = EventStream::new();
-        let mut ticker = tokio::time::interval(Duration::from_millis(1000 / ANIMATION_FPS));
+        let fps = self
+            .options
+            .fps
+            .unwrap_or(ANIMATION_FPS)
+            .clamp(1, MAX_ANIMATION_FPS);
+        let mut ticker = tokio::time::interval(Duration::from_millis(1000 / fps));
+        let mut idle_ticker = tokio::time::interval(IDLE_TICK);
 
         // Show splash screen
-        self.show_splash_animation(&mut terminal).await?;
-        self.view = AppView::Chat;
-
-        // Auto-connect
-        self.connect().await?;
+        if self.animations_enabled && self.state.show_splash && !self.options.no_splash {
+            self.show_splash_animation(&mut terminal, &mut input_stream)
+                .await?;
+        }
+        self.view = self
+            .options
+            .view
+            .as_deref()
+            .or(self.state.last_view.as_deref())
+            .and_then(AppView::from_name)
+            .unwrap_or(AppView::Chat);
+        self.check_certificate_expiry();
+
+        if !self.options.replay.is_empty() {
+            self.replay_frames().await;
+        } else if self.options.offline {
+            self.add_notification(
+                "Offline mode: /connect to go online".to_string(),
+                NotificationLevel::Info,
+            );
+        } else if !self.state.auto_connect {
+            self.add_notification(
+                "Auto-connect is off (Settings, c): /connect to go online".to_string(),
+                NotificationLevel::Info,
+            );
+        } else {
+            // Auto-connect
+            self.connect().await?;
+        }
 
+        let mut last_size = terminal.size()?;
         loop {
             // Update animations
             let now = Instant::now();
@@ -363,14 +994,33 @@ impl EnhancedApp {
                 Some(event) = self.events.recv() => {
                     self.handle_client_event(event).await?;
                 }
+                Some(frame) = self.media_events.recv() => {
+                    self.handle_protocol_frame(frame).await?;
+                }
+                Some(event) = self.task_events.recv() => {
+                    self.handle_task_event(event);
+                }
                 Some(Ok(event)) = input_stream.next() => {
-                    if let Event::Key(key) = event {
-                        self.handle_key(key).await?;
+                    match event {
+                        Event::Key(key) => self.handle_key(key).await?,
+                        Event::Resize(width, height) => {
+                            if width.abs_diff(last_size.width) >= RESIZE_CLEAR_THRESHOLD
+                                || height.abs_diff(last_size.height) >= RESIZE_CLEAR_THRESHOLD
+                            {
+                                terminal.clear()?;
+                            }
+                            last_size = Rect::new(0, 0, width, height);
+                            terminal.draw(|frame| self.render(frame))?;
+                        }
+                        _ => {}
                     }
                 }
-                _ = ticker.tick() => {
+                _ = ticker.tick(), if self.needs_frame_ticks() => {
                     self.frame_counter += 1;
-                    self.cleanup_expired_notifications();
+                    self.on_tick().await?;
+                }
+                _ = idle_ticker.tick(), if !self.needs_frame_ticks() => {
+                    self.on_tick().await?;
                 }
             }
 
@@ -378,21 +1028,99 @@ impl EnhancedApp {
                 break;
             }
         }
-
+        if self.unread_dirty {
+            self.save_unread();
+        }
+        self.remember_view();
+
+        // CALL_END уходит в очередь раньше Disconnect, поэтому успеет отправиться.
+        let _ = self.end_active_call(CallEndReason::Hangup).await;
+        let shutdown = self
+            .engine
+            .shutdown(self.offline_presence(), ENGINE_SHUTDOWN_TIMEOUT)
+            .await;
         restore_terminal(terminal)?;
+        if let Err(err) = shutdown {
+            eprintln!("warning: {}", err);
+        }
+        Ok(())
+    }
+
+    /// Частые тики нужны только для анимаций и живого звука; иначе
+    /// интерфейс перерисовывается по событиям и редкому служебному тику.
+    fn needs_frame_ticks(&self) -> bool {
+        self.animations_enabled
+            || !self.uploads.is_empty()
+            || !self.tasks.is_empty()
+            || self.voice_recording
+            || self.microphone.is_some()
+            || self.voice_playback.is_some()
+    }
+
+    async fn on_tick(&mut self) -> Result<()> {
+        self.pump_microphone().await?;
+        self.poll_voice_playback();
+        self.cleanup_expired_notifications();
+        self.flush_presence_burst();
+        self.expire_pending_sends();
+        self.pump_uploads().await?;
+        self.flow_window
+            .sample(self.engine.flow_stats(), Instant::now());
+        if self.last_certificate_check.elapsed() >= CERTIFICATE_CHECK_INTERVAL {
+            self.check_certificate_expiry();
+        }
+        if self.unread_dirty {
+            self.save_unread();
+        }
         Ok(())
     }
 
+    /// Запоминает вкладку для следующего запуска; replay профиль не трогает.
+    fn remember_view(&mut self) {
+        if !self.options.replay.is_empty() || self.view == AppView::Splash {
+            return;
+        }
+        let view = Some(self.view.as_str().to_string());
+        if self.state.last_view == view {
+            return;
+        }
+        let result = ClientState::load().and_then(|mut stored| {
+            stored.last_view = view;
+            stored.save()
+        });
+        if let Err(err) = result {
+            tracing::warn!(target: LOG_TARGET, "failed to save last view: {:#}", err);
+        }
+    }
+
+    fn save_unread(&mut self) {
+        self.unread_dirty = false;
+        if !self.options.replay.is_empty() {
+            return;
+        }
+        if let Err(err) = self.unread.save() {
+            tracing::warn!(target: LOG_TARGET, "failed to save unread state: {:#}", err);
+        }
+    }
+
     async fn show_splash_animation(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        input_stream: &mut EventStream,
     ) -> Result<()> {
         let splash_duration = Duration::from_secs(2);
         let start = Instant::now();
 
         while start.elapsed() < splash_duration && !self.should_quit {
-            terminal.draw(|frame| self.render_splash(frame))?;
-            tokio::time::sleep(Duration::from_millis(50)).await;
+            terminal.draw(|frame| {
+                self.render_splash(frame);
+                self.apply_display_filters(frame);
+            })?;
+            // Любая клавиша закрывает заставку и дальше не обрабатывается.
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+                Some(Ok(Event::Key(_))) = input_stream.next() => break,
+            }
             self.transition_progress =
                 start.elapsed().as_millis() as f32 / splash_duration.as_millis() as f32;
         }
@@ -417,16 +1145,13 @@ impl EnhancedApp {
             .split(area);
 
         let cat = Paragraph::new(ascii_art::CAT_HAPPY)
-            .style(Style::default().fg(Color::LightMagenta))
+            .style(self.get_theme_tone_style(Tone::Splash))
             .alignment(Alignment::Center);
         frame.render_widget(cat, chunks[0]);
 
         // Render ASCII art logo with fade-in effect
-        let alpha = (self.transition_progress * 255.0) as u8;
-        let color = Color::Rgb(0, alpha, alpha);
-
         let logo = Paragraph::new(ascii_art::LOGO)
-            .style(Style::default().fg(color))
+            .style(self.get_theme_logo_style(self.transition_progress))
             .alignment(Alignment::Center);
         frame.render_widget(logo, chunks[1]);
 
@@ -434,7 +1159,7 @@ impl EnhancedApp {
         let progress = (self.transition_progress * 100.0) as u16;
         let gauge = Gauge::default()
             .block(Block::default().borders(Borders::NONE))
-            .gauge_style(Style::default().fg(Color::Cyan).bg(Color::Black))
+            .gauge_style(self.get_theme_gauge_style())
             .percent(progress)
             .label(format!("Loading CommuCat... {}%", progress));
 
@@ -449,7 +1174,7 @@ impl EnhancedApp {
         // Animated neko
         let neko = self.neko_animation.tick(Duration::from_millis(50));
         let neko_text = Paragraph::new(neko)
-            .style(Style::default().fg(Color::Magenta))
+            .style(self.get_theme_tone_style(Tone::Highlight))
             .alignment(Alignment::Center);
 
         let neko_area = Layout::default()
@@ -490,7 +1215,7 @@ impl EnhancedApp {
         }
 
         // Simulate voice amplitude changes when recording
-        if self.voice_recording {
+        if self.voice_recording && self.microphone.is_none() {
             self.voice_amplitude = ((self.frame_counter as f32 * 0.1).sin() + 1.0) * 0.5;
         }
     }
@@ -500,6 +1225,15 @@ impl EnhancedApp {
             AppView::Splash => self.render_splash(frame),
             _ => self.render_main(frame),
         }
+        self.apply_display_filters(frame);
+    }
+
+    /// ASCII-режим — проход по готовому кадру: так замена символов
+    /// действует и на виджеты, и на рамки, и на текст сообщений.
+    fn apply_display_filters(&self, frame: &mut UiFrame) {
+        if self.options.ascii {
+            glyphs::asciify(frame.buffer_mut());
+        }
     }
 
     fn render_main(&mut self, frame: &mut UiFrame) {
@@ -521,6 +1255,22 @@ impl EnhancedApp {
         self.render_input(frame, chunks[2]);
         self.render_status_bar(frame, chunks[3]);
         self.render_notifications(frame, area);
+        self.render_url_picker(frame, area);
+        self.render_search(frame, area);
+        self.render_palette(frame, area);
+        self.render_reaction_picker(frame, area);
+        self.render_call_prompt(frame, area);
+        self.render_friend_prompt(frame, area);
+        self.render_friend_remove_confirm(frame, area);
+        self.render_revoke_confirm(frame, area);
+        self.render_quit_confirm(frame, area);
+        self.render_pair_ticket(frame, area);
+        self.render_my_info(frame, area);
+        self.render_raw_view(frame, area);
+        self.render_assist_report(frame, area);
+        self.render_engine_log(frame, area);
+        self.render_help(frame, area);
+        self.render_tasks(frame, chunks[2]);
 
         self.input_rect = Some(chunks[2]);
     }
@@ -531,7 +1281,7 @@ impl EnhancedApp {
             .constraints([
                 Constraint::Length(20),
                 Constraint::Min(10),
-                Constraint::Length(30),
+                Constraint::Length(36),
             ])
             .split(area);
 
@@ -548,7 +1298,14 @@ impl EnhancedApp {
         let titles = self
             .menu_items
             .iter()
-            .map(|item| format!("{} {}", item.icon, item.label))
+            .map(|item| {
+                let unread = self.unread.total_unread();
+                if item.view == AppView::Chat && unread > 0 {
+                    format!("{} {} ({})", item.icon, item.label, unread)
+                } else {
+                    format!("{} {}", item.icon, item.label)
+                }
+            })
             .collect::<Vec<_>>();
 
         let selected = self
@@ -564,7 +1321,7 @@ impl EnhancedApp {
                     .border_type(BorderType::Rounded),
             )
             .select(selected)
-            .style(Style::default().fg(Color::Gray))
+            .style(self.get_theme_tone_style(Tone::Dim))
             .highlight_style(
                 Style::default()
                     .fg(self.get_theme_primary_color())
@@ -572,19 +1329,40 @@ impl EnhancedApp {
             );
         frame.render_widget(tabs, header_chunks[1]);
 
-        // Connection status with animation
-        let status_text = if self.connected {
-            format!("{} Online", self.pulse_animation.tick(Duration::ZERO))
+        // Connection status with animation and link quality
+        let mut status_line = if self.connected {
+            vec![Span::styled(
+                format!("{} Online", self.pulse_animation.tick(Duration::ZERO)),
+                self.get_theme_tone_style(Tone::Success),
+            )]
         } else {
-            format!("{} Offline", self.loading_animation.tick(Duration::ZERO))
+            vec![Span::styled(
+                format!("{} Offline", self.loading_animation.tick(Duration::ZERO)),
+                self.get_theme_tone_style(Tone::Error),
+            )]
         };
+        if self.connected
+            && let Some(rtt) = self.latency.latest()
+        {
+            let quality = LinkQuality::from_rtt(rtt);
+            status_line.push(Span::styled(
+                format!(" {}", quality.bars()),
+                self.get_theme_style(Style::default().fg(quality.color())),
+            ));
+            status_line.push(Span::styled(
+                match rtt {
+                    Some(rtt) => format!(" {}ms", rtt.as_millis()),
+                    None => " timeout".to_string(),
+                },
+                self.get_theme_style(Style::default().fg(quality.color())),
+            ));
+            status_line.push(Span::styled(
+                format!(" {}", self.latency.sparkline(LATENCY_SPARKLINE_WIDTH)),
+                self.get_theme_tone_style(Tone::Muted),
+            ));
+        }
 
-        let status = Paragraph::new(status_text)
-            .style(Style::default().fg(if self.connected {
-                Color::Green
-            } else {
-                Color::Red
-            }))
+        let status = Paragraph::new(Line::from(status_line))
             .alignment(Alignment::Center)
             .block(
                 Block::default()
@@ -634,18 +1412,27 @@ impl EnhancedApp {
             .enumerate()
             .map(|(i, channel)| {
                 let icon = if channel.is_group { "👥" } else { "💬" };
-                let unread = if channel.unread_count > 0 {
+                let mut unread = if channel.unread_count > 0 {
                     format!(" ({})", channel.unread_count)
                 } else {
                     String::new()
                 };
+                if channel.unread_mentions > 0 {
+                    unread.push_str(&format!(" @{}", channel.unread_mentions));
+                }
+                if channel.muted {
+                    unread.push_str(" 🔕");
+                }
 
                 let style = if i == self.active_channel {
                     Style::default()
                         .fg(self.get_theme_primary_color())
                         .add_modifier(Modifier::BOLD)
+                } else if channel.unread_mentions > 0 {
+                    self.get_theme_tone_style(Tone::Mention)
+                        .add_modifier(Modifier::BOLD)
                 } else if channel.unread_count > 0 {
-                    Style::default().fg(Color::Yellow)
+                    self.get_theme_tone_style(Tone::Warning)
                 } else {
                     Style::default()
                 };
@@ -662,8 +1449,7 @@ impl EnhancedApp {
                     .border_type(BorderType::Rounded),
             )
             .highlight_style(
-                Style::default()
-                    .bg(self.get_theme_secondary_color())
+                self.get_theme_highlight_style()
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol("▶ ");
@@ -674,6 +1460,7 @@ impl EnhancedApp {
     }
 
     fn render_messages(&mut self, frame: &mut UiFrame, area: Rect) {
+        let spinner = self.loading_animation.tick(Duration::ZERO).to_string();
         let channel = &self.channels[self.active_channel];
 
         // Split for messages and typing indicator
@@ -684,8 +1471,15 @@ impl EnhancedApp {
 
         // Messages
         let mut lines = Vec::new();
-        for entry in channel.messages.iter().rev().take(50) {
-            let timestamp = entry.timestamp.format("%H:%M").to_string();
+        for (idx, entry) in channel
+            .messages
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(self.message_scroll)
+            .take(VISIBLE_MESSAGES)
+        {
+            let timestamp = self.format_time(entry.timestamp);
 
             let (prefix, content) = match &entry.content {
                 MessageContent::Text(text) => {
@@ -704,38 +1498,113 @@ impl EnhancedApp {
                 MessageContent::Call(info) => {
                     let id = self.short_id(&info.call_id);
                     let details = if let Some(duration) = info.duration {
-                        format!("Call {} {} ({}s)", id, info.action, duration.as_secs())
+                        format!(
+                            "Call {} {} ({})",
+                            id,
+                            info.action,
+                            format_call_duration(duration.as_secs())
+                        )
                     } else {
                         format!("Call {} {}", id, info.action)
                     };
                     (format!("[{}] 📞", timestamp), details)
                 }
                 MessageContent::GroupEvent(event) => (format!("[{}] 👥", timestamp), event.clone()),
+                MessageContent::File(file) => {
+                    let sender = self.get_friend_display_name(&entry.sender);
+                    let progress = if file.transferred < file.size {
+                        format!(" — {}%", file.transferred * 100 / file.size)
+                    } else {
+                        String::new()
+                    };
+                    (
+                        format!("[{}] {} 📎", timestamp, sender),
+                        format!(
+                            "{} ({}, {}){}",
+                            file.name,
+                            attachments::format_size(file.size),
+                            file.mime,
+                            progress
+                        ),
+                    )
+                }
             };
 
             // Add message with styling
+            let mut body = match entry.content {
+                MessageContent::Text(_) if self.markdown_enabled => {
+                    render_markdown(&content, text_with_links)
+                }
+                MessageContent::Text(_) => vec![text_with_links(&content)],
+                _ => vec![vec![Span::raw(content)]],
+            };
+            for span in body.iter_mut().flatten() {
+                span.style = self.get_theme_style(span.style);
+            }
             let mut spans = vec![
-                Span::styled(prefix, Style::default().fg(Color::DarkGray)),
+                Span::styled(prefix, self.get_theme_tone_style(Tone::Muted)),
                 Span::raw(": "),
-                Span::raw(content),
             ];
+            spans.append(&mut body[0]);
+            body[0] = spans;
+            for rest in body.iter_mut().skip(1) {
+                rest.insert(0, Span::raw("    "));
+            }
+            let spans = body.last_mut().expect("message has at least one line");
+            if let Some(outgoing) = entry.outgoing {
+                let (marker, style) = match outgoing.delivery {
+                    Delivery::Pending => (spinner.clone(), self.get_theme_tone_style(Tone::Muted)),
+                    Delivery::Failed => (
+                        "✗ failed · Ctrl+R to retry".to_string(),
+                        self.get_theme_tone_style(Tone::Error)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    delivered => (
+                        delivered.marker().to_string(),
+                        self.get_theme_tone_style(Tone::Success),
+                    ),
+                };
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(marker, style));
+            }
 
             // Add reactions
-            if !entry.reactions.is_empty() {
-                let reactions_text = entry
-                    .reactions
-                    .iter()
-                    .map(|(emoji, users)| format!("{}{}", emoji, users.len()))
-                    .collect::<Vec<_>>()
-                    .join(" ");
+            let mut reactions: Vec<_> = entry.reactions.iter().collect();
+            reactions.sort_by(|a, b| a.0.cmp(b.0));
+            for (emoji, users) in reactions {
                 spans.push(Span::raw(" "));
-                spans.push(Span::styled(
-                    reactions_text,
-                    Style::default().fg(Color::Yellow),
-                ));
+                if users.contains(&self.state.device_id) {
+                    spans.push(Span::styled(
+                        format!("[{}{}]", emoji, users.len()),
+                        self.get_theme_tone_style(Tone::Highlight)
+                            .add_modifier(Modifier::BOLD),
+                    ));
+                } else {
+                    spans.push(Span::styled(
+                        format!("{}{}", emoji, users.len()),
+                        self.get_theme_tone_style(Tone::Warning),
+                    ));
+                }
             }
 
-            lines.push(Line::from(spans));
+            let selected = self.selected_message == Some(idx);
+            let mention = self.is_mention(entry);
+            for spans in body {
+                let mut line = Line::from(spans);
+                if mention {
+                    line = line.patch_style(self.get_theme_highlight_style());
+                }
+                if selected {
+                    // Упоминание в монохромной теме уже инвертировано.
+                    let modifier = if mention && self.theme == Theme::Mono {
+                        Modifier::REVERSED | Modifier::UNDERLINED
+                    } else {
+                        Modifier::REVERSED
+                    };
+                    line = line.patch_style(Style::default().add_modifier(modifier));
+                }
+                lines.push(line);
+            }
         }
 
         let messages = Paragraph::new(lines)
@@ -745,8 +1614,7 @@ impl EnhancedApp {
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded),
             )
-            .wrap(Wrap { trim: true })
-            .scroll((self.message_scroll as u16, 0));
+            .wrap(Wrap { trim: true });
 
         frame.render_widget(messages, chunks[0]);
 
@@ -768,7 +1636,7 @@ impl EnhancedApp {
                 .join(", ");
 
             let typing = Paragraph::new(typing_text)
-                .style(Style::default().fg(Color::Gray).italic())
+                .style(self.get_theme_tone_style(Tone::Dim).italic())
                 .block(Block::default().borders(Borders::TOP));
 
             frame.render_widget(typing, chunks[1]);
@@ -803,6 +1671,42 @@ impl EnhancedApp {
             )));
         }
 
+        if let Some(last) = channel
+            .messages
+            .iter()
+            .rev()
+            .find(|entry| entry.outgoing.is_some())
+        {
+            let delivery = last
+                .outgoing
+                .map_or(Delivery::Pending, |outgoing| outgoing.delivery);
+            details.push(Line::from(format!(
+                "📨 Last sent: {} {}",
+                delivery.marker(),
+                delivery.label()
+            )));
+            if channel.is_group {
+                let reactors: HashSet<&str> = last
+                    .reactions
+                    .values()
+                    .flatten()
+                    .filter(|reactor| !self.is_me(reactor))
+                    .map(|reactor| self.identities.user_of(reactor))
+                    .collect();
+                details.push(Line::from(format!(
+                    "💬 Reacted: {}/{} members",
+                    reactors.len(),
+                    channel
+                        .members
+                        .iter()
+                        .filter(|member| !self.is_me(member))
+                        .map(|member| self.identities.user_of(member))
+                        .collect::<HashSet<_>>()
+                        .len()
+                )));
+            }
+        }
+
         let info = Paragraph::new(details).block(
             Block::default()
                 .title(" Info ")
@@ -832,14 +1736,14 @@ impl EnhancedApp {
         frame.render_widget(members_list, chunks[1]);
 
         // Quick actions
-        let actions = vec![
-            Line::from("📞 Ctrl+F3 - Voice call"),
-            Line::from("🎥 Ctrl+F4 - Video call"),
-            Line::from("📎 Ctrl+F5 - Send file"),
-            Line::from("🎤 Ctrl+F6 - Voice message"),
-            Line::from("➕ Ctrl+F7 - Add member"),
-            Line::from("⚙️ Ctrl+F8 - Settings"),
-        ];
+        let actions: Vec<Line> = help::quick_actions()
+            .map(|binding| {
+                Line::from(format!(
+                    "{} {} - {}",
+                    binding.icon, binding.keys, binding.description
+                ))
+            })
+            .collect();
 
         let actions_widget = Paragraph::new(actions).block(
             Block::default()
@@ -887,8 +1791,7 @@ impl EnhancedApp {
                     .border_type(BorderType::Rounded),
             )
             .highlight_style(
-                Style::default()
-                    .bg(self.get_theme_secondary_color())
+                self.get_theme_highlight_style()
                     .add_modifier(Modifier::BOLD),
             );
 
@@ -1022,18 +1925,26 @@ impl EnhancedApp {
     }
 
     fn render_calls(&mut self, frame: &mut UiFrame, area: Rect) {
+        let showing_video = self
+            .active_call
+            .as_deref()
+            .is_some_and(|call_id| self.media.latest_frame(call_id).is_some());
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(15), // Active call
-                Constraint::Min(10),    // Call history
-                Constraint::Length(8),  // Stats
+                if showing_video {
+                    Constraint::Percentage(60)
+                } else {
+                    Constraint::Length(15)
+                }, // Active call
+                Constraint::Min(10),   // Call history
+                Constraint::Length(8), // Stats
             ])
             .split(area);
 
         // Active call display
-        if let Some(call_id) = &self.active_call {
-            self.render_active_call(frame, chunks[0], call_id);
+        if let Some(call_id) = self.active_call.clone() {
+            self.render_active_call(frame, chunks[0], &call_id);
         } else {
             let no_call = Paragraph::new(vec![
                 Line::from(""),
@@ -1053,10 +1964,34 @@ impl EnhancedApp {
         }
 
         // Call history
-        let history = self.call_manager.get_active_calls();
-        let history_items: Vec<ListItem> = history
-            .iter()
-            .map(|call_id| ListItem::new(format!("📞 Call: {}", &call_id[..8])))
+        let history_items: Vec<ListItem> = self
+            .call_manager
+            .history()
+            .into_iter()
+            .map(|call| {
+                let status = match (call.started_at, call.ended_at) {
+                    (_, Some(_)) => "ended",
+                    (Some(_), None) => "live",
+                    (None, None) => "ringing",
+                };
+                let duration = call
+                    .duration()
+                    .map(|duration| format_call_duration(duration.as_secs()))
+                    .unwrap_or_else(|| "--:--".to_string());
+                let started = call
+                    .started_at
+                    .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+                    .map(|ts| format!(" · {}", self.format_time(ts)))
+                    .unwrap_or_default();
+                ListItem::new(format!(
+                    "📞 {} · {} · {} · {}{}",
+                    self.short_id(&call.offer.call_id),
+                    self.get_friend_display_name(&call.offer.from),
+                    status,
+                    duration,
+                    started
+                ))
+            })
             .collect();
 
         let history_list = List::new(history_items).block(
@@ -1071,7 +2006,43 @@ impl EnhancedApp {
         self.render_call_stats(frame, chunks[2]);
     }
 
-    fn render_active_call(&self, frame: &mut UiFrame, area: Rect, _call_id: &str) {
+    fn render_active_call(&mut self, frame: &mut UiFrame, area: Rect, call_id: &str) {
+        let area = if let Some(video) = self.media.latest_frame(call_id) {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .split(area);
+            let title = match &self.call_video_metrics {
+                Some(metrics) => format!(
+                    " 📹 {}×{} · {:.1} fps ",
+                    metrics.width, metrics.height, metrics.fps
+                ),
+                None => " 📹 Video ".to_string(),
+            };
+            let block = Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded);
+            let inner = block.inner(columns[1]);
+            let lines = if self.theme == Theme::Mono {
+                // Полублоки передают картинку только цветом.
+                vec![Line::styled(
+                    "video needs a color theme",
+                    self.get_theme_tone_style(Tone::Muted),
+                )]
+            } else {
+                videoart::half_block_lines(video, inner.width, inner.height)
+            };
+            frame.render_widget(
+                Paragraph::new(lines)
+                    .alignment(Alignment::Center)
+                    .block(block),
+                columns[1],
+            );
+            columns[0]
+        } else {
+            area
+        };
         // Animated call display
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -1083,29 +2054,97 @@ impl EnhancedApp {
             ])
             .split(area);
 
+        let call = self.call_manager.get_call(call_id).cloned();
+        let started_at = call.as_ref().and_then(|call| call.started_at);
+        let ended_at = call.as_ref().and_then(|call| call.ended_at);
+
         // Call status
-        let status = Paragraph::new("🔴 Connected")
-            .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        let (status_text, status_tone) = match (started_at, ended_at) {
+            (_, Some(_)) => ("⚫ Ended", Tone::Dim),
+            (Some(_), None) => ("🔴 Connected", Tone::Error),
+            (None, None) => ("🔔 Ringing", Tone::Warning),
+        };
+        let status = Paragraph::new(status_text)
+            .style(
+                self.get_theme_tone_style(status_tone)
+                    .add_modifier(Modifier::BOLD),
+            )
             .alignment(Alignment::Center);
         frame.render_widget(status, chunks[0]);
 
         // Participants
+        let (peers, video) = match call.as_ref() {
+            Some(call) => {
+                let from = self.get_friend_display_name(&call.offer.from);
+                let to = call
+                    .offer
+                    .to
+                    .iter()
+                    .map(|target| self.get_friend_display_name(target))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let video = call
+                    .answer
+                    .as_ref()
+                    .and_then(|answer| answer.media.as_ref())
+                    .unwrap_or(&call.offer.media)
+                    .video
+                    .is_some();
+                (format!("{} ←→ {}", from, to), video)
+            }
+            None => (format!("Call {}", self.short_id(call_id)), false),
+        };
+        let muted = call.as_ref().is_some_and(|call| call.muted);
+        let mic = if muted {
+            Span::styled(
+                "🔇 MUTED",
+                self.get_theme_tone_style(Tone::Error)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled("🎤 Mic on", self.get_theme_tone_style(Tone::Dim))
+        };
+        let camera = if video {
+            "📹 Video on"
+        } else {
+            "📹 Video off"
+        };
         let participants = Paragraph::new(vec![
             Line::from(""),
-            Line::from("  You ←→ Peer"),
-            Line::from(""),
+            Line::from(peers),
+            Line::from(vec![
+                mic,
+                Span::styled(
+                    format!(" | {}", camera),
+                    self.get_theme_tone_style(Tone::Dim),
+                ),
+            ]),
         ])
         .alignment(Alignment::Center);
         frame.render_widget(participants, chunks[1]);
 
         // Duration
-        let duration = Paragraph::new("Duration: 00:42").alignment(Alignment::Center);
+        let duration_text = match call.as_ref().and_then(|call| call.duration()) {
+            Some(duration) => format!("Duration: {}", format_call_duration(duration.as_secs())),
+            None => format!("{} Ringing…", self.loading_animation.tick(Duration::ZERO)),
+        };
+        let duration = Paragraph::new(duration_text).alignment(Alignment::Center);
         frame.render_widget(duration, chunks[2]);
 
         // Controls
-        let controls = Paragraph::new("🔇 Mute (m) | 📹 Video (v) | 📴 End (e)")
+        let incoming = self
+            .pending_incoming_call()
+            .is_some_and(|(pending, _)| pending == call_id);
+        let controls = if incoming {
+            "✅ Accept (a) | ❌ Reject (x)"
+        } else if muted {
+            "🎤 Unmute (m) | 📹 Video (v) | 📴 End (e)"
+        } else {
+            "🔇 Mute (m) | 📹 Video (v) | 📴 End (e)"
+        };
+        let controls = Paragraph::new(controls)
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Gray));
+            .style(self.get_theme_tone_style(Tone::Dim));
         frame.render_widget(controls, chunks[3]);
     }
 
@@ -1115,13 +2154,18 @@ impl EnhancedApp {
             .constraints([Constraint::Length(6), Constraint::Min(3)])
             .split(area);
 
-        let quality_data: Vec<u64> = self
-            .call_quality_history
-            .iter()
-            .map(|&v| (v * 100.0) as u64)
-            .collect();
+        let call_id = self.active_call.clone().or_else(|| {
+            self.call_manager
+                .history()
+                .into_iter()
+                .find(|call| !call.stats.is_empty())
+                .map(|call| call.offer.call_id.clone())
+        });
+        let series = call_id
+            .map(|call_id| self.call_manager.quality_series(&call_id))
+            .unwrap_or_default();
 
-        if quality_data.is_empty() {
+        if series.is_empty() {
             let no_stats = Paragraph::new("No call statistics available")
                 .alignment(Alignment::Center)
                 .block(
@@ -1132,17 +2176,76 @@ impl EnhancedApp {
                 );
             frame.render_widget(no_stats, sections[0]);
         } else {
-            let sparkline = Sparkline::default()
-                .block(
-                    Block::default()
-                        .title(" Call Quality (%) ")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                )
-                .data(&quality_data)
-                .max(100)
-                .style(Style::default().fg(Color::Green));
-            frame.render_widget(sparkline, sections[0]);
+            let charts = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(33),
+                    Constraint::Percentage(33),
+                    Constraint::Percentage(34),
+                ])
+                .split(sections[0]);
+            let audio_loss: Vec<u64> = series
+                .iter()
+                .map(|sample| sample.audio_loss.unwrap_or(0.0) as u64)
+                .collect();
+            let video_loss: Vec<u64> = series
+                .iter()
+                .map(|sample| sample.video_loss.unwrap_or(0.0) as u64)
+                .collect();
+            let bitrate: Vec<u64> = series
+                .iter()
+                .map(|sample| u64::from(sample.bitrate / 1_000))
+                .collect();
+            let latest = series.last();
+            let jitter = latest
+                .and_then(|sample| sample.jitter_ms)
+                .map(|jitter| format!(" · {} ms jitter", jitter))
+                .unwrap_or_default();
+            let charts_data = [
+                (
+                    format!(
+                        " Audio loss {:.1}%{} ",
+                        latest.and_then(|s| s.audio_loss).unwrap_or(0.0),
+                        jitter
+                    ),
+                    audio_loss,
+                    Some(100),
+                    Tone::Success,
+                ),
+                (
+                    format!(
+                        " Video loss {:.1}% ",
+                        latest.and_then(|s| s.video_loss).unwrap_or(0.0)
+                    ),
+                    video_loss,
+                    Some(100),
+                    Tone::Highlight,
+                ),
+                (
+                    format!(" Bitrate {} kbps ", latest.map_or(0, |s| s.bitrate / 1_000)),
+                    bitrate,
+                    None,
+                    Tone::Accent,
+                ),
+            ];
+            for ((title, data, max, tone), area) in charts_data.into_iter().zip(charts.iter()) {
+                // Sparkline рисует с начала ряда: показываются последние точки.
+                let visible = usize::from(area.width.saturating_sub(2));
+                let data = &data[data.len().saturating_sub(visible)..];
+                let mut sparkline = Sparkline::default()
+                    .block(
+                        Block::default()
+                            .title(title)
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded),
+                    )
+                    .data(data)
+                    .style(self.get_theme_tone_style(tone));
+                if let Some(max) = max {
+                    sparkline = sparkline.max(max);
+                }
+                frame.render_widget(sparkline, *area);
+            }
         }
 
         let mut info_lines = Vec::new();
@@ -1153,17 +2256,17 @@ impl EnhancedApp {
             )));
             info_lines.push(Line::from(format!(
                 "Audio updated {}",
-                audio.timestamp.format("%H:%M:%S")
+                self.format_time(audio.timestamp)
             )));
         }
         if let Some(video) = &self.call_video_metrics {
             info_lines.push(Line::from(format!(
-                "Video · {}×{} · {} frames",
-                video.width, video.height, video.frames_decoded
+                "Video · {}×{} · {:.1} fps · {} frames · {} dropped",
+                video.width, video.height, video.fps, video.frames_decoded, video.frames_dropped
             )));
             info_lines.push(Line::from(format!(
                 "Video updated {}",
-                video.timestamp.format("%H:%M:%S")
+                self.format_time(video.timestamp)
             )));
         }
         if info_lines.is_empty() {
@@ -1207,8 +2310,10 @@ impl EnhancedApp {
         let recording_status = if self.voice_recording {
             vec![
                 Line::from(""),
-                Line::from("🔴 RECORDING")
-                    .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Line::from("🔴 RECORDING").style(
+                    self.get_theme_tone_style(Tone::Error)
+                        .add_modifier(Modifier::BOLD),
+                ),
                 Line::from(""),
                 Line::from(format!(
                     "{} {}",
@@ -1225,9 +2330,27 @@ impl EnhancedApp {
                 Line::from(""),
                 Line::from("Press SPACE to start recording"),
                 Line::from("Press P to play last message"),
+                Line::from("↑/↓ select, S to save as WAV"),
             ]
         };
 
+        let recording_status = match self.voice_playback.as_ref() {
+            Some(playback) if !self.voice_recording => vec![
+                Line::from(""),
+                Line::from("▶ PLAYING").style(
+                    self.get_theme_tone_style(Tone::Success)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Line::from(""),
+                Line::from(format!(
+                    "{} {:.0}% of {:.1} s",
+                    visualize_audio_wave(playback.progress(), 30),
+                    playback.progress() * 100.0,
+                    playback.duration().as_secs_f32()
+                )),
+            ],
+            _ => recording_status,
+        };
         let controls = Paragraph::new(recording_status)
             .alignment(Alignment::Center)
             .block(
@@ -1239,7 +2362,15 @@ impl EnhancedApp {
         frame.render_widget(controls, chunks[0]);
 
         // Audio waveform visualization
-        let mut waveform_data: Vec<u64> = if self.voice_buffer.is_empty() {
+        let mut waveform_data: Vec<u64> = if let Some(playback) = self.voice_playback.as_ref() {
+            playback
+                .played_envelope()
+                .iter()
+                .rev()
+                .take(64)
+                .map(|value| u64::from(*value))
+                .collect()
+        } else if self.voice_buffer.is_empty() {
             vec![0; 64]
         } else {
             self.voice_buffer
@@ -1264,7 +2395,7 @@ impl EnhancedApp {
             )
             .data(&waveform_data)
             .max(255)
-            .style(Style::default().fg(Color::Cyan));
+            .style(self.get_theme_tone_style(Tone::Accent));
         let waveform_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
@@ -1281,23 +2412,22 @@ impl EnhancedApp {
 
         // Voice messages list
         let mut voice_messages = Vec::new();
-        for channel in &self.channels {
-            for entry in channel.messages.iter().rev() {
-                if let MessageContent::Voice(voice) = &entry.content {
-                    voice_messages.push(Line::from(format!(
-                        "🎵 {} ({} frames, {} ms)",
-                        self.get_friend_display_name(&entry.sender),
-                        voice.frames.len(),
-                        voice.duration_ms
-                    )));
-                    if voice_messages.len() >= 8 {
-                        break;
-                    }
-                }
-            }
-            if voice_messages.len() >= 8 {
-                break;
-            }
+        for (index, (timestamp, sender, voice)) in
+            self.recent_voice_messages().into_iter().enumerate()
+        {
+            let line = Line::from(format!(
+                "{}. 🎵 [{}] {} ({} frames, {} ms)",
+                index + 1,
+                self.format_time(timestamp),
+                self.get_friend_display_name(&sender),
+                voice.frames.len(),
+                voice.duration_ms
+            ));
+            voice_messages.push(if index == self.voice_selected {
+                line.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                line
+            });
         }
         if voice_messages.is_empty() {
             voice_messages.push(Line::from("No voice messages yet"));
@@ -1314,6 +2444,7 @@ impl EnhancedApp {
 
     fn render_friends(&mut self, frame: &mut UiFrame, area: Rect) {
         // Similar to original but with enhanced styling
+        self.clamp_friend_selection();
         let friends = self.state.friends();
         let items: Vec<ListItem> = if friends.is_empty() {
             vec![ListItem::new("No friends yet. Press 'a' to add.")]
@@ -1321,31 +2452,31 @@ impl EnhancedApp {
             friends
                 .iter()
                 .map(|friend| {
-                    let fallback = friend
-                        .alias
-                        .as_ref()
-                        .or(friend.handle.as_ref())
-                        .unwrap_or(&friend.user_id);
-                    let presence = self.presence.get(&friend.user_id);
+                    let name = self.get_friend_display_name(&friend.user_id);
+                    let presence = self.presence_of(&friend.user_id);
                     let online = presence
                         .map(|info| info.state == "online" && info.is_active())
                         .unwrap_or(false);
                     let status = if online { "🟢" } else { "⚫" };
-                    let mut label = format!("{} {}", status, fallback);
-                    if let Some(info) = presence {
-                        if let Some(name) = info.display_name.as_ref() {
-                            label.push_str(&format!(" · {}", name));
+                    let mut label = format!("{} {}", status, name);
+                    let other_names = [
+                        friend.alias.as_deref(),
+                        presence.and_then(|info| info.display_name.as_deref()),
+                    ];
+                    for other in other_names.into_iter().flatten() {
+                        if other != name {
+                            label.push_str(&format!(" · {}", other));
                         }
+                    }
+                    if let Some(info) = presence {
                         if let Some(id) = info.user_id.as_ref() {
                             label.push_str(&format!(" · {}", self.short_id(id)));
                         }
                         if info.avatar_url.is_some() {
                             label.push_str(" · 📸");
                         }
-                        label.push_str(&format!(
-                            " · updated {}",
-                            info.updated_at.format("%H:%M:%S")
-                        ));
+                        label
+                            .push_str(&format!(" · updated {}", self.format_time(info.updated_at)));
                     }
                     ListItem::new(label)
                 })
@@ -1355,43 +2486,277 @@ impl EnhancedApp {
         let list = List::new(items)
             .block(
                 Block::default()
-                    .title(" Friends ")
+                    .title(" Friends · a add · d remove · Enter message · r pull · p push ")
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded),
             )
             .highlight_style(
-                Style::default()
-                    .bg(self.get_theme_secondary_color())
+                self.get_theme_highlight_style()
                     .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+
+        frame.render_stateful_widget(list, area, &mut self.friends_state);
+    }
+
+    /// Держит выделение в пределах списка друзей (он меняется и из CLI/pull).
+    fn clamp_friend_selection(&mut self) {
+        let len = self.state.friends().len();
+        let selected = (len > 0).then(|| self.friends_state.selected().unwrap_or(0).min(len - 1));
+        self.friends_state.select(selected);
+    }
+
+    fn move_friend_selection(&mut self, up: bool) {
+        self.clamp_friend_selection();
+        if let Some(selected) = self.friends_state.selected() {
+            let last = self.state.friends().len() - 1;
+            let next = if up {
+                selected.saturating_sub(1)
+            } else {
+                (selected + 1).min(last)
+            };
+            self.friends_state.select(Some(next));
+        }
+    }
+
+    fn selected_friend(&self) -> Option<FriendEntry> {
+        self.friends_state
+            .selected()
+            .and_then(|idx| self.state.friends().get(idx))
+            .cloned()
+    }
+
+    fn handle_friend_prompt_key(&mut self, key: KeyEvent) {
+        let Some(input) = self.friend_prompt.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.friend_prompt = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                if let Some(input) = self.friend_prompt.take() {
+                    self.add_friend(&input);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_friend(&mut self, input: &str) {
+        let Some(entry) = FriendEntry::parse(input) else {
+            self.add_notification(
+                "Usage: <user_id> [@handle] [alias]".to_string(),
+                NotificationLevel::Warning,
             );
+            return;
+        };
+        let user_id = entry.user_id.clone();
+        let label = entry.label().to_string();
+        let added = self.state.upsert_friend(entry);
+        let position = self
+            .state
+            .friends()
+            .iter()
+            .position(|friend| friend.user_id == user_id);
+        self.friends_state.select(position);
+        self.persist_friends();
+        let verb = if added { "added" } else { "updated" };
+        self.add_notification(
+            format!("👫 Friend {} {}", label, verb),
+            NotificationLevel::Success,
+        );
+    }
 
-        let mut state = ListState::default();
-        frame.render_stateful_widget(list, area, &mut state);
+    fn request_friend_removal(&mut self) {
+        match self.selected_friend() {
+            Some(friend) => self.friend_remove_confirm = Some(friend),
+            None => {
+                self.add_notification("No friend selected".to_string(), NotificationLevel::Warning)
+            }
+        }
+    }
+
+    fn remove_friend(&mut self, friend: &FriendEntry) {
+        if self.state.remove_friend(&friend.user_id) {
+            self.clamp_friend_selection();
+            self.persist_friends();
+            self.add_notification(
+                format!("👫 Friend {} removed", friend.label()),
+                NotificationLevel::Info,
+            );
+        }
+    }
+
+    /// Открывает личный чат с выделенным другом.
+    async fn open_friend_chat(&mut self) -> Result<()> {
+        let Some(friend) = self.selected_friend() else {
+            self.add_notification("No friend selected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        };
+        if !self.connected {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+        self.open_direct_chat(&friend.user_id, friend.label().to_string())
+            .await
+    }
+
+    /// REST-клиент и токен для синхронизации друзей; без них — предупреждение.
+    fn friends_sync_session(&mut self) -> Option<(RestClient, String)> {
+        let Some(client) = self.rest_client.clone() else {
+            self.add_notification(
+                "REST client unavailable".to_string(),
+                NotificationLevel::Warning,
+            );
+            return None;
+        };
+        let Some(session) = self.session_id.clone() else {
+            self.add_notification(
+                "No active session: /connect before syncing friends".to_string(),
+                NotificationLevel::Warning,
+            );
+            return None;
+        };
+        Some((client, session))
+    }
+
+    /// Отправляет локальный список друзей на сервер, как `friends push` в CLI.
+    async fn push_friends(&mut self) {
+        let Some((client, session)) = self.friends_sync_session() else {
+            return;
+        };
+        let payload = friends_to_payload(self.state.friends());
+        match client.update_friends(&session, &payload).await {
+            Ok(()) => self.add_notification(
+                format!("👫 Pushed {} friends to the server", payload.len()),
+                NotificationLevel::Success,
+            ),
+            Err(err) => self.add_notification(
+                format!("Friends push failed: {}", rest_error_text(&err)),
+                NotificationLevel::Error,
+            ),
+        }
+    }
+
+    /// Заменяет локальный список серверным, как `friends pull` в CLI.
+    async fn pull_friends(&mut self) {
+        let Some((client, session)) = self.friends_sync_session() else {
+            return;
+        };
+        match client.list_friends(&session).await {
+            Ok(remote) => {
+                let entries: Vec<FriendEntry> =
+                    remote.into_iter().map(friend_from_payload).collect();
+                let count = entries.len();
+                self.state.set_friends(entries);
+                self.clamp_friend_selection();
+                self.persist_friends();
+                self.add_notification(
+                    format!("👫 Pulled {} friends from the server", count),
+                    NotificationLevel::Success,
+                );
+            }
+            Err(err) => self.add_notification(
+                format!("Friends pull failed: {}", rest_error_text(&err)),
+                NotificationLevel::Error,
+            ),
+        }
+    }
+
+    /// Сохраняет список друзей поверх актуального файла состояния.
+    fn persist_friends(&mut self) {
+        let friends = self.state.friends().to_vec();
+        let result = ClientState::load().and_then(|mut stored| {
+            stored.set_friends(friends);
+            stored.save()
+        });
+        if let Err(err) = result {
+            self.add_notification(
+                format!("Failed to save friends: {}", err),
+                NotificationLevel::Warning,
+            );
+        }
     }
 
     fn render_devices(&mut self, frame: &mut UiFrame, area: Rect) {
+        let certificate = match self.state.certificate_status() {
+            CertificateStatus::Valid => Line::styled(
+                "🔏 Certificate: valid",
+                self.get_theme_tone_style(Tone::Success),
+            ),
+            CertificateStatus::ExpiringSoon(left) => Line::styled(
+                format!("⏳ Certificate expires in {}", format_remaining(left)),
+                self.get_theme_tone_style(Tone::Warning)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            CertificateStatus::Expired => Line::styled(
+                "⛔ Certificate EXPIRED",
+                self.get_theme_tone_style(Tone::Error)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            CertificateStatus::None => Line::styled(
+                "🔓 Certificate: none",
+                self.get_theme_tone_style(Tone::Muted),
+            ),
+        };
         let mut lines = vec![
             Line::from(format!(
                 "📱 Current device: {}",
                 short_hex(&self.state.device_id)
             )),
-            Line::from(""),
+            certificate,
+            match self.server_caps.as_ref() {
+                Some(caps) => Line::from(format!("🧩 Server: {}", caps.summary())),
+                None => Line::styled(
+                    "🧩 Server capabilities: unknown (connect to load)",
+                    self.get_theme_tone_style(Tone::Muted),
+                ),
+            },
         ];
+        lines.extend(self.pairing_lines());
+        lines.push(Line::from(""));
         if self.devices.is_empty() {
             lines.push(Line::from("No devices loaded. Press 'r' to refresh."));
         } else {
-            for entry in &self.devices {
-                lines.push(Line::from(format!(
+            if let Some(cached_at) = self.devices_cached_at {
+                lines.push(Line::styled(
+                    format!(
+                        "📦 Offline, cached at {}",
+                        cached_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    ),
+                    self.get_theme_tone_style(Tone::Muted),
+                ));
+            }
+            lines.push(Line::from(format!(
+                "📊 {} · filter: {}",
+                device_status_summary(&self.devices),
+                self.devices_filter.unwrap_or("all")
+            )));
+            for (index, entry) in self.visible_devices().into_iter().enumerate() {
+                let line = Line::from(format!(
                     "{} {} [{}] created {}",
                     if entry.current { "⭐" } else { "•" },
                     short_hex(&entry.device_id),
                     entry.status,
                     entry.created_at
-                )));
+                ));
+                lines.push(if index == self.devices_selected {
+                    line.style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                });
             }
         }
         lines.push(Line::from(""));
         lines.push(Line::from("Press 'r' to refresh devices"));
+        lines.push(Line::from("Press 'f' to cycle the status filter"));
+        lines.push(Line::from("Press ↑/↓ to select, 'x' to revoke"));
+        if self.devices_cursor.is_some() {
+            lines.push(Line::from("Press PageDown to load more"));
+        }
 
         let devices = Paragraph::new(lines).block(
             Block::default()
@@ -1403,7 +2768,7 @@ impl EnhancedApp {
     }
 
     fn render_settings(&mut self, frame: &mut UiFrame, area: Rect) {
-        let settings = vec![
+        let mut settings = vec![
             Line::from(format!("🎨 Theme: {:?}", self.theme)),
             Line::from(format!(
                 "✨ Animations: {}",
@@ -1417,13 +2782,50 @@ impl EnhancedApp {
                 "😊 Emoji mode: {}",
                 if self.emoji_mode { "ON" } else { "OFF" }
             )),
+            Line::from(format!(
+                "📝 Markdown: {}",
+                if self.markdown_enabled { "ON" } else { "OFF" }
+            )),
+            Line::from(format!(
+                "🔌 Connect on launch: {}",
+                if self.state.auto_connect { "ON" } else { "OFF" }
+            )),
+            Line::from(format!(
+                "🐱 Splash screen: {}",
+                if self.state.show_splash { "ON" } else { "OFF" }
+            )),
+            Line::from(format!("🕒 Timestamps: {}", self.state.time_style.as_str())),
+            Line::from(format!(
+                "🏷 Friend names: {}",
+                if self.state.prefer_server_names {
+                    "server name first"
+                } else {
+                    "your alias first"
+                }
+            )),
+            Line::from(format!(
+                "🟢 Presence notifications: {}",
+                if self.state.presence_verbose {
+                    "EVERY CHANGE"
+                } else {
+                    "SUMMARIZED"
+                }
+            )),
+            Line::from(format!(
+                "🔕 Mentions in muted channels: {}",
+                if self.state.mentions_break_mute {
+                    "NOTIFY"
+                } else {
+                    "SILENT"
+                }
+            )),
             Line::from(""),
-            Line::from("Press 't' to change theme"),
-            Line::from("Press 'a' to toggle animations"),
-            Line::from("Press 's' to toggle sound"),
-            Line::from("Press 'e' to toggle emoji mode"),
-            Line::from("Press Ctrl+F8 to open this view"),
         ];
+        settings.extend(
+            help::bindings_for("Settings")
+                .map(|binding| Line::from(format!("{} - {}", binding.keys, binding.description))),
+        );
+        settings.push(Line::from("Ctrl+F8 - Open this view"));
 
         let settings_widget = Paragraph::new(settings).block(
             Block::default()
@@ -1442,7 +2844,7 @@ impl EnhancedApp {
 
         // Input field
         let input = Paragraph::new(format!("> {}", self.input))
-            .style(Style::default().fg(Color::White))
+            .style(self.get_theme_tone_style(Tone::Text))
             .block(
                 Block::default()
                     .title(format!(" Input - {} ", self.get_view_name()))
@@ -1469,85 +2871,859 @@ impl EnhancedApp {
     }
 
     fn render_status_bar(&self, frame: &mut UiFrame, area: Rect) {
+        if let Some((_, from)) = self.pending_incoming_call() {
+            let banner = Paragraph::new(format!(
+                " 📞 Incoming call from {} — [a]ccept / [x] reject (Calls view, 3) ",
+                self.get_friend_display_name(&from)
+            ))
+            .style(self.get_theme_banner_style(Tone::Warning));
+            frame.render_widget(banner, area);
+            return;
+        }
+
+        let mentions: usize = self.channels.iter().map(|c| c.unread_mentions).sum();
+        let mentions = if mentions > 0 {
+            format!(" | 📣 {} mention(s)", mentions)
+        } else {
+            String::new()
+        };
+        let flow = self.flow_window.recent();
+        let mentions = if flow.is_empty() {
+            mentions
+        } else {
+            format!(
+                "{} | ⚠ dropped {} media, merged {} updates",
+                mentions, flow.media_dropped, flow.coalesced
+            )
+        };
         let status = format!(
-            " {} | Device: {} | Server: {} | Session: {} | F1: Help | Ctrl+F10: Quit ",
+            " {}{} | Device: {} | Server: {} | Session: {} | F1: Help | F10: Quit ",
             if self.connected {
                 "🟢 Online"
             } else {
-                "🔴 Offline"
+                "🔴 Offline — /connect to go online"
             },
-            &self.state.device_id[..8],
+            mentions,
+            self.state
+                .device_id
+                .get(..8)
+                .unwrap_or(&self.state.device_id),
             self.state.server_url,
-            self.session_id.as_ref().map(|s| &s[..8]).unwrap_or("none")
+            self.session_id
+                .as_deref()
+                .map(|s| s.get(..8).unwrap_or(s))
+                .unwrap_or("none")
         );
 
-        let status_bar = Paragraph::new(status).style(
-            Style::default()
-                .bg(self.get_theme_secondary_color())
-                .fg(Color::White),
-        );
+        // Отключённая проверка TLS видна весь сеанс, а не только в уведомлении.
+        let mut spans = Vec::new();
+        if self.state.insecure {
+            spans.push(Span::styled(
+                " ⚠ INSECURE ",
+                self.get_theme_banner_style(Tone::Error),
+            ));
+        }
+        spans.push(Span::raw(status));
+        let status_bar = Paragraph::new(Line::from(spans)).style(self.get_theme_status_bar_style());
         frame.render_widget(status_bar, area);
     }
 
-    fn render_notifications(&mut self, frame: &mut UiFrame, area: Rect) {
-        let notifications = &self.notifications;
-        if notifications.is_empty() {
+    fn render_reaction_picker(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(picker) = self.reaction_picker.as_ref() else {
             return;
-        }
-
-        let notification_area = Rect {
-            x: area.width.saturating_sub(40),
-            y: 4,
-            width: 38.min(area.width),
-            height: (notifications.len() as u16 * 3).min(12),
         };
-
-        frame.render_widget(Clear, notification_area);
-
-        let mut y_offset = 0;
-        for notification in notifications.iter().take(4) {
-            let color = match notification.level {
-                NotificationLevel::Info => Color::Blue,
-                NotificationLevel::Success => Color::Green,
-                NotificationLevel::Warning => Color::Yellow,
-                NotificationLevel::Error => Color::Red,
-            };
-
-            let timestamp = notification.timestamp.format("%H:%M:%S").to_string();
-            let notification_widget =
-                Paragraph::new(format!("[{}] {}", timestamp, notification.message))
-                    .style(Style::default().fg(color))
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .border_type(BorderType::Rounded)
-                            .border_style(Style::default().fg(color)),
-                    );
-
-            let notification_rect = Rect {
-                x: notification_area.x,
-                y: notification_area.y + y_offset,
-                width: notification_area.width,
-                height: 3,
-            };
-
-            frame.render_widget(notification_widget, notification_rect);
-            y_offset += 3;
-        }
+        let width = 40.min(area.width);
+        let height = (KAWAII_REACTIONS.len() as u16 + 3).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let mut items: Vec<ListItem> = KAWAII_REACTIONS
+            .iter()
+            .map(|(name, face, emoji)| ListItem::new(format!("{} {} :{}:", emoji, face, name)))
+            .collect();
+        items.push(ListItem::new(format!("> {}", picker.input)));
+        let mut list_state = ListState::default();
+        list_state.select(Some(picker.selected));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(" React (↑/↓ or :name:, Enter) ")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(self.get_theme_border_style()),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut list_state);
     }
 
-    // Helper methods
-    fn get_view_name(&self) -> &str {
-        match self.view {
-            AppView::Chat => "Chat",
-            AppView::Groups => "Groups",
-            AppView::Calls => "Calls",
-            AppView::Voice => "Voice",
-            AppView::Friends => "Friends",
-            AppView::Devices => "Devices",
-            AppView::Settings => "Settings",
-            _ => "CommuCat",
-        }
+    fn render_call_prompt(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(prompt) = self.call_prompt.as_ref() else {
+            return;
+        };
+        let width = 60.min(area.width);
+        let height = 3.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let title = if prompt.video {
+            " 📹 Video call to (comma separated, Enter) "
+        } else {
+            " 📞 Call to (comma separated, Enter) "
+        };
+        let input = Paragraph::new(format!("> {}", prompt.input)).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(input, popup);
+    }
+
+    fn render_friend_prompt(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(input) = self.friend_prompt.as_ref() else {
+            return;
+        };
+        let width = 60.min(area.width);
+        let height = 3.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let prompt = Paragraph::new(format!("> {}", input)).block(
+            Block::default()
+                .title(" 👫 Add friend: <user_id> [@handle] [alias] ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(prompt, popup);
+    }
+
+    fn render_friend_remove_confirm(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(friend) = self.friend_remove_confirm.as_ref() else {
+            return;
+        };
+        let width = 60.min(area.width);
+        let height = 4.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let lines = vec![
+            Line::from(format!("{} ({})", friend.label(), friend.user_id)),
+            Line::styled(
+                "[y] remove · any other key cancels",
+                self.get_theme_tone_style(Tone::Warning),
+            ),
+        ];
+        let dialog = Paragraph::new(lines).block(
+            Block::default()
+                .title(" ⚠ Remove friend? ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_tone_style(Tone::Error)),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    fn render_revoke_confirm(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(entry) = self.revoke_confirm.as_ref() else {
+            return;
+        };
+        let width = 60.min(area.width);
+        let height = 5.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let lines = vec![
+            Line::from(format!(
+                "Device {} [{}]",
+                short_hex(&entry.device_id),
+                entry.status
+            )),
+            Line::from(format!("Created {}", entry.created_at)),
+            Line::styled(
+                "[y] revoke · any other key cancels",
+                self.get_theme_tone_style(Tone::Warning),
+            ),
+        ];
+        let dialog = Paragraph::new(lines).block(
+            Block::default()
+                .title(" ⚠ Revoke device? ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_tone_style(Tone::Error)),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    fn render_quit_confirm(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(reasons) = self.quit_confirm.as_ref() else {
+            return;
+        };
+        let width = 60.min(area.width);
+        let height = (reasons.len() as u16 + 3).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let mut lines: Vec<Line> = reasons
+            .iter()
+            .map(|reason| Line::from(format!("• {}", reason)))
+            .collect();
+        lines.push(Line::styled(
+            "[y]/Enter quit · any other key cancels",
+            self.get_theme_tone_style(Tone::Warning),
+        ));
+        let dialog = Paragraph::new(lines).block(
+            Block::default()
+                .title(" ⚠ Quit CommuCat? ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_tone_style(Tone::Error)),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    /// Что потеряется при выходе; пустой список — можно выходить без вопросов.
+    fn quit_blockers(&self) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+        if self.active_call.is_some() {
+            reasons.push("A call is in progress and will be hung up");
+        }
+        if self.voice_recording {
+            reasons.push("A voice message is being recorded and will be lost");
+        }
+        if !self.input.trim().is_empty() {
+            reasons.push("The input line has an unsent draft");
+        }
+        reasons
+    }
+
+    fn request_quit(&mut self) {
+        let reasons = self.quit_blockers();
+        if reasons.is_empty() {
+            self.should_quit = true;
+        } else {
+            self.quit_confirm = Some(reasons);
+        }
+    }
+
+    fn render_pair_ticket(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(ticket) = self.pair_ticket.as_ref() else {
+            return;
+        };
+        let mut lines = vec![
+            Line::styled(
+                format!("Code: {}", ticket.pair_code),
+                self.get_theme_tone_style(Tone::Accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Line::from(format!("Expires: {}", ticket.expires_at)),
+            Line::from(format!("Seed: {}", ticket.device_seed)),
+        ];
+        let rendered = qr::pair_uri(
+            &ticket.pair_code,
+            &self.state.server_url,
+            &self.state.domain,
+        )
+        .and_then(|link| qr::render_terminal(&link));
+        match rendered {
+            Ok(code) => lines.extend(code.lines().map(|row| Line::from(row.to_string()))),
+            Err(err) => lines.push(Line::from(format!("QR unavailable: {}", err))),
+        }
+        lines.push(Line::styled(
+            "QR holds a commucat://pair link for `open` · any key closes",
+            self.get_theme_tone_style(Tone::Muted),
+        ));
+        let content_width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16;
+        let width = (content_width + 4).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let dialog = Paragraph::new(lines).block(
+            Block::default()
+                .title(" 🎟 Pair code issued ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    /// Ссылка для друзей; без user_id (профиль без входа) её нет.
+    fn my_friend_link(&self) -> Option<String> {
+        let user_id = self.state.user_id.as_deref()?;
+        qr::friend_uri(
+            user_id,
+            self.state.user_handle.as_deref(),
+            &self.state.server_url,
+        )
+        .ok()
+    }
+
+    fn handle_my_info_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('c') => match self.my_friend_link() {
+                Some(link) => self.copy_to_clipboard(link, "Friend link"),
+                None => self.add_notification(
+                    "No user_id in the profile yet".to_string(),
+                    NotificationLevel::Warning,
+                ),
+            },
+            KeyCode::Char('u') => match self.state.user_id.clone() {
+                Some(user_id) => self.copy_to_clipboard(user_id, "User id"),
+                None => self.add_notification(
+                    "No user_id in the profile yet".to_string(),
+                    NotificationLevel::Warning,
+                ),
+            },
+            KeyCode::Char('q') => self.my_info_qr = !self.my_info_qr,
+            _ => self.show_my_info = false,
+        }
+    }
+
+    fn render_my_info(&self, frame: &mut UiFrame, area: Rect) {
+        if !self.show_my_info {
+            return;
+        }
+        let unknown = || "—".to_string();
+        let field = |name: &str, value: String| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<10}", name),
+                    self.get_theme_tone_style(Tone::Muted),
+                ),
+                Span::styled(value, self.get_theme_tone_style(Tone::Accent)),
+            ])
+        };
+        let link = self.my_friend_link();
+        let mut lines = vec![
+            field(
+                "user_id",
+                self.state.user_id.clone().unwrap_or_else(unknown),
+            ),
+            field(
+                "handle",
+                self.state
+                    .user_handle
+                    .as_ref()
+                    .map(|handle| format!("@{}", handle))
+                    .unwrap_or_else(unknown),
+            ),
+            field("device_id", self.state.device_id.clone()),
+            field("server", self.state.server_url.clone()),
+            field("link", link.clone().unwrap_or_else(unknown)),
+        ];
+        if self.my_info_qr
+            && let Some(link) = link.as_deref()
+        {
+            match qr::render_terminal(link) {
+                Ok(code) => lines.extend(code.lines().map(|row| Line::from(row.to_string()))),
+                Err(err) => lines.push(Line::from(format!("QR unavailable: {}", err))),
+            }
+        }
+        lines.push(Line::styled(
+            "c copy link · u copy user_id · q QR · any other key closes",
+            self.get_theme_tone_style(Tone::Muted),
+        ));
+        let content_width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16;
+        let width = (content_width + 4).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let dialog = Paragraph::new(lines).block(
+            Block::default()
+                .title(" 🪪 My info ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    fn render_raw_view(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(raw) = self.raw_view.as_deref() else {
+            return;
+        };
+        let width = (area.width * 3 / 4).max(40).min(area.width);
+        let height = (raw.lines().count() as u16 + 3)
+            .max(5)
+            .min(area.height.saturating_sub(2));
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let mut lines: Vec<Line> = raw
+            .lines()
+            .map(|line| Line::from(line.to_string()))
+            .collect();
+        lines.push(Line::styled(
+            "y copy · any other key closes",
+            self.get_theme_tone_style(Tone::Muted),
+        ));
+        let dialog = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title(" 🔍 Raw payload ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(dialog, popup);
+    }
+
+    fn render_engine_log(&self, frame: &mut UiFrame, area: Rect) {
+        if !self.show_engine_log {
+            return;
+        }
+        let popup = Rect {
+            x: area.x + 2.min(area.width),
+            y: area.y + 1.min(area.height),
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(2),
+        };
+        let height = popup.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self
+            .engine_log
+            .visible(height)
+            .map(|entry| {
+                let style = self.get_theme_style(Style::default().fg(entry.level.color()));
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{} ",
+                            entry.timestamp.with_timezone(&Local).format("%H:%M:%S")
+                        ),
+                        self.get_theme_tone_style(Tone::Muted),
+                    ),
+                    Span::styled(format!("{} ", entry.level.label()), style),
+                    Span::styled(entry.line.clone(), style),
+                ])
+            })
+            .collect();
+        let mode = if self.engine_log.is_following() {
+            "following"
+        } else {
+            "scrolled · End follows"
+        };
+        let log = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(
+                    " 📜 Engine log ({}) · {} · ↑↓ PgUp PgDn Home · Esc/F12 close ",
+                    self.engine_log.len(),
+                    mode
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(self.get_theme_border_style()),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(log, popup);
+    }
+
+    fn handle_engine_log_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::F(12) => self.show_engine_log = false,
+            KeyCode::Up | KeyCode::Char('k') => self.engine_log.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => self.engine_log.scroll_down(1),
+            KeyCode::PageUp => self.engine_log.scroll_up(ENGINE_LOG_PAGE),
+            KeyCode::PageDown => self.engine_log.scroll_down(ENGINE_LOG_PAGE),
+            KeyCode::Home => self.engine_log.scroll_to_top(),
+            KeyCode::End => self.engine_log.follow(),
+            _ => {}
+        }
+    }
+
+    /// Строки справки: клавиши по разделам из `help::KEYBINDINGS` и slash-команды.
+    fn help_lines(&self) -> Vec<Line<'static>> {
+        let heading = Style::default()
+            .fg(self.get_theme_primary_color())
+            .add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        for context in help::CONTEXTS {
+            lines.push(Line::from(Span::styled(
+                format!("{} keys", context),
+                heading,
+            )));
+            for binding in help::bindings_for(context) {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("  {:<20}", binding.keys),
+                        self.get_theme_tone_style(Tone::Warning),
+                    ),
+                    Span::raw(binding.description),
+                ]));
+            }
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled("Commands", heading)));
+        for spec in palette::COMMANDS {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  /{} {}", spec.name, spec.args),
+                    self.get_theme_tone_style(Tone::Warning),
+                ),
+                Span::styled(
+                    format!(" — {}", spec.description),
+                    self.get_theme_tone_style(Tone::Dim),
+                ),
+            ]));
+        }
+        lines
+    }
+
+    /// Оверлей длительных операций прямо над строкой ввода.
+    fn render_tasks(&self, frame: &mut UiFrame, input: Rect) {
+        if self.tasks.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let count = self.tasks.iter().count() as u16;
+        let height = (count * 2 + 2).min(input.y);
+        let width = 56.min(input.width);
+        let area = Rect {
+            x: input.x + input.width.saturating_sub(width),
+            y: input.y.saturating_sub(height),
+            width,
+            height,
+        };
+        if area.height < 4 {
+            return;
+        }
+        frame.render_widget(Clear, area);
+        let block = Block::default()
+            .title(" ⏳ Tasks · Esc to cancel ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(self.get_theme_border_style());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(2); count as usize])
+            .split(inner);
+        for (task, row) in self.tasks.iter().zip(rows.iter()) {
+            let lines = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Length(1)])
+                .split(*row);
+            let (title, bar) = (lines[0], lines[1]);
+            frame.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled(task.label.clone(), Style::default().bold()),
+                    Span::raw("  "),
+                    Span::styled(task.summary(now), self.get_theme_tone_style(Tone::Muted)),
+                ])),
+                title,
+            );
+            let gauge = match task.ratio() {
+                Some(ratio) => Gauge::default()
+                    .gauge_style(self.get_theme_gauge_style())
+                    .ratio(ratio)
+                    .label(format!("{:.0}%", ratio * 100.0)),
+                // Объём неизвестен: бегущий индикатор по счётчику кадров.
+                None => Gauge::default()
+                    .gauge_style(self.get_theme_gauge_style())
+                    .ratio((self.frame_counter % 20) as f64 / 20.0)
+                    .label("…"),
+            };
+            frame.render_widget(gauge, bar);
+        }
+    }
+
+    fn render_help(&self, frame: &mut UiFrame, area: Rect) {
+        if !self.show_help {
+            return;
+        }
+        let width = area.width.saturating_sub(8).min(90);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + 1.min(area.height),
+            width,
+            height: area.height.saturating_sub(2),
+        };
+        let lines = self.help_lines();
+        let visible = popup.height.saturating_sub(2);
+        let max_scroll = (lines.len() as u16).saturating_sub(visible);
+        let help = Paragraph::new(lines)
+            .scroll((self.help_scroll.min(max_scroll), 0))
+            .block(
+                Block::default()
+                    .title(" ❔ Help · ↑↓ PgUp PgDn · Esc/F1 close ")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(self.get_theme_border_style()),
+            );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(help, popup);
+    }
+
+    fn handle_help_key(&mut self, key: KeyEvent) {
+        let last = self.help_lines().len().saturating_sub(1) as u16;
+        self.help_scroll = match key.code {
+            KeyCode::Esc | KeyCode::F(1) => {
+                self.show_help = false;
+                return;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.help_scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.help_scroll + 1,
+            KeyCode::PageUp => self.help_scroll.saturating_sub(HELP_PAGE),
+            KeyCode::PageDown => self.help_scroll + HELP_PAGE,
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            _ => self.help_scroll,
+        }
+        .min(last);
+    }
+
+    fn push_engine_log(&mut self, level: LogLevel, line: String) {
+        self.engine_log.push(level, line, Utc::now());
+    }
+
+    fn render_search(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(search) = self.search.as_ref() else {
+            return;
+        };
+        let width = area.width.saturating_sub(8).min(100);
+        let height = area
+            .height
+            .saturating_sub(6)
+            .min(search.hits.len() as u16 + 2);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let items: Vec<ListItem> = search
+            .hits
+            .iter()
+            .map(|hit| ListItem::new(hit.label.clone()))
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(Some(search.selected));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!(
+                        " Search \"{}\" — {} matches (↑/↓, Enter, Esc) ",
+                        search.query,
+                        search.hits.len()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(self.get_theme_border_style()),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut list_state);
+    }
+
+    fn render_palette(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(palette) = self.palette.as_ref() else {
+            return;
+        };
+        let width = area.width.saturating_sub(8).min(90);
+        let height = area
+            .height
+            .saturating_sub(4)
+            .min(palette.matches.len().max(1) as u16 + 4);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 3,
+            width,
+            height,
+        };
+        let block = Block::default()
+            .title(" ⌘ Commands (type to filter, ↑/↓, Enter, Esc) ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(self.get_theme_border_style());
+        let inner = block.inner(popup);
+        frame.render_widget(Clear, popup);
+        frame.render_widget(block, popup);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(1)])
+            .split(inner);
+        let query = Paragraph::new(Line::from(vec![
+            Span::styled("› ", Style::default().fg(self.get_theme_primary_color())),
+            Span::raw(palette.query.clone()),
+            Span::styled("▏", self.get_theme_tone_style(Tone::Dim)),
+        ]))
+        .block(Block::default().borders(Borders::BOTTOM));
+        frame.render_widget(query, chunks[0]);
+
+        if palette.matches.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No matching commands").style(self.get_theme_tone_style(Tone::Dim)),
+                chunks[1],
+            );
+            return;
+        }
+        let items: Vec<ListItem> = palette
+            .matches
+            .iter()
+            .map(|idx| {
+                let entry = &palette.entries[*idx];
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<18}", entry.label),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(" {:<36}", entry.description),
+                        self.get_theme_tone_style(Tone::Dim),
+                    ),
+                    Span::styled(
+                        format!(" {}", entry.hint),
+                        self.get_theme_tone_style(Tone::Warning),
+                    ),
+                ]))
+            })
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(Some(palette.selected));
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, chunks[1], &mut list_state);
+    }
+
+    fn render_url_picker(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(urls) = self.url_picker.as_ref() else {
+            return;
+        };
+        let width = area.width.saturating_sub(8).min(80);
+        let height = (urls.len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width.saturating_sub(width)) / 2,
+            y: area.y + (area.height.saturating_sub(height)) / 2,
+            width,
+            height,
+        };
+        let lines: Vec<Line> = urls
+            .iter()
+            .enumerate()
+            .map(|(idx, url)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{}. ", idx + 1),
+                        self.get_theme_tone_style(Tone::Warning),
+                    ),
+                    Span::styled(url.clone(), link_style()),
+                ])
+            })
+            .collect();
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(" Open link (1-9, Esc) ")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(self.get_theme_border_style()),
+            ),
+            popup,
+        );
+    }
+
+    fn render_notifications(&mut self, frame: &mut UiFrame, area: Rect) {
+        let notifications = &self.notifications;
+        if notifications.is_empty() {
+            return;
+        }
+
+        let notification_area = Rect {
+            x: area.width.saturating_sub(40),
+            y: 4,
+            width: 38.min(area.width),
+            height: (notifications.len() as u16 * 3).min(12),
+        }
+        .intersection(area);
+        if notification_area.is_empty() {
+            return;
+        }
+
+        frame.render_widget(Clear, notification_area);
+
+        let mut y_offset = 0;
+        for notification in notifications.iter().take(4) {
+            let style = self.get_theme_tone_style(notification.level.into());
+
+            let timestamp = self.format_time(notification.timestamp);
+            let notification_widget =
+                Paragraph::new(format!("[{}] {}", timestamp, notification.message))
+                    .style(style)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .border_style(style),
+                    );
+
+            let notification_rect = Rect {
+                x: notification_area.x,
+                y: notification_area.y.saturating_add(y_offset),
+                width: notification_area.width,
+                height: 3,
+            }
+            .intersection(notification_area);
+            if notification_rect.height < 3 {
+                break;
+            }
+
+            frame.render_widget(notification_widget, notification_rect);
+            y_offset += 3;
+        }
+    }
+
+    // Helper methods
+    fn get_view_name(&self) -> &str {
+        match self.view {
+            AppView::Chat => "Chat",
+            AppView::Groups => "Groups",
+            AppView::Calls => "Calls",
+            AppView::Voice => "Voice",
+            AppView::Friends => "Friends",
+            AppView::Devices => "Devices",
+            AppView::Settings => "Settings",
+            _ => "CommuCat",
+        }
     }
 
     fn get_view_icon(&self) -> &str {
@@ -1569,6 +3745,7 @@ impl EnhancedApp {
             Theme::Light => Color::Blue,
             Theme::Cyberpunk => Color::Magenta,
             Theme::Kawaii => Color::LightMagenta,
+            Theme::Mono => Color::Reset,
         }
     }
 
@@ -1578,6 +3755,16 @@ impl EnhancedApp {
             Theme::Light => Color::Gray,
             Theme::Cyberpunk => Color::Rgb(64, 0, 128),
             Theme::Kawaii => Color::Rgb(255, 192, 203),
+            Theme::Mono => Color::Reset,
+        }
+    }
+
+    /// Выбранный пункт списка и упоминание. У монохромной темы нет фона,
+    /// поэтому она выделяет инверсией.
+    fn get_theme_highlight_style(&self) -> Style {
+        match self.theme {
+            Theme::Mono => Style::default().add_modifier(Modifier::REVERSED),
+            _ => Style::default().bg(self.get_theme_secondary_color()),
         }
     }
 
@@ -1585,37 +3772,285 @@ impl EnhancedApp {
         Style::default().fg(self.get_theme_primary_color())
     }
 
+    fn get_theme_tone_color(&self, tone: Tone) -> Color {
+        if self.theme == Theme::Mono {
+            return Color::Reset;
+        }
+        match tone {
+            Tone::Muted => Color::DarkGray,
+            Tone::Dim => Color::Gray,
+            Tone::Text => Color::White,
+            Tone::Accent => Color::Cyan,
+            Tone::Highlight => Color::Magenta,
+            Tone::Splash => Color::LightMagenta,
+            Tone::Mention => Color::LightRed,
+            Tone::Info => Color::Blue,
+            Tone::Success => Color::Green,
+            Tone::Warning => Color::Yellow,
+            Tone::Error => Color::Red,
+        }
+    }
+
+    /// Текст нужной роли. Монохромная тема вместо цвета выделяет
+    /// предупреждения и ошибки жирным.
+    fn get_theme_tone_style(&self, tone: Tone) -> Style {
+        let style = Style::default().fg(self.get_theme_tone_color(tone));
+        match tone {
+            Tone::Mention | Tone::Warning | Tone::Error if self.theme == Theme::Mono => {
+                style.add_modifier(Modifier::BOLD)
+            }
+            _ => style,
+        }
+    }
+
+    /// Плашка во всю строку (входящий звонок, INSECURE): цветной фон,
+    /// в монохромной теме — инверсия.
+    fn get_theme_banner_style(&self, tone: Tone) -> Style {
+        let style = match self.theme {
+            Theme::Mono => Style::default().add_modifier(Modifier::REVERSED),
+            _ => {
+                let fg = match tone {
+                    Tone::Warning | Tone::Success | Tone::Accent => Color::Black,
+                    _ => Color::White,
+                };
+                Style::default().bg(self.get_theme_tone_color(tone)).fg(fg)
+            }
+        };
+        style.add_modifier(Modifier::BOLD)
+    }
+
+    fn get_theme_status_bar_style(&self) -> Style {
+        match self.theme {
+            Theme::Mono => Style::default(),
+            _ => Style::default()
+                .bg(self.get_theme_secondary_color())
+                .fg(Color::White),
+        }
+    }
+
+    /// Полоса прогресса; без цветов `Gauge` рисует её символами цвета текста.
+    fn get_theme_gauge_style(&self) -> Style {
+        match self.theme {
+            Theme::Mono => Style::default(),
+            _ => Style::default().fg(Color::Cyan).bg(Color::Black),
+        }
+    }
+
+    /// Логотип заставки проявляется из чёрного по мере загрузки.
+    fn get_theme_logo_style(&self, progress: f32) -> Style {
+        match self.theme {
+            Theme::Mono => Style::default().add_modifier(Modifier::BOLD),
+            _ => {
+                let alpha = (progress.clamp(0.0, 1.0) * 255.0) as u8;
+                Style::default().fg(Color::Rgb(0, alpha, alpha))
+            }
+        }
+    }
+
+    /// Стиль, собранный вне темы (Markdown, журнал, качество связи), в
+    /// монохромной теме: цветной фон становится инверсией, красный и жёлтый
+    /// текст — жирным, остальные цвета сбрасываются.
+    fn get_theme_style(&self, style: Style) -> Style {
+        if self.theme != Theme::Mono {
+            return style;
+        }
+        let mut mono = Style {
+            fg: None,
+            bg: None,
+            ..style
+        };
+        if style.bg.is_some_and(|bg| bg != Color::Reset) {
+            mono = mono.add_modifier(Modifier::REVERSED);
+        }
+        if matches!(
+            style.fg,
+            Some(Color::Red | Color::LightRed | Color::Yellow | Color::LightYellow)
+        ) {
+            mono = mono.add_modifier(Modifier::BOLD);
+        }
+        mono
+    }
+
     fn get_friend_display_name(&self, device_id: &str) -> String {
-        if device_id == self.state.device_id {
+        if self.is_me(device_id) {
             return "You".to_string();
         }
 
-        if let Some(info) = self.presence.get(device_id) {
-            if let Some(name) = info.display_name.as_ref() {
-                return name.clone();
+        let user_id = self.identities.user_of(device_id);
+        let presence = self.presence_of(device_id);
+        let friend = self.state.friends().iter().find(|f| f.user_id == user_id);
+        let profile = self
+            .profiles
+            .get(device_id)
+            .or_else(|| self.profiles.get(user_id));
+        let server_name = presence
+            .and_then(|info| info.display_name.as_deref())
+            .or_else(|| profile.and_then(|user| user.display_name.as_deref()));
+        let handle = presence
+            .and_then(|info| info.handle.as_deref())
+            .or_else(|| friend.and_then(|f| f.handle.as_deref()))
+            .or_else(|| profile.map(|user| user.handle.as_str()));
+        pick_display_name(
+            friend.and_then(|f| f.alias.as_deref()),
+            server_name,
+            handle,
+            self.state.prefer_server_names,
+        )
+        .unwrap_or(device_id)
+        .to_string()
+    }
+
+    /// Ставит неизвестного собеседника в очередь на загрузку профиля.
+    fn queue_profile_lookup(&mut self, id: &str) {
+        if id == "unknown" || id == self.state.device_id || self.rest_client.is_none() {
+            return;
+        }
+        let named = self
+            .presence
+            .get(id)
+            .is_some_and(|info| info.display_name.is_some() || info.handle.is_some())
+            || self
+                .state
+                .friends()
+                .iter()
+                .any(|f| f.user_id == id && (f.alias.is_some() || f.handle.is_some()));
+        if !named && self.profiles.request(id, Instant::now()) {
+            self.pending_profiles.push(id.to_string());
+        }
+    }
+
+    /// Загружает профили из очереди в фоне: ответы приходят как
+    /// `TaskEvent::ProfileLoaded`, цикл событий их не ждёт.
+    fn resolve_pending_profiles(&mut self) {
+        if self.pending_profiles.is_empty() {
+            return;
+        }
+        let (Some(client), Some(session)) = (self.rest_client.clone(), self.session_id.clone())
+        else {
+            // Без сессии очередь ждёт подключения.
+            return;
+        };
+        let pending = std::mem::take(&mut self.pending_profiles);
+        let tx = self.task_tx.clone();
+        tokio::spawn(async move {
+            for id in pending {
+                let lookup =
+                    tokio::time::timeout(PROFILE_LOOKUP_TIMEOUT, client.get_user(&session, &id));
+                let user = match lookup.await {
+                    Ok(Ok(user)) => Some(user),
+                    Ok(Err(err)) => {
+                        tracing::debug!("profile lookup for {} failed: {}", id, err);
+                        None
+                    }
+                    Err(_) => None,
+                };
+                if tx
+                    .send(TaskEvent::ProfileLoaded { id, user })
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
             }
-            if let Some(handle) = info.handle.as_ref() {
-                return handle.clone();
+        });
+    }
+
+    fn apply_profile(&mut self, id: String, user: Option<UserSummary>) {
+        if let (Some(user), Some(info)) = (user.as_ref(), self.presence.get_mut(&id)) {
+            if info.display_name.is_none() {
+                info.display_name = user.display_name.clone();
+            }
+            if info.handle.is_none() {
+                info.handle = Some(user.handle.clone());
+            }
+            if info.avatar_url.is_none() {
+                info.avatar_url = user.avatar_url.clone();
+            }
+            if info.user_id.is_none() {
+                info.user_id = Some(user.id.clone());
             }
         }
-
-        self.state
-            .friends()
-            .iter()
-            .find(|f| f.user_id == device_id)
-            .and_then(|f| f.alias.as_ref().or(f.handle.as_ref()))
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| device_id.to_string())
+        if let Some(user) = user.as_ref() {
+            self.identities.link(&id, &user.id);
+        }
+        self.profiles.insert(&id, user, Instant::now());
     }
 
     fn is_online(&self, device_id: &str) -> bool {
-        self.presence
-            .get(device_id)
+        self.presence_of(device_id)
             .map(|info| info.state == "online" && info.is_active())
             .unwrap_or(false)
     }
 
+    /// Наше устройство или другое устройство нашего пользователя.
+    fn is_me(&self, id: &str) -> bool {
+        id == self.state.device_id
+            || self
+                .state
+                .user_id
+                .as_deref()
+                .is_some_and(|user_id| self.identities.user_of(id) == user_id)
+    }
+
+    /// Presence по id устройства или пользователя; из устройств пользователя
+    /// берётся активное онлайн, иначе самое свежее.
+    fn presence_of(&self, id: &str) -> Option<&PresenceInfo> {
+        if let Some(info) = self.presence.get(id) {
+            return Some(info);
+        }
+        self.identities
+            .devices_of(id)
+            .filter_map(|device| self.presence.get(device))
+            .max_by_key(|info| (info.state == "online" && info.is_active(), info.updated_at))
+    }
+
+    /// Добавляет участника, если этот человек ещё не числится в канале
+    /// под другим устройством или user_id.
+    fn push_member(&mut self, idx: usize, member: &str) {
+        let identities = &self.identities;
+        let channel = &mut self.channels[idx];
+        if !channel
+            .members
+            .iter()
+            .any(|known| identities.same(known, member))
+        {
+            channel.members.push(member.to_string());
+        }
+    }
+
+    fn check_certificate_expiry(&mut self) {
+        self.last_certificate_check = Instant::now();
+        match self.state.certificate_status() {
+            CertificateStatus::ExpiringSoon(left) => self.add_notification(
+                format!(
+                    "Device certificate expires in {}; renew it via pair/claim",
+                    format_remaining(left)
+                ),
+                NotificationLevel::Warning,
+            ),
+            CertificateStatus::Expired => self.add_notification(
+                "Device certificate expired; the server will reject this device".to_string(),
+                NotificationLevel::Error,
+            ),
+            CertificateStatus::Valid | CertificateStatus::None => {}
+        }
+    }
+
     fn add_notification(&mut self, message: String, level: NotificationLevel) {
+        self.push_notification(message, level);
+        self.play_notification_sound(level);
+    }
+
+    fn push_notification(&mut self, message: String, level: NotificationLevel) {
+        match level {
+            NotificationLevel::Warning => {
+                tracing::warn!(target: LOG_TARGET, "notification: {}", message)
+            }
+            NotificationLevel::Error => {
+                tracing::error!(target: LOG_TARGET, "notification: {}", message)
+            }
+            _ => tracing::debug!(target: LOG_TARGET, "notification: {}", message),
+        }
         let mut text = message;
         if level == NotificationLevel::Success && self.emoji_mode {
             text = format!("{} {}", text, ascii_art::random_kawaii());
@@ -1625,12 +4060,125 @@ impl EnhancedApp {
             level,
             timestamp: Utc::now(),
             expires_at: Utc::now() + ChronoDuration::seconds(5),
+            sticky: false,
         };
         self.notifications.push_back(notification);
 
         // Keep only last 10 notifications
         while self.notifications.len() > 10 {
-            self.notifications.pop_front();
+            let Some(oldest) = self.notifications.iter().position(|n| !n.sticky) else {
+                break;
+            };
+            self.notifications.remove(oldest);
+        }
+    }
+
+    /// Предупреждение, которое не истекает до выхода; повтор не дублируется.
+    fn add_sticky_warning(&mut self, message: String) {
+        if self
+            .notifications
+            .iter()
+            .any(|n| n.sticky && n.message == message)
+        {
+            return;
+        }
+        tracing::warn!(target: LOG_TARGET, "notification: {}", message);
+        self.notifications.push_front(Notification {
+            message,
+            level: NotificationLevel::Warning,
+            timestamp: Utc::now(),
+            expires_at: DateTime::<Utc>::MAX_UTC,
+            sticky: true,
+        });
+        self.play_notification_sound(NotificationLevel::Warning);
+    }
+
+    /// Время в локальной зоне по формату `time_format`; хранится всегда UTC.
+    /// В режиме `relative` свежие события показываются как «5m ago» и
+    /// пересчитываются при каждой перерисовке.
+    fn format_time(&self, timestamp: DateTime<Utc>) -> String {
+        let local = timestamp.with_timezone(&Local);
+        let absolute = local.format(&self.state.time_format).to_string();
+        match self.state.time_style {
+            TimeStyle::Absolute => absolute,
+            TimeStyle::Relative => relative_time(&Local::now(), &local, &absolute),
+        }
+    }
+
+    /// Делает канал активным, сбрасывает его счётчики непрочитанного
+    /// и сразу сохраняет отметку о прочтении.
+    fn open_channel(&mut self, idx: usize) {
+        self.active_channel = idx;
+        self.message_scroll = 0;
+        let channel = &mut self.channels[idx];
+        let had_unread = channel.unread_count > 0 || channel.unread_mentions > 0;
+        channel.unread_count = 0;
+        channel.unread_mentions = 0;
+        let channel_id = channel.id;
+        let last_sequence = channel
+            .messages
+            .iter()
+            .rev()
+            .find_map(|message| message.sequence);
+        if channel_id != 0 && (had_unread || last_sequence.is_some()) {
+            self.unread.mark_read(channel_id, last_sequence);
+            self.save_unread();
+        }
+    }
+
+    /// Упоминает ли текст наш `user_handle` или `device_id`.
+    fn mentions_me(&self, text: &str) -> bool {
+        let handle = self.state.user_handle.as_deref();
+        text.split(|c: char| !(c.is_alphanumeric() || matches!(c, '@' | '_' | '-' | '.')))
+            .map(|word| word.trim_start_matches('@').trim_end_matches('.'))
+            .filter(|word| !word.is_empty())
+            .any(|word| {
+                word == self.state.device_id
+                    || handle.is_some_and(|handle| word.eq_ignore_ascii_case(handle))
+            })
+    }
+
+    fn is_mention(&self, entry: &MessageEntry) -> bool {
+        match &entry.content {
+            MessageContent::Text(text) => !self.is_me(&entry.sender) && self.mentions_me(text),
+            _ => false,
+        }
+    }
+
+    fn play_notification_sound(&mut self, level: NotificationLevel) {
+        if let Some(hook) = level.sound_hook() {
+            self.play_sound(hook);
+        }
+    }
+
+    /// Звук хука: команда из `sound_commands`/`sound_command` или bell.
+    /// Пачка уведомлений подряд даёт один звук.
+    fn play_sound(&mut self, hook: &str) {
+        if !self.sound_enabled {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_sound
+            .is_some_and(|last| now.duration_since(last) < SOUND_DEBOUNCE)
+        {
+            return;
+        }
+        self.last_sound = Some(now);
+        let command = self
+            .state
+            .sound_commands
+            .get(hook)
+            .or(self.state.sound_command.as_ref());
+        let result = match command {
+            Some(command) => spawn_sound_command(command),
+            None => ring_bell(),
+        };
+        if let Err(err) = result {
+            self.push_notification(
+                format!("Sound hook '{}' failed: {}", hook, err),
+                NotificationLevel::Warning,
+            );
         }
     }
 
@@ -1651,12 +4199,64 @@ impl EnhancedApp {
             Theme::Dark => Theme::Light,
             Theme::Light => Theme::Cyberpunk,
             Theme::Cyberpunk => Theme::Kawaii,
-            Theme::Kawaii => Theme::Dark,
+            Theme::Kawaii => Theme::Mono,
+            Theme::Mono => Theme::Dark,
         };
         self.add_notification(
             format!("Theme changed to {:?}", self.theme),
             NotificationLevel::Info,
         );
+        self.persist_settings();
+    }
+
+    /// Кладёт живую сессию из рукопожатия в профиль, чтобы CLI не ходил в REST
+    /// с устаревшим `session_token`.
+    fn remember_session(&mut self, session_id: &str) {
+        if self.state.session_token.as_deref() == Some(session_id) {
+            return;
+        }
+        self.state.session_token = Some(session_id.to_string());
+        let result = ClientState::load().and_then(|mut stored| {
+            stored.session_token = Some(session_id.to_string());
+            stored.save()
+        });
+        if let Err(err) = result {
+            tracing::warn!(target: LOG_TARGET, "failed to save session token: {:#}", err);
+        }
+    }
+
+    /// Сохраняет настройки интерфейса поверх актуального файла состояния,
+    /// чтобы не затереть то, что успел записать engine.
+    fn persist_settings(&mut self) {
+        if !self.options.no_color {
+            self.state.ui_theme = Some(self.theme.as_str().to_string());
+        }
+        self.state.animations_enabled = self.animations_enabled;
+        self.state.sound_enabled = self.sound_enabled;
+        if !self.options.ascii {
+            self.state.emoji_mode = self.emoji_mode;
+        }
+        self.state.markdown_enabled = self.markdown_enabled;
+        let result = ClientState::load().and_then(|mut stored| {
+            stored.auto_connect = self.state.auto_connect;
+            stored.mentions_break_mute = self.state.mentions_break_mute;
+            stored.time_style = self.state.time_style;
+            stored.prefer_server_names = self.state.prefer_server_names;
+            stored.presence_verbose = self.state.presence_verbose;
+            stored.show_splash = self.state.show_splash;
+            stored.ui_theme = self.state.ui_theme.clone();
+            stored.animations_enabled = self.animations_enabled;
+            stored.sound_enabled = self.sound_enabled;
+            stored.emoji_mode = self.state.emoji_mode;
+            stored.markdown_enabled = self.markdown_enabled;
+            stored.save()
+        });
+        if let Err(err) = result {
+            self.add_notification(
+                format!("Failed to save settings: {}", err),
+                NotificationLevel::Warning,
+            );
+        }
     }
 
     fn toggle_animations(&mut self) {
@@ -1672,6 +4272,7 @@ impl EnhancedApp {
             ),
             NotificationLevel::Info,
         );
+        self.persist_settings();
     }
 
     fn toggle_sound(&mut self) {
@@ -1680,6 +4281,7 @@ impl EnhancedApp {
             format!("Sound {}", if self.sound_enabled { "on" } else { "muted" }),
             NotificationLevel::Info,
         );
+        self.persist_settings();
     }
 
     fn toggle_emoji_mode(&mut self) {
@@ -1695,12 +4297,147 @@ impl EnhancedApp {
             ),
             NotificationLevel::Info,
         );
+        self.persist_settings();
+    }
+
+    fn toggle_time_style(&mut self) {
+        self.state.time_style = match self.state.time_style {
+            TimeStyle::Absolute => TimeStyle::Relative,
+            TimeStyle::Relative => TimeStyle::Absolute,
+        };
+        self.add_notification(
+            format!("Timestamps: {}", self.state.time_style.as_str()),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    fn toggle_prefer_server_names(&mut self) {
+        self.state.prefer_server_names = !self.state.prefer_server_names;
+        self.add_notification(
+            format!(
+                "Friend names: {}",
+                if self.state.prefer_server_names {
+                    "server name first"
+                } else {
+                    "your alias first"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    fn toggle_splash(&mut self) {
+        self.state.show_splash = !self.state.show_splash;
+        self.add_notification(
+            format!(
+                "Splash screen {}",
+                if self.state.show_splash {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    fn toggle_presence_verbose(&mut self) {
+        self.state.presence_verbose = !self.state.presence_verbose;
+        self.add_notification(
+            format!(
+                "Presence notifications: {}",
+                if self.state.presence_verbose {
+                    "every change"
+                } else {
+                    "summarized"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    fn toggle_mentions_break_mute(&mut self) {
+        self.state.mentions_break_mute = !self.state.mentions_break_mute;
+        self.add_notification(
+            format!(
+                "Mentions in muted channels {}",
+                if self.state.mentions_break_mute {
+                    "notify"
+                } else {
+                    "stay silent"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    /// Заглушает канал или снимает mute; отметка хранится в `unread.json`.
+    fn toggle_channel_mute(&mut self, channel_id: u64) {
+        if channel_id == 0 {
+            self.add_notification(
+                "The system channel can't be muted".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        }
+        let idx = self.ensure_channel(channel_id);
+        let channel = &mut self.channels[idx];
+        channel.muted = !channel.muted;
+        let (muted, name) = (channel.muted, channel.name.clone());
+        self.unread.set_muted(channel_id, muted);
+        self.save_unread();
+        self.add_notification(
+            if muted {
+                format!("🔕 {} muted", name)
+            } else {
+                format!("🔔 {} unmuted", name)
+            },
+            NotificationLevel::Info,
+        );
+    }
+
+    fn toggle_auto_connect(&mut self) {
+        self.state.auto_connect = !self.state.auto_connect;
+        self.add_notification(
+            format!(
+                "Connect on launch {}",
+                if self.state.auto_connect {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
+    }
+
+    fn toggle_markdown(&mut self) {
+        self.markdown_enabled = !self.markdown_enabled;
+        self.add_notification(
+            format!(
+                "Markdown rendering {}",
+                if self.markdown_enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            ),
+            NotificationLevel::Info,
+        );
+        self.persist_settings();
     }
 
     fn begin_voice_recording(&mut self) {
         self.voice_recording = true;
         self.wave_animation.reset();
         self.voice_buffer.clear();
+        self.voice_frames.clear();
         self.voice_amplitude = 0.0;
         self.add_notification(
             "🎙️ Voice recording started (press Space or Ctrl+F6 to finish)".to_string(),
@@ -1722,38 +4459,126 @@ impl EnhancedApp {
             );
             return;
         }
-
-        let Some((channel_name, _channel_id)) = self
-            .channels
-            .get(self.active_channel)
-            .filter(|channel| channel.id != 0)
-            .map(|channel| (channel.name.clone(), channel.id))
-        else {
+        if self.active_call.is_some() {
             self.add_notification(
-                "Select an active chat before starting a call".to_string(),
+                "A call is already in progress".to_string(),
                 NotificationLevel::Warning,
             );
             return;
-        };
+        }
 
-        let action = if video { "Video" } else { "Voice" };
+        let input = self
+            .channels
+            .get(self.active_channel)
+            .filter(|channel| channel.id != 0)
+            .map(|channel| {
+                channel
+                    .members
+                    .iter()
+                    .filter(|member| !self.is_me(member))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
         self.switch_view(AppView::Calls);
+        self.call_prompt = Some(CallPrompt { video, input });
+    }
+
+    async fn handle_call_prompt_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(prompt) = self.call_prompt.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.call_prompt = None,
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            KeyCode::Enter => {
+                if let Some(prompt) = self.call_prompt.take() {
+                    self.start_call(&prompt.input, prompt.video).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn start_call(&mut self, input: &str, video: bool) -> Result<()> {
+        let mut targets = Vec::new();
+        let mut labels = Vec::new();
+        for target in input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|target| !target.is_empty())
+        {
+            let resolved = self.resolve_contact(target).or_else(|| {
+                self.channels
+                    .get(self.active_channel)
+                    .filter(|channel| channel.members.iter().any(|member| member == target))
+                    .map(|_| (target.to_string(), self.get_friend_display_name(target)))
+            });
+            let Some((user_id, label)) = resolved else {
+                self.add_notification(
+                    format!("Unknown contact: {}", target),
+                    NotificationLevel::Warning,
+                );
+                return Ok(());
+            };
+            targets.push(user_id);
+            labels.push(label);
+        }
+        if targets.is_empty() {
+            self.add_notification("Nobody to call".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+
+        let active = self
+            .channels
+            .get(self.active_channel)
+            .filter(|channel| channel.id != 0)
+            .filter(|channel| {
+                targets
+                    .iter()
+                    .all(|target| channel.members.contains(target))
+            })
+            .map(|channel| channel.id);
+        let channel_id = match active {
+            Some(channel_id) => channel_id,
+            None if targets.len() == 1 => self.ensure_direct_channel(&targets[0]).await?,
+            None => {
+                self.add_notification(
+                    "Group calls need an open chat with all participants".to_string(),
+                    NotificationLevel::Warning,
+                );
+                return Ok(());
+            }
+        };
+
+        let media = CallMediaProfile {
+            video: video.then(VideoParameters::default),
+            ..CallMediaProfile::default()
+        };
+        self.engine
+            .send(EngineCommand::StartCall {
+                channel_id,
+                to: targets,
+                media,
+            })
+            .await?;
         self.add_notification(
-            format!("{} call shortcut prepared for {}", action, channel_name),
+            format!("📞 Calling {}…", labels.join(", ")),
             NotificationLevel::Info,
         );
-        self.add_system_message(format!(
-            "{} call shortcut pressed for channel {} — awaiting implementation",
-            action, channel_name
-        ));
+        Ok(())
     }
 
     fn handle_send_file_shortcut(&mut self) {
-        let Some((channel_name, channel_id)) = self
+        let Some(channel_name) = self
             .channels
             .get(self.active_channel)
             .filter(|channel| channel.id != 0)
-            .map(|channel| (channel.name.clone(), channel.id))
+            .map(|channel| channel.name.clone())
         else {
             self.add_notification(
                 "Select an active chat before sending files".to_string(),
@@ -1763,12 +4588,9 @@ impl EnhancedApp {
         };
 
         self.switch_view(AppView::Chat);
-        self.input = format!("/send-file {} ", channel_id);
+        self.input = "/send-file ".to_string();
         self.add_notification(
-            format!(
-                "Enter a file path for {} after the shortcut (feature placeholder)",
-                channel_name
-            ),
+            format!("📎 Enter a file path to send to {}", channel_name),
             NotificationLevel::Info,
         );
     }
@@ -1821,10 +4643,93 @@ impl EnhancedApp {
 
     // Event handlers (stubs for now)
     async fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        // Ctrl+C завершает работу всегда, даже поверх модальных окон.
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return Ok(());
+        }
+        if self.quit_confirm.take().is_some() {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Enter) {
+                self.should_quit = true;
+            }
+            return Ok(());
+        }
+        if self.url_picker.is_some() {
+            self.handle_url_picker_key(key);
+            return Ok(());
+        }
+        if self.search.is_some() {
+            self.handle_search_key(key);
+            return Ok(());
+        }
+        if self.palette.is_some() {
+            return self.handle_palette_key(key).await;
+        }
+        if self.reaction_picker.is_some() {
+            return self.handle_reaction_picker_key(key).await;
+        }
+        if self.call_prompt.is_some() {
+            return self.handle_call_prompt_key(key).await;
+        }
+        if self.show_engine_log {
+            self.handle_engine_log_key(key);
+            return Ok(());
+        }
+        if self.show_help {
+            self.handle_help_key(key);
+            return Ok(());
+        }
+        if self.pair_ticket.take().is_some() {
+            return Ok(());
+        }
+        if self.show_my_info {
+            self.handle_my_info_key(key);
+            return Ok(());
+        }
+        if let Some(raw) = self.raw_view.take() {
+            if key.code == KeyCode::Char('y') {
+                self.copy_to_clipboard(raw, "Raw payload");
+            }
+            return Ok(());
+        }
+        if self.show_assist {
+            return self.handle_assist_key(key).await;
+        }
+        if self.friend_prompt.is_some() {
+            self.handle_friend_prompt_key(key);
+            return Ok(());
+        }
+        if let Some(friend) = self.friend_remove_confirm.take() {
+            if key.code == KeyCode::Char('y') {
+                self.remove_friend(&friend);
+            } else {
+                self.add_notification("Removal cancelled".to_string(), NotificationLevel::Info);
+            }
+            return Ok(());
+        }
+        if let Some(entry) = self.revoke_confirm.take() {
+            if key.code == KeyCode::Char('y') {
+                self.revoke_device(&entry.device_id).await?;
+            } else {
+                self.add_notification("Revoke cancelled".to_string(), NotificationLevel::Info);
+            }
+            return Ok(());
+        }
+
         // Handle key input
         match key.code {
-            KeyCode::F(10) | KeyCode::Esc if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+            KeyCode::F(10) => self.request_quit(),
+            KeyCode::F(1) => {
+                self.help_scroll = 0;
+                self.show_help = true;
+            }
+            KeyCode::F(2) => self.show_my_info = true,
+            KeyCode::F(12) => {
+                self.engine_log.follow();
+                self.show_engine_log = true;
+            }
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_palette();
             }
             KeyCode::F(n) if key.modifiers.contains(KeyModifiers::CONTROL) => match n {
                 3 => self.handle_call_shortcut(false),
@@ -1846,18 +4751,118 @@ impl EnhancedApp {
                 self.view = self.menu_items[next_idx].view;
                 self.transition_progress = 0.0;
             }
+            KeyCode::Char('s')
+                if self.view == AppView::Chat && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.toggle_message_selection();
+            }
+            KeyCode::Char('r')
+                if self.view == AppView::Chat && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.retry_failed_messages().await?;
+            }
+            KeyCode::Char('n')
+                if self.view == AppView::Chat && key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.toggle_channel_mute(self.channels[self.active_channel].id);
+            }
+            KeyCode::Esc if self.selected_message.is_some() => {
+                self.selected_message = None;
+                self.message_scroll = 0;
+            }
+            KeyCode::Esc if self.input.is_empty() && !self.tasks.is_empty() => {
+                self.cancel_latest_task();
+            }
+            KeyCode::Esc => {
+                self.input.clear();
+                self.update_typing().await?;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.selected_message.is_some() => {
+                self.move_message_selection(true);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.selected_message.is_some() => {
+                self.move_message_selection(false);
+            }
+            KeyCode::Char('v') if self.selected_message.is_some() => {
+                self.show_raw_message();
+            }
+            KeyCode::Char('y') if self.selected_message.is_some() => {
+                self.copy_selected_message();
+            }
+            KeyCode::Char('r') if self.selected_message.is_some() => {
+                if let Some(message) = self.selected_message {
+                    self.reaction_picker = Some(ReactionPicker {
+                        message,
+                        selected: 0,
+                        input: String::new(),
+                    });
+                }
+            }
+            KeyCode::Char('o') if self.selected_message.is_some() => {
+                self.open_message_links(None);
+            }
+            KeyCode::Char('s') if self.selected_message.is_some() => {
+                self.save_file_message(None);
+            }
+            KeyCode::Up if self.view == AppView::Voice => {
+                self.voice_selected = self.voice_selected.saturating_sub(1);
+            }
+            KeyCode::Down if self.view == AppView::Voice => {
+                let last = self.recent_voice_messages().len().saturating_sub(1);
+                self.voice_selected = (self.voice_selected + 1).min(last);
+            }
+            KeyCode::Char('p') if self.view == AppView::Voice && key.modifiers.is_empty() => {
+                self.play_last_voice_message();
+            }
+            KeyCode::Char('s') if self.view == AppView::Voice && key.modifiers.is_empty() => {
+                let path = format!("voice-{}.wav", Local::now().format("%Y%m%d-%H%M%S"));
+                self.save_voice_message(self.voice_selected + 1, &path, false);
+            }
+            KeyCode::Up if self.view == AppView::Devices => {
+                self.devices_selected = self.devices_selected.saturating_sub(1);
+            }
+            KeyCode::Down if self.view == AppView::Devices => {
+                let last = self.visible_devices().len().saturating_sub(1);
+                self.devices_selected = (self.devices_selected + 1).min(last);
+            }
+            KeyCode::Char('x') if self.view == AppView::Devices && key.modifiers.is_empty() => {
+                self.request_device_revoke();
+            }
+            KeyCode::Up if self.view == AppView::Friends => self.move_friend_selection(true),
+            KeyCode::Down if self.view == AppView::Friends => self.move_friend_selection(false),
+            KeyCode::Char('a') if self.view == AppView::Friends && key.modifiers.is_empty() => {
+                self.friend_prompt = Some(String::new());
+            }
+            KeyCode::Char('d') if self.view == AppView::Friends && key.modifiers.is_empty() => {
+                self.request_friend_removal();
+            }
+            KeyCode::Char('p') if self.view == AppView::Friends && key.modifiers.is_empty() => {
+                self.push_friends().await;
+            }
+            KeyCode::Char('r') if self.view == AppView::Friends && key.modifiers.is_empty() => {
+                self.pull_friends().await;
+            }
+            KeyCode::Enter if self.view == AppView::Friends && self.input.is_empty() => {
+                self.open_friend_chat().await?;
+            }
             KeyCode::Up => {
                 if self.active_channel > 0 {
-                    self.active_channel -= 1;
+                    self.open_channel(self.active_channel - 1);
                 }
             }
             KeyCode::Down => {
                 if self.active_channel + 1 < self.channels.len() {
-                    self.active_channel += 1;
+                    self.open_channel(self.active_channel + 1);
                 }
             }
             KeyCode::Char('r') if self.view == AppView::Devices => {
-                self.refresh_devices().await?;
+                self.refresh_devices();
+            }
+            KeyCode::Char('f') if self.view == AppView::Devices && key.modifiers.is_empty() => {
+                self.cycle_devices_filter();
+            }
+            KeyCode::PageDown if self.view == AppView::Devices => {
+                self.load_more_devices();
             }
             KeyCode::Char(' ') if self.view == AppView::Voice && key.modifiers.is_empty() => {
                 if self.voice_recording {
@@ -1889,24 +4894,57 @@ impl EnhancedApp {
             KeyCode::Char('e') if self.view == AppView::Settings && key.modifiers.is_empty() => {
                 self.toggle_emoji_mode();
             }
+            KeyCode::Char('m') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_markdown();
+            }
+            KeyCode::Char('c') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_auto_connect();
+            }
+            KeyCode::Char('b') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_mentions_break_mute();
+            }
+            KeyCode::Char('r') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_time_style();
+            }
+            KeyCode::Char('n') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_prefer_server_names();
+            }
+            KeyCode::Char('v') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_presence_verbose();
+            }
+            KeyCode::Char('i') if self.view == AppView::Settings && key.modifiers.is_empty() => {
+                self.toggle_splash();
+            }
             KeyCode::Char('c') if self.view == AppView::Calls && key.modifiers.is_empty() => {
                 self.handle_call_shortcut(false);
             }
             KeyCode::Char('v') if self.view == AppView::Calls && key.modifiers.is_empty() => {
                 self.handle_call_shortcut(true);
             }
+            KeyCode::Char('a') if self.view == AppView::Calls && key.modifiers.is_empty() => {
+                self.answer_call(true).await?;
+            }
+            KeyCode::Char('x') if self.view == AppView::Calls && key.modifiers.is_empty() => {
+                self.answer_call(false).await?;
+            }
             KeyCode::Char('m') if self.view == AppView::Calls && key.modifiers.is_empty() => {
-                self.add_notification(
-                    "Mute toggle placeholder — audio controls not yet wired".to_string(),
-                    NotificationLevel::Info,
-                );
+                self.toggle_call_mute();
             }
             KeyCode::Char('e') if self.view == AppView::Calls && key.modifiers.is_empty() => {
-                self.add_notification(
-                    "End call shortcut acknowledged — call teardown pending implementation"
-                        .to_string(),
-                    NotificationLevel::Info,
-                );
+                let reason = match self
+                    .active_call
+                    .as_deref()
+                    .and_then(|call_id| self.call_manager.get_call(call_id))
+                {
+                    Some(call) if call.started_at.is_some() => CallEndReason::Hangup,
+                    _ => CallEndReason::Cancel,
+                };
+                if !self.end_active_call(reason).await? {
+                    self.add_notification(
+                        "No active call to end".to_string(),
+                        NotificationLevel::Warning,
+                    );
+                }
             }
             KeyCode::Char(c) => {
                 if let Some(view) = self
@@ -1918,14 +4956,17 @@ impl EnhancedApp {
                     self.transition_progress = 0.0;
                 } else {
                     self.input.push(c);
+                    self.update_typing().await?;
                 }
             }
             KeyCode::Backspace => {
                 self.input.pop();
+                self.update_typing().await?;
             }
             KeyCode::Enter => {
                 let input = self.input.clone();
                 self.input.clear();
+                self.update_typing().await?;
                 self.process_input(input).await?;
             }
             _ => {}
@@ -1934,47 +4975,630 @@ impl EnhancedApp {
         Ok(())
     }
 
+    /// Сообщает собеседникам о наборе: `typing:true` не чаще раза в
+    /// `TYPING_SEND_INTERVAL`, `typing:false` — при отправке или очистке ввода.
+    async fn update_typing(&mut self) -> Result<()> {
+        let typing = self.connected
+            && self.view == AppView::Chat
+            && !self.input.is_empty()
+            && !self.input.starts_with(':')
+            && !self.input.starts_with('/');
+        let Some(channel) = self.channels.get_mut(self.active_channel) else {
+            return Ok(());
+        };
+        if channel.id == 0 {
+            return Ok(());
+        }
+        let channel_id = channel.id;
+        if typing {
+            if !channel.typing_limiter.check(Instant::now()) {
+                return Ok(());
+            }
+            channel.typing_sent = true;
+        } else if channel.typing_sent {
+            channel.typing_sent = false;
+            channel.typing_limiter.reset();
+        } else {
+            return Ok(());
+        }
+        self.engine
+            .send(EngineCommand::Typing { channel_id, typing })
+            .await
+    }
+
+    fn toggle_message_selection(&mut self) {
+        if self.selected_message.is_some() {
+            self.selected_message = None;
+            return;
+        }
+        let count = self.channels[self.active_channel].messages.len();
+        if count == 0 {
+            self.add_notification("No messages to select".to_string(), NotificationLevel::Info);
+            return;
+        }
+        self.selected_message = Some(count - 1);
+        self.add_notification(
+            "Select mode: ↑/↓ or j/k to move, y to copy, Esc to exit".to_string(),
+            NotificationLevel::Info,
+        );
+    }
+
+    /// Сообщения отображаются от новых к старым, поэтому «вверх» — к более новому.
+    fn move_message_selection(&mut self, newer: bool) {
+        let count = self.channels[self.active_channel].messages.len();
+        if let Some(selected) = self.selected_message.as_mut() {
+            if newer {
+                *selected = (*selected + 1).min(count.saturating_sub(1));
+            } else {
+                *selected = selected.saturating_sub(1);
+            }
+            let row = count.saturating_sub(1) - *selected;
+            if row < self.message_scroll {
+                self.message_scroll = row;
+            } else if row >= self.message_scroll + VISIBLE_MESSAGES {
+                self.message_scroll = row + 1 - VISIBLE_MESSAGES;
+            }
+        }
+    }
+
+    fn search_messages(&mut self, query: &str) {
+        let needle = query.to_lowercase();
+        let mut hits: Vec<(DateTime<Utc>, SearchHit)> = Vec::new();
+        for (channel_idx, channel) in self.channels.iter().enumerate() {
+            for (message_idx, entry) in channel.messages.iter().enumerate() {
+                let body = match &entry.content {
+                    MessageContent::Text(text) | MessageContent::System(text) => text,
+                    _ => continue,
+                };
+                if !body.to_lowercase().contains(&needle) {
+                    continue;
+                }
+                let label = format!(
+                    "#{} [{}] {}: {}",
+                    channel.name,
+                    self.format_time(entry.timestamp),
+                    self.get_friend_display_name(&entry.sender),
+                    self.preview_text(body)
+                );
+                hits.push((
+                    entry.timestamp,
+                    SearchHit {
+                        channel: channel_idx,
+                        message: message_idx,
+                        label,
+                    },
+                ));
+            }
+        }
+        if hits.is_empty() {
+            self.add_notification(
+                format!("No messages match \"{}\"", query),
+                NotificationLevel::Info,
+            );
+            return;
+        }
+        hits.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+        hits.truncate(SEARCH_RESULT_LIMIT);
+        self.search = Some(SearchOverlay {
+            query: query.to_string(),
+            hits: hits.into_iter().map(|(_, hit)| hit).collect(),
+            selected: 0,
+        });
+    }
+
+    async fn handle_reaction_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(picker) = self.reaction_picker.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.reaction_picker = None,
+            KeyCode::Up => picker.selected = picker.selected.saturating_sub(1),
+            KeyCode::Down => {
+                picker.selected = (picker.selected + 1).min(KAWAII_REACTIONS.len() - 1)
+            }
+            KeyCode::Backspace => {
+                picker.input.pop();
+            }
+            KeyCode::Char(c) => picker.input.push(c),
+            KeyCode::Enter => {
+                let emoji = resolve_reaction(&picker.input).or_else(|| {
+                    KAWAII_REACTIONS
+                        .get(picker.selected)
+                        .map(|r| r.2.to_string())
+                });
+                let message = picker.message;
+                self.reaction_picker = None;
+                if let Some(emoji) = emoji {
+                    self.toggle_reaction(message, emoji).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn toggle_reaction(&mut self, message: usize, emoji: String) -> Result<()> {
+        if !self.connected {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+        let channel_id = self.channels[self.active_channel].id;
+        let me = self.state.device_id.clone();
+        let Some(entry) = self.channels[self.active_channel].messages.get_mut(message) else {
+            return Ok(());
+        };
+        let Some(target_sequence) = entry.sequence else {
+            self.add_notification(
+                "This message can't be reacted to yet".to_string(),
+                NotificationLevel::Warning,
+            );
+            return Ok(());
+        };
+        let remove = entry
+            .reactions
+            .get(&emoji)
+            .is_some_and(|users| users.contains(&me));
+        apply_reaction(&mut entry.reactions, &emoji, &me, remove);
+        let target_sender = entry.sender.clone();
+        self.engine
+            .send(EngineCommand::React {
+                channel_id,
+                target_sequence,
+                target_sender,
+                emoji,
+                remove,
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn open_palette(&mut self) {
+        let mut entries: Vec<PaletteEntry> = palette::COMMANDS
+            .iter()
+            .map(|spec| PaletteEntry {
+                target: PaletteTarget::Command(spec),
+                label: format!("/{}", spec.name),
+                description: spec.description.to_string(),
+                hint: spec.args.to_string(),
+            })
+            .collect();
+        entries.extend(self.menu_items.iter().map(|item| {
+            PaletteEntry {
+                target: PaletteTarget::View(item.view),
+                label: format!("Go to {}", item.label),
+                description: format!("{} Switch view", item.icon),
+                hint: item
+                    .hotkey
+                    .map(|key| format!("[{}]", key))
+                    .unwrap_or_else(|| "[Tab]".to_string()),
+            }
+        }));
+        let mut palette = CommandPalette {
+            query: String::new(),
+            entries,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        palette.refilter();
+        self.palette = Some(palette);
+    }
+
+    async fn handle_palette_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(palette) = self.palette.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Down => {
+                palette.selected =
+                    (palette.selected + 1).min(palette.matches.len().saturating_sub(1))
+            }
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.refilter();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                palette.query.push(c);
+                palette.refilter();
+            }
+            KeyCode::Enter => {
+                let target = palette.matches.get(palette.selected).map(|idx| {
+                    match &palette.entries[*idx].target {
+                        PaletteTarget::Command(spec) => PaletteTarget::Command(spec),
+                        PaletteTarget::View(view) => PaletteTarget::View(*view),
+                    }
+                });
+                self.palette = None;
+                match target {
+                    // Аргументы дописывает пользователь: команда подставляется в строку ввода.
+                    Some(PaletteTarget::Command(spec)) if spec.needs_args() => {
+                        self.input = format!("/{} ", spec.name);
+                    }
+                    Some(PaletteTarget::Command(spec)) => self.process_command(spec.name).await?,
+                    Some(PaletteTarget::View(view)) => {
+                        self.view = view;
+                        self.transition_progress = 0.0;
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some(search) = self.search.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Up => search.selected = search.selected.saturating_sub(1),
+            KeyCode::Down => {
+                search.selected = (search.selected + 1).min(search.hits.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                let target = search
+                    .hits
+                    .get(search.selected)
+                    .map(|hit| (hit.channel, hit.message));
+                self.search = None;
+                if let Some((channel, message)) = target
+                    && channel < self.channels.len()
+                {
+                    self.open_channel(channel);
+                    self.view = AppView::Chat;
+                    let count = self.channels[channel].messages.len();
+                    self.message_scroll = count.saturating_sub(1).saturating_sub(message);
+                    self.selected_message = Some(message);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn copy_selected_message(&mut self) {
+        let text = self.selected_message.and_then(|idx| {
+            match self.channels[self.active_channel].messages.get(idx) {
+                Some(MessageEntry {
+                    content: MessageContent::Text(text),
+                    ..
+                }) => Some(text.clone()),
+                _ => None,
+            }
+        });
+        let Some(text) = text else {
+            self.add_notification(
+                "Only text messages can be copied".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        self.copy_to_clipboard(text, "Message");
+    }
+
+    fn show_raw_message(&mut self) {
+        if !self.options.debug_frames {
+            self.add_notification(
+                "Raw payloads are kept only with tui --debug-frames".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        }
+        let raw = self
+            .selected_message
+            .and_then(|idx| self.channels[self.active_channel].messages.get(idx))
+            .and_then(|entry| entry.raw.clone());
+        match raw {
+            Some(raw) => self.raw_view = Some(framedump::pretty_body(&raw)),
+            None => self.add_notification(
+                "No raw payload for this message".to_string(),
+                NotificationLevel::Warning,
+            ),
+        }
+    }
+
+    /// Кладёт текст в системный буфер обмена; `what` — для уведомления.
+    fn copy_to_clipboard(&mut self, text: String, what: &str) {
+        if self.clipboard.is_none() {
+            match Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(err) => {
+                    self.add_notification(
+                        format!("Clipboard unavailable: {}", err),
+                        NotificationLevel::Warning,
+                    );
+                    return;
+                }
+            }
+        }
+        let result = self
+            .clipboard
+            .as_mut()
+            .map(|clipboard| clipboard.set_text(text))
+            .unwrap_or(Ok(()));
+        match result {
+            Ok(()) => self.add_notification(
+                format!("📋 {} copied to clipboard", what),
+                NotificationLevel::Success,
+            ),
+            Err(err) => self.add_notification(
+                format!("Clipboard copy failed: {}", err),
+                NotificationLevel::Warning,
+            ),
+        }
+    }
+
+    fn handle_url_picker_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.url_picker = None,
+            KeyCode::Char(c) => {
+                let url = c.to_digit(10).and_then(|digit| {
+                    self.url_picker
+                        .as_ref()
+                        .and_then(|urls| urls.get((digit as usize).checked_sub(1)?))
+                        .cloned()
+                });
+                if let Some(url) = url {
+                    self.url_picker = None;
+                    self.open_url(&url);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ссылки из выбранного сообщения, а без выбора — из самого свежего сообщения со ссылками.
+    fn open_message_links(&mut self, choice: Option<usize>) {
+        let channel = &self.channels[self.active_channel];
+        let text_of = |entry: &MessageEntry| match &entry.content {
+            MessageContent::Text(text) => Some(text.clone()),
+            _ => None,
+        };
+        let urls = match self.selected_message {
+            Some(idx) => channel
+                .messages
+                .get(idx)
+                .and_then(text_of)
+                .map(|text| extract_urls(&text))
+                .unwrap_or_default(),
+            None => channel
+                .messages
+                .iter()
+                .rev()
+                .filter_map(text_of)
+                .map(|text| extract_urls(&text))
+                .find(|urls| !urls.is_empty())
+                .unwrap_or_default(),
+        };
+        if urls.is_empty() {
+            self.add_notification("No links found".to_string(), NotificationLevel::Info);
+            return;
+        }
+        match choice {
+            Some(n) => match n.checked_sub(1).and_then(|idx| urls.get(idx)) {
+                Some(url) => {
+                    let url = url.clone();
+                    self.open_url(&url);
+                }
+                None => self.add_notification(
+                    format!("Link {} not found ({} available)", n, urls.len()),
+                    NotificationLevel::Warning,
+                ),
+            },
+            None if urls.len() == 1 => self.open_url(&urls[0]),
+            None => {
+                let mut urls = urls;
+                urls.truncate(9);
+                self.url_picker = Some(urls);
+            }
+        }
+    }
+
+    fn open_url(&mut self, url: &str) {
+        if self.options.no_url_open {
+            self.add_notification(
+                "Opening links is disabled (--no-url-open)".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        }
+        match open::that_detached(url) {
+            Ok(()) => {
+                self.add_notification(format!("🔗 Opening {}", url), NotificationLevel::Success)
+            }
+            Err(err) => self.add_notification(
+                format!("Unable to open link: {}", err),
+                NotificationLevel::Warning,
+            ),
+        }
+    }
+
     async fn handle_client_event(&mut self, event: ClientEvent) -> Result<()> {
         match event {
             ClientEvent::Connected {
                 session_id,
                 pairing_required,
+                server,
             } => {
                 self.connected = true;
+                self.presence_notifier.start_burst(Instant::now());
+                self.remember_session(&session_id);
                 self.session_id = Some(session_id);
                 self.add_notification(
                     "✅ Connected successfully".to_string(),
                     NotificationLevel::Success,
                 );
+                if self.state.insecure {
+                    self.add_sticky_warning(
+                        "⚠ TLS verification DISABLED (insecure=true in profile)".to_string(),
+                    );
+                }
+                if let Some(version) = server.protocol_version
+                    && version != u64::from(PROTOCOL_VERSION)
+                {
+                    self.add_notification(
+                        format!(
+                            "⚠ Server speaks protocol v{}, this client v{}",
+                            version, PROTOCOL_VERSION
+                        ),
+                        NotificationLevel::Warning,
+                    );
+                }
+                self.server_caps = Some(server);
                 if pairing_required {
                     self.add_notification(
                         "🔐 Pairing required to access secure features".to_string(),
                         NotificationLevel::Warning,
                     );
                 }
-                let _ = self.refresh_devices().await;
+                self.refresh_devices();
+                self.refresh_server_info().await;
             }
             ClientEvent::Disconnected { reason } => {
+                self.push_engine_log(LogLevel::Warn, format!("disconnected: {}", reason));
                 self.connected = false;
+                self.latency.clear();
                 self.session_id = None;
+                self.server_caps = None;
+                self._assist_socket = None;
+                self.chunk_sequences.clear();
+                let pending: Vec<u64> = self.pending_sends.keys().copied().collect();
+                for local_id in pending {
+                    self.fail_outgoing(local_id, "connection lost");
+                }
+                if let Some(call_id) = self.active_call.clone() {
+                    self.finish_call(&call_id);
+                }
                 self.add_notification(
                     format!("❌ Disconnected: {}", reason),
                     NotificationLevel::Error,
                 );
             }
             ClientEvent::Error { detail } => {
+                tracing::error!(target: LOG_TARGET, "engine: {}", detail);
+                self.push_engine_log(LogLevel::Error, detail.clone());
                 self.last_error = Some(detail.clone());
                 self.add_notification(format!("⚠️ {}", detail), NotificationLevel::Error);
             }
             ClientEvent::Frame(frame) => {
                 self.handle_protocol_frame(frame).await?;
+                self.resolve_pending_profiles();
+            }
+            ClientEvent::CallStarted { channel_id, offer } => {
+                self.process_outgoing_call(channel_id, offer)?;
+            }
+            ClientEvent::Gap {
+                channel_id,
+                expected,
+                got,
+            } => {
+                let missed = got - expected;
+                self.push_engine_log(
+                    LogLevel::Warn,
+                    format!(
+                        "sequence gap on channel {}: expected {}, got {}",
+                        channel_id, expected, got
+                    ),
+                );
+                self.add_system_message(format!(
+                    "⚠️ Channel {}: expected frame #{}, got #{} ({} possibly lost)",
+                    channel_id, expected, got, missed
+                ));
+                self.add_notification(
+                    format!(
+                        "⚠️ Possible message loss on channel {} ({} frames)",
+                        channel_id, missed
+                    ),
+                    NotificationLevel::Warning,
+                );
+            }
+            ClientEvent::MessageSent {
+                channel_id,
+                local_id,
+                sequence,
+            } => {
+                if !self.note_chunk_sent(channel_id, local_id, sequence) {
+                    self.assign_sent_sequence(channel_id, local_id, sequence);
+                }
+            }
+            // Номер нужен только команде `presence`; TUI не ждёт ACK на статус.
+            ClientEvent::PresenceSent { .. } => {}
+            ClientEvent::SendFailed { local_id, detail } => {
+                self.fail_outgoing(local_id, &detail);
             }
             ClientEvent::Log { line } => {
-                // Add to system channel
-                self.add_system_message(line);
+                tracing::info!(target: LOG_TARGET, "engine: {}", line);
+                self.push_engine_log(LogLevel::classify(&line), line);
+            }
+            ClientEvent::Latency { rtt } => {
+                if rtt.is_none() {
+                    self.push_engine_log(
+                        LogLevel::Warn,
+                        "ping timed out, link quality degraded".to_string(),
+                    );
+                }
+                self.latency.push(rtt);
+            }
+            ClientEvent::ServerKeyPinned {
+                server_static,
+                fingerprint,
+                previous,
+            } => {
+                self.state.pin_server_key(&server_static);
+                match previous {
+                    Some(previous) => {
+                        self.add_system_message(format!(
+                            "🔑 Server key replaced: {} → {}",
+                            previous, fingerprint
+                        ));
+                        self.add_notification(
+                            format!("🔑 Server key replaced, now pinned {}", fingerprint),
+                            NotificationLevel::Warning,
+                        );
+                    }
+                    None => {
+                        self.add_system_message(format!(
+                            "🔑 Server key pinned on first use: {}",
+                            fingerprint
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Прогоняет записанные кадры; ошибки обработчика попадают в журнал F12,
+    /// а не прерывают прогон.
+    async fn replay_frames(&mut self) {
+        let frames = self.options.replay.clone();
+        let mut failed = 0;
+        for (index, frame) in frames.into_iter().enumerate() {
+            let summary = format!(
+                "#{} {:?} channel={} seq={}",
+                index + 1,
+                frame.frame_type,
+                frame.channel_id,
+                frame.sequence
+            );
+            match self.handle_protocol_frame(frame).await {
+                Ok(()) => self.push_engine_log(LogLevel::Info, format!("replay {}", summary)),
+                Err(err) => {
+                    failed += 1;
+                    self.push_engine_log(
+                        LogLevel::Error,
+                        format!("replay {} failed: {:#}", summary, err),
+                    );
+                }
             }
         }
-        Ok(())
+        self.add_system_message(format!(
+            "⏪ Replayed {} frame(s), {} failed (F12 for details)",
+            self.options.replay.len(),
+            failed
+        ));
     }
 
     async fn handle_protocol_frame(&mut self, frame: ProtoFrame) -> Result<()> {
@@ -2160,10 +5784,50 @@ impl EnhancedApp {
         Ok(())
     }
 
-    fn process_msg_frame(&mut self, channel_id: u64, _sequence: u64, data: Vec<u8>) -> Result<()> {
+    fn process_msg_frame(&mut self, channel_id: u64, sequence: u64, data: Vec<u8>) -> Result<()> {
         let idx = self.ensure_channel(channel_id);
         let now = Utc::now();
 
+        if let Ok(value) = serde_json::from_slice::<Value>(&data)
+            && let Some(reaction) = Reaction::parse(&value)
+        {
+            if let Some(entry) = self.channels[idx].messages.iter_mut().rev().find(|entry| {
+                entry.sequence.is_some()
+                    && entry.sequence == reaction.target
+                    && reaction
+                        .target_sender
+                        .as_deref()
+                        .is_none_or(|sender| sender == entry.sender)
+            }) {
+                apply_reaction(
+                    &mut entry.reactions,
+                    &reaction.emoji,
+                    &reaction.sender,
+                    reaction.remove,
+                );
+            }
+            return Ok(());
+        }
+
+        if let Some(chunk) = FileChunk::parse(&data) {
+            let sender = Some(chunk.header.sender.clone())
+                .filter(|sender| !sender.is_empty())
+                .or_else(|| {
+                    serde_json::from_slice::<Value>(&data)
+                        .ok()
+                        .and_then(|value| {
+                            value
+                                .get("sender")
+                                .or_else(|| value.get("from"))
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string)
+                        })
+                })
+                .unwrap_or_else(|| "unknown".to_string());
+            self.process_file_chunk(idx, sequence, sender, chunk);
+            return Ok(());
+        }
+
         let mut sender = String::new();
         let mut body: Option<String> = None;
         let mut reactions: HashMap<String, Vec<String>> = HashMap::new();
@@ -2201,8 +5865,10 @@ impl EnhancedApp {
         }
 
         let text = body.unwrap_or_else(|| String::from_utf8_lossy(&data).to_string());
-        if sender != "unknown" && !self.channels[idx].members.contains(&sender) {
-            self.channels[idx].members.push(sender.clone());
+        let raw = self.options.debug_frames.then(|| Arc::new(data));
+        self.queue_profile_lookup(&sender);
+        if sender != "unknown" {
+            self.push_member(idx, &sender);
         }
 
         let entry = MessageEntry {
@@ -2210,27 +5876,79 @@ impl EnhancedApp {
             sender: sender.clone(),
             content: MessageContent::Text(text.clone()),
             reactions,
+            sequence: Some(sequence),
+            outgoing: None,
+            raw,
         };
         self.push_channel_message(idx, entry);
 
-        if sender != self.state.device_id {
-            if idx != self.active_channel {
-                self.channels[idx].unread_count = self.channels[idx].unread_count.saturating_add(1);
+        let mention = self.mentions_me(&text);
+        let preview = self.preview_text(&text);
+        self.announce_incoming(idx, sequence, &sender, mention, preview);
+        Ok(())
+    }
+
+    /// Счётчики непрочитанного и уведомление о чужом сообщении; заглушённый
+    /// канал только копит непрочитанное.
+    fn announce_incoming(
+        &mut self,
+        idx: usize,
+        sequence: u64,
+        sender: &str,
+        mention: bool,
+        preview: String,
+    ) {
+        if self.is_me(sender) {
+            return;
+        }
+        let channel_id = self.channels[idx].id;
+        if idx != self.active_channel {
+            if self.unread.record(channel_id, sequence, mention) {
+                let channel = &mut self.channels[idx];
+                channel.unread_count = channel.unread_count.saturating_add(1);
+                if mention {
+                    channel.unread_mentions = channel.unread_mentions.saturating_add(1);
+                }
+                self.unread_dirty = true;
             }
-            let preview = self.preview_text(&text);
+        } else {
+            self.unread.mark_read(channel_id, Some(sequence));
+            self.unread_dirty = true;
+        }
+        let muted = self.channels[idx].muted;
+        if muted && !(mention && self.state.mentions_break_mute) {
+            return;
+        }
+        let sender = self.get_friend_display_name(sender);
+        if mention {
+            self.push_notification(
+                format!("📣 {} mentioned you: {}", sender, preview),
+                NotificationLevel::Warning,
+            );
+            self.play_sound("mention");
+        } else {
             self.add_notification(
-                format!("💌 {}: {}", self.get_friend_display_name(&sender), preview),
+                format!("💌 {}: {}", sender, preview),
                 NotificationLevel::Info,
             );
         }
-
-        Ok(())
     }
 
     fn process_ack_frame(&mut self, channel_id: u64, envelope: ControlEnvelope) -> Result<()> {
         if let Some(obj) = envelope.properties.as_object() {
-            if let Some(seq) = obj.get("ack").and_then(|v| v.as_u64()) {
-                self.add_system_message(format!("✅ ACK {} on channel {}", seq, channel_id));
+            if let Some(seq) = obj.get("ack").and_then(|v| v.as_u64())
+                && !self.chunk_sequences.remove(&(channel_id, seq))
+            {
+                let read = obj.get("read").and_then(|v| v.as_bool()).unwrap_or(false)
+                    || obj.get("status").and_then(|v| v.as_str()) == Some("read");
+                let status = if read {
+                    Delivery::Read
+                } else {
+                    Delivery::Delivered
+                };
+                if !self.mark_delivery(channel_id, seq, status) {
+                    self.add_system_message(format!("✅ ACK {} on channel {}", seq, channel_id));
+                }
             }
             if let Some(call_id) = obj.get("call_id").and_then(|v| v.as_str()) {
                 self.add_notification(
@@ -2242,6 +5960,162 @@ impl EnhancedApp {
         Ok(())
     }
 
+    /// Последнее собственное сообщение канала с номером `sequence`: после
+    /// переподключения номера начинаются заново, поэтому ищем с конца.
+    fn own_message_mut(&mut self, channel_id: u64, sequence: u64) -> Option<&mut MessageEntry> {
+        let me = self.state.device_id.clone();
+        self.channels
+            .iter_mut()
+            .find(|channel| channel.id == channel_id)?
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|entry| entry.sender == me && entry.sequence == Some(sequence))
+    }
+
+    /// Статус только растёт: позднее «delivered» не отменяет «read», а ACK,
+    /// пришедший после тайм-аута, снимает отметку «failed».
+    fn mark_delivery(&mut self, channel_id: u64, sequence: u64, status: Delivery) -> bool {
+        let Some(outgoing) = self
+            .own_message_mut(channel_id, sequence)
+            .and_then(|entry| entry.outgoing.as_mut())
+        else {
+            return false;
+        };
+        outgoing.delivery = outgoing.delivery.max(status);
+        let local_id = outgoing.local_id;
+        self.pending_sends.remove(&local_id);
+        true
+    }
+
+    fn outgoing_message_mut(
+        &mut self,
+        channel_id: u64,
+        local_id: u64,
+    ) -> Option<&mut MessageEntry> {
+        self.channels
+            .iter_mut()
+            .find(|channel| channel.id == channel_id)?
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|entry| {
+                entry
+                    .outgoing
+                    .is_some_and(|outgoing| outgoing.local_id == local_id)
+            })
+    }
+
+    /// Движок присвоил сообщению номер кадра; по нему придёт ACK.
+    fn assign_sent_sequence(&mut self, channel_id: u64, local_id: u64, sequence: u64) {
+        if let Some(entry) = self.outgoing_message_mut(channel_id, local_id) {
+            entry.sequence = Some(sequence);
+        }
+    }
+
+    fn fail_outgoing(&mut self, local_id: u64, reason: &str) {
+        let Some(pending) = self.pending_sends.remove(&local_id) else {
+            return;
+        };
+        if let Some(outgoing) = self
+            .outgoing_message_mut(pending.channel_id, local_id)
+            .and_then(|entry| entry.outgoing.as_mut())
+        {
+            outgoing.delivery = Delivery::Failed;
+        }
+        self.push_engine_log(
+            LogLevel::Warn,
+            format!("message {} not delivered: {}", local_id, reason),
+        );
+        self.add_notification(
+            format!("✗ Message not delivered: {} (Ctrl+R to retry)", reason),
+            NotificationLevel::Error,
+        );
+    }
+
+    /// Сообщения без ACK дольше `DELIVERY_TIMEOUT` считаются неотправленными.
+    fn expire_pending_sends(&mut self) {
+        let expired: Vec<u64> = self
+            .pending_sends
+            .iter()
+            .filter(|(_, pending)| pending.sent_at.elapsed() >= DELIVERY_TIMEOUT)
+            .map(|(local_id, _)| *local_id)
+            .collect();
+        for local_id in expired {
+            self.fail_outgoing(
+                local_id,
+                &format!("no ACK within {}s", DELIVERY_TIMEOUT.as_secs()),
+            );
+        }
+    }
+
+    /// Повторно отправляет неудавшиеся сообщения активного канала.
+    async fn retry_failed_messages(&mut self) -> Result<()> {
+        if !self.connected {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+        let Some(channel) = self.channels.get(self.active_channel) else {
+            return Ok(());
+        };
+        let channel_id = channel.id;
+        let failed: Vec<(u64, String)> = channel
+            .messages
+            .iter()
+            .filter_map(|entry| match (&entry.content, entry.outgoing) {
+                (MessageContent::Text(text), Some(outgoing))
+                    if outgoing.delivery == Delivery::Failed =>
+                {
+                    Some((outgoing.local_id, text.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        if failed.is_empty() {
+            self.add_notification(
+                "No failed messages in this channel".to_string(),
+                NotificationLevel::Info,
+            );
+            return Ok(());
+        }
+        let count = failed.len();
+        for (old_id, text) in failed {
+            let local_id = self.track_outgoing(channel_id);
+            if let Some(entry) = self.outgoing_message_mut(channel_id, old_id) {
+                entry.sequence = None;
+                entry.outgoing = Some(Outgoing {
+                    local_id,
+                    delivery: Delivery::Pending,
+                });
+            }
+            self.engine
+                .send(EngineCommand::SendMessage {
+                    channel_id,
+                    local_id,
+                    body: text.into_bytes(),
+                })
+                .await?;
+        }
+        self.add_notification(
+            format!("🔁 Retrying {} message(s)", count),
+            NotificationLevel::Info,
+        );
+        Ok(())
+    }
+
+    /// Новый локальный номер исходящего сообщения, ждущего ACK.
+    fn track_outgoing(&mut self, channel_id: u64) -> u64 {
+        self.next_local_id += 1;
+        self.pending_sends.insert(
+            self.next_local_id,
+            PendingSend {
+                channel_id,
+                sent_at: Instant::now(),
+            },
+        );
+        self.next_local_id
+    }
+
     fn process_typing_frame(&mut self, channel_id: u64, envelope: ControlEnvelope) -> Result<()> {
         let idx = self.ensure_channel(channel_id);
         let payload = envelope
@@ -2320,11 +6194,25 @@ impl EnhancedApp {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
-        let notify = self
+        let changed = self
             .presence
             .get(entity)
             .map(|info| info.state != state)
             .unwrap_or(true);
+        if let Some(user_id) = user_id.as_deref() {
+            self.identities.link(entity, user_id);
+        }
+        let notice = self.presence_notifier.observe(
+            self.identities.user_of(entity),
+            state == "online",
+            changed,
+            self.state.presence_verbose,
+            Instant::now(),
+        );
+        let cached = self.profiles.get(entity);
+        let display_name = display_name.or_else(|| cached.and_then(|u| u.display_name.clone()));
+        let handle = handle.or_else(|| cached.map(|u| u.handle.clone()));
+        let avatar_url = avatar_url.or_else(|| cached.and_then(|u| u.avatar_url.clone()));
 
         self.presence.insert(
             entity.to_string(),
@@ -2338,8 +6226,9 @@ impl EnhancedApp {
                 updated_at: Utc::now(),
             },
         );
+        self.queue_profile_lookup(entity);
 
-        if notify {
+        if notice == PresenceNotice::Show {
             let icon = if state == "online" { "🟢" } else { "⚫" };
             self.add_notification(
                 format!(
@@ -2355,6 +6244,21 @@ impl EnhancedApp {
         Ok(())
     }
 
+    /// Одно уведомление вместо волны presence после подключения.
+    fn flush_presence_burst(&mut self) {
+        let Some(online) = self.presence_notifier.finish_burst(Instant::now()) else {
+            return;
+        };
+        if online == 0 || self.state.presence_verbose {
+            return;
+        }
+        let noun = if online == 1 { "friend" } else { "friends" };
+        self.add_notification(
+            format!("🟢 {} {} online", online, noun),
+            NotificationLevel::Info,
+        );
+    }
+
     fn process_join_frame(&mut self, channel_id: u64, envelope: ControlEnvelope) -> Result<()> {
         let idx = self.ensure_channel(channel_id);
         let obj = envelope
@@ -2486,9 +6390,7 @@ impl EnhancedApp {
         group.add_member(device.clone(), role);
 
         let idx = self.ensure_channel(channel_id);
-        if !self.channels[idx].members.contains(&device) {
-            self.channels[idx].members.push(device.clone());
-        }
+        self.push_member(idx, &device);
 
         self.add_notification(
             format!(
@@ -2524,6 +6426,9 @@ impl EnhancedApp {
             sender: "System".to_string(),
             content: MessageContent::GroupEvent(description.clone()),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         self.push_channel_message(idx, entry);
         Ok(())
@@ -2548,12 +6453,12 @@ impl EnhancedApp {
                 duration: None,
             }),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         self.push_channel_message(idx, entry);
-        let is_target = offer
-            .to
-            .iter()
-            .any(|target| target == &self.state.device_id);
+        let is_target = offer.to.iter().any(|target| self.is_me(target));
         let label = if is_target { "Incoming" } else { "Relay" };
         self.add_notification(
             format!(
@@ -2566,6 +6471,104 @@ impl EnhancedApp {
         Ok(())
     }
 
+    /// Входящий звонок этому пользователю или устройству, на который ещё не ответили.
+    fn pending_incoming_call(&self) -> Option<(String, String)> {
+        let call_id = self.active_call.as_deref()?;
+        let call = self.call_manager.get_call(call_id)?;
+        let targets_me = call.offer.to.iter().any(|target| self.is_me(target));
+        (targets_me
+            && call.offer.from != self.state.device_id
+            && call.answer.is_none()
+            && call.ended_at.is_none())
+        .then(|| (call_id.to_string(), call.offer.from.clone()))
+    }
+
+    async fn answer_call(&mut self, accept: bool) -> Result<()> {
+        let Some((call_id, from)) = self.pending_incoming_call() else {
+            self.add_notification(
+                "No incoming call to answer".to_string(),
+                NotificationLevel::Warning,
+            );
+            return Ok(());
+        };
+        if !self.connected {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+        let Some(channel_id) = self
+            .call_channels
+            .iter()
+            .find_map(|(channel_id, id)| (id == &call_id).then_some(*channel_id))
+        else {
+            return Ok(());
+        };
+        let media = self
+            .call_manager
+            .get_call(&call_id)
+            .map(|call| call.offer.media.clone());
+        let answer = CallAnswer {
+            call_id: call_id.clone(),
+            accept,
+            media: if accept { media } else { None },
+            transport: None,
+            reason: (!accept).then_some(CallRejectReason::Decline),
+            metadata: Value::Null,
+        };
+        self.engine
+            .send(EngineCommand::AnswerCall {
+                channel_id,
+                answer: answer.clone(),
+            })
+            .await?;
+        self.call_manager.accept_answer(answer);
+
+        let peer = self.get_friend_display_name(&from);
+        if accept {
+            self.add_notification(
+                format!("📞 Call with {} accepted", peer),
+                NotificationLevel::Success,
+            );
+        } else {
+            self.media.remove_call(&call_id);
+            self.call_channels.retain(|_, id| id != &call_id);
+            self.active_call = None;
+            self.call_audio_metrics = None;
+            self.call_video_metrics = None;
+            self.pending_snapshot = None;
+            self.add_notification(
+                format!("📵 Call from {} rejected", peer),
+                NotificationLevel::Info,
+            );
+        }
+        Ok(())
+    }
+
+    fn process_outgoing_call(&mut self, channel_id: u64, offer: CallOffer) -> Result<()> {
+        self.call_manager.upsert_offer(offer.clone());
+        self.call_channels.insert(channel_id, offer.call_id.clone());
+        self.media
+            .initialise_from_media(&offer.call_id, &offer.media)
+            .with_context(|| format!("initialise media pipeline for call {}", offer.call_id))?;
+        self.active_call = Some(offer.call_id.clone());
+
+        let idx = self.ensure_channel(channel_id);
+        let entry = MessageEntry {
+            timestamp: Utc::now(),
+            sender: offer.from.clone(),
+            content: MessageContent::Call(CallInfo {
+                call_id: offer.call_id.clone(),
+                action: "offer".to_string(),
+                duration: None,
+            }),
+            reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
+        };
+        self.push_channel_message(idx, entry);
+        Ok(())
+    }
+
     fn process_call_answer(&mut self, channel_id: u64, envelope: ControlEnvelope) -> Result<()> {
         let answer = CallAnswer::try_from(&envelope).context("decode CALL_ANSWER payload")?;
         let accepted = answer.accept;
@@ -2598,6 +6601,9 @@ impl EnhancedApp {
                 duration: None,
             }),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         self.push_channel_message(idx, entry);
         Ok(())
@@ -2605,65 +6611,204 @@ impl EnhancedApp {
 
     fn process_call_end(&mut self, channel_id: u64, envelope: ControlEnvelope) -> Result<()> {
         let end = CallEnd::try_from(&envelope).context("decode CALL_END payload")?;
-        self.call_manager.end_call(&end.call_id);
-        self.media.remove_call(&end.call_id);
-        self.call_channels.retain(|_, id| id != &end.call_id);
-        if self.active_call.as_deref() == Some(&end.call_id) {
-            self.active_call = None;
+        self.finish_call(&end.call_id);
+        self.push_call_end_message(channel_id, &end.call_id, end.reason);
+        self.add_notification(
+            format!("📴 Call {} ended", self.short_id(&end.call_id)),
+            NotificationLevel::Info,
+        );
+        Ok(())
+    }
+
+    /// Отправляет CALL_END для текущего звонка и освобождает его ресурсы.
+    /// Возвращает `false`, если активного звонка не было.
+    async fn end_active_call(&mut self, reason: CallEndReason) -> Result<bool> {
+        let Some(call_id) = self.active_call.clone() else {
+            return Ok(false);
+        };
+        let channel_id = self
+            .call_channels
+            .iter()
+            .find_map(|(channel_id, id)| (id == &call_id).then_some(*channel_id));
+        if self.connected
+            && let Some(channel_id) = channel_id
+        {
+            self.engine
+                .send(EngineCommand::EndCall {
+                    channel_id,
+                    call_id: call_id.clone(),
+                    reason,
+                })
+                .await?;
         }
-        self.call_audio_metrics = None;
-        self.call_video_metrics = None;
+        self.finish_call(&call_id);
+        if let Some(channel_id) = channel_id {
+            self.push_call_end_message(channel_id, &call_id, reason);
+        }
+        self.add_notification(
+            format!("📴 Call {} ended", self.short_id(&call_id)),
+            NotificationLevel::Info,
+        );
+        Ok(true)
+    }
+
+    /// Канал активного звонка, в который нужно отправлять голос.
+    fn outgoing_audio_channel(&self) -> Option<u64> {
+        let call_id = self.active_call.as_deref()?;
+        let call = self.call_manager.get_call(call_id)?;
+        if call.started_at.is_none() || call.ended_at.is_some() || call.muted {
+            return None;
+        }
+        self.call_channels
+            .iter()
+            .find_map(|(channel_id, id)| (id == call_id).then_some(*channel_id))
+    }
+
+    async fn pump_microphone(&mut self) -> Result<()> {
+        let in_call = self.active_call.as_deref().is_some_and(|call_id| {
+            self.call_manager
+                .get_call(call_id)
+                .is_some_and(|call| call.started_at.is_some() && call.ended_at.is_none())
+        });
+        if !self.voice_recording && !in_call {
+            self.microphone = None;
+            return Ok(());
+        }
+        if self.microphone.is_none() {
+            if !self.options.audio_in {
+                return Ok(());
+            }
+            match MicrophoneCapture::open() {
+                Ok(capture) => self.microphone = Some(capture),
+                Err(err) => {
+                    // Без устройства ввода остаёмся на синтетической записи.
+                    self.options.audio_in = false;
+                    self.add_notification(
+                        format!("🎙️ Microphone unavailable: {}", err),
+                        NotificationLevel::Warning,
+                    );
+                    return Ok(());
+                }
+            }
+        }
+        let Some(microphone) = self.microphone.as_mut() else {
+            return Ok(());
+        };
+        let packets = match microphone.drain() {
+            Ok(packets) => packets,
+            Err(err) => {
+                self.drop_failed_microphone(err);
+                return Ok(());
+            }
+        };
+        self.voice_amplitude = microphone.level();
+        if self.voice_recording {
+            self.voice_frames.extend(packets.iter().cloned());
+        }
+        if self.connected
+            && let Some(channel_id) = self.outgoing_audio_channel()
+        {
+            for payload in packets {
+                self.engine
+                    .send(EngineCommand::SendVoiceFrame {
+                        channel_id,
+                        payload,
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Сбой захвата или кодека посреди звонка: микрофон закрывается, запись
+    /// сохраняется как есть, звонок остаётся без звука, интерфейс работает.
+    fn drop_failed_microphone(&mut self, err: anyhow::Error) {
+        self.microphone = None;
+        self.options.audio_in = false;
+        self.add_notification(
+            format!("🎙️ Microphone failed: {:#}", err),
+            NotificationLevel::Error,
+        );
+        if self.voice_recording
+            && let Err(err) = self.end_voice_recording()
+        {
+            self.add_notification(
+                format!("Failed to save voice memo: {:#}", err),
+                NotificationLevel::Error,
+            );
+        }
+        if let Some(call_id) = self.active_call.clone()
+            && self
+                .call_manager
+                .get_call(&call_id)
+                .is_some_and(|call| !call.muted)
+        {
+            self.call_manager.toggle_mute(&call_id);
+        }
+    }
+
+    fn toggle_call_mute(&mut self) {
+        let muted = self
+            .active_call
+            .clone()
+            .and_then(|call_id| self.call_manager.toggle_mute(&call_id));
+        match muted {
+            Some(true) => {
+                self.add_notification("🔇 Microphone muted".to_string(), NotificationLevel::Info)
+            }
+            Some(false) => {
+                self.add_notification("🎤 Microphone unmuted".to_string(), NotificationLevel::Info)
+            }
+            None => self.add_notification(
+                "No active call to mute".to_string(),
+                NotificationLevel::Warning,
+            ),
+        }
+    }
+
+    fn push_call_end_message(&mut self, channel_id: u64, call_id: &str, reason: CallEndReason) {
         let duration = self
             .call_manager
-            .get_call(&end.call_id)
-            .and_then(|call| call.started_at.zip(call.ended_at))
-            .and_then(|(start, end_ts)| {
-                let diff = end_ts - start;
-                if diff > 0 {
-                    Some(Duration::from_secs(diff as u64))
-                } else {
-                    None
-                }
-            });
+            .get_call(call_id)
+            .and_then(|call| call.duration());
         let idx = self.ensure_channel(channel_id);
         let entry = MessageEntry {
             timestamp: Utc::now(),
             sender: "Call".to_string(),
             content: MessageContent::Call(CallInfo {
-                call_id: end.call_id.clone(),
-                action: format!("ended ({:?})", end.reason),
+                call_id: call_id.to_string(),
+                action: format!("ended ({:?})", reason),
                 duration,
             }),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         self.push_channel_message(idx, entry);
-        self.add_notification(
-            format!("📴 Call {} ended", self.short_id(&end.call_id)),
-            NotificationLevel::Info,
-        );
-        Ok(())
+    }
+
+    fn finish_call(&mut self, call_id: &str) {
+        self.call_manager.end_call(call_id);
+        self.media.remove_call(call_id);
+        self.call_channels.retain(|_, id| id != call_id);
+        if self.active_call.as_deref() == Some(call_id) {
+            self.active_call = None;
+        }
+        self.call_audio_metrics = None;
+        self.call_video_metrics = None;
+        self.pending_snapshot = None;
     }
 
     fn process_call_stats(&mut self, envelope: ControlEnvelope) -> Result<()> {
         let stats = CallStats::try_from(&envelope).context("decode CALL_STATS payload")?;
-        self.call_manager.push_stats(stats.clone());
-        let audio_quality = stats
-            .audio
-            .as_ref()
-            .map(|audio| (1.0_f32 - audio.packet_loss.clamp(0.0, 1.0)).clamp(0.0, 1.0))
-            .unwrap_or(1.0_f32);
-        let video_quality = stats
-            .video
-            .as_ref()
-            .map(|video| (1.0_f32 - video.packet_loss.clamp(0.0, 1.0)).clamp(0.0, 1.0))
-            .unwrap_or(1.0_f32);
-        let combined = ((audio_quality + video_quality) / 2.0_f32).clamp(0.0, 1.0);
-        self.push_quality_sample(combined);
+        self.call_manager.push_stats(stats);
         Ok(())
     }
 
     fn process_voice_frame(&mut self, channel_id: u64, data: Vec<u8>) -> Result<()> {
         if let Some(call_id) = self.call_channels.get(&channel_id).cloned() {
+            self.call_manager.mark_media(&call_id);
             if let Some(metrics) = self.media.decode_audio(&call_id, &data)? {
                 let level = metrics.level.clamp(0.0, 1.0);
                 self.voice_amplitude = level;
@@ -2687,10 +6832,32 @@ impl EnhancedApp {
 
     fn process_video_frame(&mut self, channel_id: u64, data: Vec<u8>) -> Result<()> {
         if let Some(call_id) = self.call_channels.get(&channel_id).cloned() {
+            self.call_manager.mark_media(&call_id);
             if let Some(metrics) = self.media.decode_video(&call_id, &data)? {
-                self.call_video_metrics = Some(metrics.clone());
-                let quality = ((metrics.frames_decoded % 60) as f32 / 60.0).clamp(0.0, 1.0);
-                self.push_quality_sample((0.7 + quality).min(1.0));
+                self.call_video_metrics = Some(metrics);
+            }
+            if self
+                .pending_snapshot
+                .as_ref()
+                .is_some_and(|(pending, _)| *pending == call_id)
+                && let Some(snapshot) = self.media.take_snapshot(&call_id)
+                && let Some((_, path)) = self.pending_snapshot.take()
+            {
+                match snapshot.write_png(&path) {
+                    Ok(()) => self.add_notification(
+                        format!(
+                            "📸 Saved {} ({}×{})",
+                            path.display(),
+                            snapshot.width,
+                            snapshot.height
+                        ),
+                        NotificationLevel::Success,
+                    ),
+                    Err(err) => self.add_notification(
+                        format!("Failed to save snapshot: {:#}", err),
+                        NotificationLevel::Error,
+                    ),
+                }
             }
         } else {
             self.add_system_message(format!(
@@ -2721,15 +6888,20 @@ impl EnhancedApp {
         if let Some(idx) = self.channels.iter().position(|c| c.id == channel_id) {
             idx
         } else {
+            let marker = self.unread.get(channel_id).cloned().unwrap_or_default();
             let channel = ChannelView {
                 id: channel_id,
                 name: format!("Channel {}", channel_id),
                 members: Vec::new(),
                 messages: VecDeque::new(),
                 typing: HashMap::new(),
-                unread_count: 0,
+                unread_count: marker.unread,
+                unread_mentions: marker.mentions,
+                muted: marker.muted,
                 is_group: false,
                 group_id: None,
+                typing_limiter: RateLimiter::new(TYPING_SEND_INTERVAL),
+                typing_sent: false,
             };
             self.channels.push(channel);
             self.channels.len() - 1
@@ -2739,8 +6911,19 @@ impl EnhancedApp {
     fn push_channel_message(&mut self, idx: usize, entry: MessageEntry) {
         let channel = &mut self.channels[idx];
         channel.messages.push_back(entry);
-        while channel.messages.len() > MESSAGE_HISTORY_LIMIT {
-            channel.messages.pop_front();
+        while channel.messages.len() > self.state.message_history_limit {
+            // Копия входящего файла в кэше уходит вместе с записью.
+            if let Some(MessageEntry {
+                content:
+                    MessageContent::File(FileMessage {
+                        path: Some(path), ..
+                    }),
+                outgoing: None,
+                ..
+            }) = channel.messages.pop_front()
+            {
+                let _ = std::fs::remove_file(path);
+            }
         }
     }
 
@@ -2762,15 +6945,7 @@ impl EnhancedApp {
         }
         short_hex(trimmed)
     }
-
-    fn push_quality_sample(&mut self, value: f32) {
-        const MAX_SAMPLES: usize = 128;
-        let clamped = value.clamp(0.0, 1.0);
-        self.call_quality_history.push_back(clamped);
-        while self.call_quality_history.len() > MAX_SAMPLES {
-            self.call_quality_history.pop_front();
-        }
-    }
+
     fn parse_group_role(value: &str) -> GroupRole {
         match value.to_lowercase().as_str() {
             "owner" => GroupRole::Owner,
@@ -2841,6 +7016,58 @@ impl EnhancedApp {
                     }
                 }
             }
+            "search" => {
+                if parts.len() < 2 {
+                    self.add_notification(
+                        "Usage: /search <query>".to_string(),
+                        NotificationLevel::Warning,
+                    );
+                } else {
+                    let query = parts[1..].join(" ");
+                    self.search_messages(&query);
+                }
+            }
+            "open" => match parts.get(1).map(|value| value.parse::<usize>()) {
+                Some(Ok(n)) => self.open_message_links(Some(n)),
+                Some(Err(_)) => self.add_notification(
+                    "Usage: /open [link_number]".to_string(),
+                    NotificationLevel::Warning,
+                ),
+                None => self.open_message_links(None),
+            },
+            "voice" => match parts.as_slice() {
+                [_, "save", index, path, rest @ ..] if rest.iter().all(|flag| *flag == "--raw") => {
+                    match index.parse::<usize>() {
+                        Ok(index) => self.save_voice_message(index, path, !rest.is_empty()),
+                        Err(_) => self.add_notification(
+                            format!("Invalid voice message index: {}", index),
+                            NotificationLevel::Error,
+                        ),
+                    }
+                }
+                _ => self.add_notification(
+                    "Usage: /voice save <index> <path> [--raw]".to_string(),
+                    NotificationLevel::Warning,
+                ),
+            },
+            "video" => match parts.as_slice() {
+                [_, "snapshot", path] => self.request_video_snapshot(path),
+                _ => self.add_notification(
+                    "Usage: /video snapshot <path>".to_string(),
+                    NotificationLevel::Warning,
+                ),
+            },
+            "msg" => {
+                if parts.len() < 3 {
+                    self.add_notification(
+                        "Usage: /msg <user_id|handle> <text>".to_string(),
+                        NotificationLevel::Warning,
+                    );
+                } else {
+                    let text = parts[2..].join(" ");
+                    self.send_direct_message(parts[1], text).await?;
+                }
+            }
             "presence" => {
                 if parts.len() < 2 {
                     self.add_notification(
@@ -2853,30 +7080,79 @@ impl EnhancedApp {
                 }
             }
             "theme" => self.cycle_theme(),
+            "mute" => match parts.get(1).map(|value| value.parse::<u64>()) {
+                Some(Err(_)) => self.add_notification(
+                    "Usage: /mute [channel_id]".to_string(),
+                    NotificationLevel::Warning,
+                ),
+                channel_id => {
+                    let channel_id = channel_id
+                        .and_then(Result::ok)
+                        .unwrap_or(self.channels[self.active_channel].id);
+                    self.toggle_channel_mute(channel_id);
+                }
+            },
+            "friends" => match parts.get(1).copied() {
+                Some("push") => self.push_friends().await,
+                Some("pull") => self.pull_friends().await,
+                _ => self.add_notification(
+                    "Usage: /friends <push|pull>".to_string(),
+                    NotificationLevel::Warning,
+                ),
+            },
             "group" => self.handle_group_command(&parts[1..]).await?,
+            "whoami" => self.show_my_info = true,
+            "pair" => match parts.get(1).map(|value| value.parse::<i64>()) {
+                Some(Err(_)) => self.add_notification(
+                    "Usage: /pair [ttl_seconds]".to_string(),
+                    NotificationLevel::Warning,
+                ),
+                ttl => self.issue_pairing(ttl.and_then(Result::ok)).await,
+            },
             "assist" => {
-                if parts.len() < 2 {
+                if parts.len() < 2 && self.assist_report.is_some() {
+                    self.show_assist = true;
+                } else if parts.len() < 2 {
                     self.add_notification(
-                        "Usage: /assist <peer_hint>".to_string(),
+                        "Usage: /assist <peer_hint> [ip:port...]".to_string(),
                         NotificationLevel::Warning,
                     );
                 } else {
-                    self.request_p2p_assist(parts[1]).await?;
+                    match parts[2..]
+                        .iter()
+                        .map(|hint| assist::parse_hint(hint))
+                        .collect::<Result<Vec<_>>>()
+                    {
+                        Ok(hints) => self.request_p2p_assist(parts[1], hints).await?,
+                        Err(err) => {
+                            self.add_notification(err.to_string(), NotificationLevel::Warning)
+                        }
+                    }
                 }
             }
             "send-file" => {
-                if parts.len() < 2 {
+                let path = command
+                    .trim_start()
+                    .strip_prefix("send-file")
+                    .unwrap_or_default()
+                    .trim();
+                if path.is_empty() {
                     self.add_notification(
                         "Usage: /send-file <path>".to_string(),
                         NotificationLevel::Warning,
                     );
                 } else {
-                    self.add_notification(
-                        format!("Sending files isn't wired yet ({} provided)", parts[1]),
-                        NotificationLevel::Info,
-                    );
+                    self.send_file(Path::new(path))?;
                 }
             }
+            "save-file" => {
+                let path = command
+                    .trim_start()
+                    .strip_prefix("save-file")
+                    .unwrap_or_default()
+                    .trim();
+                self.save_file_message((!path.is_empty()).then(|| Path::new(path)));
+            }
             "quit" | "exit" => self.should_quit = true,
             _ => {
                 self.add_notification(
@@ -2930,7 +7206,11 @@ impl EnhancedApp {
                             .iter_mut()
                             .filter(|ch| ch.group_id.as_deref() == Some(group_id))
                         {
-                            if !channel.members.contains(&device.to_string()) {
+                            if !channel
+                                .members
+                                .iter()
+                                .any(|member| self.identities.same(member, device))
+                            {
                                 channel.members.push(device.to_string());
                             }
                         }
@@ -3067,7 +7347,55 @@ impl EnhancedApp {
         Ok(())
     }
 
-    async fn request_p2p_assist(&mut self, peer_hint: &str) -> Result<()> {
+    /// Выдаёт pair-код для нового устройства и сохраняет его в профиль, как `pair` в CLI.
+    async fn issue_pairing(&mut self, ttl: Option<i64>) {
+        let Some(client) = self.rest_client.clone() else {
+            self.add_notification(
+                "REST client unavailable".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        let Some(session) = self.session_id.clone() else {
+            self.add_notification(
+                "No active session: /connect before issuing a pair code".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        let ticket = match client.create_pairing(&session, ttl).await {
+            Ok(ticket) => ticket,
+            Err(err) => {
+                self.add_notification(
+                    format!("Pairing failed: {}", rest_error_text(&err)),
+                    NotificationLevel::Error,
+                );
+                return;
+            }
+        };
+        self.state.last_pairing_code = Some(ticket.pair_code.clone());
+        self.state.last_pairing_expires_at = Some(ticket.expires_at.clone());
+        self.state.last_pairing_issuer_device_id = ticket.issuer_device_id.clone();
+        let result = ClientState::load().and_then(|mut stored| {
+            stored.last_pairing_code = self.state.last_pairing_code.clone();
+            stored.last_pairing_expires_at = self.state.last_pairing_expires_at.clone();
+            stored.last_pairing_issuer_device_id = self.state.last_pairing_issuer_device_id.clone();
+            stored.save()
+        });
+        if let Err(err) = result {
+            self.add_notification(
+                format!("Failed to save pair code: {}", err),
+                NotificationLevel::Warning,
+            );
+        }
+        self.add_notification(
+            format!("🎟 Pair code {} issued", ticket.pair_code),
+            NotificationLevel::Success,
+        );
+        self.pair_ticket = Some(ticket);
+    }
+
+    async fn request_p2p_assist(&mut self, peer_hint: &str, hints: Vec<SocketAddr>) -> Result<()> {
         let Some(client) = self.rest_client.clone() else {
             self.add_notification(
                 "REST client unavailable".to_string(),
@@ -3083,31 +7411,41 @@ impl EnhancedApp {
             return Ok(());
         };
 
+        let candidates =
+            assist::gather_candidates(self.state.assist_stun_server.as_deref(), &hints).await;
+        if let Some(err) = candidates.stun_error {
+            self.add_notification(
+                format!("STUN failed, sending local paths only: {}", err),
+                NotificationLevel::Warning,
+            );
+        }
+        if candidates.paths.is_empty() {
+            self.add_notification(
+                "No local paths found; pass hints as /assist <peer> <ip:port>".to_string(),
+                NotificationLevel::Warning,
+            );
+            return Ok(());
+        }
+
         let request = P2pAssistRequest {
             peer_hint: Some(peer_hint.to_string()),
-            paths: vec![AssistPathHint {
-                address: Some("127.0.0.1".to_string()),
-                id: Some(format!("hint-{}", short_hex(peer_hint))),
-                port: Some(3478),
-                server_name: Some(self.state.server_url.clone()),
-                priority: Some(1),
-                ..Default::default()
-            }],
-            prefer_reality: Some(true),
+            paths: candidates.paths,
+            prefer_reality: Some(self.state.assist_prefer_reality),
             fec: Some(AssistFecHint {
-                mtu: Some(1200),
-                repair_overhead: Some(0.18),
+                mtu: Some(self.state.assist_fec_mtu),
+                repair_overhead: Some(self.state.assist_fec_overhead),
             }),
             min_paths: Some(1),
         };
 
         match client.p2p_assist(&session, &request).await {
             Ok(response) => {
-                self.handle_assist_response(peer_hint, response);
+                self._assist_socket = candidates.socket;
+                self.handle_assist_response(peer_hint, hints, response);
             }
             Err(err) => {
                 self.add_notification(
-                    format!("Assist request failed: {}", err),
+                    format!("Assist request failed: {}", rest_error_text(&err)),
                     NotificationLevel::Error,
                 );
             }
@@ -3116,38 +7454,12 @@ impl EnhancedApp {
         Ok(())
     }
 
-    fn handle_assist_response(&mut self, peer_hint: &str, response: P2pAssistResponse) {
-        let transport_summary = if response.transports.is_empty() {
-            "No transports suggested".to_string()
-        } else {
-            response
-                .transports
-                .iter()
-                .map(|t| {
-                    format!(
-                        "{} via {} ({} · {} · {})",
-                        t.path_id, t.transport, t.resistance, t.latency, t.throughput
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join(" | ")
-        };
-
-        let (total_samples, repair_samples) = response
-            .multipath
-            .sample_segments
-            .values()
-            .fold((0usize, 0usize), |(total, repair), seg| {
-                (total + seg.total, repair + seg.repair)
-            });
-
-        let fingerprint = response
-            .obfuscation
-            .reality_fingerprint_hex
-            .as_deref()
-            .map(short_hex)
-            .unwrap_or_else(|| "-".to_string());
-
+    fn handle_assist_response(
+        &mut self,
+        peer_hint: &str,
+        hints: Vec<SocketAddr>,
+        response: P2pAssistResponse,
+    ) {
         let notification = format!(
             "Assist {} · {} transports · primary {} · MTU {} ({:.0}% FEC)",
             short_hex(peer_hint),
@@ -3161,71 +7473,240 @@ impl EnhancedApp {
             response.multipath.fec_overhead * 100.0,
         );
         self.add_notification(notification, NotificationLevel::Success);
+        self.assist_report = Some(AssistReport {
+            peer_hint: peer_hint.to_string(),
+            hints,
+            response,
+            received_at: Utc::now(),
+        });
+        self.assist_selected = 0;
+        self.show_assist = true;
+    }
 
-        let noise_summary = format!(
-            "Noise {} prologue {} static {} seed {}",
-            response.noise.pattern,
-            short_hex(&response.noise.prologue_hex),
-            short_hex(&response.noise.static_public_hex),
-            short_hex(&response.noise.device_seed_hex)
-        );
-
-        let pq_summary = format!(
-            "PQ id {} signed {} kem {} sig {}",
-            short_hex(&response.pq.identity_public_hex),
-            short_hex(&response.pq.signed_prekey_public_hex),
-            short_hex(&response.pq.kem_public_hex),
-            short_hex(&response.pq.signature_public_hex)
-        );
-
-        let obfuscation_summary = format!(
-            "Obfuscation fingerprint {} fronting {} mimicry {} tor {}",
-            fingerprint,
-            response.obfuscation.domain_fronting,
-            response.obfuscation.protocol_mimicry,
-            response.obfuscation.tor_bridge
-        );
-
-        let sample_summary = format!(
-            "Samples total={} repair={} across {} paths",
-            total_samples,
-            repair_samples,
-            response.multipath.sample_segments.len()
-        );
+    fn render_assist_report(&self, frame: &mut UiFrame, area: Rect) {
+        let Some(report) = self.assist_report.as_ref().filter(|_| self.show_assist) else {
+            return;
+        };
+        let response = &report.response;
+        let popup = Rect {
+            x: area.x + 2.min(area.width),
+            y: area.y + 1.min(area.height),
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(2),
+        };
+        frame.render_widget(Clear, popup);
+        let block = Block::default()
+            .title(format!(
+                " 🛰 Transport diagnostics · {} · {} · ↑↓ select · r refresh · Esc close ",
+                short_hex(&report.peer_hint),
+                self.format_time(report.received_at)
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(self.get_theme_border_style());
+        let inner = block.inner(popup);
+        frame.render_widget(block, popup);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Min(3)])
+            .split(inner);
 
+        let flag = |enabled: bool| {
+            if enabled {
+                Span::styled("on", self.get_theme_tone_style(Tone::Success))
+            } else {
+                Span::styled("off", self.get_theme_tone_style(Tone::Muted))
+            }
+        };
+        let label = |text: &str| Span::styled(format!("{:<12}", text), Style::default().bold());
         let security = &response.security;
-        let security_summary = format!(
-            "Security: noise={} pq={} fec={} sessions={} paths={:.1} deflections={}",
-            security.noise_handshakes,
-            security.pq_handshakes,
-            security.fec_packets,
-            security.multipath_sessions,
-            security.average_paths,
-            security.censorship_deflections
-        );
+        let summary = vec![
+            Line::from(vec![
+                label("Noise"),
+                Span::raw(format!(
+                    "{} · prologue {} · static {} · seed {}",
+                    response.noise.pattern,
+                    short_hex(&response.noise.prologue_hex),
+                    short_hex(&response.noise.static_public_hex),
+                    short_hex(&response.noise.device_seed_hex)
+                )),
+            ]),
+            Line::from(vec![
+                label("PQ"),
+                Span::raw(format!(
+                    "id {} · signed {} · kem {} · sig {}",
+                    short_hex(&response.pq.identity_public_hex),
+                    short_hex(&response.pq.signed_prekey_public_hex),
+                    short_hex(&response.pq.kem_public_hex),
+                    short_hex(&response.pq.signature_public_hex)
+                )),
+            ]),
+            Line::from(vec![
+                label("Multipath"),
+                Span::raw(format!(
+                    "primary {} · FEC MTU {} · overhead {:.0}%",
+                    response.multipath.primary_path.as_deref().unwrap_or("none"),
+                    response.multipath.fec_mtu,
+                    response.multipath.fec_overhead * 100.0
+                )),
+            ]),
+            Line::from(vec![
+                label("Obfuscation"),
+                Span::raw(format!(
+                    "fingerprint {} · fronting ",
+                    response
+                        .obfuscation
+                        .reality_fingerprint_hex
+                        .as_deref()
+                        .map(short_hex)
+                        .unwrap_or_else(|| "-".to_string())
+                )),
+                flag(response.obfuscation.domain_fronting),
+                Span::raw(" · mimicry "),
+                flag(response.obfuscation.protocol_mimicry),
+                Span::raw(" · tor "),
+                flag(response.obfuscation.tor_bridge),
+            ]),
+            Line::from(vec![
+                label("Security"),
+                Span::raw(format!(
+                    "noise {} · pq {} · fec {} · sessions {} · paths {:.1} · deflections {}",
+                    security.noise_handshakes,
+                    security.pq_handshakes,
+                    security.fec_packets,
+                    security.multipath_sessions,
+                    security.average_paths,
+                    security.censorship_deflections
+                )),
+            ]),
+        ];
+        frame.render_widget(Paragraph::new(summary), chunks[0]);
 
-        self.add_system_message(format!(
-            "Assist guidance:\n{}\n{}\n{}\n{}\n{}\n{}",
-            noise_summary,
-            pq_summary,
-            obfuscation_summary,
-            transport_summary,
-            sample_summary,
-            security_summary
-        ));
+        let primary = response.multipath.primary_path.as_deref();
+        let rows: Vec<Row> = response
+            .transports
+            .iter()
+            .map(|transport| {
+                let samples = response.multipath.sample_segments.get(&transport.path_id);
+                let row = Row::new(vec![
+                    format!(
+                        "{}{}",
+                        if primary == Some(transport.path_id.as_str()) {
+                            "★ "
+                        } else {
+                            "  "
+                        },
+                        transport.path_id
+                    ),
+                    transport.transport.clone(),
+                    transport.resistance.clone(),
+                    transport.latency.clone(),
+                    transport.throughput.clone(),
+                    samples.map_or_else(|| "-".to_string(), |s| s.total.to_string()),
+                    samples.map_or_else(|| "-".to_string(), |s| s.repair.to_string()),
+                ]);
+                if primary == Some(transport.path_id.as_str()) {
+                    row.style(Style::default().fg(self.get_theme_primary_color()))
+                } else {
+                    row
+                }
+            })
+            .collect();
+        let empty = rows.is_empty();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(14),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(10),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(vec![
+                "Path",
+                "Transport",
+                "Resistance",
+                "Latency",
+                "Throughput",
+                "Samples",
+                "Repair",
+            ])
+            .style(Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED)),
+        )
+        .block(
+            Block::default()
+                .title(if empty {
+                    " Transports · none suggested "
+                } else {
+                    " Transports "
+                })
+                .borders(Borders::TOP),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut table_state = TableState::default();
+        if !empty {
+            table_state.select(Some(
+                self.assist_selected.min(response.transports.len() - 1),
+            ));
+        }
+        frame.render_stateful_widget(table, chunks[1], &mut table_state);
+    }
+
+    async fn handle_assist_key(&mut self, key: KeyEvent) -> Result<()> {
+        let transports = self
+            .assist_report
+            .as_ref()
+            .map_or(0, |report| report.response.transports.len());
+        match key.code {
+            KeyCode::Esc => self.show_assist = false,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.assist_selected = self.assist_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.assist_selected = (self.assist_selected + 1).min(transports.saturating_sub(1));
+            }
+            KeyCode::Char('r') => {
+                if let Some((peer, hints)) = self
+                    .assist_report
+                    .as_ref()
+                    .map(|report| (report.peer_hint.clone(), report.hints.clone()))
+                {
+                    self.request_p2p_assist(&peer, hints).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
     }
 
     async fn connect(&mut self) -> Result<()> {
         self.add_notification("Connecting...".to_string(), NotificationLevel::Info);
         self.engine
-            .send(EngineCommand::Connect(Box::new(self.state.clone())))
+            .send(EngineCommand::Connect {
+                state: Box::new(self.state.clone()),
+                accept_new_server_key: self.options.accept_new_server_key,
+            })
             .await?;
         Ok(())
     }
 
+    /// Статус присутствия, объявляемый перед отключением (пустой — не слать).
+    fn offline_presence(&self) -> Option<String> {
+        Some(self.options.offline_state.clone()).filter(|state| !state.is_empty())
+    }
+
     async fn disconnect(&mut self) -> Result<()> {
         self.add_notification("Disconnecting...".to_string(), NotificationLevel::Info);
-        self.engine.send(EngineCommand::Disconnect).await?;
+        self.end_active_call(CallEndReason::Hangup).await?;
+        self.engine
+            .send(EngineCommand::Disconnect {
+                presence: self.offline_presence(),
+            })
+            .await?;
         Ok(())
     }
 
@@ -3249,6 +7730,99 @@ impl EnhancedApp {
         Ok(())
     }
 
+    fn resolve_contact(&self, target: &str) -> Option<(String, String)> {
+        if let Some(friend) = self.state.friends().iter().find(|f| {
+            f.user_id == target
+                || f.handle.as_deref() == Some(target)
+                || f.alias.as_deref() == Some(target)
+        }) {
+            let label = friend
+                .alias
+                .clone()
+                .or_else(|| friend.handle.clone())
+                .unwrap_or_else(|| friend.user_id.clone());
+            return Some((friend.user_id.clone(), label));
+        }
+        self.presence.iter().find_map(|(device, info)| {
+            let matches = device == target
+                || info.user_id.as_deref() == Some(target)
+                || info.handle.as_deref() == Some(target);
+            matches.then(|| {
+                let user_id = info.user_id.clone().unwrap_or_else(|| device.clone());
+                let label = info
+                    .display_name
+                    .clone()
+                    .or_else(|| info.handle.clone())
+                    .unwrap_or_else(|| user_id.clone());
+                (user_id, label)
+            })
+        })
+    }
+
+    async fn send_direct_message(&mut self, target: &str, text: String) -> Result<()> {
+        if !self.connected {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        }
+        let Some((user_id, label)) = self.resolve_contact(target) else {
+            self.add_notification(
+                format!("Unknown contact: {}", target),
+                NotificationLevel::Warning,
+            );
+            return Ok(());
+        };
+
+        self.open_direct_chat(&user_id, label).await?;
+        self.send_message(text).await
+    }
+
+    async fn open_direct_chat(&mut self, user_id: &str, label: String) -> Result<()> {
+        let channel_id = self.ensure_direct_channel(user_id).await?;
+        let idx = self.ensure_channel(channel_id);
+        self.channels[idx].name = label;
+        self.open_channel(idx);
+        self.view = AppView::Chat;
+        Ok(())
+    }
+
+    async fn ensure_direct_channel(&mut self, user_id: &str) -> Result<u64> {
+        let channel_id = match self.direct_channels.get(user_id) {
+            Some(channel_id) => *channel_id,
+            None => {
+                let own = self
+                    .state
+                    .user_id
+                    .as_deref()
+                    .unwrap_or(&self.state.device_id);
+                let channel_id = direct_channel_id(own, user_id);
+                let mut members = vec![self.state.device_id.clone()];
+                members.extend(
+                    self.presence
+                        .iter()
+                        .filter(|(device, info)| {
+                            info.user_id.as_deref() == Some(user_id) || device.as_str() == user_id
+                        })
+                        .map(|(device, _)| device.clone()),
+                );
+                if members.len() == 1 {
+                    members.push(user_id.to_string());
+                }
+                self.engine
+                    .send(EngineCommand::Join {
+                        channel_id,
+                        members: members.clone(),
+                        relay: true,
+                    })
+                    .await?;
+                self.direct_channels.insert(user_id.to_string(), channel_id);
+                let idx = self.ensure_channel(channel_id);
+                self.channels[idx].members = members;
+                channel_id
+            }
+        };
+        Ok(channel_id)
+    }
+
     async fn leave_channel(&mut self, channel_id: u64) -> Result<()> {
         if !self.connected {
             self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
@@ -3283,31 +7857,297 @@ impl EnhancedApp {
         Ok(())
     }
 
-    async fn refresh_devices(&mut self) -> Result<()> {
-        if let (Some(client), Some(session)) = (self.rest_client.clone(), self.session_id.clone()) {
-            match client.list_devices(&session).await {
-                Ok(devices) => {
-                    self.devices = devices;
-                    self.add_notification(
-                        format!("🔁 Devices synced ({} entries)", self.devices.len()),
-                        NotificationLevel::Success,
-                    );
+    async fn refresh_server_info(&mut self) {
+        let Some(client) = self.rest_client.clone() else {
+            return;
+        };
+        let info = match tokio::time::timeout(SERVER_INFO_TIMEOUT, client.server_info()).await {
+            Ok(Ok(info)) => info,
+            Ok(Err(err)) => {
+                self.push_engine_log(LogLevel::Warn, format!("server info failed: {}", err));
+                return;
+            }
+            Err(_) => {
+                self.push_engine_log(LogLevel::Warn, "server info timed out".to_string());
+                return;
+            }
+        };
+        if let Some(pairing) = info.pairing.as_ref()
+            && !pairing.auto_approve
+        {
+            self.add_notification(
+                "🔐 Server does not auto-approve new devices: a human must approve them"
+                    .to_string(),
+                NotificationLevel::Warning,
+            );
+        }
+        self.server_pairing = info.pairing;
+    }
+
+    /// Политика pairing сервера и обратный отсчёт последнего выданного кода.
+    fn pairing_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        match self.server_pairing.as_ref() {
+            Some(pairing) if pairing.auto_approve => lines.push(Line::from(format!(
+                "🔗 Pairing: auto-approve up to {} devices · codes live {}",
+                pairing.max_auto_devices,
+                format_countdown(pairing.pairing_ttl)
+            ))),
+            Some(pairing) => lines.push(Line::styled(
+                format!(
+                    "🔗 Pairing: manual approval required · codes live {}",
+                    format_countdown(pairing.pairing_ttl)
+                ),
+                self.get_theme_tone_style(Tone::Warning)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            None => lines.push(Line::styled(
+                "🔗 Pairing policy: unknown (connect to load)",
+                self.get_theme_tone_style(Tone::Muted),
+            )),
+        }
+        if let (Some(code), Some(expires_at)) = (
+            self.state.last_pairing_code.as_deref(),
+            self.state.last_pairing_expires_at.as_deref(),
+        ) && let Ok(expires_at) = DateTime::parse_from_rfc3339(expires_at)
+        {
+            let left = expires_at
+                .with_timezone(&Utc)
+                .signed_duration_since(Utc::now())
+                .num_seconds();
+            lines.push(if left > 0 {
+                Line::styled(
+                    format!("🎟 Pair code {} expires in {}", code, format_countdown(left)),
+                    self.get_theme_tone_style(Tone::Accent),
+                )
+            } else {
+                Line::styled(
+                    format!("🎟 Pair code {} expired", code),
+                    self.get_theme_tone_style(Tone::Muted),
+                )
+            });
+        }
+        lines
+    }
+
+    /// Список очищается, когда придёт первая страница, а не сразу.
+    fn refresh_devices(&mut self) {
+        self.fetch_devices_page(None);
+    }
+
+    fn load_more_devices(&mut self) {
+        let Some(cursor) = self.devices_cursor.clone() else {
+            self.add_notification("No more devices".to_string(), NotificationLevel::Info);
+            return;
+        };
+        self.fetch_devices_page(Some(cursor));
+    }
+
+    fn visible_devices(&self) -> Vec<&DeviceEntry> {
+        self.devices
+            .iter()
+            .filter(|entry| {
+                self.devices_filter
+                    .is_none_or(|status| entry.status.eq_ignore_ascii_case(status))
+            })
+            .collect()
+    }
+
+    /// Первый шаг отзыва: текущее устройство отзывать из TUI нельзя.
+    fn request_device_revoke(&mut self) {
+        let Some(entry) = self
+            .visible_devices()
+            .get(self.devices_selected)
+            .map(|entry| (*entry).clone())
+        else {
+            self.add_notification("No device selected".to_string(), NotificationLevel::Warning);
+            return;
+        };
+        if entry.current || entry.device_id == self.state.device_id {
+            self.add_notification(
+                "Refusing to revoke the current device (use the CLI with --force)".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        }
+        self.revoke_confirm = Some(entry);
+    }
+
+    async fn revoke_device(&mut self, device_id: &str) -> Result<()> {
+        let (Some(client), Some(session)) = (self.rest_client.clone(), self.session_id.clone())
+        else {
+            self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
+            return Ok(());
+        };
+        match client.revoke_device(&session, device_id).await {
+            Ok(()) => {
+                self.add_notification(
+                    format!("🚫 Device {} revoked", short_hex(device_id)),
+                    NotificationLevel::Success,
+                );
+                self.refresh_devices();
+            }
+            Err(err) => self.add_notification(
+                format!("Revoke failed: {}", rest_error_text(&err)),
+                NotificationLevel::Error,
+            ),
+        }
+        Ok(())
+    }
+
+    fn cycle_devices_filter(&mut self) {
+        self.devices_selected = 0;
+        self.devices_filter = match self.devices_filter {
+            None => Some(DEVICE_STATUS_FILTERS[0]),
+            Some(current) => DEVICE_STATUS_FILTERS
+                .iter()
+                .position(|status| *status == current)
+                .and_then(|idx| DEVICE_STATUS_FILTERS.get(idx + 1))
+                .copied(),
+        };
+    }
+
+    /// Запрашивает страницу устройств в фоне; `None` — с начала списка.
+    fn fetch_devices_page(&mut self, cursor: Option<String>) {
+        let (Some(client), Some(session)) = (self.rest_client.clone(), self.session_id.clone())
+        else {
+            let text = match self.devices_cached_at {
+                Some(_) => "Offline: showing cached devices, /connect to refresh",
+                None => "Offline: /connect to load devices",
+            };
+            self.add_notification(text.to_string(), NotificationLevel::Warning);
+            return;
+        };
+        let reset = cursor.is_none();
+        if !self.tasks.start(
+            TaskKind::DeviceSync,
+            "🔁 Syncing devices",
+            Measure::Bytes,
+            None,
+            Instant::now(),
+        ) {
+            return;
+        }
+        let query = DevicesQuery {
+            limit: Some(DEVICES_PAGE_SIZE),
+            cursor,
+            status: None,
+        };
+        let tx = self.task_tx.clone();
+        let handle = tokio::spawn(async move {
+            let result = client
+                .list_devices(&session, &query)
+                .await
+                .map_err(|err| rest_error_text(&err));
+            let _ = tx.send(TaskEvent::DevicesLoaded { reset, result }).await;
+        });
+        self.tasks
+            .set_abort(&TaskKind::DeviceSync, handle.abort_handle());
+    }
+
+    fn handle_task_event(&mut self, event: TaskEvent) {
+        match event {
+            TaskEvent::DevicesLoaded { reset, result } => {
+                if !self.tasks.finish(&TaskKind::DeviceSync) {
+                    return;
                 }
-                Err(err) => {
-                    self.add_notification(
+                match result {
+                    Ok(page) => {
+                        if reset {
+                            self.devices_selected = 0;
+                            self.devices.clear();
+                        }
+                        if let Some(user_id) = self.state.user_id.as_deref() {
+                            for device in &page.devices {
+                                self.identities.link(&device.device_id, user_id);
+                            }
+                        }
+                        self.devices.extend(page.devices);
+                        self.devices_cursor = page.next_cursor;
+                        self.devices_cached_at = None;
+                        let cache = DeviceCache {
+                            fetched_at: Utc::now(),
+                            devices: self.devices.clone(),
+                        };
+                        if let Err(err) = cache.save() {
+                            tracing::warn!(target: LOG_TARGET, "device cache not saved: {:#}", err);
+                        }
+                        self.add_notification(
+                            format!("🔁 Devices synced ({} entries)", self.devices.len()),
+                            NotificationLevel::Success,
+                        );
+                    }
+                    Err(err) => self.add_notification(
                         format!("Device sync failed: {}", err),
                         NotificationLevel::Error,
-                    );
+                    ),
+                }
+            }
+            TaskEvent::VoiceExported { path, temp, result } => {
+                if !self.tasks.finish(&TaskKind::VoiceExport) {
+                    // Отменено, пока файл писался: целевой файл не тронут.
+                    let _ = std::fs::remove_file(&temp);
+                    return;
+                }
+                let result = result.and_then(|duration| {
+                    std::fs::rename(&temp, &path)
+                        .map(|()| duration)
+                        .map_err(|err| format!("rename {}: {}", temp.display(), err))
+                });
+                if result.is_err() {
+                    let _ = std::fs::remove_file(&temp);
+                }
+                match result {
+                    Ok(duration) => self.add_notification(
+                        format!(
+                            "💾 Saved {} ({:.1} s)",
+                            path.display(),
+                            duration.as_secs_f32()
+                        ),
+                        NotificationLevel::Success,
+                    ),
+                    Err(err) => self.add_notification(
+                        format!("Failed to save voice message: {}", err),
+                        NotificationLevel::Error,
+                    ),
                 }
             }
+            TaskEvent::ProfileLoaded { id, user } => self.apply_profile(id, user),
         }
-        Ok(())
     }
 
-    async fn send_message(&mut self, text: String) -> Result<()> {
+    /// Esc при пустом вводе: отменяет самую новую длительную операцию.
+    fn cancel_latest_task(&mut self) {
+        let Some(kind) = self.tasks.cancel_latest() else {
+            return;
+        };
+        let what = match kind {
+            TaskKind::Upload(local_id) => {
+                self.uploads.retain(|upload| upload.local_id != local_id);
+                if let Some(pending) = self.pending_sends.remove(&local_id)
+                    && let Some(outgoing) = self
+                        .outgoing_message_mut(pending.channel_id, local_id)
+                        .and_then(|entry| entry.outgoing.as_mut())
+                {
+                    outgoing.delivery = Delivery::Failed;
+                }
+                "Upload"
+            }
+            TaskKind::VoicePlayback => {
+                self.voice_playback = None;
+                "Playback"
+            }
+            TaskKind::VoiceExport => "Voice export",
+            TaskKind::DeviceSync => "Device sync",
+        };
+        self.add_notification(format!("✖ {} cancelled", what), NotificationLevel::Info);
+    }
+
+    /// Канал, куда можно писать прямо сейчас; иначе — уведомление и `None`.
+    fn sendable_channel(&mut self) -> Option<u64> {
         if !self.connected {
             self.add_notification("Not connected".to_string(), NotificationLevel::Warning);
-            return Ok(());
+            return None;
         }
 
         let (channel_id, channel_group_id) = self
@@ -3321,7 +8161,7 @@ impl EnhancedApp {
                 "Select a conversation channel before sending messages".to_string(),
                 NotificationLevel::Warning,
             );
-            return Ok(());
+            return None;
         }
 
         if let Some(group_id) = channel_group_id
@@ -3333,9 +8173,17 @@ impl EnhancedApp {
                 format!("You lack send permission in group {}", short_hex(&group_id)),
                 NotificationLevel::Warning,
             );
-            return Ok(());
+            return None;
         }
+        Some(channel_id)
+    }
+
+    async fn send_message(&mut self, text: String) -> Result<()> {
+        let Some(channel_id) = self.sendable_channel() else {
+            return Ok(());
+        };
 
+        let local_id = self.track_outgoing(channel_id);
         let channel = &mut self.channels[self.active_channel];
 
         // Add message to local history
@@ -3344,6 +8192,12 @@ impl EnhancedApp {
             sender: self.state.device_id.clone(),
             content: MessageContent::Text(text.clone()),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: Some(Outgoing {
+                local_id,
+                delivery: Delivery::Pending,
+            }),
+            raw: None,
         };
         channel.messages.push_back(entry);
 
@@ -3351,6 +8205,7 @@ impl EnhancedApp {
         self.engine
             .send(EngineCommand::SendMessage {
                 channel_id,
+                local_id,
                 body: text.into_bytes(),
             })
             .await?;
@@ -3358,7 +8213,509 @@ impl EnhancedApp {
         Ok(())
     }
 
+    /// Ставит файл в очередь: куски читаются и уходят на тиках (`pump_uploads`).
+    fn send_file(&mut self, path: &Path) -> Result<()> {
+        let Some(channel_id) = self.sendable_channel() else {
+            return Ok(());
+        };
+        let limit = self.state.max_attachment_bytes;
+        let size = match std::fs::metadata(path) {
+            Ok(meta) if meta.is_file() => meta.len(),
+            Ok(_) => {
+                self.add_notification(
+                    format!("{} is not a file", path.display()),
+                    NotificationLevel::Warning,
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                self.add_notification(
+                    format!("Can't read {}: {}", path.display(), err),
+                    NotificationLevel::Warning,
+                );
+                return Ok(());
+            }
+        };
+        if size > limit {
+            self.add_notification(
+                format!(
+                    "📎 {} is {}, over the {} limit (max_attachment_bytes)",
+                    path.display(),
+                    attachments::format_size(size),
+                    attachments::format_size(limit)
+                ),
+                NotificationLevel::Warning,
+            );
+            return Ok(());
+        }
+        let source = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                self.add_notification(
+                    format!("Can't read {}: {}", path.display(), err),
+                    NotificationLevel::Warning,
+                );
+                return Ok(());
+            }
+        };
+        let name = attachments::safe_file_name(
+            &path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        let mime = attachments::mime_for(path);
+        let upload = Upload::new(
+            &self.state.device_id,
+            &name,
+            mime,
+            source,
+            size,
+            attachments::CHUNK_SIZE,
+        )?;
+        let chunks = upload.chunks;
+        let local_id = self.track_outgoing(channel_id);
+        let entry = MessageEntry {
+            timestamp: Utc::now(),
+            sender: self.state.device_id.clone(),
+            content: MessageContent::File(FileMessage {
+                id: upload.id.clone(),
+                name: name.clone(),
+                mime: mime.to_string(),
+                size,
+                transferred: 0,
+                path: Some(path.to_path_buf()),
+            }),
+            reactions: HashMap::new(),
+            sequence: None,
+            outgoing: Some(Outgoing {
+                local_id,
+                delivery: Delivery::Pending,
+            }),
+            raw: None,
+        };
+        self.push_channel_message(self.active_channel, entry);
+        self.uploads.push_back(PendingUpload {
+            channel_id,
+            local_id,
+            upload,
+            announced: 0,
+        });
+        self.tasks.start(
+            TaskKind::Upload(local_id),
+            format!("📤 {}", name),
+            Measure::Bytes,
+            Some(size),
+            Instant::now(),
+        );
+        self.add_notification(
+            format!(
+                "📎 Sending {} ({}, {} chunks)",
+                name,
+                attachments::format_size(size),
+                chunks
+            ),
+            NotificationLevel::Info,
+        );
+        Ok(())
+    }
+
+    /// Отдаёт движку очередные куски файлов, не больше
+    /// `UPLOAD_CHUNKS_PER_TICK` за тик, чтобы не забить очередь команд.
+    async fn pump_uploads(&mut self) -> Result<()> {
+        // Сообщение уже помечено failed (обрыв, тайм-аут) — файл не досылаем.
+        let tasks = &mut self.tasks;
+        self.uploads.retain(|upload| {
+            let alive = self.pending_sends.contains_key(&upload.local_id);
+            if !alive {
+                tasks.finish(&TaskKind::Upload(upload.local_id));
+            }
+            alive
+        });
+        if !self.connected {
+            return Ok(());
+        }
+        // Тайм-аут доставки отсчитывается от последнего куска, а не от очереди.
+        let now = Instant::now();
+        for upload in self.uploads.iter().filter(|u| u.upload.remaining() > 0) {
+            if let Some(pending) = self.pending_sends.get_mut(&upload.local_id) {
+                pending.sent_at = now;
+            }
+        }
+        for _ in 0..UPLOAD_CHUNKS_PER_TICK {
+            let Some(current) = self
+                .uploads
+                .iter_mut()
+                .find(|upload| upload.upload.remaining() > 0)
+            else {
+                break;
+            };
+            let (channel_id, local_id) = (current.channel_id, current.local_id);
+            let body = match current.upload.next_body() {
+                Ok(Some(body)) => body,
+                Ok(None) => break,
+                Err(err) => {
+                    self.uploads.retain(|upload| upload.local_id != local_id);
+                    self.tasks.finish(&TaskKind::Upload(local_id));
+                    self.fail_outgoing(local_id, &format!("{:#}", err));
+                    continue;
+                }
+            };
+            self.engine
+                .send(EngineCommand::SendMessage {
+                    channel_id,
+                    local_id,
+                    body,
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Номер кадра для куска файла. Промежуточные запоминаются, чтобы их ACK
+    /// не путался с сообщениями; номер последнего достаётся самому сообщению.
+    fn note_chunk_sent(&mut self, channel_id: u64, local_id: u64, sequence: u64) -> bool {
+        let Some(pos) = self
+            .uploads
+            .iter()
+            .position(|upload| upload.local_id == local_id)
+        else {
+            return false;
+        };
+        let upload = &mut self.uploads[pos];
+        upload.announced += 1;
+        let last = upload.announced >= upload.upload.chunks;
+        let size = upload.upload.size;
+        if last {
+            self.uploads.remove(pos);
+        } else {
+            self.chunk_sequences.insert((channel_id, sequence));
+        }
+        let mut transferred = None;
+        if let Some(entry) = self.outgoing_message_mut(channel_id, local_id)
+            && let MessageContent::File(file) = &mut entry.content
+        {
+            file.transferred = if last {
+                size
+            } else {
+                (file.transferred + attachments::CHUNK_SIZE as u64).min(size)
+            };
+            transferred = Some(file.transferred);
+        }
+        if last {
+            self.tasks.finish(&TaskKind::Upload(local_id));
+        } else if let Some(transferred) = transferred {
+            self.tasks.update(&TaskKind::Upload(local_id), transferred);
+        }
+        !last
+    }
+
+    /// Входящий кусок файла: запись в ленте появляется с первым куском и
+    /// показывает прогресс, уведомление — когда файл собран.
+    fn process_file_chunk(&mut self, idx: usize, sequence: u64, sender: String, chunk: FileChunk) {
+        let header = chunk.header.clone();
+        let progress = self.incoming_files.push(&sender, chunk);
+        if let Some((evicted_sender, evicted)) = self.incoming_files.take_evicted()
+            && self.remove_incoming_file(&evicted_sender, &evicted.id)
+        {
+            self.notify_dropped_file(&evicted, &evicted_sender, "too many files at once");
+        }
+        let name = attachments::safe_file_name(&header.name);
+        let (transferred, path) = match progress {
+            Progress::Rejected(reason) => {
+                if self.remove_incoming_file(&sender, &header.id) || header.chunk == 0 {
+                    self.notify_dropped_file(&header, &sender, &reason);
+                }
+                return;
+            }
+            Progress::Partial { received } => (received, None),
+            Progress::Complete(data) => match attachments::store_received(&name, &data) {
+                Ok(path) => (header.size, Some(path)),
+                Err(err) => {
+                    self.remove_incoming_file(&sender, &header.id);
+                    self.notify_dropped_file(&header, &sender, &format!("{:#}", err));
+                    return;
+                }
+            },
+        };
+        let complete = path.is_some();
+        let position = self.channels[idx].messages.iter().rposition(|entry| {
+            entry.sender == sender
+                && matches!(&entry.content, MessageContent::File(file) if file.id == header.id)
+        });
+        match position {
+            Some(pos) => {
+                if let MessageContent::File(file) = &mut self.channels[idx].messages[pos].content {
+                    file.transferred = transferred;
+                    file.path = path;
+                }
+            }
+            None => {
+                self.queue_profile_lookup(&sender);
+                if sender != "unknown" {
+                    self.push_member(idx, &sender);
+                }
+                let entry = MessageEntry {
+                    timestamp: Utc::now(),
+                    sender: sender.clone(),
+                    content: MessageContent::File(FileMessage {
+                        id: header.id,
+                        name: name.clone(),
+                        mime: header.mime.chars().filter(|c| !c.is_control()).collect(),
+                        size: header.size,
+                        transferred,
+                        path,
+                    }),
+                    reactions: HashMap::new(),
+                    sequence: Some(sequence),
+                    outgoing: None,
+                    raw: None,
+                };
+                self.push_channel_message(idx, entry);
+            }
+        }
+        if complete {
+            let preview = format!("📎 {} ({})", name, attachments::format_size(header.size));
+            self.announce_incoming(idx, sequence, &sender, false, preview);
+        }
+    }
+
+    /// Убирает из ленты недособранный входящий файл, в каком бы канале он
+    /// ни был. `false` — записи не было.
+    fn remove_incoming_file(&mut self, sender: &str, id: &str) -> bool {
+        for channel in &mut self.channels {
+            if let Some(pos) = channel.messages.iter().rposition(|entry| {
+                entry.outgoing.is_none()
+                    && entry.sender == sender
+                    && matches!(&entry.content, MessageContent::File(file) if file.id == id)
+            }) {
+                channel.messages.remove(pos);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn notify_dropped_file(&mut self, header: &FileHeader, sender: &str, reason: &str) {
+        self.add_notification(
+            format!(
+                "📎 Dropped {} from {}: {}",
+                attachments::safe_file_name(&header.name),
+                self.get_friend_display_name(sender),
+                reason
+            ),
+            NotificationLevel::Warning,
+        );
+    }
+
+    /// Сохраняет файл из выделенного сообщения или последний собранный в
+    /// канале. Без пути — в папку загрузок, не перезаписывая чужие файлы.
+    fn save_file_message(&mut self, path: Option<&Path>) {
+        let messages = &self.channels[self.active_channel].messages;
+        let entry = match self.selected_message {
+            Some(idx) => messages.get(idx),
+            None => messages.iter().rev().find(
+                |entry| matches!(&entry.content, MessageContent::File(file) if file.path.is_some()),
+            ),
+        };
+        let Some(MessageEntry {
+            content: MessageContent::File(file),
+            ..
+        }) = entry
+        else {
+            self.add_notification(
+                "No file to save here".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        let Some(source) = file.path.clone() else {
+            self.add_notification(
+                format!("{} is still transferring", file.name),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        let target = match path {
+            Some(path) if path.is_dir() => attachments::free_path(path, &file.name),
+            Some(path) => path.to_path_buf(),
+            None => attachments::free_path(&attachments::download_dir(), &file.name),
+        };
+        // Копия файла в самого себя обнулила бы его.
+        let same_file = target
+            .canonicalize()
+            .is_ok_and(|target| source.canonicalize().is_ok_and(|source| source == target));
+        let copied = if same_file {
+            Ok(0)
+        } else {
+            std::fs::copy(&source, &target)
+        };
+        match copied {
+            Ok(_) => self.add_notification(
+                format!("💾 Saved {}", target.display()),
+                NotificationLevel::Success,
+            ),
+            Err(err) => self.add_notification(
+                format!("Failed to save {}: {}", target.display(), err),
+                NotificationLevel::Error,
+            ),
+        }
+    }
+
+    /// Последние голосовые сообщения (новые первыми), как в списке Voice.
+    fn recent_voice_messages(&self) -> Vec<(DateTime<Utc>, String, VoiceMessage)> {
+        self.channels
+            .iter()
+            .flat_map(|channel| channel.messages.iter().rev())
+            .filter_map(|entry| match &entry.content {
+                MessageContent::Voice(voice) => {
+                    Some((entry.timestamp, entry.sender.clone(), voice.clone()))
+                }
+                _ => None,
+            })
+            .take(VOICE_LIST_LIMIT)
+            .collect()
+    }
+
+    fn play_last_voice_message(&mut self) {
+        let voice = self.channels[self.active_channel]
+            .messages
+            .iter()
+            .rev()
+            .find_map(|entry| match &entry.content {
+                MessageContent::Voice(voice) => Some(voice.clone()),
+                _ => None,
+            });
+        let Some(voice) = voice else {
+            self.add_notification(
+                "No voice messages in this channel".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        match VoicePlayback::start(&voice) {
+            Ok(playback) => {
+                self.tasks.finish(&TaskKind::VoicePlayback);
+                self.tasks.start(
+                    TaskKind::VoicePlayback,
+                    "▶ Voice message",
+                    Measure::Millis,
+                    Some(playback.duration().as_millis() as u64),
+                    Instant::now(),
+                );
+                self.voice_playback = Some(playback);
+            }
+            Err(err) => self.add_notification(
+                format!("Playback failed: {:#}", err),
+                NotificationLevel::Error,
+            ),
+        }
+    }
+
+    fn poll_voice_playback(&mut self) {
+        if let Some(playback) = self.voice_playback.as_ref() {
+            let played = playback
+                .duration()
+                .mul_f32(playback.progress().clamp(0.0, 1.0));
+            self.tasks
+                .update(&TaskKind::VoicePlayback, played.as_millis() as u64);
+        }
+        if self
+            .voice_playback
+            .as_ref()
+            .is_some_and(VoicePlayback::finished)
+        {
+            self.voice_playback = None;
+            self.tasks.finish(&TaskKind::VoicePlayback);
+            self.add_notification("⏹ Playback finished".to_string(), NotificationLevel::Info);
+        }
+    }
+
+    fn request_video_snapshot(&mut self, path: &str) {
+        let Some(call_id) = self
+            .active_call
+            .clone()
+            .filter(|call_id| self.media.request_snapshot(call_id))
+        else {
+            self.add_notification(
+                "No active video stream to snapshot".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        self.pending_snapshot = Some((call_id, PathBuf::from(path)));
+        self.add_notification(
+            format!("📸 Next video frame will be saved to {}", path),
+            NotificationLevel::Info,
+        );
+    }
+
+    fn save_voice_message(&mut self, index: usize, path: &str, raw: bool) {
+        let messages = self.recent_voice_messages();
+        let Some((_, _, voice)) = index.checked_sub(1).and_then(|idx| messages.get(idx)) else {
+            self.add_notification(
+                format!("No voice message #{}", index),
+                NotificationLevel::Warning,
+            );
+            return;
+        };
+        if !self.tasks.start(
+            TaskKind::VoiceExport,
+            format!("💾 {}", path),
+            Measure::Millis,
+            None,
+            Instant::now(),
+        ) {
+            self.add_notification(
+                "A voice message is already being saved".to_string(),
+                NotificationLevel::Warning,
+            );
+            return;
+        }
+        // Декодирование Opus и запись WAV идут вне цикла событий, во временный
+        // файл: `path` появится только после переименования в handle_task_event,
+        // так что отмена не оставит и не удалит чужой файл.
+        let voice = voice.clone();
+        let path = PathBuf::from(path);
+        let mut temp = path.clone().into_os_string();
+        temp.push(".part");
+        let temp = PathBuf::from(temp);
+        let tx = self.task_tx.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            let result =
+                export_voice_message(&voice, &temp, raw).map_err(|err| format!("{:#}", err));
+            let _ = tx.blocking_send(TaskEvent::VoiceExported { path, temp, result });
+        });
+        self.tasks
+            .set_abort(&TaskKind::VoiceExport, handle.abort_handle());
+    }
+
     fn finalize_voice_recording(&mut self) -> Result<()> {
+        if !self.voice_frames.is_empty() {
+            let frames = std::mem::take(&mut self.voice_frames);
+            let mut voice = VoiceMessage::new(frames.len() as u32 * FRAME_DURATION_MS);
+            for frame in &frames {
+                voice.add_frame(frame);
+            }
+            let frame_count = voice.frames.len();
+            let entry = MessageEntry {
+                timestamp: Utc::now(),
+                sender: self.state.device_id.clone(),
+                content: MessageContent::Voice(voice),
+                reactions: HashMap::new(),
+                sequence: None,
+                outgoing: None,
+                raw: None,
+            };
+            self.channels[self.active_channel].messages.push_back(entry);
+            self.add_notification(
+                format!("🎙️ Voice memo saved ({} frames)", frame_count),
+                NotificationLevel::Success,
+            );
+            self.voice_buffer.clear();
+            return Ok(());
+        }
+
         if self.voice_buffer.is_empty() {
             self.add_notification(
                 "Voice recording discarded (no audio captured)".to_string(),
@@ -3382,6 +8739,9 @@ impl EnhancedApp {
             sender: self.state.device_id.clone(),
             content: MessageContent::Voice(restored.clone()),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         self.channels[self.active_channel].messages.push_back(entry);
 
@@ -3401,11 +8761,14 @@ impl EnhancedApp {
             sender: "System".to_string(),
             content: MessageContent::System(message),
             reactions: HashMap::new(),
+            sequence: None,
+            outgoing: None,
+            raw: None,
         };
         system_channel.messages.push_back(entry);
 
         // Limit history
-        while system_channel.messages.len() > MESSAGE_HISTORY_LIMIT {
+        while system_channel.messages.len() > self.state.message_history_limit {
             system_channel.messages.pop_front();
         }
     }
@@ -3420,8 +8783,12 @@ impl ChannelView {
             messages: VecDeque::new(),
             typing: HashMap::new(),
             unread_count: 0,
+            unread_mentions: 0,
+            muted: false,
             is_group: false,
             group_id: None,
+            typing_limiter: RateLimiter::new(TYPING_SEND_INTERVAL),
+            typing_sent: false,
         }
     }
 }
@@ -3443,6 +8810,118 @@ fn restore_terminal(mut terminal: Terminal<CrosstermBackend<Stdout>>) -> Result<
     Ok(())
 }
 
+/// `:name:` из KAWAII_REACTIONS или произвольный эмодзи, введённый вручную.
+fn resolve_reaction(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    match input
+        .strip_prefix(':')
+        .and_then(|rest| rest.strip_suffix(':'))
+    {
+        Some(name) => KAWAII_REACTIONS
+            .iter()
+            .find(|(candidate, _, _)| *candidate == name)
+            .map(|(_, _, emoji)| emoji.to_string()),
+        None => Some(input.to_string()),
+    }
+}
+
+fn apply_reaction(
+    reactions: &mut HashMap<String, Vec<String>>,
+    emoji: &str,
+    user: &str,
+    remove: bool,
+) {
+    let users = reactions.entry(emoji.to_string()).or_default();
+    if remove {
+        users.retain(|existing| existing != user);
+    } else if !users.iter().any(|existing| existing == user) {
+        users.push(user.to_string());
+    }
+    if users.is_empty() {
+        reactions.remove(emoji);
+    }
+}
+
+/// Текст ошибки REST; при 401/403 подсказывает переподключиться за новой сессией.
+fn rest_error_text(err: &anyhow::Error) -> String {
+    if is_session_rejected(err) {
+        format!("{} (session rejected, /connect to refresh it)", err)
+    } else {
+        err.to_string()
+    }
+}
+
+fn spawn_sound_command(command: &str) -> Result<()> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().context("empty sound command")?;
+    let mut child = std::process::Command::new(program)
+        .args(parts)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("run {}", program))?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+fn ring_bell() -> Result<()> {
+    let mut out = stdout();
+    out.write_all(b"\x07")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Детерминированный id личного канала: одинаков у обоих собеседников.
+fn direct_channel_id(left: &str, right: &str) -> u64 {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in first.bytes().chain([0]).chain(second.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash.max(1)
+}
+
+fn format_call_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Секунды в виде `1h 05m 09s` / `4m 09s` / `9s` для обратного отсчёта.
+fn format_countdown(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn format_remaining(left: Duration) -> String {
+    let minutes = left.as_secs() / 60;
+    if minutes >= 24 * 60 {
+        format!("{}d {}h", minutes / (24 * 60), minutes / 60 % 24)
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
+
 fn set_cursor(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     area: Option<Rect>,
@@ -3456,12 +8935,19 @@ fn set_cursor(
     Ok(())
 }
 
-pub async fn run_tui(state: ClientState) -> Result<()> {
-    run_enhanced_tui(state).await
+pub async fn run_tui(state: ClientState, options: TuiOptions) -> Result<()> {
+    run_enhanced_tui(state, options).await
 }
 
-pub async fn run_enhanced_tui(state: ClientState) -> Result<()> {
-    let (engine, events) = create_engine(ENGINE_COMMAND_BUFFER, ENGINE_EVENT_BUFFER);
-    let mut app = EnhancedApp::new(state, engine, events);
-    app.run().await
+pub async fn run_enhanced_tui(state: ClientState, options: TuiOptions) -> Result<()> {
+    let (engine, events, media) = create_engine(
+        ENGINE_COMMAND_BUFFER,
+        ENGINE_EVENT_BUFFER,
+        MEDIA_EVENT_BUFFER,
+    );
+    let mut app = EnhancedApp::new(state, engine, events, media, options);
+    let result = app.run().await;
+    // Собранные входящие файлы живут, пока жива лента, которая на них ссылается.
+    attachments::clear_received();
+    result
 }
@@ -0,0 +1,183 @@
+use crate::config::{config_dir, data_path};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Счётчики непрочитанного одного канала.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelMarker {
+    pub unread: usize,
+    pub mentions: usize,
+    /// Последняя последовательность, которую пользователь видел в канале.
+    #[serde(default)]
+    pub last_read_sequence: Option<u64>,
+    /// Последняя учтённая последовательность: повторная доставка не накручивает счётчик.
+    #[serde(default)]
+    pub last_seen_sequence: Option<u64>,
+    /// Заглушённый канал копит непрочитанное, но не уведомляет.
+    #[serde(default)]
+    pub muted: bool,
+}
+
+/// Непрочитанное и отметки mute по каналам, переживающие перезапуск TUI (`unread.json`).
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UnreadStore {
+    #[serde(default)]
+    channels: BTreeMap<u64, ChannelMarker>,
+}
+
+impl UnreadStore {
+    pub fn path() -> Result<PathBuf> {
+        data_path("unread.json")
+    }
+
+    /// Отсутствующий файл означает пустое состояние. Файл из каталога
+    /// настроек (где он лежал раньше) переносится в каталог данных и
+    /// удаляется, когда копия записана.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        let legacy = config_dir()?.join("unread.json");
+        if !path.exists() && legacy.exists() {
+            return Self::migrate(&legacy, &path);
+        }
+        Self::load_from(&path)
+    }
+
+    fn migrate(legacy: &Path, path: &Path) -> Result<Self> {
+        let store = Self::load_from(legacy)?;
+        store.save_to(path)?;
+        fs::remove_file(legacy).context("remove legacy unread state")?;
+        Ok(store)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).context("invalid unread state"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).context("read unread state"),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("state directory")?;
+        }
+        let payload = serde_json::to_string_pretty(self).context("serialize unread state")?;
+        fs::write(path, payload).context("write unread state")
+    }
+
+    pub fn get(&self, channel_id: u64) -> Option<&ChannelMarker> {
+        self.channels.get(&channel_id)
+    }
+
+    pub fn set_muted(&mut self, channel_id: u64, muted: bool) {
+        self.channels.entry(channel_id).or_default().muted = muted;
+    }
+
+    /// Каналы, в которых есть непрочитанное.
+    pub fn unread_channels(&self) -> Vec<u64> {
+        self.channels
+            .iter()
+            .filter(|(_, marker)| marker.unread > 0)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn total_unread(&self) -> usize {
+        self.channels.values().map(|marker| marker.unread).sum()
+    }
+
+    /// Учитывает входящее сообщение в неактивном канале. Возвращает `false`,
+    /// если сообщение уже прочитано или учтено раньше.
+    pub fn record(&mut self, channel_id: u64, sequence: u64, mention: bool) -> bool {
+        let marker = self.channels.entry(channel_id).or_default();
+        let known = marker.last_read_sequence.max(marker.last_seen_sequence);
+        if known.is_some_and(|known| sequence <= known) {
+            return false;
+        }
+        marker.last_seen_sequence = Some(sequence);
+        marker.unread += 1;
+        if mention {
+            marker.mentions += 1;
+        }
+        true
+    }
+
+    /// Сбрасывает счётчики и запоминает, докуда канал прочитан.
+    pub fn mark_read(&mut self, channel_id: u64, sequence: Option<u64>) {
+        let marker = self.channels.entry(channel_id).or_default();
+        marker.unread = 0;
+        marker.mentions = 0;
+        marker.last_read_sequence = marker
+            .last_read_sequence
+            .max(sequence)
+            .max(marker.last_seen_sequence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redelivered_messages_do_not_inflate_counts() {
+        let mut store = UnreadStore::default();
+        assert!(store.record(7, 10, false));
+        assert!(store.record(7, 11, true));
+        assert!(!store.record(7, 11, false));
+        assert_eq!(store.get(7).unwrap().unread, 2);
+        assert_eq!(store.get(7).unwrap().mentions, 1);
+        assert_eq!(store.unread_channels(), [7]);
+
+        store.mark_read(7, None);
+        assert_eq!(store.total_unread(), 0);
+        assert_eq!(store.get(7).unwrap().last_read_sequence, Some(11));
+        assert!(!store.record(7, 9, false));
+        assert!(store.record(7, 12, false));
+    }
+
+    #[test]
+    fn counts_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("commucat-unread-{}", std::process::id()));
+        let path = dir.join("unread.json");
+        assert_eq!(UnreadStore::load_from(&path).unwrap().total_unread(), 0);
+
+        let mut store = UnreadStore::default();
+        store.record(3, 1, false);
+        store.record(4, 5, true);
+        store.mark_read(4, Some(6));
+        store.set_muted(4, true);
+        store.save_to(&path).unwrap();
+
+        let restored = UnreadStore::load_from(&path).unwrap();
+        assert_eq!(restored.get(3).unwrap().unread, 1);
+        assert_eq!(restored.get(4).unwrap().last_read_sequence, Some(6));
+        assert_eq!(restored.total_unread(), 1);
+        assert!(restored.get(4).unwrap().muted);
+        assert!(!restored.get(3).unwrap().muted);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_file_is_moved_not_copied() {
+        let dir =
+            std::env::temp_dir().join(format!("commucat-unread-legacy-{}", std::process::id()));
+        let legacy = dir.join("config").join("unread.json");
+        let path = dir.join("data").join("unread.json");
+        let mut store = UnreadStore::default();
+        store.record(9, 1, true);
+        store.save_to(&legacy).unwrap();
+
+        let migrated = UnreadStore::migrate(&legacy, &path).unwrap();
+        assert_eq!(migrated.get(9).unwrap().mentions, 1);
+        assert!(!legacy.exists());
+        assert_eq!(UnreadStore::load_from(&path).unwrap().total_unread(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use crate::media::VideoFrame;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+/// Рисует кадр полублоками `▀`: верхний пиксель — цвет символа, нижний — фон.
+/// Кадр вписывается в `cols`×`rows` ячеек с сохранением пропорций.
+pub fn half_block_lines(frame: &VideoFrame, cols: u16, rows: u16) -> Vec<Line<'static>> {
+    let (cols, rows) = (cols as usize, rows as usize);
+    if cols == 0 || rows == 0 || frame.width == 0 || frame.height == 0 {
+        return Vec::new();
+    }
+    let scale = (cols as f32 / frame.width as f32).min((rows * 2) as f32 / frame.height as f32);
+    let width = ((frame.width as f32 * scale) as usize).clamp(1, cols);
+    let height = ((frame.height as f32 * scale) as usize).clamp(1, rows * 2);
+    let sample = |col: usize, row: usize| {
+        let [r, g, b] = frame.pixel(col * frame.width / width, row * frame.height / height);
+        Color::Rgb(r, g, b)
+    };
+    (0..height.div_ceil(2))
+        .map(|cell_row| {
+            let top = cell_row * 2;
+            let bottom = (top + 1).min(height - 1);
+            Line::from(
+                (0..width)
+                    .map(|col| {
+                        Span::styled(
+                            "▀",
+                            Style::default()
+                                .fg(sample(col, top))
+                                .bg(sample(col, bottom)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_pack_two_pixels_per_cell() {
+        let frame = VideoFrame {
+            width: 2,
+            height: 2,
+            pixels: vec![[255, 0, 0], [0, 255, 0], [0, 0, 255], [9, 9, 9]],
+        };
+        let lines = half_block_lines(&frame, 2, 1);
+        assert_eq!(lines.len(), 1);
+        let spans = &lines[0].spans;
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].style.fg, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(spans[0].style.bg, Some(Color::Rgb(0, 0, 255)));
+        assert_eq!(spans[1].style.bg, Some(Color::Rgb(9, 9, 9)));
+    }
+
+    #[test]
+    fn frame_fits_the_grid_keeping_aspect() {
+        let frame = VideoFrame {
+            width: 160,
+            height: 90,
+            pixels: vec![[0, 0, 0]; 160 * 90],
+        };
+        let lines = half_block_lines(&frame, 40, 40);
+        assert_eq!(lines[0].spans.len(), 40);
+        assert_eq!(lines.len(), 11);
+        assert!(half_block_lines(&frame, 0, 10).is_empty());
+    }
+}
@@ -33,6 +33,13 @@ impl VoiceMessage {
         self.frames.push(encoded);
     }
 
+    pub fn decode_frames(&self) -> Result<Vec<Vec<u8>>, base64::DecodeError> {
+        self.frames
+            .iter()
+            .map(|frame| BASE64.decode(frame))
+            .collect()
+    }
+
     pub fn to_bytes(&self) -> Result<Bytes, serde_json::Error> {
         let json = serde_json::to_vec(self)?;
         Ok(Bytes::from(json))
@@ -74,6 +81,10 @@ mod tests {
         let decoded = VoiceMessage::from_bytes(&bytes).expect("deserialize");
         assert_eq!(decoded.frames.len(), 2);
         assert_eq!(decoded.duration_ms, 2_000);
+        assert_eq!(
+            decoded.decode_frames().expect("base64"),
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]
+        );
     }
 
     #[test]